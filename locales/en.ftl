@@ -0,0 +1,727 @@
+# English messages, and the template for translations: copy this file to
+# "locales/<language>.ftl" in the config folder, e.g. locales/de.ftl, and
+# translate the text after each "=". Leave { $name } placeholders as they are;
+# they can move within the text. Counts pick a variant by the language's plural
+# rules, e.g. [one] and [other] in English, and the selectors on other values
+# list the values they can take. Anything left out is shown in English. See
+# https://projectfluent.org/fluent/guide/ for the syntax.
+
+## Tasks
+
+task-not-found = Task with ID { $id } not found.
+task-added = Task '{ $title }' added with ID: { $id }
+task-updated = Task '{ $title }' updated.
+task-nothing-to-change = Nothing to change; see `todo_task edit --help`.
+task-done = Task '{ $title }' marked as done.
+task-occurrence-done = '{ $title }' on { $start } marked as done.
+task-no-occurrence = Task { $id } has no occurrence to complete.
+task-remove-question = Remove task { $id } '{ $title }'?
+task-remove-with-event-question = Remove task { $id } '{ $title }' and its calendar event?
+task-kept = Kept task { $id }.
+task-removed = Removed task { $id } '{ $title }'.
+task-cron-invalid = Invalid cron expression: { $error }
+task-cron-never = The cron expression never matches
+task-recurrence-invalid = Invalid recurrence: { $error }
+task-recurrence-no-time = Give a start time, or a time of day in the recurrence, e.g. "weekday at 9am"
+task-start-past = Start time must be in the future; add --force to log a past task
+task-end-before-start = End time must be after the start time
+task-end-missing = An end time is required when a start time is given
+task-start-missing = A start time is required when an end time is given
+add-clipboard-empty = Error: The clipboard is empty.
+add-clipboard-unreadable = Error reading the clipboard: { $error }
+add-file-added = Added { $count ->
+        [one] 1 task
+       *[other] { $count } tasks
+    } from { $path }, with { $range }.
+add-file-empty = There are no tasks in { $path }.
+add-file-id = ID { $id }
+add-file-ids = IDs { $first } to { $last }
+add-file-invalid-task = Error: Task { $number }{ $title } in { $path }: { $message }.
+add-file-no-title = it has no title
+add-file-none-added = Added none of the tasks in { $path }, as { $invalid } of them can't be added.
+add-file-push-failed = Error adding tasks to the calendar: { $error }; `todo_task sync` pushes the rest.
+add-file-pushed = { $count } of them added to the calendar.
+add-file-recurrence-unsynced = Warning: The recurrence of '{ $title }' can't be synced as a calendar recurrence ({ $reason }); the calendar gets the first occurrence only.
+add-file-unreadable = Error: Can't read the tasks in { $path }: { $error }.
+add-no-such-parent = Error: There is no task #{ $id }.
+add-pushed = Task successfully added to the calendar.
+add-recurrence-unsynced = Warning: The recurrence can't be synced as a calendar recurrence ({ $reason }); the calendar gets the first occurrence only, `todo_task serve` reminds you of the rest.
+done-duplicate-open = Warning: '{ $title }' duplicates #{ $id } '{ $original_title }', which is still open.
+done-github-closed = Closed GitHub issue { $issue }.
+done-github-failed = Error closing GitHub issue { $issue }: { $error }
+done-jira-failed = Error updating Jira issue { $key }: { $error }
+done-jira-updated = Updated Jira issue { $key }.
+edit-assigned = Task '{ $title }' is assigned to { $name }.
+edit-estimated = Task '{ $title }' is estimated at { $length }.
+edit-local-only = Task '{ $title }' is now kept local.
+edit-local-time = Task '{ $title }' is now in local time, starting { $start }.
+edit-no-estimate = Task '{ $title }' no longer has an estimate.
+edit-not-waiting = Task '{ $title }' is no longer waiting.
+edit-synced = Task '{ $title }' is synced again.
+edit-time-zone = Task '{ $title }' is now in { $name }, starting { $start }.
+edit-unassigned = Task '{ $title }' is no longer assigned.
+edit-waiting = Task '{ $title }' is waiting on { $on }.
+inbox-captured = Captured as #{ $id }; `todo_task triage` to sort it out.
+inbox-deleted = Deleted.
+inbox-empty = The inbox is empty.
+inbox-left = { $error }; left in the inbox.
+inbox-passed = { $answer } has passed; left in the inbox.
+inbox-push-failed = Error adding it to the calendar: { $error }
+inbox-scheduled = Scheduled for { $start }.
+inbox-someday = Kept for someday.
+link-added = Task '{ $title }' linked to #{ $other }.
+link-removed = Task '{ $title }' is no longer linked to #{ $other }.
+pause-already = Task { $id } is already paused since { $time }.
+pause-done = Paused '{ $title }'. Resume it with `todo_task resume { $id }`.
+progress-from-subtasks = Error: Task #{ $id } has subtasks; its progress comes from theirs.
+progress-parent = Parent task #{ $parent } is { $percent }% done.
+progress-set = Task '{ $title }' is { $percent }% done.
+resume-done = Resumed '{ $title }'; next occurrence { $start }.
+resume-not-paused = Task { $id } isn't paused.
+skip-done = Skipped '{ $title }' on { $start }.
+skip-no-occurrence = Task { $id } has no occurrence to skip.
+task-not-recurring = Task { $id } doesn't repeat.
+
+## Listings
+
+list-past-end = Page { $page } is past the end; there are only { $total } matching tasks.
+list-more = ... { $more } more; use --page { $next } for the next { $count }, or --limit 0 to list them all.
+agenda-empty = Nothing scheduled in the next { $days } days.
+block-didnt-fit = Didn't fit: #{ $id } '{ $title }' ({ $length })
+block-done = Blocked { $count ->
+        [one] 1 task
+       *[other] { $count } tasks
+    } on { $date }.
+block-invalid-day = Error: '{ $day }' is not a day; use today, tomorrow, a weekday or YYYY-MM-DD
+block-move-failed = Error moving task { $id } off Google Tasks: { $error }
+block-nothing = Nothing to block on { $date }: no estimated, undated tasks fit the free working hours.
+block-push-failed = Error adding the blocks to the calendar: { $error }
+block-pushed = Added { $count ->
+        [one] 1 block
+       *[other] { $count } blocks
+    } to the calendar.
+burndown-empty = No tasks in project '{ $project }'.
+capacity-over = over by { $length }
+capacity-unscheduled = Unscheduled: { $length } estimated on { $count ->
+        [one] 1 task
+       *[other] { $count } tasks
+    }.
+capacity-warning = { $estimated } of estimated work, { $free } free
+context-defined = Defined context '{ $name }'; switch to it with `todo_task context { $name }`.
+context-deleted = Deleted context '{ $name }'.
+context-in-use = Context '{ $name }' is in use.
+context-line = { $name }{ $in_use ->
+        [true] {" "}(in use)
+       *[false] {""}
+    }: filter { $filter }, project { $project }, tags { $tags }
+context-none-defined = No contexts defined.
+context-none-in-use = No context is in use.
+context-none-reserved = Error: 'none' is reserved for leaving a context.
+context-not-found = Error: There is no context named '{ $name }'.
+context-not-found-hint = Error: There is no context named '{ $name }'; see `todo_task context list`.
+context-undefined = Warning: The context '{ $name }' is no longer defined; run `todo_task context none`.
+history-empty = No occurrences in the last { $days } days.
+history-summary = Last { $days } days: { $completed } completed, { $skipped } skipped, { $missed } missed; current streak { $streak }.
+history-summary-rate = Last { $days } days: { $completed } completed, { $skipped } skipped, { $missed } missed ({ $percent }% done); current streak { $streak }.
+list-assigned = assigned to { $name }
+list-default-view-missing = Warning: The default view '{ $name }' doesn't exist; listing everything.
+list-depends-on = depends on { $ids }
+list-not-recurring = No
+list-occurrence-cancelled = { $original }: cancelled
+list-occurrence-moved = { $original }: moved to { $start } - { $end }
+list-occurrence-moved-renamed = { $original }: moved to { $start } - { $end } as '{ $title }'
+list-paused = { $recurrence } (paused since { $since })
+list-priority-raised = { $priority } (raised from { $from })
+list-progress = progress { $bar }
+list-subtask-of = subtask of #{ $parent }
+list-task = ID: { $id }, Title: '{ $title }', Details: '{ $details }', Start: { $start }, End: { $end }, Recurring: { $recurring }, Priority: { $priority }, Project: { $project }, Tags: { $tags }, Synced: { $synced ->
+        [local] local only
+        [inbox] in the inbox
+        [yes] yes
+       *[no] not yet
+    }
+list-waiting = waiting on { $on } since { $since }
+list-waiting-none = Not waiting on anyone.
+list-waiting-task = ID: { $id }, Title: '{ $title }', Waiting on: { $on } for { $days ->
+        [one] 1 day
+       *[other] { $days } days
+    } (since { $time }), Start: { $start }
+log-created = created
+log-deleted = removed
+log-depends-on = { $added ->
+        [true] now depends
+       *[false] no longer depends
+    } on { $task }
+log-empty = No changes recorded.
+log-none = none
+log-removed-id = #{ $id } (removed)
+log-removed-task = a removed task
+log-tag = tag { $tag } { $added ->
+        [true] added
+       *[false] removed
+    }
+menu-invalid-duration = Invalid duration '{ $duration }'. Use e.g. '30m' or '2h'
+menu-no-id = Error: Could not find a task ID in '{ $line }'.
+menu-no-title = Title must not be empty.
+menu-someday = someday
+menu-snoozed = Task '{ $title }' snoozed until { $start }.
+menu-unreadable = Error reading selection from stdin: { $error }
+pick-done = Picked from { $count ->
+        [one] 1 actionable task
+       *[other] { $count } actionable tasks
+    }.
+pick-nothing = No actionable task matches.
+picker-prompt = Number to pick, or text to search for (empty to cancel):
+quota-empty = No calendar requests in the last 7 days.
+quota-limit = Daily limit: { $limit } requests per service
+quota-no-limit = Daily limit: none (set sync.daily_request_limit in config.json)
+replan-didnt-fit = Doesn't fit in the rest of the day: #{ $id } '{ $title }'
+replan-done = Moved { $count ->
+        [one] 1 task
+       *[other] { $count } tasks
+    }.
+replan-nothing = Nothing to move: the rest of { $date } still fits.
+replan-update-failed = Error updating the calendar event of task { $id }: { $error }
+show-assigned = Assigned to: { $name }
+show-assigned-address = Assigned to: { $name } <{ $address }>
+show-depends-on = Depends on: { $ids }
+show-details = Details: { $details }
+show-estimate = Estimate: { $length }
+show-event = Event: { $url }
+show-github-issue = GitHub issue: { $url }
+show-jira-issue = Jira issue: { $url }
+show-links = Links:
+show-meta = Meta: { $meta }
+show-priority = Priority: { $priority }
+show-progress = Progress: { $bar }{ $from_subtasks ->
+        [true] {" "}(from subtasks)
+       *[false] {""}
+    }
+show-project-tags = Project: { $project }, Tags: { $tags }
+show-recurring = Recurring: { $text }
+show-start-end = Start: { $start }, End: { $end }
+show-status = Status: { $status ->
+        [done] Done
+       *[todo] Todo
+    }
+show-subtask-of = Subtask of: #{ $parent }
+show-time-zone = Time zone: { $timezone }
+show-waiting = Waiting on { $on } since { $time }
+stale-delegated = Waiting on { $delegate } now.
+stale-dropped = Dropped.
+stale-hint = Run `todo_task stale --interactive` to reschedule, delegate or drop them.
+stale-invalid-time = '{ $answer }' is not a start time; left as it was.
+stale-moved = Moved to { $start }.
+stale-never-changed = no changes recorded
+stale-none = No stale tasks.
+stale-snoozed = snoozed { $count } times since its last change
+stale-task = #{ $id } '{ $title }' ({ $reason }), scheduled { $start }
+stale-untouched = untouched for { $days } days
+stale-update-failed = Error updating the calendar: { $error }
+view-default-cleared = `list` shows every task again.
+view-default-set = `list` now shows the view '{ $name }'; `list --all` shows everything.
+view-deleted = Deleted view '{ $name }'.
+view-line = { $name }{ $default ->
+        [true] {" "}(default)
+       *[false] {""}
+    }: { $filter }
+view-none-named = Name a view to list, or see `todo_task view --help`.
+view-none-saved = No saved views.
+view-not-found = Error: There is no view named '{ $name }'.
+view-not-found-hint = Error: There is no view named '{ $name }'; see `todo_task view list`.
+view-saved = Saved view '{ $name }'; list it with `todo_task view { $name }`.
+
+## Times
+
+time-unscheduled = unscheduled
+time-now = now
+time-in = in { $length }
+time-ago = { $length } ago
+time-minutes = { $minutes } m
+time-hours = { $hours } h
+time-hours-minutes = { $hours } h { $minutes } m
+time-days = { $days ->
+        [one] 1 day
+       *[other] { $days } days
+    }
+time-invalid = '{ $text }' is not a date and time; use e.g. { $formats }
+time-skipped = '{ $text }' doesn't exist in local time, as the clocks go forward then
+length-hours = { $hours ->
+        [one] 1 hour
+       *[other] { $hours } hours
+    }
+length-minutes = { $minutes ->
+        [one] 1 minute
+       *[other] { $minutes } minutes
+    }
+time-zone-invalid = '{ $name }' is not a time zone; use an IANA name such as Europe/Berlin or Asia/Tokyo
+# Recurrences, e.g. "every 2 weeks on Tuesday, Friday 08:00"; `--every` reads
+# the English ones
+recur-unit = { $unit ->
+        [minute] { $count ->
+            [one] minute
+           *[other] { $count } minutes
+        }
+        [hour] { $count ->
+            [one] hour
+           *[other] { $count } hours
+        }
+        [day] { $count ->
+            [one] day
+           *[other] { $count } days
+        }
+        [week] { $count ->
+            [one] week
+           *[other] { $count } weeks
+        }
+       *[month] { $count ->
+            [one] month
+           *[other] { $count } months
+        }
+    }
+recur-every = every { $unit }
+recur-every-on = every { $unit } on { $days }
+recur-every-weekday = every weekday
+recur-every-weekend-day = every weekend day
+recur-at = { $rule } { $time }
+recur-none = no
+
+## Sync
+
+calendars-already-in-use = Tasks already sync with '{ $name }'.
+calendars-ambiguous = Error: Several calendars are named '{ $name }'; use the ID of one instead.
+calendars-create-failed = Error creating the calendar: { $error }
+calendars-create-failed-using-primary = Error creating the calendar: { $error }; tasks go to the primary calendar for now.
+calendars-created = Created the calendar '{ $name }'.
+calendars-exists = Error: There is already a calendar named '{ $name }'; sync with it with `todo_task calendars use`.
+calendars-google-only = Error: `calendars` works with Google calendars; the config syncs with another service.
+calendars-list-failed = Error listing the calendars: { $error }
+calendars-not-found = Error: There is no calendar named '{ $name }'; `todo_task calendars` lists them.
+calendars-offer = Keep tasks in a calendar of their own, '{ $name }', apart from meetings? [Y/n]
+calendars-read-only = Error: '{ $name }' is read-only for this account, so tasks can't be added to it.
+calendars-sign-in-failed = Error signing in to Google: { $error }
+calendars-sign-in-failed-using-primary = Error signing in to Google: { $error }; tasks go to the primary calendar for now.
+calendars-switched = Tasks now sync with '{ $name }'.
+calendars-switched-tasks = { $count ->
+        [one] 1 task
+       *[other] { $count } tasks
+    } will be added to it at the next sync; their events in the old calendar are left as they are.
+calendars-using-primary = Tasks will go to the primary calendar; `todo_task calendars use` picks another.
+conflicts-header = Conflict { $number }/{ $total }: task #{ $task_id } on { $service } (detected { $time })
+conflicts-pick-side = Keep { $field } from [l]ocal or [r]emote?
+conflicts-update-failed = Error updating the { $service } event, keeping the conflict: { $error }
+feeds-failed = Failed to refresh feed '{ $name }': { $error }
+feeds-refreshed = Feed '{ $name }' refreshed: { $count ->
+        [one] 1 event
+       *[other] { $count } events
+    }.
+google-token-unreadable = Error reading the Google token: { $error }
+journal-skipping-task = Skipping task { $uuid } in { $path }: { $error }
+outlook-frequency-unsupported = Warning: Outlook can't repeat every { $minutes ->
+        [one] minute
+       *[other] { $minutes } minutes
+    }; '{ $title }' is added as a single event.
+outlook-recurrence-unsupported = Warning: Outlook can't repeat { $repeat }; '{ $title }' is added as a single event.
+rsvp-accepted = Accepted '{ $title }'.
+rsvp-declined = Declined '{ $title }'. Remove the task with `todo_task remove { $id }` if you no longer need it.
+rsvp-failed = Error replying to the invitation: { $error }
+rsvp-tentative = Tentatively accepted '{ $title }'.
+sync-adopt-declined = Left { $count ->
+        [one] 1 earlier event and its task
+       *[other] { $count } earlier events and their tasks
+    } alone; they're offered again at the next sync.
+sync-adopted = { $count ->
+        [one] 1 event made by this tool earlier was
+       *[other] { $count } events made by this tool earlier were
+    } adopted as tasks instead of being added again.
+sync-batch = { $count } tasks from '{ $title }'
+sync-confirm-adopt = Adopt { $count ->
+        [one] 1 event
+       *[other] { $count } events
+    } this tool made earlier, whose tasks aren't linked to them, instead of adding them again?
+sync-confirm-deletes = Delete { $count ->
+        [one] 1 event
+       *[other] { $count } events
+    } from { $service } whose tasks were removed here?
+sync-conflicts-left = { $count ->
+        [one] 1 conflict
+       *[other] { $count } conflicts
+    } left unresolved; run `todo_task sync --interactive` to settle them.
+sync-conflicts-resolved = { $resolved ->
+        [one] 1 conflict
+       *[other] { $resolved } conflicts
+    } resolved, { $left } left.
+sync-connect-failed = Failed to connect to { $service }: { $error }
+sync-done = { $service } synchronized: { $pushed } pushed, { $added } added, { $updated } updated, { $sent } sent, { $deleted } deleted, { $skipped } skipped by sync rules, { $exceptions } changed occurrences.
+sync-failed = Failed to sync tasks with { $service }: { $error }
+sync-fetch-failed = Failed to fetch events from { $name }: { $error }
+sync-fetched = { $name }: { $count ->
+        [one] 1 event
+       *[other] { $count } events
+    } fetched.
+sync-fetching = Fetching events from { $service }
+sync-git-done = Git store synchronized: { $received } changes received, { $added } added, { $updated } updated, { $removed } removed, { $pushed ->
+        [true] changes pushed
+       *[false] nothing to push
+    }.
+sync-git-failed = Error syncing with { $remote }: { $error }
+sync-git-not-configured = No Git repository configured; add "git": {"{"} "remote": ... {"}"} to { $path }.
+sync-google-tasks-connect-failed = Failed to connect to Google Tasks: { $error }
+sync-invalid-pattern = Invalid { $setting } pattern '{ $pattern }': { $error }
+sync-invitation = Invitation awaiting your reply: #{ $id } '{ $title }' (todo_task rsvp { $id } accept|decline|tentative)
+sync-merging = Merging events from { $service }
+sync-no-color = { $service } events have no color
+sync-no-replies = { $service } doesn't support replying to invitations
+sync-pushing = Pushing to { $service }
+sync-rules-invalid = Error in the sync rules of { $path }: { $error }
+sync-section-missing = backend is "{ $section }" but config.json has no "{ $section }" section
+sync-calendar-section-missing = calendar '{ $name }' is "{ $section }" but has no "{ $section }" section
+sync-skipped = Skipped syncing with { $service }; `todo_task sync --dry-run` shows what it would change.
+sync-status-background = Background sync, by the server (pid { $pid })
+sync-status-background-no-interval = Background sync: off; the server (pid { $pid }) was started without an interval
+sync-status-background-off = Background sync: off; `todo_task serve` runs it with `sync.every_minutes` in { $path }
+sync-status-cached = Cached events: { $count } as of { $time }
+sync-status-conflict = #{ $id } local '{ $local }' ({ $local_start }) vs remote '{ $remote }' ({ $remote_start })
+sync-status-conflicts = Unresolved conflicts: { $count }
+sync-status-every = every { $minutes ->
+        [one] minute
+       *[other] { $minutes } minutes
+    }
+sync-status-every-jitter = every { $minutes ->
+        [one] minute
+       *[other] { $minutes } minutes
+    }, up to { $jitter } more at random
+sync-status-last-failure = Last attempt failed at { $time }: { $error }
+sync-status-last-run = Last run: { $time }
+sync-status-last-run-failed = Last run: { $time }, failed for { $services }
+sync-status-last-success = Last successful sync: { $time }
+sync-status-never = Never synced. Run `todo_task sync`.
+sync-status-never-run = never
+sync-status-next-run = Next run: { $time }
+sync-status-no-token = Sync token: none (full pull on every sync)
+sync-status-not-yet = not yet
+sync-status-on-push = on changes pushed from Google Calendar
+sync-status-pending = Pending: { $changed } local edits, { $deleted } deletions
+sync-status-runs = Runs: { $schedule }
+sync-status-token = Sync token: { $token }
+sync-status-unpushed = Tasks not yet pushed: { $count }
+sync-todoist-done = Todoist synchronized: { $added } added, { $updated } updated, { $completed } completed here, { $closed } closed in Todoist.
+sync-todoist-failed = Failed to sync tasks with Todoist: { $error }
+sync-todoist-syncing = Syncing with Todoist
+sync-would-add = Would add '{ $title }' (project: { $project }, tags: { $tags })
+sync-would-adopt = Would adopt '{ $title }', made by this tool earlier
+sync-would-conflict = Conflict on #{ $id } '{ $title }'
+sync-would-delete = Would delete '{ $title }', whose task was removed
+sync-would-push = Would push #{ $id } '{ $title }'
+sync-would-record = Would record the { $cancelled ->
+        [true] cancelled
+       *[false] changed
+    } occurrence of #{ $id } '{ $title }' on { $time }
+sync-would-send = Would send the local edits of #{ $id } '{ $title }'
+sync-would-skip = Would skip '{ $title }' (sync rules)
+sync-would-update = Would update #{ $id } '{ $title }' from the event
+steps-done = { $label }: { $done } of { $total } done in { $seconds } s
+steps-done-failed = { $label }: { $done } of { $total } done, { $failed } failed, in { $seconds } s
+
+## Issue trackers and imports
+
+export-failed = Failed to export tasks: { $error }
+github-invalid-issue = Error: Invalid issue '{ $issue }'. Use the form owner/repo#123.
+github-linked = Task '{ $title }' linked to { $issue }.
+github-not-configured = Error: Add a "github" section with a personal access token to config.json.
+github-sync-failed = Failed to sync GitHub issues: { $error }
+github-synced = GitHub synchronized: { $added } added, { $updated } updated, { $completed } completed here, { $closed } issues closed.
+github-syncing = Syncing GitHub issues
+import-org-done = Imported from org: { $added } added, { $updated } updated.
+import-org-importing = Importing from org
+import-taskwarrior-done = Imported from Taskwarrior: { $added } added, { $updated } updated, { $skipped } skipped.
+import-taskwarrior-failed = Failed to import from Taskwarrior: { $error }
+import-taskwarrior-importing = Importing from Taskwarrior
+import-todoist-done = Imported from Todoist: { $added } added, { $updated } updated.
+import-todoist-failed = Failed to import from Todoist: { $error }
+import-todoist-importing = Importing from Todoist
+import-trello-done = Imported from Trello: { $added } added, { $updated } updated, { $skipped } archived cards skipped.
+import-trello-failed = Failed to import from Trello: { $error }
+import-trello-importing = Importing from Trello
+jira-linked = Task '{ $title }' linked to { $key }.
+jira-not-configured = Error: Add a "jira" section with your site URL and API token to config.json.
+jira-sync-failed = Failed to sync Jira issues: { $error }
+jira-synced = Jira synchronized: { $added } added, { $updated } updated, { $completed } completed here, { $closed } issues updated.
+jira-syncing = Syncing Jira issues
+todoist-not-configured = Error: Add a "todoist" section with your API token to config.json.
+
+## Reminders and the server
+
+daemon-launchd-install-failed = Error installing the launchd agent: { $error }
+daemon-launchd-loaded = Wrote and loaded { $path }
+daemon-launchd-none = No todo_task launchd agent is installed.
+daemon-launchd-uninstall-failed = Error removing the launchd agent: { $error }
+daemon-removed = Removed { $path }
+daemon-sync-every = `todo_task sync` runs every { $minutes ->
+        [one] minute
+       *[other] { $minutes } minutes
+    }.
+daemon-systemd-install-failed = Error installing the systemd service: { $error }
+daemon-systemd-none = No todo_task service is installed.
+daemon-systemd-started = Started the { $unit } service; check on it with `systemctl --user status { $unit }`.
+daemon-systemd-sync-every = `todo_task sync` runs every { $minutes ->
+        [one] minute
+       *[other] { $minutes } minutes
+    }; see `systemctl --user list-timers`.
+daemon-systemd-uninstall-failed = Error removing the systemd service: { $error }
+daemon-windows-install-failed = Error registering the scheduled task: { $error }
+daemon-windows-installed = Registered and started the scheduled { $count ->
+        [one] task { $names }; it runs
+       *[other] tasks { $names }; they run
+    } at each logon.
+daemon-windows-none = No todo_task scheduled task is registered.
+daemon-windows-uninstall-failed = Error removing the scheduled task: { $error }
+daemon-windows-uninstalled = Removed the scheduled { $count ->
+        [one] task
+       *[other] tasks
+    } { $names }.
+daemon-wrote = Wrote { $path }
+desktop-osascript-failed = Error showing a notification: osascript exited with { $status }
+desktop-osascript-missing = Error showing a notification: can't run osascript ({ $error })
+desktop-powershell-failed = Error showing a notification: powershell exited with { $status }
+desktop-powershell-missing = Error showing a notification: can't run powershell ({ $error })
+digest-empty = No open assigned tasks.
+digest-no-address = Skipping { $name }: no address; add one under "people" in { $path }.
+digest-send-failed = Error emailing { $address }: { $error }
+digest-sent = Sent { $count ->
+        [one] 1 task
+       *[other] { $count } tasks
+    } to { $recipient }.
+mqtt-publish-failed = Error publishing the { $event } event to MQTT: { $error }
+mqtt-not-acknowledged = MQTT broker did not acknowledge the connection
+mqtt-refused = MQTT broker refused the connection (return code { $code })
+mqtt-timed-out = Timed out talking to the MQTT broker
+mqtt-too-long = The MQTT { $field } is { $length } bytes long; MQTT allows at most 65535
+notify-failed = Error sending the reminder through { $notifier }: { $error }
+reload-done = Reloaded { $path }
+reload-done-restart = Reloaded { $path }; { $settings } only take effect when the server is restarted.
+reload-failed = Error reading { $path }: { $error }; keeping the settings read before.
+remind-follow-up = follow up with { $name }
+remind-message = Reminder: '{ $title }' { $message }!
+remind-no-channel = No channel reminds of task { $id }.
+remind-none-upcoming = No reminders in the next { $hours } hours.
+remind-upcoming = { $time }  #{ $id } '{ $title }' { $message } -> { $channels }
+remind-upcoming-no-channel = no channel
+remind-send-failed = Error sending through { $name }: { $error }
+remind-sent = Sent through { $name }.
+remind-server-down = `todo_task serve` isn't running; reminders are only fired while it runs.
+remind-starts-in = starts in { $length }
+remind-ends-in = ends in { $length }
+remind-test-title = Test reminder
+remind-test = is a test of your reminders
+remind-starts-now = starts now
+serve-connection-failed = Error serving connection: { $error }
+serve-failed = Failed to run the server: { $error }
+serve-feed = Serving tasks at http://{ $address }{ $feed_path }
+serve-metrics = Serving metrics at http://{ $address }/metrics
+serve-notifier-invalid = Invalid notifier in { $path }: { $error }; keeping the notifiers from before.
+serve-stopped = Stopped by { $signal }; shutting down.
+serve-token-unsaved = Error saving the feed token to { $path }: { $error }
+service-notify-failed = Error notifying systemd at { $socket }: { $error }
+stopped-saving = Stopped by { $signal }; saving the changes made so far.
+webhook-close-failed = Error closing the Google Calendar push channel { $id }: { $error }
+webhook-open-failed = Error opening a Google Calendar push channel to { $url }: { $error }; trying again in 5 minutes.
+webhook-receiving = Receiving Google Calendar changes at { $url }
+
+## Upkeep
+
+doctor-caldav-failed = { $url } failed: { $error }
+doctor-caldav-failed-fix = Check the collection URL and the username and (app) password in the "caldav" section.
+doctor-caldav-missing-fix = Add a "caldav" section with url, username and password to { $path }.
+doctor-caldav-ok = { $url } reachable and credentials accepted
+doctor-caldav-set = URL and username set
+doctor-caldav-skipped = skipped, CalDAV isn't configured
+doctor-caldav-skipped-fix = See the CalDAV credentials check.
+doctor-check-caldav-credentials = CalDAV credentials
+doctor-check-config = Config
+doctor-check-environment = Environment
+doctor-check-folders = Folders
+doctor-check-github-credentials = GitHub credentials
+doctor-check-google-credentials = Google credentials
+doctor-check-google-token = Google token
+doctor-check-jira-credentials = Jira credentials
+doctor-check-network = Network
+doctor-check-notifiers = Notifiers
+doctor-check-outlook-credentials = Outlook credentials
+doctor-check-outlook-token = Outlook token
+doctor-check-server = Server
+doctor-check-storage = Storage
+doctor-check-todoist-credentials = Todoist credentials
+doctor-config-invalid = { $path } could not be parsed: { $error }
+doctor-config-invalid-fix = Correct the JSON syntax or the misspelled setting at the position shown.
+doctor-config-missing = { $path } not found, using defaults (Google Calendar)
+doctor-config-missing-fix = Create { $path } to choose a backend and enable integrations.
+doctor-config-valid = { $path } is valid
+doctor-environment = { $names } take precedence over config.json
+doctor-fail = [FAIL] { $check }: { $detail }
+doctor-file-found = { $path } found
+doctor-file-missing = { $path } not found
+doctor-fix = Fix: { $fix }
+doctor-folder = { $kind ->
+        [config] config
+        [data] data
+       *[cache] cache
+    } in { $path }
+doctor-google-credentials-fix = Create an OAuth client ID (Desktop app) in the Google Cloud console and save its JSON as { $path }.
+doctor-google-token-fix = Delete { $location } and run `todo_task sync` to sign in again.
+doctor-google-token-ok = refresh token accepted
+doctor-keyring-fix = Make sure the system keyring is unlocked, or set "token_store": "file" in config.json.
+doctor-network-fix = Check your internet connection, DNS and any proxy or firewall.
+doctor-no-problems = No problems found.
+doctor-notifiers-invalid-fix = Correct the "notifiers" and "notification_routes" sections of { $path }.
+doctor-notifiers-none = none configured; reminders are printed and shown on the desktop
+doctor-ok = [ok]   { $check }: { $detail }
+doctor-outlook-missing-fix = Add an "outlook" section with the client_id of your Azure app to { $path }.
+doctor-outlook-set = client ID set
+doctor-outlook-token-ok = token valid
+doctor-plugins-missing = { $programs } not found on the PATH
+doctor-plugins-missing-fix = Install the plugin, or remove its notifier.
+doctor-problems = { $count ->
+        [one] 1 problem
+       *[other] { $count } problems
+    } found.
+doctor-reachable = { $service } reachable
+doctor-section-missing = backend is "{ $section }" but there is no "{ $section }" section
+doctor-server-down = not running
+doctor-server-info-corrupt = { $path } is corrupt
+doctor-server-info-corrupt-fix = Delete { $path } and restart the server.
+doctor-server-ok = pid { $pid } answering on { $address } since { $since }
+doctor-server-start = Start it with `todo_task serve --ics` (or --metrics) if you want reminders and background sync.
+doctor-server-unresponsive = server started at { $since } (pid { $pid }) is not responding on { $address }: { $error }
+doctor-server-unresponsive-fix = Restart `todo_task serve`, or delete { $path } if it was stopped on purpose.
+doctor-setting-empty = "{ $field }" in the "{ $section }" section is empty
+doctor-setting-empty-fix = Fill in "{ $field }" in the "{ $section }" section of { $path }.
+doctor-sign-in = Run `todo_task sync` to sign in with your browser.
+doctor-sign-in-again = Run `todo_task sync` to sign in again.
+doctor-signed-out = not signed in yet
+doctor-store-corrupt = { $path } is corrupt: { $error }
+doctor-store-corrupt-fix = Restore { $path } from a backup, or fix the JSON at the position shown.
+doctor-store-duplicate-id = ID { $id } is used by more than one task
+doctor-store-duplicate-uuid = task { $id } shares its UUID with another task
+doctor-store-ends-early = task { $id } ends before it starts
+doctor-store-half-scheduled = task { $id } has only one of a start and end time
+doctor-store-missing = { $path } doesn't exist yet; it is created by the first `add`
+doctor-store-newer = { $path } was written by a newer version of todo_task (store version { $version }, this one reads up to { $supported })
+doctor-store-newer-fix = Upgrade todo_task to the version that wrote it.
+doctor-store-next-id = next_id { $next_id } would reuse an existing ID
+doctor-store-ok = { $count ->
+        [one] 1 task
+       *[other] { $count } tasks
+    }, no problems found
+doctor-store-problems-fix = Edit { $path } to correct the tasks listed.
+doctor-store-unreadable = { $path } can't be read: { $error }
+doctor-store-unreadable-fix = Check the file's permissions.
+doctor-store-upgradable = { $count ->
+        [one] 1 task
+       *[other] { $count } tasks
+    }, no problems found; store version { $version } is upgraded to { $latest } the next time it's used
+doctor-token-refresh-failed = refreshing the token failed: { $error }
+doctor-token-unreadable = reading the token failed: { $error }
+doctor-unreachable = { $service } unreachable: { $error }
+doctor-warn = [warn] { $check }: { $detail }
+keyring-moved = Moved the token in { $path } to the system keyring.
+keyring-unreachable = Can't run { $program } to reach the system keyring ({ $error }); set "token_store": "file" in config.json to keep tokens in a file instead
+keyring-failed = { $program } failed: { $error }
+keyring-file-instead = Warning: { $error }. Keeping the token in { $path } for now.
+keyring-not-stored = security couldn't store the token in the keychain
+keyring-entry = the '{ $service } { $entry }' entry in the system keyring
+maintenance-compact-failed = Error compacting { $path }: { $error }
+maintenance-conflict-task-missing = had a { $service } sync conflict but doesn't exist; dropped the conflict
+maintenance-conflict-unlinked = had a { $service } sync conflict but isn't linked to that event any more; dropped the conflict
+maintenance-dependency-loop = depended on #{ $dependency } in a loop of dependencies; removed the dependency
+maintenance-dependency-missing = depended on #{ $dependency }, which doesn't exist; removed the dependency
+maintenance-dependency-twice = listed #{ $dependency } twice as a dependency; removed one
+maintenance-depends-on-itself = depended on itself; removed the dependency
+maintenance-journal-compact = The change journal has { $count ->
+        [one] 1 operation
+       *[other] { $count } operations
+    } and nothing to compact.
+maintenance-journal-compacted = { $dropped } of the { $before } operations in the change journal { $dry_run ->
+        [true] would be
+       *[false] were
+    } dropped, leaving { $after }.
+maintenance-link-missing = was linked to #{ $other }, which doesn't exist; removed the link
+maintenance-linked-to-itself = was linked to itself; removed the link
+maintenance-nothing-broken = No broken references found.
+maintenance-own-subtask = was its own subtask; made it a top-level task
+maintenance-parent-missing = was a subtask of #{ $parent }, which doesn't exist; made it a top-level task
+maintenance-problem = Task { $id } { $problem }
+maintenance-repaired = { $count ->
+        [one] 1 problem { $dry_run ->
+            [true] would be
+           *[false] was
+        }
+       *[other] { $count } problems { $dry_run ->
+            [true] would be
+           *[false] were
+        }
+    } repaired.
+maintenance-shared-event = was linked to the same calendar event as #{ $owner }; unlinked it
+maintenance-subtask-loop = was a subtask of #{ $parent } in a loop of subtasks; made it a top-level task
+maintenance-uuid-missing = had no UUID; gave it a new one
+maintenance-uuid-shared = had the same UUID as another task; gave it a new one
+paths-moved = Moved { $source } to { $target }
+profiles-copy-failed = Error copying { $path }: { $error }
+profiles-created = Created profile '{ $name }' in { $dir }; use it with `todo_task --profile { $name } ...`.
+profiles-in-use = Error: Profile '{ $name }' is in use; run this without --profile or TODO_PROFILE.
+profiles-kept = Kept profile '{ $name }'.
+profiles-line = { $name }{ $in_use ->
+        [true] {" "}(in use)
+       *[false] {""}
+    }: { $tasks }
+profiles-list-failed = Error listing the profiles: { $error }
+profiles-main = (main)
+profiles-remove-question = Delete profile '{ $name }' with all its tasks, settings and sign-ins?
+profiles-removed = Removed { $dir }
+profiles-server-running = Error: The server of profile '{ $name }' is running; stop it or uninstall its service first.
+profiles-tasks = { $count ->
+        [one] 1 task
+       *[other] { $count } tasks
+    }
+profiles-unreadable = can't read { $path }: { $error }
+store-unsaved = Error saving tasks to { $path }: { $error }
+undo-done = { $redo ->
+        [true] Redid
+       *[false] Undid
+    } `{ $command }` from { $time }
+undo-failed = Error: Can't { $redo ->
+        [true] redo
+       *[false] undo
+    } `{ $command }`: { $error }
+undo-nothing = Nothing to { $redo ->
+        [true] redo
+       *[false] undo
+    }.
+undo-sync-hint = Run `todo_task sync` to carry this over to the calendar.
+
+## Errors
+
+error = Error: { $error }
+error-event-add = Error adding task to the calendar: { $error }
+error-event-remove = Error removing task from the calendar: { $error }
+error-event-update = Error updating the calendar event: { $error }
+error-invalid-filter = Error: Invalid filter: { $error }
+error-invalid-notifier = Error: invalid notifier in { $path }: { $error }
+error-reading = Error reading { $path }: { $error }
+error-reading-doctor = Error reading { $path }: { $error }; `todo_task doctor` can tell what's wrong.
+error-saving = Error saving { $path }: { $error }
+error-sentence = Error: { $error }.
+error-updating = Error updating { $path }: { $error }
+error-writing = Error writing { $path }: { $error }
+error-writing-list = Error writing the list: { $error }
+
+## Dates, used for LC_TIME
+# Names for %A, %a, %B and %b in time formats, separated by commas, starting
+# with Monday and January
+date-weekdays = Monday, Tuesday, Wednesday, Thursday, Friday, Saturday, Sunday
+date-weekdays-short = Mon, Tue, Wed, Thu, Fri, Sat, Sun
+date-months = January, February, March, April, May, June, July, August, September, October, November, December
+date-months-short = Jan, Feb, Mar, Apr, May, Jun, Jul, Aug, Sep, Oct, Nov, Dec
+# How times are shown when the config has no time_format, as a strftime format
+# in local time; times are shown in UTC when this is left out
+# date-time-format = %a %d %b %Y %H:%M
@@ -0,0 +1,205 @@
+// Day-by-day agenda of tasks and read-only feed events
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Duration, Local, Utc};
+
+use crate::capacity::Day;
+use crate::feeds::FeedCache;
+use crate::sync::SyncState;
+use crate::{Task, TaskStatus};
+
+#[derive(Debug, Clone)]
+pub struct AgendaItem {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub title: String,
+    /// "#<id>" for tasks, the feed or service name for events
+    pub source: String,
+    /// Set for task occurrences
+    pub task_id: Option<u32>,
+}
+
+// Most occurrences of one series listed, so a minutely series can't flood the agenda
+const MAX_OCCURRENCES: usize = 500;
+
+// Calendars and feeds merged into the agenda and free time, by name, as
+// picked with `--calendar` and `--without`
+#[derive(Default)]
+pub struct Selection {
+    /// Only these, when any are given
+    pub only: Vec<String>,
+    pub without: Vec<String>,
+    /// Calendars from "calendars" left out unless named in `only`
+    pub hidden: Vec<String>,
+}
+
+static SELECTION: OnceLock<Selection> = OnceLock::new();
+
+pub fn select(selection: Selection) {
+    let _ = SELECTION.set(selection);
+}
+
+// Whether events of the calendar or feed named `name` are merged in
+pub fn shows(name: &str) -> bool {
+    let Some(selection) = SELECTION.get() else { return true };
+    let named = |names: &[String]| names.iter().any(|other| other.eq_ignore_ascii_case(name));
+    match selection.only.is_empty() {
+        false => named(&selection.only),
+        true => !named(&selection.without) && !named(&selection.hidden),
+    }
+}
+
+// ANSI escape code that sets a color given as a name such as "blue" or as
+// "#rrggbb"; None for anything else
+pub fn color_code(color: &str) -> Option<String> {
+    let names = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+    let color = color.trim().to_lowercase();
+    if let Some(index) = names.iter().position(|name| *name == color) {
+        return Some(format!("\x1b[{}m", 30 + index));
+    }
+    if color == "gray" || color == "grey" {
+        return Some("\x1b[90m".to_string());
+    }
+    let hex = color.strip_prefix('#').filter(|hex| hex.len() == 6)?;
+    let channel = |at: usize| u8::from_str_radix(&hex[at..at + 2], 16).ok();
+    Some(format!("\x1b[38;2;{};{};{}m", channel(0)?, channel(2)?, channel(4)?))
+}
+
+// Start and end of every occurrence of a series overlapping [from, until).
+// Events without a frequency occur once.
+pub fn occurrences(
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    frequency_minutes: Option<i64>,
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let Some(frequency) = frequency_minutes.filter(|minutes| *minutes > 0) else {
+        return vec![(start_time, end_time)];
+    };
+    // Jump straight to the first occurrence that hasn't ended by `from`
+    let skipped = if end_time > from { 0 } else { (from - end_time).num_minutes() / frequency + 1 };
+    (skipped..skipped + MAX_OCCURRENCES as i64)
+        .map(|n| Duration::minutes(frequency * n))
+        .map(|offset| (start_time + offset, end_time + offset))
+        .take_while(|(start, _)| *start < until)
+        .collect()
+}
+
+// Occurrences overlapping [from, until) of a series first held at start..end,
+// whose later starts come from `next_after`
+pub fn expand(
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+    next_after: impl Fn(DateTime<Utc>) -> Option<DateTime<Utc>>,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let length = end_time - start_time;
+    let mut next = Some(start_time).filter(|start| *start + length > from).or_else(|| next_after((from - length).max(start_time)));
+    let mut found = Vec::new();
+    while let Some(start) = next
+        && start < until
+        && found.len() < MAX_OCCURRENCES
+    {
+        found.push((start, start + length));
+        next = next_after(start);
+    }
+    found
+}
+
+// Open tasks, feed events and cached calendar events overlapping [from, until),
+// in start order, with recurring tasks and events expanded into their
+// occurrences. Calendar events linked to a task are left to the task.
+pub fn collect(tasks: &[Task], feeds: &FeedCache, calendars: &SyncState, from: DateTime<Utc>, until: DateTime<Utc>) -> Vec<AgendaItem> {
+    let linked = |event_id: &str| tasks.iter().any(|task| task.event_id.as_deref() == Some(event_id));
+    let mut items: Vec<AgendaItem> = tasks
+        .iter()
+        .filter(|task| task.status == TaskStatus::Todo)
+        .flat_map(|task| {
+            task.occurrences(from, until).into_iter().filter_map(move |(start_time, end_time)| {
+                // The calendar may have moved, renamed or cancelled this occurrence
+                let Some(occurrence) = task.overrides.get(&start_time) else {
+                    return Some(AgendaItem { start_time, end_time, title: task.title.clone(), source: format!("#{}", task.id), task_id: Some(task.id) });
+                };
+                if occurrence.cancelled {
+                    return None;
+                }
+                Some(AgendaItem {
+                    start_time: occurrence.start_time.unwrap_or(start_time),
+                    end_time: occurrence.end_time.unwrap_or(end_time),
+                    title: occurrence.title.clone().unwrap_or_else(|| task.title.clone()),
+                    source: format!("#{}", task.id),
+                    task_id: Some(task.id),
+                })
+            })
+        })
+        .chain(feeds.feeds.iter().filter(|(name, _)| shows(name)).flat_map(|(name, feed)| {
+            feed.events.iter().map(move |event| AgendaItem {
+                start_time: event.start_time,
+                end_time: event.end_time,
+                title: event.title.clone(),
+                source: name.clone(),
+                task_id: None,
+            })
+        }))
+        .chain(calendars.services.iter().filter(|(name, _)| shows(name)).flat_map(|(name, service)| {
+            service.events.iter().filter(|event| !linked(&event.id)).flat_map(move |event| {
+                occurrences(event.start_time, event.end_time, event.frequency_minutes, from, until)
+                    .into_iter()
+                    .map(move |(start_time, end_time)| AgendaItem { start_time, end_time, title: event.title.clone(), source: name.clone(), task_id: None })
+            })
+        }))
+        .filter(|item| item.start_time < until && item.end_time > from)
+        .collect();
+    items.sort_by_key(|item| item.start_time);
+    items
+}
+
+// Note for each service whose cached events are older than `max_age`
+pub fn stale_calendars(calendars: &SyncState, max_age: Duration) -> Vec<String> {
+    calendars
+        .services
+        .iter()
+        .filter(|(name, _)| shows(name))
+        .filter_map(|(name, service)| {
+            let fetched_at = service.events_fetched_at?;
+            (Utc::now() - fetched_at > max_age).then(|| {
+                format!(
+                    "Showing {} events as of {}; run `todo_task sync` to refresh.",
+                    name,
+                    fetched_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                )
+            })
+        })
+        .collect()
+}
+
+// Items under a header per day, warning on days with more estimated work than
+// free time; with `relative`, each shows how far from now it starts. Events
+// are shown in the ANSI color `colors` has for their calendar, if any.
+pub fn render(items: &[AgendaItem], capacity: &[Day], relative: bool, colors: &BTreeMap<String, String>) {
+    let now = Utc::now();
+    let mut current_day = None;
+    for item in items {
+        let start = item.start_time.with_timezone(&Local);
+        let end = item.end_time.with_timezone(&Local);
+        if current_day != Some(start.date_naive()) {
+            current_day = Some(start.date_naive());
+            println!("{}", crate::i18n::format(&start, "%A %Y-%m-%d"));
+            if let Some(day) = capacity.iter().find(|day| day.date == start.date_naive() && day.over()) {
+                println!("  {}", day.warning());
+            }
+        }
+        let when = if relative {
+            crate::relative_time(item.start_time, now)
+        } else {
+            format!("{}-{}", start.format("%H:%M"), end.format("%H:%M"))
+        };
+        match colors.get(&item.source) {
+            Some(color) => println!("  {:<11}  {}{} ({})\x1b[0m", when, color, item.title, item.source),
+            None => println!("  {:<11}  {} ({})", when, item.title, item.source),
+        }
+    }
+}
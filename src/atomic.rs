@@ -0,0 +1,63 @@
+// Files more than one todo_task can be using at once, e.g. `add` run in two
+// terminals while `serve` is up. Files are written beside themselves and
+// renamed over the old one, so nobody ever reads half a file, and one that's
+// read, changed and written back is locked for the time, so two processes
+// can't both start from the same version and lose one's changes. The locks
+// are advisory: they keep todo_task processes apart, not other programs.
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Tells apart the files one process writes at the same time
+static WRITES: AtomicU32 = AtomicU32::new(0);
+
+// A lock on a file, let go when dropped
+pub struct Lock {
+    _file: File,
+}
+
+// Wait for the lock on `path` and take it. It's on a file beside it,
+// "tasks.json.lock" for tasks.json, as `path` itself is replaced by writes.
+pub fn lock(path: &str) -> std::io::Result<Lock> {
+    let file = OpenOptions::new().create(true).truncate(false).write(true).open(format!("{}.lock", path))?;
+    file.lock()?;
+    Ok(Lock { _file: file })
+}
+
+// Replace `path` with `contents` in one step: they're written to a file beside
+// it, flushed to disk and renamed over it. The file keeps its permissions,
+// and a symlink stays one, the file it points to being replaced.
+pub fn write(path: &str, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    replace(path, contents.as_ref(), false)
+}
+
+// Like `write`, for secrets such as tokens: on unix the file, and the one
+// written beside it, can only be read by its owner (mode 0600)
+pub fn write_private(path: &str, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    replace(path, contents.as_ref(), true)
+}
+
+fn replace(path: &str, contents: &[u8], private: bool) -> std::io::Result<()> {
+    let path = std::fs::canonicalize(path).map_or_else(|_| path.to_string(), |path| path.to_string_lossy().into_owned());
+    let temporary = format!("{}.{}-{}.tmp", path, std::process::id(), WRITES.fetch_add(1, Ordering::Relaxed));
+    let written = (|| {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        if private {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&temporary)?;
+        file.write_all(contents)?;
+        if !private && let Ok(metadata) = std::fs::metadata(&path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()?;
+        std::fs::rename(&temporary, &path)
+    })();
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temporary);
+    }
+    written
+}
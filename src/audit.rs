@@ -0,0 +1,197 @@
+// Append-only log of every change to a task: when, by whom, through which
+// command, and the field's value before and after
+use std::io::Write;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::Task;
+use crate::crdt::Change;
+use crate::i18n::t;
+use crate::paths;
+
+pub const AUDIT_PATH: &str = "audit.jsonl";
+
+/// ID of the run changes are logged for, see `begin`; picked on first use
+static RUN: Mutex<String> = Mutex::new(String::new());
+
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+    pub time: DateTime<Utc>,
+    /// User who ran the command, or the device a change came from
+    pub who: String,
+    /// Command line that made the change
+    pub command: String,
+    /// The run of todo_task that made it, e.g. one command; unset in entries
+    /// from before runs were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run: Option<String>,
+    /// UUID of the task
+    pub task: String,
+    /// ID and title of the task at the time
+    pub id: u32,
+    pub title: String,
+    /// Field, "tags/<tag>", "depends_on/<uuid>", "meta/<key>", "created" or "deleted"
+    pub field: String,
+    /// Values of the field; the whole task before "deleted" and after "created"
+    pub before: Value,
+    pub after: Value,
+}
+
+impl Entry {
+    pub fn new(change: Change, who: &str, command: &str, time: DateTime<Utc>) -> Entry {
+        Entry {
+            time,
+            who: who.to_string(),
+            command: command.to_string(),
+            run: Some(run()),
+            task: change.task,
+            id: change.id,
+            title: change.title,
+            field: change.key,
+            before: change.before,
+            after: change.after,
+        }
+    }
+}
+
+impl Entry {
+    // Whether two entries were made together, by one run for one user or
+    // device; older entries go by their time and command instead
+    pub fn same_run(&self, other: &Entry) -> bool {
+        self.who == other.who
+            && match (&self.run, &other.run) {
+                (Some(run), Some(other)) => run == other,
+                (None, None) => self.time == other.time && self.command == other.command,
+                _ => false,
+            }
+    }
+}
+
+// Start a new run: changes logged from now on are one step for undo, however
+// many saves they take. Each process is a run to start with; the server starts
+// one for each sync.
+pub fn begin() {
+    *RUN.lock().unwrap_or_else(|e| e.into_inner()) = crate::new_uuid();
+}
+
+fn run() -> String {
+    let mut run = RUN.lock().unwrap_or_else(|e| e.into_inner());
+    if run.is_empty() {
+        *run = crate::new_uuid();
+    }
+    run.clone()
+}
+
+// The user running this process
+pub fn user() -> String {
+    std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_else(|_| "unknown".to_string())
+}
+
+// This process's command line, without the program
+pub fn command() -> String {
+    let args: Vec<String> = std::env::args()
+        .skip(1)
+        .map(|arg| if arg.is_empty() || arg.contains(' ') { format!("\"{}\"", arg) } else { arg })
+        .collect();
+    args.join(" ")
+}
+
+// Log changes made by `who` with `command`, from the tasks `before` to `after`.
+// Tasks added or removed are logged whole, so undo can bring them back.
+pub fn log(changes: Vec<Change>, before: &[Task], after: &[Task], who: &str, command: &str, time: DateTime<Utc>) -> Result<(), Box<dyn std::error::Error>> {
+    let whole = |tasks: &[Task], uuid: &str| tasks.iter().find(|task| task.uuid == uuid).and_then(|task| serde_json::to_value(task).ok());
+    let mut entries = Vec::new();
+    for mut change in changes {
+        match change.key.as_str() {
+            "created" => change.after = whole(after, &change.task).unwrap_or(change.after),
+            "deleted" => change.before = whole(before, &change.task).unwrap_or(change.before),
+            _ => {}
+        }
+        entries.push(Entry::new(change, who, command, time));
+    }
+    append(entries)
+}
+
+// Add entries, leaving out the time of the last change which every edit moves
+pub fn append(entries: Vec<Entry>) -> Result<(), Box<dyn std::error::Error>> {
+    let entries: Vec<Entry> = entries.into_iter().filter(|entry| entry.field != "touched_at").collect();
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(paths::data(AUDIT_PATH))?;
+    for entry in entries {
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    }
+    Ok(())
+}
+
+pub fn load(path: &str) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(Box::new(e)),
+    };
+    let mut entries = Vec::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        entries.push(serde_json::from_str(line)?);
+    }
+    Ok(entries)
+}
+
+fn show_value(value: &Value) -> String {
+    match value {
+        Value::Null => t!("log-none"),
+        Value::String(text) => match text.parse::<DateTime<Utc>>() {
+            Ok(time) => time.to_string(),
+            Err(_) => format!("'{}'", text),
+        },
+        other => other.to_string(),
+    }
+}
+
+// What an entry changed, in words. Other tasks are named by their current ID, or
+// the last one logged if they were removed.
+fn describe(entry: &Entry, entries: &[Entry], tasks: &[Task]) -> String {
+    let task_id = |uuid: &str| match tasks.iter().find(|task| task.uuid == uuid) {
+        Some(task) => format!("#{}", task.id),
+        None => entries.iter().rev().find(|other| other.task == uuid).map_or_else(|| t!("log-removed-task"), |other| t!("log-removed-id", id = other.id)),
+    };
+    let added = !entry.after.is_null();
+    match entry.field.split_once('/') {
+        _ if entry.field == "created" => t!("log-created"),
+        _ if entry.field == "deleted" => t!("log-deleted"),
+        Some(("tags", tag)) => t!("log-tag", tag = tag, added = added),
+        Some(("depends_on", uuid)) => t!("log-depends-on", task = task_id(uuid), added = added),
+        Some(("meta", key)) => format!("meta {}: {} -> {}", key, show_value(&entry.before), show_value(&entry.after)),
+        _ if entry.field == "parent" => {
+            let parent = |value: &Value| value.as_str().map_or_else(|| t!("log-none"), task_id);
+            format!("parent: {} -> {}", parent(&entry.before), parent(&entry.after))
+        }
+        _ => format!("{}: {} -> {}", entry.field, show_value(&entry.before), show_value(&entry.after)),
+    }
+}
+
+// The last `limit` commands' changes, newest first, of one task (by UUID) or all
+pub fn print(entries: &[Entry], task: Option<&str>, tasks: &[Task], limit: usize) {
+    // Changes made together, by one command
+    let mut groups: Vec<Vec<&Entry>> = Vec::new();
+    for entry in entries.iter().filter(|entry| task.is_none_or(|uuid| entry.task == uuid)) {
+        match groups.last_mut() {
+            Some(group) if group[0].same_run(entry) => group.push(entry),
+            _ => groups.push(vec![entry]),
+        }
+    }
+    if groups.is_empty() {
+        println!("{}", t!("log-empty"));
+    }
+    for group in groups.iter().rev().take(limit) {
+        let first = group[0];
+        println!("{} {}: {}", first.time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"), first.who, first.command);
+        for entry in group {
+            println!("  #{} '{}': {}", entry.id, entry.title, describe(entry, entries, tasks));
+        }
+    }
+}
@@ -0,0 +1,106 @@
+// Time-blocking: open tasks with an estimate but no time placed one after
+// another into the free working hours of a day, most urgent first, and the
+// rest of a day's tasks moved later when the schedule slips
+use std::cmp::Reverse;
+
+use chrono::{DateTime, Duration, DurationRound, Local, NaiveDate, Utc};
+
+use crate::agenda::AgendaItem;
+use crate::config::Config;
+use crate::{capacity, escalation};
+use crate::{Task, TaskStatus};
+
+// Where a task goes
+pub struct Block {
+    pub id: u32,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+}
+
+pub struct Plan {
+    pub blocks: Vec<Block>,
+    /// Tasks left out for lack of a free stretch long enough
+    pub left_over: Vec<u32>,
+}
+
+// Tasks that can be blocked: open and undated, with an estimate, not waiting
+// on someone or on an open dependency, most urgent first
+fn candidates<'a>(config: &Config, tasks: &'a [Task], now: DateTime<Utc>) -> Vec<&'a Task> {
+    let open = |id: &u32| tasks.iter().any(|task| task.id == *id && task.status == TaskStatus::Todo);
+    let mut candidates: Vec<&Task> = tasks
+        .iter()
+        .filter(|task| task.status == TaskStatus::Todo && task.start_time.is_none() && !task.is_recurring)
+        .filter(|task| task.estimate_minutes.is_some_and(|minutes| minutes > 0))
+        .filter(|task| task.waiting.is_none() && !task.depends_on.iter().any(open))
+        .collect();
+    candidates.sort_by_key(|task| (Reverse(escalation::priority(&config.escalation, task, now)), task.id));
+    candidates
+}
+
+// Fill the free working hours of `date` from `now` on, given what's already on
+// the agenda that day, with the candidates in order, each in the first free
+// stretch it fits
+pub fn plan(config: &Config, tasks: &[Task], items: &[AgendaItem], date: NaiveDate, now: DateTime<Utc>) -> Plan {
+    let busy: Vec<&AgendaItem> = items.iter().collect();
+    let mut slots = capacity::free_slots(date, &config.work_hours, &busy, round_up(now));
+    let mut plan = Plan { blocks: Vec::new(), left_over: Vec::new() };
+    for task in candidates(config, tasks, now) {
+        let length = Duration::minutes(task.estimate_minutes.unwrap_or_default());
+        match slots.iter_mut().find(|(start, end)| *end - *start >= length) {
+            Some((start, _)) => {
+                plan.blocks.push(Block { id: task.id, start_time: *start, end_time: *start + length });
+                *start += length;
+            }
+            None => plan.left_over.push(task.id),
+        }
+    }
+    plan.blocks.sort_by_key(|block| block.start_time);
+    plan
+}
+
+// The next round five minutes, where blocks start
+fn round_up(time: DateTime<Utc>) -> DateTime<Utc> {
+    time.duration_trunc(Duration::minutes(5)).map_or(time, |rounded| if rounded < time { rounded + Duration::minutes(5) } else { rounded })
+}
+
+// Move the day's remaining one-off tasks that `from` has overtaken or that now
+// clash with each other later, keeping their order and length, into the free
+// working hours around fixed events. A task whose time is still free keeps
+// it; the blocks are every remaining task's time, moved or not.
+pub fn replan(config: &Config, tasks: &[Task], items: &[AgendaItem], from: DateTime<Utc>) -> Plan {
+    let date = from.with_timezone(&Local).date_naive();
+    let mut remaining: Vec<&Task> = tasks
+        .iter()
+        .filter(|task| task.status == TaskStatus::Todo && !task.is_recurring)
+        .filter(|task| match (task.start_time, task.end_time) {
+            (Some(start), Some(end)) => start.with_timezone(&Local).date_naive() == date && end > from,
+            _ => false,
+        })
+        .collect();
+    remaining.sort_by_key(|task| (task.start_time, task.id));
+    let moving: Vec<u32> = remaining.iter().map(|task| task.id).collect();
+    let mut busy: Vec<AgendaItem> = items.iter().filter(|item| !item.task_id.is_some_and(|id| moving.contains(&id))).cloned().collect();
+    let mut plan = Plan { blocks: Vec::new(), left_over: Vec::new() };
+    let mut cursor = round_up(from);
+    for task in remaining {
+        let (Some(start), Some(end)) = (task.start_time, task.end_time) else { continue };
+        let length = end - start;
+        let clashes = busy.iter().any(|item| item.start_time < end && start < item.end_time);
+        let time = match start >= cursor && !clashes {
+            true => Some(start),
+            false => {
+                let events: Vec<&AgendaItem> = busy.iter().collect();
+                let earliest = cursor.max(start);
+                capacity::free_slots(date, &config.work_hours, &events, earliest).into_iter().find(|(from, until)| *until - *from >= length).map(|(from, _)| from)
+            }
+        };
+        let Some(time) = time else {
+            plan.left_over.push(task.id);
+            continue;
+        };
+        busy.push(AgendaItem { start_time: time, end_time: time + length, title: task.title.clone(), source: format!("#{}", task.id), task_id: Some(task.id) });
+        plan.blocks.push(Block { id: task.id, start_time: time, end_time: time + length });
+        cursor = time + length;
+    }
+    plan
+}
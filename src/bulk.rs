@@ -0,0 +1,146 @@
+// Files of tasks for `add --file`: a list of tasks in YAML, TOML or JSON, each
+// with the fields `add` takes. Each format is read into JSON values first, so
+// every task is checked on its own and all of what's wrong can be reported.
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::Priority;
+
+// One task in the file, named like `add`'s arguments
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Entry {
+    pub title: String,
+    pub details: String,
+    #[serde(alias = "start")]
+    pub start_time: Option<String>,
+    #[serde(alias = "end")]
+    pub end_time: Option<String>,
+    pub recurring: bool,
+    pub frequency_minutes: Option<i64>,
+    pub cron: Option<String>,
+    pub every: Option<String>,
+    pub project: Option<String>,
+    #[serde(alias = "tag")]
+    pub tags: Vec<String>,
+    pub priority: Priority,
+    pub local_only: bool,
+    pub waiting_for: Option<String>,
+    pub depends_on: Vec<u32>,
+    pub parent: Option<u32>,
+    #[serde(alias = "remind")]
+    pub reminders: Vec<i64>,
+    pub color: Option<String>,
+    /// As for `--estimate`, e.g. "1h30m"
+    pub estimate: Option<String>,
+    pub meta: BTreeMap<String, String>,
+    pub assignee: Option<String>,
+    pub force: bool,
+    /// As for `--tz`, e.g. "Asia/Tokyo"
+    #[serde(alias = "tz")]
+    pub timezone: Option<String>,
+}
+
+// The entries of a file, read by its extension, each with what's wrong with
+// it if anything; for stdin ("-") the format is guessed from the text
+pub fn parse(path: &str, text: &str) -> Result<Vec<Result<Entry, String>>, String> {
+    let extension = path.rsplit_once('.').map(|(_, extension)| extension.to_lowercase());
+    let value = match extension.as_deref() {
+        Some("json") => serde_json::from_str(text).map_err(|e| e.to_string())?,
+        Some("toml") => toml(text)?,
+        Some("yaml" | "yml") => yaml(text)?,
+        // YAML reads JSON as well
+        _ if is_toml(text) => toml(text)?,
+        _ => yaml(text)?,
+    };
+    // A list of tasks, or a table with one under "tasks"
+    let list = match value {
+        Value::Array(list) => list,
+        Value::Object(mut table) if table.len() == 1 && table.contains_key("tasks") => match table.remove("tasks") {
+            Some(Value::Array(list)) => list,
+            _ => return Err("\"tasks\" isn't a list".to_string()),
+        },
+        _ => return Err("expected a list of tasks, or a list under \"tasks\"".to_string()),
+    };
+    Ok(list.into_iter().map(|value| serde_json::from_value(value).map_err(|e| e.to_string())).collect())
+}
+
+// Whether text without a file extension is TOML: it has a table header such
+// as `[[tasks]]` or `[tasks.meta]` on a line of its own
+fn is_toml(text: &str) -> bool {
+    text.lines().map(str::trim_end).any(|line| {
+        let name = line.trim_start_matches('[').trim_end_matches(']');
+        line.starts_with('[') && line.ends_with(']') && !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || "_-. ".contains(c))
+    })
+}
+
+fn yaml(text: &str) -> Result<Value, String> {
+    serde_yaml::from_str(text).map_err(|e| e.to_string())
+}
+
+fn toml(text: &str) -> Result<Value, String> {
+    text.parse::<toml::Table>().map(|table| from_toml(toml::Value::Table(table))).map_err(|e| e.to_string())
+}
+
+// A TOML value as JSON, with dates and times written out as text, the way
+// `add` takes them
+fn from_toml(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(text) => Value::String(text),
+        toml::Value::Integer(number) => Value::from(number),
+        toml::Value::Float(number) => Value::from(number),
+        toml::Value::Boolean(flag) => Value::Bool(flag),
+        toml::Value::Datetime(time) => Value::String(time.to_string()),
+        toml::Value::Array(values) => Value::Array(values.into_iter().map(from_toml).collect()),
+        toml::Value::Table(table) => Value::Object(table.into_iter().map(|(key, value)| (key, from_toml(value))).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn titles(path: &str, text: &str) -> Vec<String> {
+        parse(path, text).unwrap().into_iter().map(|entry| entry.unwrap().title).collect()
+    }
+
+    #[test]
+    fn reads_yaml() {
+        let text = "tasks:\n  - title: Pack # for Friday\n    details: |-\n      Passport\n      Chargers\n    tags: [travel, 'a # b']\n    meta: {ticket: \"T-1\"}\n  - {title: \"Book \\u00e9\", remind: [10, 60]}\n";
+        let entries: Vec<Entry> = parse("tasks.yaml", text).unwrap().into_iter().map(Result::unwrap).collect();
+        assert_eq!(entries[0].title, "Pack");
+        assert_eq!(entries[0].details, "Passport\nChargers");
+        assert_eq!(entries[0].tags, ["travel", "a # b"]);
+        assert_eq!(entries[0].meta["ticket"], "T-1");
+        assert_eq!(entries[1].title, "Book é");
+        assert_eq!(entries[1].reminders, [10, 60]);
+    }
+
+    #[test]
+    fn reads_toml() {
+        let text = "[[tasks]]\ntitle = \"Pack\"\ndetails = \"\"\"\nPassport\nChargers\"\"\"\nstart = 2024-07-12T09:00:00\n\n[tasks.meta]\n\"ticket id\" = \"T-1\"\n\n[[tasks]]\ntitle = 'Book'\n";
+        let entries: Vec<Entry> = parse("tasks.toml", text).unwrap().into_iter().map(Result::unwrap).collect();
+        assert_eq!(entries[0].details, "Passport\nChargers");
+        assert_eq!(entries[0].start_time.as_deref(), Some("2024-07-12T09:00:00"));
+        assert_eq!(entries[0].meta["ticket id"], "T-1");
+        assert_eq!(entries[1].title, "Book");
+        assert!(parse("tasks.toml", "[tasks]\ntitle = \"Pack\"\n").is_err());
+    }
+
+    #[test]
+    fn stdin_format_is_guessed() {
+        assert_eq!(titles("-", "[[tasks]]\ntitle = \"Pack\"\n"), ["Pack"]);
+        assert_eq!(titles("-", "[{\"title\": \"Pack\", \"tags\": [\"a\"]}]"), ["Pack"]);
+        assert_eq!(titles("-", "- title: Pack\n  tags:\n    [a]\n"), ["Pack"]);
+    }
+
+    #[test]
+    fn each_task_is_checked() {
+        let entries = parse("tasks.json", "[{\"title\": \"Pack\"}, {\"title\": \"Book\", \"colour\": \"red\"}]").unwrap();
+        assert!(entries[0].is_ok());
+        assert!(entries[1].as_ref().is_err_and(|e| e.contains("colour")));
+        assert!(parse("tasks.json", "{\"title\": \"Pack\"}").is_err());
+    }
+}
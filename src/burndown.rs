@@ -0,0 +1,128 @@
+// Burndown of a project: open and completed one-off tasks at the end of each
+// day, drawn in the terminal or as SVG
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+
+use crate::{Task, TaskStatus};
+
+// Rows of the terminal chart
+const HEIGHT: usize = 10;
+
+pub struct Point {
+    pub date: NaiveDate,
+    pub open: usize,
+    pub done: usize,
+}
+
+fn local_date(time: DateTime<Utc>) -> NaiveDate {
+    time.with_timezone(&Local).date_naive()
+}
+
+// Open and completed tasks of `project` at the end of each day up to `today`, over
+// the last `days` days or since its first task was added if that is later.
+// Recurring tasks are left out since they are never finished.
+pub fn points(tasks: &[Task], project: &str, days: i64, today: NaiveDate) -> Vec<Point> {
+    let tasks: Vec<&Task> = tasks.iter().filter(|task| task.project.as_deref() == Some(project) && !task.is_recurring).collect();
+    let earliest = tasks.iter().filter_map(|task| task.created_at).map(local_date).min().unwrap_or(today);
+    let first = earliest.max(today - Duration::days(days.max(1) - 1));
+    // Tasks from before creation times were kept count from the start, and
+    // completions from before they were recorded count on the last change
+    let created = |task: &Task| task.created_at.map_or(NaiveDate::MIN, local_date);
+    let completed = |task: &Task| match task.status {
+        TaskStatus::Done => Some(task.completed_at.or(task.touched_at).map_or(NaiveDate::MIN, local_date)),
+        _ => None,
+    };
+    first
+        .iter_days()
+        .take_while(|date| *date <= today)
+        .map(|date| {
+            let added = tasks.iter().filter(|task| created(task) <= date);
+            let done = added.clone().filter(|task| completed(task).is_some_and(|completed| completed <= date)).count();
+            Point { date, open: added.count() - done, done }
+        })
+        .collect()
+}
+
+// Bars of open tasks ('#') with the completed ones stacked on top ('.'), so the
+// height of a column is all the work in the project that day
+pub fn ascii(points: &[Point]) -> String {
+    let max = points.iter().map(|point| point.open + point.done).max().unwrap_or(0).max(1);
+    let height = |value: usize| (value * HEIGHT + max / 2) / max;
+    let mut out = String::new();
+    for row in (1..=HEIGHT).rev() {
+        let label = if row == HEIGHT { max.to_string() } else if row == HEIGHT / 2 { (max / 2).to_string() } else { String::new() };
+        out.push_str(&format!("{:>4} |", label));
+        for point in points {
+            out.push_str(if height(point.open) >= row {
+                "##"
+            } else if height(point.open + point.done) >= row {
+                ".."
+            } else {
+                "  "
+            });
+        }
+        let trimmed = out.trim_end_matches(' ').len();
+        out.truncate(trimmed);
+        out.push('\n');
+    }
+    out.push_str(&format!("{:>4} +{}\n", 0, "-".repeat(points.len() * 2)));
+    // A date under the first day of each week
+    let mut dates = String::from("      ");
+    for (index, point) in points.iter().enumerate().step_by(7) {
+        dates.push_str(&" ".repeat((6 + index * 2).saturating_sub(dates.len())));
+        dates.push_str(&point.date.format("%m-%d").to_string());
+    }
+    out.push_str(&dates);
+    out.push('\n');
+    if let Some(last) = points.last() {
+        out.push_str(&format!("# open ({})  . completed ({})\n", last.open, last.done));
+    }
+    out
+}
+
+// The same chart as an SVG image
+pub fn svg(points: &[Point], project: &str) -> String {
+    let (bar, chart_height, left, top) = (16, 200, 40, 30);
+    let max = points.iter().map(|point| point.open + point.done).max().unwrap_or(0).max(1);
+    let scale = |value: usize| value * chart_height / max;
+    let width = left + points.len() * bar + 10;
+    let height = top + chart_height + 30;
+    let escape = |text: &str| text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"sans-serif\" font-size=\"11\">\n",
+        width, height
+    );
+    out.push_str(&format!("  <text x=\"{}\" y=\"18\" font-size=\"14\">Burndown of {}</text>\n", left, escape(project)));
+    out.push_str(&format!("  <text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>\n", left - 6, top + 4, max));
+    out.push_str(&format!("  <text x=\"{}\" y=\"{}\" text-anchor=\"end\">0</text>\n", left - 6, top + chart_height));
+    for (index, point) in points.iter().enumerate() {
+        let x = left + index * bar;
+        let open = scale(point.open);
+        let done = scale(point.open + point.done) - open;
+        let bottom = top + chart_height;
+        out.push_str(&format!(
+            "  <g><title>{}: {} open, {} completed</title>\n    <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#d9534f\"/>\n    <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#cccccc\"/>\n  </g>\n",
+            point.date,
+            point.open,
+            point.done,
+            x,
+            bottom - open,
+            bar - 2,
+            open,
+            x,
+            bottom - open - done,
+            bar - 2,
+            done
+        ));
+        if index % 7 == 0 {
+            out.push_str(&format!("  <text x=\"{}\" y=\"{}\">{}</text>\n", x, bottom + 16, point.date.format("%m-%d")));
+        }
+    }
+    out.push_str(&format!(
+        "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"black\"/>\n</svg>\n",
+        left - 2,
+        top + chart_height,
+        width - 10,
+        top + chart_height
+    ));
+    out
+}
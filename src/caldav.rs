@@ -0,0 +1,270 @@
+// CalDAV (RFC 4791) backend for Nextcloud, Fastmail, iCloud and friends
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hyper::{Method, Request};
+
+use crate::config::CalDavConfig;
+use crate::http::{self, HttpClient};
+use crate::ics;
+use crate::sync::{self, CalendarBackend, RemoteEvent, Rsvp};
+use crate::Task;
+
+// An event resource returned by a REPORT
+struct Resource {
+    href: String,
+    etag: Option<String>,
+    calendar: String,
+}
+
+pub struct CalDavBackend {
+    client: HttpClient,
+    url: String,
+    /// Matched against attendee addresses to find the user's own replies
+    username: String,
+    authorization: String,
+}
+
+impl CalDavBackend {
+    pub fn new(config: &CalDavConfig) -> Result<CalDavBackend, Box<dyn std::error::Error>> {
+        let credentials = format!("{}:{}", config.username, config.password);
+        let mut url = config.url.clone();
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+        Ok(CalDavBackend {
+            client: http::client()?,
+            url,
+            username: config.username.clone(),
+            authorization: format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(credentials)),
+        })
+    }
+
+    // Write the task as <uid>.ics; when creating, never overwrite an existing
+    // resource. `color` overrides the task's own.
+    async fn put_event(&self, uid: &str, task: &Task, create: bool, color: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        let mut event = ics::task_event(task, uid).ok_or("Only dated tasks can be added to a CalDAV calendar")?;
+        event.color = color.or(task.color.as_deref()).map(str::to_string);
+        // The task's own fields ride along with its custom attributes, as on Google
+        event.meta = sync::task_properties(task);
+        let mut request = self
+            .request(Method::PUT, &format!("{}{}.ics", self.url, uid))
+            .header("Content-Type", "text/calendar; charset=utf-8");
+        if create {
+            request = request.header("If-None-Match", "*");
+        }
+        let (status, body) = http::send(&self.client, request.body(ics::write_calendar(&[event]))?).await?;
+        http::check(status, &body, "CalDAV PUT")
+    }
+
+    // Fetch the collection's properties to check the URL and credentials
+    pub async fn check(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let request = self
+            .request(Method::from_bytes(b"PROPFIND")?, &self.url)
+            .header("Depth", "0")
+            .body(String::new())?;
+        let (status, body) = http::send(&self.client, request).await?;
+        http::check(status, &body, "CalDAV PROPFIND")
+    }
+
+    // The user's PARTSTAT on the event, if they are an attendee
+    fn own_partstat<'a>(&self, event: &'a ics::VEvent) -> Option<&'a str> {
+        event.attendees.iter().find_map(|(address, partstat)| {
+            let address = address.strip_prefix("mailto:").unwrap_or(address);
+            address.eq_ignore_ascii_case(&self.username).then_some(partstat.as_str())
+        })
+    }
+
+    // Run a calendar-query REPORT, returning the href, ETag and calendar data of each match
+    async fn report(&self, filter: &str) -> Result<Vec<Resource>, Box<dyn std::error::Error>> {
+        let query = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><d:getetag/><c:calendar-data/></d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR">
+      <c:comp-filter name="VEVENT">
+        {}
+      </c:comp-filter>
+    </c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#,
+            filter
+        );
+        let request = self
+            .request(Method::from_bytes(b"REPORT")?, &self.url)
+            .header("Depth", "1")
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(query)?;
+        let (status, body) = http::send(&self.client, request).await?;
+        http::check(status, &body, "CalDAV REPORT")?;
+        Ok(raw_xml_elements(&body, "response")
+            .into_iter()
+            .filter_map(|response| {
+                Some(Resource {
+                    href: xml_elements(response, "href").pop()?,
+                    etag: xml_elements(response, "getetag").pop(),
+                    calendar: xml_elements(response, "calendar-data").pop()?,
+                })
+            })
+            .collect())
+    }
+
+    fn request(&self, method: Method, url: &str) -> hyper::http::request::Builder {
+        Request::builder()
+            .method(method)
+            .uri(url)
+            .header("Authorization", &self.authorization)
+    }
+}
+
+impl CalendarBackend for CalDavBackend {
+    fn name(&self) -> &'static str {
+        "CalDAV"
+    }
+
+    async fn push(&self, task: &Task) -> Result<String, Box<dyn std::error::Error>> {
+        let uid = format!("todo-task-{}-{}@todo_task", task.id, Utc::now().timestamp());
+        self.put_event(&uid, task, true, None).await?;
+        Ok(uid)
+    }
+
+    async fn update(&self, event_id: &str, task: &Task) -> Result<(), Box<dyn std::error::Error>> {
+        self.put_event(event_id, task, false, None).await
+    }
+
+    async fn set_color(&self, event_id: &str, task: &Task, color: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        self.put_event(event_id, task, false, Some(color.unwrap_or("gray"))).await
+    }
+
+    async fn delete(&self, event_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let request = self
+            .request(Method::DELETE, &format!("{}{}.ics", self.url, event_id))
+            .body(String::new())?;
+        let (status, body) = http::send(&self.client, request).await?;
+        http::check(status, &body, "CalDAV DELETE")
+    }
+
+    async fn pull(&self) -> Result<Vec<RemoteEvent>, Box<dyn std::error::Error>> {
+        let filter = format!(r#"<c:time-range start="{}"/>"#, ics::format_datetime(&Utc::now()));
+        // The collection's name is the last segment of its URL
+        let collection = self.url.trim_end_matches('/').rsplit('/').next().filter(|name| !name.is_empty()).map(str::to_string);
+        let mut events = Vec::new();
+        for resource in self.report(&filter).await? {
+            for event in ics::parse_events(&resource.calendar) {
+                // Occurrences share the series' UID, so they get IDs of their own
+                let occurrence_id = |start: &DateTime<Utc>| format!("{}/{}", event.uid, ics::format_datetime(start));
+                for exdate in &event.exdates {
+                    events.push(RemoteEvent {
+                        id: occurrence_id(exdate),
+                        series_id: Some(event.uid.clone()),
+                        original_start: Some(*exdate),
+                        cancelled: true,
+                        ..Default::default()
+                    });
+                }
+                let (id, series_id) = match &event.recurrence_id {
+                    Some(recurrence_id) => (occurrence_id(recurrence_id), Some(event.uid.clone())),
+                    None => (event.uid.clone(), None),
+                };
+                if let (Some(start_time), Some(end_time)) = (event.start, event.end) {
+                    let own_partstat = self.own_partstat(&event).map(str::to_ascii_uppercase);
+                    events.push(RemoteEvent {
+                        id,
+                        title: event.summary,
+                        details: event.description,
+                        start_time: Some(start_time),
+                        end_time: Some(end_time),
+                        frequency_minutes: event.rrule.as_deref().and_then(ics::rrule_to_minutes),
+                        declined: own_partstat.as_deref() == Some("DECLINED"),
+                        awaiting_reply: own_partstat.as_deref() == Some("NEEDS-ACTION"),
+                        // The calendar's PRODID names the app that wrote it
+                        creator: event.prodid,
+                        calendar: collection.clone(),
+                        etag: resource.etag.clone(),
+                        updated: None,
+                        series_id,
+                        original_start: event.recurrence_id,
+                        cancelled: false,
+                        meta: event.meta,
+                        link: None,
+                    });
+                }
+            }
+        }
+        Ok(events)
+    }
+
+    // Rewrite the user's PARTSTAT in the event resource; the server's scheduling
+    // support passes the reply on to the organizer
+    async fn respond(&self, event_id: &str, reply: Rsvp) -> Result<(), Box<dyn std::error::Error>> {
+        let filter = format!(
+            r#"<c:prop-filter name="UID"><c:text-match collation="i;octet">{}</c:text-match></c:prop-filter>"#,
+            event_id.replace('&', "&amp;").replace('<', "&lt;")
+        );
+        let resource = self
+            .report(&filter)
+            .await?
+            .into_iter()
+            // Servers without filter support return everything
+            .find(|resource| ics::parse_events(&resource.calendar).iter().any(|event| event.uid == event_id))
+            .ok_or("The event wasn't found on the CalDAV server")?;
+        let partstat = match reply {
+            Rsvp::Accept => "ACCEPTED",
+            Rsvp::Decline => "DECLINED",
+            Rsvp::Tentative => "TENTATIVE",
+        };
+        let calendar = ics::set_partstat(&resource.calendar, &self.username, partstat)
+            .ok_or_else(|| format!("{} isn't an attendee of this event", self.username))?;
+        let url = url::Url::parse(&self.url)?.join(&resource.href)?;
+        let mut request = self
+            .request(Method::PUT, url.as_str())
+            .header("Content-Type", "text/calendar; charset=utf-8");
+        // Don't overwrite an edit made since the event was read
+        if let Some(etag) = &resource.etag {
+            request = request.header("If-Match", etag);
+        }
+        let request = request.body(calendar)?;
+        let (status, body) = http::send(&self.client, request).await?;
+        http::check(status, &body, "CalDAV PUT")
+    }
+}
+
+// Text content of every element with the given local name, whatever its namespace prefix
+fn xml_elements(xml: &str, local_name: &str) -> Vec<String> {
+    raw_xml_elements(xml, local_name).into_iter().map(xml_unescape).collect()
+}
+
+// Like xml_elements, but without unescaping, so nested elements can be searched again
+fn raw_xml_elements<'a>(xml: &'a str, local_name: &str) -> Vec<&'a str> {
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find('>') else { break };
+        let tag = &rest[..end];
+        let name = tag.split_whitespace().next().unwrap_or("");
+        let local = name.rsplit(':').next().unwrap_or(name);
+        rest = &rest[end + 1..];
+        if local != local_name || name.starts_with('/') || tag.ends_with('/') {
+            continue;
+        }
+        let Some(close) = rest.find(&format!("</{}>", name)) else { break };
+        found.push(&rest[..close]);
+        rest = &rest[close..];
+    }
+    found
+}
+
+fn xml_unescape(text: &str) -> String {
+    let text = text.trim();
+    if let Some(cdata) = text.strip_prefix("<![CDATA[").and_then(|t| t.strip_suffix("]]>")) {
+        return cdata.to_string();
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&#xD;", "\r")
+        .replace("&amp;", "&")
+}
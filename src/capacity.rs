@@ -0,0 +1,179 @@
+// Capacity: estimated work on open tasks against the free time calendar
+// events leave in each day's working hours
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+
+use crate::agenda::AgendaItem;
+use crate::config::WorkHours;
+use crate::Task;
+use crate::i18n::t;
+
+pub struct Day {
+    pub date: NaiveDate,
+    /// Working hours not taken by calendar events
+    pub free: Duration,
+    /// Estimates of the task occurrences on the day
+    pub estimated: Duration,
+}
+
+impl Day {
+    pub fn over(&self) -> bool {
+        self.estimated > self.free
+    }
+
+    // "! 6h 30m of estimated work, 4h free"
+    pub fn warning(&self) -> String {
+        format!("! {}", t!("capacity-warning", estimated = format_length(self.estimated), free = format_length(self.free)))
+    }
+}
+
+// "2h", "90m", "1h30m", "1.5h" or a plain number of minutes
+pub fn parse_estimate(text: &str) -> Result<i64, String> {
+    let invalid = || format!("'{}' is not a length of time; use e.g. 2h, 90m or 1h30m", text);
+    let text = text.trim().to_lowercase();
+    if let Ok(minutes) = text.parse::<i64>() {
+        return Some(minutes).filter(|minutes| *minutes > 0).ok_or_else(invalid);
+    }
+    let mut minutes = 0.0;
+    let mut number = String::new();
+    for c in text.chars() {
+        match c {
+            '0'..='9' | '.' => number.push(c),
+            'h' | 'm' if !number.is_empty() => {
+                let value: f64 = number.parse().map_err(|_| invalid())?;
+                minutes += if c == 'h' { value * 60.0 } else { value };
+                number.clear();
+            }
+            ' ' => {}
+            _ => return Err(invalid()),
+        }
+    }
+    if !number.is_empty() || minutes < 1.0 {
+        return Err(invalid());
+    }
+    Ok(minutes.round() as i64)
+}
+
+// "2h 30m", "45m", "0m"
+pub fn format_length(length: Duration) -> String {
+    match (length.num_hours(), length.num_minutes() % 60) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
+
+fn local(date: NaiveDate, time: NaiveTime) -> Option<DateTime<Utc>> {
+    Local.from_local_datetime(&date.and_time(time)).earliest().map(|time| time.with_timezone(&Utc))
+}
+
+// Stretches of the working hours on `date` from `from` on that no event
+// covers, earliest first
+pub fn free_slots(date: NaiveDate, hours: &WorkHours, events: &[&AgendaItem], from: DateTime<Utc>) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    if !hours.days.contains(&date.weekday()) {
+        return Vec::new();
+    }
+    let (Some(start), Some(end)) = (local(date, hours.start), local(date, hours.end)) else { return Vec::new() };
+    let start = start.max(from);
+    let mut busy: Vec<(DateTime<Utc>, DateTime<Utc>)> = events
+        .iter()
+        .map(|event| (event.start_time.max(start), event.end_time.min(end)))
+        .filter(|(from, until)| from < until)
+        .collect();
+    busy.sort();
+    // Overlapping events only count once
+    let mut slots = Vec::new();
+    let mut covered_until = start;
+    for (from, until) in busy {
+        if from > covered_until {
+            slots.push((covered_until, from));
+        }
+        covered_until = covered_until.max(until);
+    }
+    if end > covered_until {
+        slots.push((covered_until, end));
+    }
+    slots
+}
+
+// Working hours on `date` not covered by any event
+fn free_time(date: NaiveDate, hours: &WorkHours, events: &[&AgendaItem]) -> Duration {
+    free_slots(date, hours, events, DateTime::<Utc>::MIN_UTC).into_iter().map(|(from, until)| until - from).fold(Duration::zero(), |total, length| total + length)
+}
+
+// Capacity of each of the `count` days from `first`, given the agenda items
+// of those days
+pub fn days(tasks: &[Task], items: &[AgendaItem], hours: &WorkHours, first: NaiveDate, count: i64) -> Vec<Day> {
+    let mut estimated: BTreeMap<NaiveDate, Duration> = BTreeMap::new();
+    let mut events: BTreeMap<NaiveDate, Vec<&AgendaItem>> = BTreeMap::new();
+    for item in items {
+        let date = item.start_time.with_timezone(&Local).date_naive();
+        match item.task_id {
+            Some(id) => {
+                let estimate = tasks.iter().find(|task| task.id == id).and_then(|task| task.estimate_minutes);
+                *estimated.entry(date).or_insert_with(Duration::zero) += Duration::minutes(estimate.unwrap_or(0));
+            }
+            // Events can run into the next day
+            None => {
+                let last = item.end_time.with_timezone(&Local).date_naive();
+                for date in date.iter_days().take_while(|date| *date <= last) {
+                    events.entry(date).or_default().push(item);
+                }
+            }
+        }
+    }
+    first
+        .iter_days()
+        .take(count.max(0) as usize)
+        .map(|date| Day {
+            date,
+            free: free_time(date, hours, events.get(&date).map_or(&[], Vec::as_slice)),
+            estimated: estimated.get(&date).copied().unwrap_or_else(Duration::zero),
+        })
+        .collect()
+}
+
+pub fn print(days: &[Day], tasks: &[Task]) {
+    println!("{:<16} {:>10} {:>10}", "Day", "Estimated", "Free");
+    for day in days {
+        let over = if day.over() { format!("  {}", t!("capacity-over", length = format_length(day.estimated - day.free))) } else { String::new() };
+        println!("{:<16} {:>10} {:>10}{}", day.date.format("%a %Y-%m-%d"), format_length(day.estimated), format_length(day.free), over);
+    }
+    let unscheduled: Vec<i64> = tasks
+        .iter()
+        .filter(|task| task.status == crate::TaskStatus::Todo && task.start_time.is_none())
+        .filter_map(|task| task.estimate_minutes)
+        .collect();
+    if !unscheduled.is_empty() {
+        println!(
+            "{}",
+            t!("capacity-unscheduled", length = format_length(Duration::minutes(unscheduled.iter().sum())), count = unscheduled.len())
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_take_hours_and_minutes() {
+        assert_eq!(parse_estimate("2h"), Ok(120));
+        assert_eq!(parse_estimate("90m"), Ok(90));
+        assert_eq!(parse_estimate("1h 30m"), Ok(90));
+        assert_eq!(parse_estimate("1.5H"), Ok(90));
+        assert_eq!(parse_estimate("45"), Ok(45));
+        for text in ["", "0", "-5", "2d", "h", "1h30"] {
+            assert!(parse_estimate(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn lengths_are_written_in_hours_and_minutes() {
+        assert_eq!(format_length(Duration::minutes(0)), "0m");
+        assert_eq!(format_length(Duration::minutes(45)), "45m");
+        assert_eq!(format_length(Duration::minutes(120)), "2h");
+        assert_eq!(format_length(Duration::minutes(150)), "2h 30m");
+    }
+}
@@ -0,0 +1,36 @@
+// The system clipboard's text, read through the platform's own tools:
+// pbpaste on macOS, PowerShell on Windows, and wl-paste, xclip or xsel,
+// whichever is installed, elsewhere
+use std::process::Command;
+
+// Tools to try in order, with their arguments
+#[cfg(target_os = "macos")]
+const TOOLS: &[(&str, &[&str])] = &[("pbpaste", &[])];
+
+#[cfg(windows)]
+const TOOLS: &[(&str, &[&str])] = &[("powershell", &["-NoProfile", "-NonInteractive", "-Command", "Get-Clipboard -Raw"])];
+
+#[cfg(not(any(windows, target_os = "macos")))]
+const TOOLS: &[(&str, &[&str])] = &[
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-out"]),
+    ("xsel", &["--clipboard", "--output"]),
+];
+
+// Text on the clipboard, from the first tool that's installed
+pub fn read() -> Result<String, Box<dyn std::error::Error>> {
+    for (tool, args) in TOOLS {
+        let output = match Command::new(tool).args(*args).output() {
+            Ok(output) => output,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(format!("can't run {}: {}", tool, e).into()),
+        };
+        if !output.status.success() {
+            let message = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("{} exited with {}: {}", tool, output.status, message.trim()).into());
+        }
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+    }
+    let tools: Vec<&str> = TOOLS.iter().map(|(tool, _)| *tool).collect();
+    Err(format!("no clipboard tool found; install one of {}", tools.join(", ")).into())
+}
@@ -0,0 +1,635 @@
+use std::collections::BTreeMap;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::Priority;
+use crate::{atomic, paths};
+
+// File the user configuration is read from
+pub const CONFIG_PATH: &str = "config.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    #[default]
+    Google,
+    Caldav,
+    Outlook,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+    /// Calendar service tasks are synced with
+    pub backend: BackendKind,
+    /// ID of the Google calendar tasks are synced with, as set by `calendars
+    /// use`; "primary" is the account's primary one. Unset until the first
+    /// sync asks, and the primary one till then.
+    pub calendar_id: Option<String>,
+    pub caldav: Option<CalDavConfig>,
+    pub outlook: Option<GraphConfig>,
+    /// Sync undated tasks to Google Tasks when set
+    pub google_tasks: Option<GoogleTasksConfig>,
+    pub todoist: Option<TodoistConfig>,
+    pub github: Option<GitHubConfig>,
+    pub jira: Option<JiraConfig>,
+    pub trello: Option<TrelloConfig>,
+    /// Read-only calendars shown in the agenda
+    pub ics_feeds: Vec<FeedConfig>,
+    /// More calendars, on other accounts or services, whose events `sync`
+    /// fetches into the agenda and free time without syncing tasks with them
+    pub calendars: Vec<CalendarConfig>,
+    /// Secret path segment of the feed served by `serve --ics`, generated on first use
+    pub serve_token: Option<String>,
+    /// Public HTTPS URL reaching `serve` at /google/notify, e.g. through a
+    /// reverse proxy; the server has Google Calendar call it on every change
+    pub webhook_url: Option<String>,
+    /// Broker task and reminder events are published to
+    pub mqtt: Option<MqttConfig>,
+    /// Channels reminders go out on; printed and shown on the desktop when empty
+    pub notifiers: Vec<NotifierConfig>,
+    /// Channels, and when they remind, for tasks with some tags or in some
+    /// projects; the first route a task takes applies
+    pub notification_routes: Vec<NotificationRoute>,
+    /// Which tasks are pushed and which events are pulled
+    pub sync: SyncRules,
+    /// Days between reminders to follow up on a task waiting on someone; 3 when unset
+    pub follow_up_days: Option<i64>,
+    /// Raise the priority of one-off tasks, and remind more often, as they come due
+    pub escalation: Vec<EscalationRule>,
+    /// Filter expressions saved with `view save`, by name
+    pub views: BTreeMap<String, String>,
+    /// View `list` shows when given no filter
+    pub default_view: Option<String>,
+    /// Contexts to switch between with `context`, by name
+    pub contexts: BTreeMap<String, ContextConfig>,
+    /// Defaults for new tasks in a project, by project name
+    pub projects: BTreeMap<String, ProjectConfig>,
+    /// Hours estimated work is planned into, for `capacity` and the agenda
+    pub work_hours: WorkHours,
+    /// Day weeks start on in week views and repeats every few weeks, e.g.
+    /// "Sun"; Monday when unset
+    pub week_start: Option<Weekday>,
+    /// Numbers week views give weeks; none when unset
+    pub week_numbers: Option<WeekNumbers>,
+    /// Where OAuth tokens are kept
+    pub token_store: TokenStore,
+    /// Google OAuth client file, relative to the config folder; credentials.json when unset
+    pub credentials: Option<String>,
+    /// Repository `sync --git` keeps the task store in
+    pub git: Option<GitConfig>,
+    /// Email addresses of the people tasks are assigned to, by name
+    pub people: BTreeMap<String, String>,
+    /// Sender of `digest --send` emails; sendmail's default when unset
+    pub mail_from: Option<String>,
+    /// Ask before deleting tasks, events or profiles on a terminal; true when
+    /// unset, false never asks, like `--yes`
+    pub confirm: Option<bool>,
+    /// Print only results, warnings and errors, like `--quiet`
+    pub quiet: Option<bool>,
+    /// Write terminal hyperlinks; by default only when output goes to a terminal
+    pub hyperlinks: Option<bool>,
+    /// strftime format times are shown in, in local time, e.g. "%a %d %b %H:%M";
+    /// UTC as "2024-12-31 15:00:00 UTC" when unset
+    pub time_format: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenStore {
+    /// Secret Service, macOS Keychain or Windows Credential Manager
+    #[default]
+    Keyring,
+    /// token_store.json and graph_token.json in plain text
+    File,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WeekNumbers {
+    /// ISO 8601 weeks, from Monday, week 1 holding the first Thursday of the year
+    Iso,
+    /// Weeks from Sunday, week 1 holding 1 January
+    Us,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct WorkHours {
+    /// Local times such as "09:00"
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    /// Working days, e.g. ["Mon", "Tue"]
+    pub days: Vec<Weekday>,
+}
+
+impl Default for WorkHours {
+    fn default() -> Self {
+        WorkHours {
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap_or_default(),
+            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap_or_default(),
+            days: vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri],
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ProjectConfig {
+    /// Tags added to every new task in the project
+    pub tags: Vec<String>,
+    /// Minutes before the start to remind at, for tasks added without `--remind`
+    pub reminders: Vec<i64>,
+    /// Event color for tasks added without `--color`, as for `add --color`
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ContextConfig {
+    /// Filter expression applied to `list`, `view` and `menu` on top of their own
+    pub filter: Option<String>,
+    /// Project and tags new tasks get unless given their own project
+    pub project: Option<String>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct NotifierConfig {
+    /// "stdout", "desktop", "email", "slack" or "webhook"; any other type runs
+    /// the program "todo_task-notify-<type>"
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// What routes call the channel; its type when unset
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Only reminders of tasks matching this filter go to the channel
+    #[serde(default)]
+    pub filter: Option<String>,
+    /// The channel's own settings, e.g. "to" for email or "url" for a webhook
+    #[serde(flatten)]
+    pub settings: serde_json::Map<String, Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct NotificationRoute {
+    /// Tasks with any of these tags, or in any of these projects, take the route
+    pub tags: Vec<String>,
+    pub projects: Vec<String>,
+    pub to: Vec<RouteTarget>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RouteTarget {
+    /// Name of the channel, as in "notifiers"
+    pub notifier: String,
+    /// Minutes before the start to remind at, 0 for right at the start; the
+    /// task's own reminders when unset
+    #[serde(default)]
+    pub remind: Option<Vec<i64>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct EscalationRule {
+    /// Applies from this many hours before the task starts, and after it is overdue
+    pub within_hours: i64,
+    /// Priority the task has at least while the rule applies
+    pub priority: Option<Priority>,
+    /// Remind every this many minutes while the rule applies, until the task starts
+    pub remind_every_minutes: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SyncRules {
+    /// Only push tasks with one of these tags or projects; everything is pushed when both are empty
+    pub push_tags: Vec<String>,
+    pub push_projects: Vec<String>,
+    /// Only add events whose title matches one of these regular expressions; all when empty
+    pub pull_titles: Vec<String>,
+    /// Events that are never added as tasks
+    pub ignore: IgnoreRules,
+    /// Project and tags for tasks created from matching events
+    pub rules: Vec<MappingRule>,
+    /// Requests allowed per calendar service and day; unlimited when unset
+    pub daily_request_limit: Option<u64>,
+    /// Events pushed at once by `sync` and `add --file` (batches of them for
+    /// Google); 5 when unset
+    pub push_concurrency: Option<usize>,
+    /// Minutes between the syncs `serve` runs in the background, unless
+    /// `--sync-minutes` says otherwise
+    pub every_minutes: Option<u64>,
+    /// Most minutes each background sync is put off at random, so devices
+    /// don't all sync at once; a tenth of the interval when unset
+    pub jitter_minutes: Option<u64>,
+    /// What happens to the calendar event of a completed task
+    pub completed: CompletedEvents,
+    /// Title prefix for "prefix"; "✔ " when unset
+    pub completed_prefix: Option<String>,
+    /// Color for "color": a Google color ID ("8" when unset), a CSS color name
+    /// for CalDAV ("gray") or an Outlook category ("Completed")
+    pub completed_color: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CompletedEvents {
+    /// Leave the event as it is
+    #[default]
+    Keep,
+    Prefix,
+    Color,
+    Delete,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct MappingRule {
+    /// Name of the calendar the event comes from, compared case-insensitively
+    pub calendar: Option<String>,
+    /// Regular expression matched against the event title
+    pub title: Option<String>,
+    pub project: Option<String>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct IgnoreRules {
+    /// Exact titles, compared case-insensitively
+    pub titles: Vec<String>,
+    /// Regular expressions matched against the title
+    pub title_patterns: Vec<String>,
+    /// Skip invitations you declined
+    pub declined: bool,
+    /// Regular expressions matched against the creating app or account
+    pub creators: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CalDavConfig {
+    /// Collection URL, e.g. "https://cloud.example.com/remote.php/dav/calendars/me/personal/"
+    pub url: String,
+    pub username: String,
+    /// Password or app-specific password
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GraphConfig {
+    /// Application (client) ID of an Azure app registration with public client flows enabled
+    pub client_id: String,
+    /// "common", "consumers", "organizations" or a tenant ID
+    #[serde(default = "default_tenant")]
+    pub tenant: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GoogleTasksConfig {
+    /// Task list ID; "@default" is the user's default list
+    #[serde(default = "default_tasklist")]
+    pub tasklist: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TodoistConfig {
+    /// API token from Todoist's Settings > Integrations > Developer
+    pub token: String,
+    /// Also pull from Todoist and push completions back on every `sync`
+    #[serde(default)]
+    pub sync: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitHubConfig {
+    /// Personal access token with access to the repositories' issues
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitConfig {
+    /// URL or path of the repository shared by your devices
+    pub remote: String,
+    #[serde(default = "default_git_branch")]
+    pub branch: String,
+    /// Local clone the store is committed in, relative to the data folder
+    #[serde(default = "default_git_dir")]
+    pub dir: String,
+}
+
+fn default_git_branch() -> String {
+    "main".to_string()
+}
+
+fn default_git_dir() -> String {
+    "git_store".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JiraConfig {
+    /// Site URL, e.g. "https://example.atlassian.net"
+    pub url: String,
+    pub email: String,
+    /// API token from id.atlassian.com
+    pub api_token: String,
+    /// Issues to pull into tasks
+    #[serde(default = "default_jql")]
+    pub jql: String,
+    /// Transition to apply when the task is completed, e.g. "Done"
+    pub done_transition: Option<String>,
+    /// Comment to post when the task is completed
+    pub done_comment: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrelloConfig {
+    /// API key and token from trello.com/power-ups/admin
+    pub key: String,
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeedConfig {
+    pub name: String,
+    /// http(s):// or webcal:// URL of the .ics file
+    pub url: String,
+    /// Minimum time between downloads during `sync`
+    #[serde(default = "default_refresh_minutes")]
+    pub refresh_minutes: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CalendarConfig {
+    /// Name the agenda shows its events under and `--calendar` picks it by
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: BackendKind,
+    /// Google calendar ID; "primary" when unset
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Collection and sign-in of a "caldav" calendar
+    #[serde(default)]
+    pub caldav: Option<CalDavConfig>,
+    /// App registration of an "outlook" calendar
+    #[serde(default)]
+    pub outlook: Option<GraphConfig>,
+    /// Color its events are shown in: a name such as "blue" or "#4285f4"
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Merged into every command unless left out with `--without`; with
+    /// false only when named with `--calendar`
+    #[serde(default = "default_include")]
+    pub include: bool,
+}
+
+fn default_include() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MqttConfig {
+    pub host: String,
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    #[serde(default = "default_mqtt_client_id")]
+    pub client_id: String,
+    /// Events are published on "<topic_prefix>/<event>"
+    #[serde(default = "default_mqtt_topic_prefix")]
+    pub topic_prefix: String,
+}
+
+fn default_mqtt_port() -> u16 {
+    1883
+}
+
+fn default_mqtt_client_id() -> String {
+    "todo_task".to_string()
+}
+
+fn default_mqtt_topic_prefix() -> String {
+    "todo_task".to_string()
+}
+
+fn default_refresh_minutes() -> i64 {
+    60
+}
+
+fn default_jql() -> String {
+    "assignee = currentUser() AND statusCategory != Done".to_string()
+}
+
+fn default_tasklist() -> String {
+    "@default".to_string()
+}
+
+fn default_tenant() -> String {
+    "common".to_string()
+}
+
+// Lay `over` on top of `base`, field by field in objects
+fn merge(base: &mut Value, over: Value) {
+    match (base, over) {
+        (Value::Object(base), Value::Object(over)) => {
+            for (key, value) in over {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, over) => *base = over,
+    }
+}
+
+// What of `value` differs from `base`, field by field in objects
+fn diff(value: Value, base: &Value) -> Option<Value> {
+    if &value == base {
+        return None;
+    }
+    match (value, base) {
+        (Value::Object(value), Value::Object(base)) => Some(Value::Object(
+            value
+                .into_iter()
+                .filter_map(|(key, value)| match base.get(&key) {
+                    Some(base) => diff(value, base).map(|value| (key, value)),
+                    None => Some((key, value)),
+                })
+                .collect(),
+        )),
+        (value, _) => Some(value),
+    }
+}
+
+// Prefix of the environment variables settings can be given in
+const ENV_PREFIX: &str = "TODO_";
+
+// TODO_* variables that aren't settings
+const NOT_SETTINGS: [&str; 2] = ["TODO_HOME", "TODO_PROFILE"];
+
+// A setting given in the environment: TODO_ and its path in config.json in
+// capitals, with "__" between levels, e.g. TODO_SYNC__DAILY_REQUEST_LIMIT for
+// "daily_request_limit" in "sync"
+struct EnvSetting {
+    name: String,
+    path: Vec<String>,
+    value: String,
+}
+
+// The settings in the environment, by name. Variables that don't name a
+// setting are left alone, since other tools use TODO_* names too.
+fn env_settings() -> Vec<EnvSetting> {
+    let known = match serde_json::to_value(Config::default()) {
+        Ok(Value::Object(known)) => known,
+        _ => return Vec::new(),
+    };
+    let mut settings: Vec<EnvSetting> = std::env::vars()
+        .filter(|(name, _)| !NOT_SETTINGS.contains(&name.as_str()))
+        .filter_map(|(name, value)| {
+            let path: Vec<String> = name.strip_prefix(ENV_PREFIX)?.split("__").map(|part| part.to_lowercase()).collect();
+            known.contains_key(&path[0]).then_some(EnvSetting { name, path, value })
+        })
+        .collect();
+    settings.sort_by(|a, b| a.name.cmp(&b.name));
+    settings
+}
+
+// Names of the TODO_* variables overriding settings, for `doctor`
+pub fn env_names() -> Vec<String> {
+    env_settings().into_iter().map(|setting| setting.name).collect()
+}
+
+// The value at `path`, if there is one
+fn lookup<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, part| value.get(part))
+}
+
+// The object holding the value at `path`, made where missing, and the key
+// within it
+fn parent<'a, 'b>(value: &'a mut Value, path: &'b [String]) -> Option<(&'a mut serde_json::Map<String, Value>, &'b String)> {
+    let (last, parents) = path.split_last()?;
+    let mut value = value;
+    for part in parents {
+        if !value.is_object() {
+            *value = Value::Object(Default::default());
+        }
+        value = value.as_object_mut()?.entry(part.clone()).or_insert(Value::Null);
+    }
+    if !value.is_object() {
+        *value = Value::Object(Default::default());
+    }
+    Some((value.as_object_mut()?, last))
+}
+
+// Lay a setting from the environment over `config`. The value is taken as
+// text where the setting is text, as a comma-separated list where it's a list
+// (unless written as a JSON array), and as JSON otherwise, e.g. 500, true or
+// {"url": ...}.
+fn apply(config: &mut Value, setting: &EnvSetting) {
+    if let Some((object, key)) = parent(config, &setting.path) {
+        let text = Value::String(setting.value.clone());
+        let value = match object.get(key) {
+            Some(Value::String(_)) => text,
+            Some(Value::Array(_)) => match serde_json::from_str(&setting.value) {
+                Ok(Value::Array(items)) => Value::Array(items),
+                _ => Value::Array(
+                    setting.value.split(',').map(str::trim).filter(|item| !item.is_empty()).map(|item| Value::String(item.to_string())).collect(),
+                ),
+            },
+            _ => serde_json::from_str(&setting.value).unwrap_or(text),
+        };
+        object.insert(key.clone(), value);
+    }
+}
+
+impl Config {
+    // The settings in the files, with defaults filled in. A profile's
+    // config.json only holds what it changes; the rest comes from the main one.
+    fn from_files(path: &str) -> Result<Value, Box<dyn std::error::Error>> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => Some(contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(Box::new(e)),
+        };
+        let config: Config = match paths::main_config(CONFIG_PATH).filter(|main| main != path) {
+            Some(main) => {
+                let mut config = Config::from_files(&main)?;
+                if let Some(contents) = contents {
+                    merge(&mut config, serde_json::from_str(&contents)?);
+                }
+                serde_json::from_value(config)?
+            }
+            None => match contents {
+                Some(contents) => serde_json::from_str(&contents)?,
+                None => Config::default(),
+            },
+        };
+        Ok(serde_json::to_value(config)?)
+    }
+
+    // Load the config, falling back to defaults if the file doesn't exist yet,
+    // with the TODO_* environment variables taking precedence over the files
+    pub fn load(path: &str) -> Result<Config, Box<dyn std::error::Error>> {
+        let files = Config::from_files(path)?;
+        let settings = env_settings();
+        let mut config = files.clone();
+        for setting in &settings {
+            apply(&mut config, setting);
+        }
+        let config: Config = serde_json::from_value(config).map_err(|e| -> Box<dyn std::error::Error> {
+            // Name the variable at fault: the one the others are fine without
+            let wrong = settings.iter().find(|wrong| {
+                let mut config = files.clone();
+                for setting in settings.iter().filter(|setting| setting.name != wrong.name) {
+                    apply(&mut config, setting);
+                }
+                serde_json::from_value::<Config>(config).is_ok()
+            });
+            match wrong {
+                Some(setting) => format!("{} is invalid: {}", setting.name, e).into(),
+                None => e.into(),
+            }
+        })?;
+        if let Some(format) = &config.time_format
+            && StrftimeItems::new(format).any(|item| item == Item::Error)
+        {
+            return Err(format!("time_format '{}' is not a valid strftime format", format).into());
+        }
+        for (index, calendar) in config.calendars.iter().enumerate() {
+            if calendar.name.trim().is_empty() || config.calendars[..index].iter().any(|other| other.name.eq_ignore_ascii_case(&calendar.name)) {
+                return Err(format!("calendars need a name of their own; '{}' is empty or taken", calendar.name).into());
+            }
+            if let Some(color) = calendar.color.as_ref().filter(|color| crate::agenda::color_code(color).is_none()) {
+                return Err(format!("color '{}' of calendar '{}' is neither a color name such as \"blue\" nor \"#rrggbb\"", color, calendar.name).into());
+            }
+        }
+        Ok(config)
+    }
+
+    // Save the config, leaving out what came from the environment
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut config = serde_json::to_value(self)?;
+        let settings = env_settings();
+        if !settings.is_empty() {
+            let files = Config::from_files(path)?;
+            for setting in settings {
+                let saved = lookup(&files, &setting.path).cloned();
+                if let Some((object, key)) = parent(&mut config, &setting.path) {
+                    match saved {
+                        Some(saved) => object.insert(key.clone(), saved),
+                        None => object.remove(key),
+                    };
+                }
+            }
+        }
+        if let Some(main) = paths::main_config(CONFIG_PATH).filter(|main| main != path) {
+            let main = Config::from_files(&main)?;
+            config = diff(config, &main).unwrap_or_else(|| Value::Object(Default::default()));
+        }
+        atomic::write(path, serde_json::to_string_pretty(&config)?)?;
+        Ok(())
+    }
+}
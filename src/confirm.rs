@@ -0,0 +1,23 @@
+// Confirmation before commands that delete things. Asked only when there's
+// someone at a terminal to answer; `--yes` or "confirm": false in the config
+// turn it off for scripts that do have one.
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::conflicts::read_answer;
+
+/// Set by `--yes` or the config
+static SKIP: AtomicBool = AtomicBool::new(false);
+
+pub fn skip(skip: bool) {
+    SKIP.store(skip, Ordering::SeqCst);
+}
+
+// Whether to go ahead: yes without asking unless stdin and stdout are a
+// terminal, otherwise only if the answer is yes
+pub fn ask(question: &str) -> bool {
+    if SKIP.load(Ordering::SeqCst) || !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        return true;
+    }
+    read_answer(&format!("{} [y/N]", question)).is_some_and(|answer| matches!(answer.to_lowercase().as_str(), "y" | "yes"))
+}
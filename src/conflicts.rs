@@ -0,0 +1,187 @@
+// Interactive resolution of sync conflicts (`sync --interactive`)
+use std::io::Write;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::t;
+use crate::recurrence;
+use crate::sync::{CalendarBackend, Conflict, ServiceState, Snapshot};
+use crate::{format_time, AppState};
+
+// How a conflict was settled, kept in the service's sync state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Resolution {
+    pub task_id: u32,
+    pub event_id: String,
+    /// "local", "remote", or the side picked per field, e.g. "title=local, time=remote"
+    pub choice: String,
+    pub resolved: Snapshot,
+    pub resolved_at: DateTime<Utc>,
+}
+
+enum Side {
+    Local,
+    Remote,
+}
+
+fn repeat_text(frequency_minutes: Option<i64>) -> String {
+    frequency_minutes.map_or_else(|| t!("recur-none"), recurrence::describe_minutes)
+}
+
+fn time_text(snapshot: &Snapshot) -> String {
+    format!("{} - {}", format_time(snapshot.start_time), format_time(snapshot.end_time))
+}
+
+// The fields that differ, as (name, local, remote). Start and end are one field
+// so picking them separately can't produce an event that ends before it starts.
+fn differences(conflict: &Conflict) -> Vec<(&'static str, String, String)> {
+    let (local, remote) = (&conflict.local, &conflict.remote);
+    let fields = [
+        ("title", local.title.clone(), remote.title.clone()),
+        ("details", local.details.clone(), remote.details.clone()),
+        ("time", time_text(local), time_text(remote)),
+        ("repeat", repeat_text(local.frequency_minutes), repeat_text(remote.frequency_minutes)),
+    ];
+    fields.into_iter().filter(|(_, local, remote)| local != remote).collect()
+}
+
+// Ask a question on the terminal; None at the end of input
+pub fn read_answer(question: &str) -> Option<String> {
+    print!("{} ", question);
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).unwrap_or(0) == 0 {
+        return None;
+    }
+    Some(answer.trim().to_string())
+}
+
+pub fn prompt(question: &str) -> String {
+    // Treat end of input as skipping everything left
+    read_answer(question).map_or_else(|| "q".to_string(), |answer| answer.to_lowercase())
+}
+
+fn pick_side(field: &str) -> Option<Side> {
+    loop {
+        match prompt(&format!("  {}", t!("conflicts-pick-side", field = field))).as_str() {
+            "l" | "local" => return Some(Side::Local),
+            "r" | "remote" => return Some(Side::Remote),
+            "q" => return None,
+            _ => {}
+        }
+    }
+}
+
+fn merge(conflict: &Conflict, field: &str, side: &Side, merged: &mut Snapshot) {
+    let source = match side {
+        Side::Local => &conflict.local,
+        Side::Remote => &conflict.remote,
+    };
+    match field {
+        "title" => merged.title = source.title.clone(),
+        "details" => merged.details = source.details.clone(),
+        "time" => {
+            merged.start_time = source.start_time;
+            merged.end_time = source.end_time;
+        }
+        "repeat" => merged.frequency_minutes = source.frequency_minutes,
+        _ => {}
+    }
+}
+
+fn print_conflict(index: usize, total: usize, conflict: &Conflict, service: &str) {
+    println!();
+    println!(
+        "{}",
+        t!("conflicts-header", number = index + 1, total = total, task_id = conflict.task_id, service = service, time = format_time(Some(conflict.detected_at)))
+    );
+    let rows = differences(conflict);
+    let width = rows.iter().map(|(_, local, _)| local.chars().count()).max().unwrap_or(0).max(5);
+    println!("  {:<8} {:<width$}  Remote", "Field", "Local", width = width);
+    for (field, local, remote) in &rows {
+        println!("  {:<8} {:<width$}  {}", field, local, remote, width = width);
+    }
+}
+
+// Ask how to settle each of the service's conflicts, then apply the answers to
+// the task and the event. Returns how many were resolved.
+pub async fn resolve<B: CalendarBackend>(backend: &B, state: &AppState, service: &mut ServiceState) -> usize {
+    let conflicts = std::mem::take(&mut service.conflicts);
+    let total = conflicts.len();
+    let mut resolved = 0;
+    let mut remaining = conflicts.into_iter().enumerate();
+    while let Some((index, conflict)) = remaining.next() {
+        print_conflict(index, total, &conflict, backend.name());
+        let answer = loop {
+            let answer = prompt("Keep [l]ocal, [r]emote, pick [f]ield by field, [s]kip or [q]uit?");
+            if ["l", "local", "r", "remote", "f", "field", "s", "skip", "q", "quit"].contains(&answer.as_str()) {
+                break answer;
+            }
+        };
+        let (merged, choice) = match answer.as_str() {
+            "l" | "local" => (conflict.local.clone(), "local".to_string()),
+            "r" | "remote" => (conflict.remote.clone(), "remote".to_string()),
+            "f" | "field" => {
+                let mut merged = conflict.local.clone();
+                let mut picks = Vec::new();
+                let mut quit = false;
+                for (field, _, _) in differences(&conflict) {
+                    let Some(side) = pick_side(field) else {
+                        quit = true;
+                        break;
+                    };
+                    merge(&conflict, field, &side, &mut merged);
+                    picks.push(format!("{}={}", field, if matches!(side, Side::Local) { "local" } else { "remote" }));
+                }
+                if quit {
+                    service.conflicts.push(conflict);
+                    service.conflicts.extend(remaining.map(|(_, conflict)| conflict));
+                    break;
+                }
+                (merged, picks.join(", "))
+            }
+            "q" | "quit" => {
+                service.conflicts.push(conflict);
+                service.conflicts.extend(remaining.map(|(_, conflict)| conflict));
+                break;
+            }
+            _ => {
+                service.conflicts.push(conflict);
+                continue;
+            }
+        };
+        if settle(backend, state, service, conflict, merged, choice).await {
+            resolved += 1;
+        }
+    }
+    resolved
+}
+
+// Give the task and, where it differs, the event the `merged` fields and make
+// them the base for the next sync. False when the task was removed since, its
+// event being handled by the next sync, or the event couldn't be updated, in
+// which case the conflict is kept.
+pub async fn settle<B: CalendarBackend>(backend: &B, state: &AppState, service: &mut ServiceState, conflict: Conflict, merged: Snapshot, choice: String) -> bool {
+    let Some(mut task) = state.get_task(conflict.task_id).await else {
+        return false;
+    };
+    merged.apply_to(&mut task);
+    if merged != conflict.remote
+        && let Err(e) = backend.update(&conflict.event_id, &task).await
+    {
+        eprintln!("{}", t!("conflicts-update-failed", service = backend.name(), error = format!("{:?}", e)));
+        service.conflicts.push(conflict);
+        return false;
+    }
+    state.apply_snapshot(conflict.task_id, &merged).await;
+    service.base.insert(conflict.event_id.clone(), merged.clone());
+    service.resolutions.push(Resolution {
+        task_id: conflict.task_id,
+        event_id: conflict.event_id,
+        choice,
+        resolved: merged,
+        resolved_at: Utc::now(),
+    });
+    true
+}
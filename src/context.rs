@@ -0,0 +1,69 @@
+// Contexts ("work", "home"): a filter and defaults for new tasks that apply to
+// every command until another context is chosen, like Taskwarrior's
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, ContextConfig};
+use crate::filter::Filter;
+use crate::Task;
+use crate::i18n::t;
+use crate::{atomic, paths};
+
+// File the current context is kept in between runs
+pub const CONTEXT_PATH: &str = "context.json";
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ContextState {
+    /// Name of the context in use; none when unset
+    pub current: Option<String>,
+}
+
+impl ContextState {
+    pub fn load(path: &str) -> Result<ContextState, Box<dyn std::error::Error>> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(ContextState::default()),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        atomic::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+// The context in use with its settings, if it is still defined
+pub fn current(config: &Config) -> Option<(String, ContextConfig)> {
+    let state = ContextState::load(&paths::data(CONTEXT_PATH)).unwrap_or_else(|e| {
+        eprintln!("{}", t!("error-reading", path = paths::data(CONTEXT_PATH), error = format!("{:?}", e)));
+        ContextState::default()
+    });
+    let name = state.current?;
+    match config.contexts.get(&name) {
+        Some(context) => Some((name, context.clone())),
+        None => {
+            eprintln!("{}", t!("context-undefined", name = name));
+            None
+        }
+    }
+}
+
+// The current context's filter, if it has one
+pub fn filter(config: &Config) -> Result<Option<Filter>, String> {
+    let Some((name, context)) = current(config) else { return Ok(None) };
+    let Some(filter) = context.filter else { return Ok(None) };
+    Filter::parse(&filter).map(Some).map_err(|e| format!("in the filter of context '{}': {}", name, e))
+}
+
+// Fill in the current context's project and tags on a new task
+pub fn apply_defaults(config: &Config, task: &mut Task) {
+    let Some((_, context)) = current(config) else { return };
+    if task.project.is_none() {
+        task.project = context.project;
+    }
+    for tag in context.tags {
+        if !task.tags.contains(&tag) {
+            task.tags.push(tag);
+        }
+    }
+}
@@ -0,0 +1,608 @@
+// Change journal: every change to a task is recorded as an operation setting one
+// register, stamped with a hybrid logical clock and the device that made it.
+// Tags, dependencies and custom attributes get a register per element, so the
+// journals of devices that edited the same task offline merge into one state no
+// matter the order they are applied in.
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::i18n::t;
+use crate::{atomic, paths, schema};
+use crate::{StoreFile, Task};
+
+// Operations made here and received from other devices, one per line
+pub const JOURNAL_PATH: &str = "ops.jsonl";
+
+// This device's ID and clock
+pub const DEVICE_PATH: &str = "device.json";
+
+// The journal folded, see `Snapshot`
+const SNAPSHOT_PATH: &str = "ops.state.json";
+
+// When an operation was made; ordered by time, then counter, then device
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Stamp {
+    /// Milliseconds since the epoch, or later if another device's clock is ahead
+    pub time: i64,
+    /// Operations made within the same millisecond
+    pub counter: u32,
+    pub device: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Op {
+    #[serde(flatten)]
+    pub stamp: Stamp,
+    /// UUID of the task
+    pub task: String,
+    /// Field, or "tags/<tag>", "depends_on/<uuid>" or "meta/<key>" for one element,
+    /// or "deleted" when the task is removed
+    pub key: String,
+    /// New value; null for an element that was removed
+    pub value: Value,
+}
+
+// A register that changed, for the audit log
+pub struct Change {
+    /// UUID of the task
+    pub task: String,
+    pub id: u32,
+    pub title: String,
+    /// Register as in `Op`, or "created" for a new task
+    pub key: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Device {
+    pub id: String,
+    /// Latest stamp made or seen here
+    #[serde(default)]
+    time: i64,
+    #[serde(default)]
+    counter: u32,
+}
+
+impl Device {
+    // Load this device's clock, picking an ID on first use
+    pub fn load(path: &str) -> Result<Device, Box<dyn std::error::Error>> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Device { id: crate::new_uuid(), time: 0, counter: 0 }),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        atomic::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // A stamp later than any made or seen here
+    fn tick(&mut self) -> Stamp {
+        let now = Utc::now().timestamp_millis();
+        if now > self.time {
+            self.time = now;
+            self.counter = 0;
+        } else {
+            self.counter += 1;
+        }
+        Stamp { time: self.time, counter: self.counter, device: self.id.clone() }
+    }
+
+    // Move the clock past a stamp from another device
+    fn observe(&mut self, stamp: &Stamp) {
+        if (stamp.time, stamp.counter) > (self.time, self.counter) {
+            self.time = stamp.time;
+            self.counter = stamp.counter;
+        }
+    }
+}
+
+// Every operation in the journal, oldest first
+pub fn load(path: &str) -> Result<Vec<Op>, Box<dyn std::error::Error>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(Box::new(e)),
+    };
+    parse(&contents)
+}
+
+// Operations in JSON Lines text
+pub fn parse(contents: &str) -> Result<Vec<Op>, Box<dyn std::error::Error>> {
+    let mut ops = Vec::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        ops.push(serde_json::from_str(line)?);
+    }
+    Ok(ops)
+}
+
+fn append(path: &str, ops: &[Op]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    for op in ops {
+        writeln!(file, "{}", serde_json::to_string(op)?)?;
+    }
+    Ok(())
+}
+
+// A task as registers; references to other tasks are kept by UUID, since IDs
+// differ between devices
+fn registers(task: &Task, uuids: &HashMap<u32, String>) -> Result<BTreeMap<String, Value>, Box<dyn std::error::Error>> {
+    let Value::Object(fields) = serde_json::to_value(task)? else { return Ok(BTreeMap::new()) };
+    let mut out = BTreeMap::new();
+    for (key, value) in fields {
+        match key.as_str() {
+            "uuid" => {}
+            "tags" => out.extend(task.tags.iter().map(|tag| (format!("tags/{}", tag), Value::Bool(true)))),
+            "meta" => out.extend(task.meta.iter().map(|(name, value)| (format!("meta/{}", name), Value::from(value.clone())))),
+            "depends_on" => out.extend(
+                task.depends_on.iter().filter_map(|id| uuids.get(id)).map(|uuid| (format!("depends_on/{}", uuid), Value::Bool(true))),
+            ),
+            "parent" => {
+                out.insert(key, task.parent.and_then(|id| uuids.get(&id)).map_or(Value::Null, |uuid| Value::from(uuid.clone())));
+            }
+            _ => {
+                out.insert(key, value);
+            }
+        }
+    }
+    Ok(out)
+}
+
+// Latest value of each register of a task
+type Registers = BTreeMap<String, (Stamp, Value)>;
+
+// Registers of each task, by UUID
+type State = HashMap<String, Registers>;
+
+// The journal folded into registers, removed tasks included, kept beside it
+// so that recording a change only reads the operations appended since
+#[derive(Serialize, Deserialize, Default)]
+struct Snapshot {
+    /// Bytes of the journal folded in
+    length: u64,
+    state: State,
+}
+
+// The folded journal, brought up to date. The snapshot is started over when
+// the journal is no longer the one it was made from, e.g. after `compact`.
+fn snapshot() -> Result<Snapshot, Box<dyn std::error::Error>> {
+    let mut snapshot: Snapshot = std::fs::read_to_string(paths::data(SNAPSHOT_PATH))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default();
+    let mut journal = match std::fs::File::open(paths::data(JOURNAL_PATH)) {
+        Ok(journal) => journal,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Snapshot::default()),
+        Err(e) => return Err(Box::new(e)),
+    };
+    // The snapshot ends after a whole line of the journal
+    let mut last = [0];
+    let follows = snapshot.length == 0
+        || (snapshot.length <= journal.metadata()?.len()
+            && journal.seek(SeekFrom::Start(snapshot.length - 1)).is_ok()
+            && journal.read_exact(&mut last).is_ok()
+            && last[0] == b'\n');
+    if !follows {
+        snapshot = Snapshot::default();
+    }
+    journal.seek(SeekFrom::Start(snapshot.length))?;
+    let mut rest = String::new();
+    snapshot.length += journal.read_to_string(&mut rest)? as u64;
+    for op in parse(&rest)? {
+        apply(&mut snapshot.state, &op);
+    }
+    Ok(snapshot)
+}
+
+impl Snapshot {
+    // Take in operations just appended to the journal and save
+    fn save(&mut self, ops: &[Op]) -> Result<(), Box<dyn std::error::Error>> {
+        for op in ops {
+            apply(&mut self.state, op);
+        }
+        self.length = std::fs::metadata(paths::data(JOURNAL_PATH)).map_or(0, |metadata| metadata.len());
+        atomic::write(&paths::data(SNAPSHOT_PATH), serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+// Apply an operation, returning the value it replaced, or None if a later one
+// was already applied
+fn apply(state: &mut State, op: &Op) -> Option<Value> {
+    let registers = state.entry(op.task.clone()).or_default();
+    match registers.get(&op.key) {
+        Some((stamp, _)) if *stamp >= op.stamp => None,
+        _ => registers.insert(op.key.clone(), (op.stamp.clone(), op.value.clone())).map_or(Some(Value::Null), |(_, before)| Some(before)),
+    }
+}
+
+// A removed task stays removed unless it was changed after
+fn alive(registers: &Registers) -> bool {
+    match registers.get("deleted") {
+        Some((deleted, Value::Bool(true))) => registers.iter().any(|(key, (stamp, _))| key != "deleted" && stamp > deleted),
+        _ => true,
+    }
+}
+
+// Values of the registers of live tasks, without their stamps
+fn values(state: &State) -> HashMap<&str, BTreeMap<&str, &Value>> {
+    state
+        .iter()
+        .filter(|(_, registers)| alive(registers))
+        .map(|(uuid, registers)| (uuid.as_str(), registers.iter().filter(|(key, _)| *key != "deleted").map(|(key, (_, value))| (key.as_str(), value)).collect()))
+        .collect()
+}
+
+// The ID and title a task has in `state`
+fn label(state: &State, uuid: &str) -> (u32, String) {
+    let value = |key: &str| state.get(uuid).and_then(|registers| registers.get(key)).map(|(_, value)| value);
+    (value("id").and_then(Value::as_u64).unwrap_or(0) as u32, value("title").and_then(Value::as_str).unwrap_or_default().to_string())
+}
+
+// Record the operations turning the journal's state into `store`, as just saved,
+// and return what changed. The first time, every task is recorded.
+pub fn record(store: &StoreFile) -> Result<Vec<Change>, Box<dyn std::error::Error>> {
+    let mut snapshot = snapshot()?;
+    let mut device = Device::load(&paths::data(DEVICE_PATH))?;
+    let (ops, changes) = diff(&snapshot.state, store, &mut device)?;
+    if ops.is_empty() {
+        return Ok(changes);
+    }
+    append(&paths::data(JOURNAL_PATH), &ops)?;
+    device.save(&paths::data(DEVICE_PATH))?;
+    snapshot.save(&ops)?;
+    Ok(changes)
+}
+
+// The operations taking `state` to `store`, stamped by `device`, with the
+// changes they make
+fn diff(state: &State, store: &StoreFile, device: &mut Device) -> Result<(Vec<Op>, Vec<Change>), Box<dyn std::error::Error>> {
+    let known = values(state);
+    let uuids: HashMap<u32, String> = store.tasks.iter().map(|task| (task.id, task.uuid.clone())).collect();
+    let mut ops = Vec::new();
+    let mut changes = Vec::new();
+    let mut live = HashSet::new();
+    for task in &store.tasks {
+        live.insert(task.uuid.as_str());
+        let before = known.get(task.uuid.as_str());
+        let after = registers(task, &uuids)?;
+        let mut changed: Vec<(String, Value)> =
+            after.iter().filter(|(key, value)| before.and_then(|before| before.get(key.as_str())) != Some(value)).map(|(key, value)| (key.clone(), value.clone())).collect();
+        // Elements no longer there
+        if let Some(before) = before {
+            changed.extend(before.iter().filter(|(key, value)| key.contains('/') && !value.is_null() && !after.contains_key(**key)).map(|(key, _)| (key.to_string(), Value::Null)));
+        }
+        let change = |key: &str, before: Value, after: Value| Change {
+            task: task.uuid.clone(),
+            id: task.id,
+            title: task.title.clone(),
+            key: key.to_string(),
+            before,
+            after,
+        };
+        if before.is_none() {
+            changes.push(change("created", Value::Null, Value::from(task.title.clone())));
+        }
+        for (key, value) in changed {
+            if let Some(before) = before {
+                changes.push(change(&key, before.get(key.as_str()).map_or(Value::Null, |value| (*value).clone()), value.clone()));
+            }
+            ops.push(Op { stamp: device.tick(), task: task.uuid.clone(), key, value });
+        }
+    }
+    for uuid in known.keys().filter(|uuid| !live.contains(*uuid)) {
+        let (id, title) = label(state, uuid);
+        changes.push(Change { task: uuid.to_string(), id, title, key: "deleted".to_string(), before: Value::Null, after: Value::Bool(true) });
+        ops.push(Op { stamp: device.tick(), task: uuid.to_string(), key: "deleted".to_string(), value: Value::Bool(true) });
+    }
+    Ok((ops, changes))
+}
+
+// Add operations from other devices to the journal, returning the changes that
+// took effect with the stamp of the operation that made them
+pub fn merge(ops: Vec<Op>) -> Result<Vec<(Stamp, Change)>, Box<dyn std::error::Error>> {
+    let mut snapshot = snapshot()?;
+    let mut device = Device::load(&paths::data(DEVICE_PATH))?;
+    let mut state = snapshot.state.clone();
+    let (new, changes) = take_in(&mut state, ops, &mut device);
+    if new.is_empty() {
+        return Ok(Vec::new());
+    }
+    append(&paths::data(JOURNAL_PATH), &new)?;
+    device.save(&paths::data(DEVICE_PATH))?;
+    snapshot.save(&new)?;
+    Ok(changes)
+}
+
+// Apply the operations `state` doesn't have yet, in stamp order, moving the
+// device's clock past them. Returns those operations, and the changes they
+// made with the stamp of the one that made each. An operation already applied,
+// or overtaken by a later one, changes nothing and isn't returned.
+fn take_in(state: &mut State, ops: Vec<Op>, device: &mut Device) -> (Vec<Op>, Vec<(Stamp, Change)>) {
+    let mut new: Vec<Op> = ops
+        .into_iter()
+        .filter(|op| state.get(&op.task).and_then(|registers| registers.get(&op.key)).is_none_or(|(stamp, _)| *stamp < op.stamp))
+        .collect();
+    new.sort_by(|a, b| a.stamp.cmp(&b.stamp));
+    new.dedup_by(|a, b| a.stamp == b.stamp);
+    let existing: HashSet<String> = state.keys().cloned().collect();
+    let mut applied = Vec::new();
+    for op in &new {
+        device.observe(&op.stamp);
+        if let Some(before) = apply(state, op) {
+            applied.push((op, before));
+        }
+    }
+    // Tasks new here are one change each
+    let mut created = HashSet::new();
+    let mut changes = Vec::new();
+    for (op, before) in applied {
+        let (id, title) = label(state, &op.task);
+        let (key, after) = match existing.contains(&op.task) {
+            true => (op.key.clone(), op.value.clone()),
+            false if created.insert(op.task.clone()) => ("created".to_string(), Value::from(title.clone())),
+            false => continue,
+        };
+        changes.push((op.stamp.clone(), Change { task: op.task.clone(), id, title, key, before, after }));
+    }
+    (new, changes)
+}
+
+// The store the journal adds up to. Two tasks made on different devices with the
+// same ID keep it in the order the IDs were given, the later one getting the next
+// free ID.
+pub fn materialize(next_id: u32) -> Result<StoreFile, Box<dyn std::error::Error>> {
+    Ok(build(&snapshot()?.state, next_id))
+}
+
+fn build(state: &State, next_id: u32) -> StoreFile {
+    let id_of = |registers: &Registers| registers.get("id").and_then(|(_, id)| id.as_u64()).unwrap_or(0) as u32;
+    let mut order: Vec<(&String, &Registers)> = state.iter().filter(|(_, registers)| alive(registers)).collect();
+    order.sort_by(|a, b| (id_of(a.1), a.1.get("id").map(|(stamp, _)| stamp)).cmp(&(id_of(b.1), b.1.get("id").map(|(stamp, _)| stamp))));
+    let mut next_id = next_id.max(order.iter().map(|(_, registers)| id_of(registers) + 1).max().unwrap_or(0));
+    let mut ids = HashMap::new();
+    let mut taken = HashSet::new();
+    for (uuid, registers) in &order {
+        let mut id = id_of(registers);
+        if !taken.insert(id) {
+            id = next_id;
+            next_id += 1;
+            taken.insert(id);
+        }
+        ids.insert(uuid.as_str(), id);
+    }
+
+    let mut tasks = Vec::new();
+    for (uuid, registers) in order {
+        let mut fields = Map::new();
+        let mut elements: HashMap<&str, Vec<(&Stamp, &str, &Value)>> = HashMap::new();
+        for (key, (stamp, value)) in registers {
+            match key.split_once('/') {
+                Some((field, element)) if !value.is_null() => elements.entry(field).or_default().push((stamp, element, value)),
+                Some(_) => {}
+                None if key == "deleted" => {}
+                None => {
+                    fields.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        // Elements in the order they were added
+        for list in elements.values_mut() {
+            list.sort_by(|a, b| a.0.cmp(b.0));
+        }
+        let list = |field: &str| elements.get(field).into_iter().flatten();
+        fields.insert("uuid".to_string(), Value::from(uuid.clone()));
+        fields.insert("id".to_string(), Value::from(ids[uuid.as_str()]));
+        fields.insert("tags".to_string(), list("tags").map(|(_, tag, _)| Value::from(*tag)).collect());
+        fields.insert("meta".to_string(), Value::Object(list("meta").map(|(_, name, value)| (name.to_string(), (*value).clone())).collect()));
+        fields.insert("depends_on".to_string(), list("depends_on").filter_map(|(_, uuid, _)| ids.get(uuid)).map(|id| Value::from(*id)).collect());
+        if let Some(parent) = fields.get("parent").and_then(Value::as_str) {
+            let parent = ids.get(parent).map_or(Value::Null, |id| Value::from(*id));
+            fields.insert("parent".to_string(), parent);
+        }
+        match serde_json::from_value::<Task>(Value::Object(fields)) {
+            Ok(task) => tasks.push(task),
+            Err(e) => eprintln!("{}", t!("journal-skipping-task", uuid = uuid, path = paths::data(JOURNAL_PATH), error = e)),
+        }
+    }
+    tasks.sort_by_key(|task| task.id);
+    StoreFile { version: schema::VERSION, next_id, tasks }
+}
+
+// Rewrite the journal keeping only the operations that still decide a
+// register, returning how many there were and how many are left; with
+// `dry_run` nothing is written. Removed tasks and elements are forgotten
+// unless the journal is `shared` with other devices, whose journals could
+// bring them back; then only this device's operations are dropped, as the
+// others' would arrive again with the next sync.
+pub fn compact(shared: bool, dry_run: bool) -> Result<(usize, usize), Box<dyn std::error::Error>> {
+    let path = paths::data(JOURNAL_PATH);
+    // Saving the store adds to the journal
+    let _lock = atomic::lock(&paths::data(crate::STORE_PATH))?;
+    let ops = load(&path)?;
+    let mut state: State = HashMap::new();
+    for op in &ops {
+        apply(&mut state, op);
+    }
+    let removed: HashSet<&str> = state
+        .iter()
+        .filter(|(_, registers)| match registers.get("deleted") {
+            Some((deleted, Value::Bool(true))) => registers.iter().all(|(key, (stamp, _))| key == "deleted" || stamp < deleted),
+            _ => false,
+        })
+        .map(|(uuid, _)| uuid.as_str())
+        .collect();
+    let device = Device::load(&paths::data(DEVICE_PATH))?;
+    let decides = |op: &Op| {
+        let latest = state.get(&op.task).and_then(|registers| registers.get(&op.key)).is_some_and(|(stamp, _)| *stamp == op.stamp);
+        match removed.contains(op.task.as_str()) {
+            true => shared && op.key == "deleted" && latest,
+            false => latest && (shared || !(op.key.contains('/') && op.value.is_null())),
+        }
+    };
+    let kept: Vec<&Op> = ops.iter().filter(|op| (shared && op.stamp.device != device.id) || decides(op)).collect();
+    if !dry_run && kept.len() < ops.len() {
+        let mut contents = String::new();
+        for op in &kept {
+            contents.push_str(&serde_json::to_string(op)?);
+            contents.push('\n');
+        }
+        // Written beside it and moved over, so an interrupted write loses nothing
+        atomic::write(&path, contents)?;
+        // Removed tasks may be forgotten, so the snapshot is folded again
+        let _ = std::fs::remove_file(paths::data(SNAPSHOT_PATH));
+    }
+    Ok((ops.len(), kept.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A device with its own copy of the journal's state, whose clock is far
+    // enough ahead that its stamps only count up
+    struct Replica {
+        state: State,
+        device: Device,
+    }
+
+    impl Replica {
+        fn new(id: &str) -> Replica {
+            Replica { state: State::new(), device: Device { id: id.to_string(), time: 4_000_000_000_000, counter: 0 } }
+        }
+
+        // Save `tasks` here, returning the operations that recorded it
+        fn save(&mut self, tasks: Vec<Task>) -> Vec<Op> {
+            let store = StoreFile { version: schema::VERSION, next_id: 0, tasks };
+            let (ops, _) = diff(&self.state, &store, &mut self.device).unwrap();
+            for op in &ops {
+                apply(&mut self.state, op);
+            }
+            ops
+        }
+
+        // Take in another device's operations, returning how many were new
+        fn receive(&mut self, ops: &[Op]) -> usize {
+            take_in(&mut self.state, ops.to_vec(), &mut self.device).0.len()
+        }
+
+        fn tasks(&self) -> Vec<Task> {
+            build(&self.state, 0).tasks
+        }
+
+        fn task(&self) -> Task {
+            let tasks = self.tasks();
+            assert_eq!(tasks.len(), 1);
+            tasks[0].clone()
+        }
+    }
+
+    fn report() -> Task {
+        Task { id: 1, uuid: "report".to_string(), title: "Write report".to_string(), tags: vec!["home".to_string(), "work".to_string()], ..Default::default() }
+    }
+
+    // Two devices that both have the report task
+    fn pair() -> (Replica, Replica) {
+        let (mut a, mut b) = (Replica::new("a"), Replica::new("b"));
+        let ops = a.save(vec![report()]);
+        assert_eq!(b.receive(&ops), ops.len());
+        (a, b)
+    }
+
+    fn same(a: &Replica, b: &Replica) {
+        assert_eq!(serde_json::to_value(a.tasks()).unwrap(), serde_json::to_value(b.tasks()).unwrap());
+    }
+
+    #[test]
+    fn edits_to_different_fields_both_apply() {
+        let (mut a, mut b) = pair();
+        let from_a = a.save(vec![Task { title: "Write the report".to_string(), ..report() }]);
+        let from_b = b.save(vec![Task { priority: crate::Priority::High, ..report() }]);
+        a.receive(&from_b);
+        b.receive(&from_a);
+        same(&a, &b);
+        assert_eq!(a.task().title, "Write the report");
+        assert_eq!(a.task().priority, crate::Priority::High);
+    }
+
+    #[test]
+    fn same_field_with_the_same_clock_goes_to_the_later_device() {
+        let (mut a, mut b) = pair();
+        let from_a = a.save(vec![Task { title: "From a".to_string(), ..report() }]);
+        let from_b = b.save(vec![Task { title: "From b".to_string(), ..report() }]);
+        assert_eq!((from_a[0].stamp.time, from_a[0].stamp.counter), (from_b[0].stamp.time, from_b[0].stamp.counter));
+        // Applied in either order
+        a.receive(&from_b);
+        b.receive(&from_a);
+        same(&a, &b);
+        assert_eq!(a.task().title, "From b");
+    }
+
+    #[test]
+    fn tags_added_and_removed_at_once_both_apply() {
+        let (mut a, mut b) = pair();
+        let tags = |tags: &[&str]| Task { tags: tags.iter().map(|tag| tag.to_string()).collect(), ..report() };
+        let from_a = a.save(vec![tags(&["home", "work", "urgent"])]);
+        let from_b = b.save(vec![tags(&["work", "urgent"])]);
+        a.receive(&from_b);
+        b.receive(&from_a);
+        same(&a, &b);
+        assert_eq!(a.task().tags, ["work", "urgent"]);
+    }
+
+    #[test]
+    fn removal_wins_over_earlier_edits() {
+        let (mut a, mut b) = pair();
+        let edit = b.save(vec![Task { title: "Edited".to_string(), ..report() }]);
+        a.receive(&edit);
+        let removal = a.save(vec![]);
+        b.receive(&removal);
+        same(&a, &b);
+        assert!(a.tasks().is_empty());
+    }
+
+    #[test]
+    fn later_edits_bring_a_removed_task_back() {
+        let (mut a, mut b) = pair();
+        let removal = a.save(vec![]);
+        // b's clock is even with a's, and b comes after a
+        let edit = b.save(vec![Task { title: "Edited".to_string(), ..report() }]);
+        assert!(edit[0].stamp > removal[0].stamp);
+        a.receive(&edit);
+        b.receive(&removal);
+        same(&a, &b);
+        assert_eq!(a.task().title, "Edited");
+    }
+
+    #[test]
+    fn merging_again_changes_nothing() {
+        let (mut a, mut b) = pair();
+        let ops = a.save(vec![Task { title: "Again".to_string(), ..report() }]);
+        assert_eq!(b.receive(&ops), 1);
+        let before = serde_json::to_value(b.tasks()).unwrap();
+        assert_eq!(b.receive(&ops), 0);
+        // Its own operations, and the ones from the start, are known too
+        let all: Vec<Op> = ops.iter().cloned().chain(b.save(vec![Task { title: "Again".to_string(), ..report() }])).collect();
+        assert_eq!(b.receive(&all), 0);
+        assert_eq!(serde_json::to_value(b.tasks()).unwrap(), before);
+    }
+
+    #[test]
+    fn tasks_added_on_both_devices_with_the_same_id_get_their_own() {
+        let (mut a, mut b) = (Replica::new("a"), Replica::new("b"));
+        let from_a = a.save(vec![Task { uuid: "one".to_string(), ..report() }]);
+        let from_b = b.save(vec![Task { uuid: "two".to_string(), ..report() }]);
+        a.receive(&from_b);
+        b.receive(&from_a);
+        same(&a, &b);
+        let ids: Vec<(String, u32)> = a.tasks().into_iter().map(|task| (task.uuid, task.id)).collect();
+        assert_eq!(ids, [("one".to_string(), 1), ("two".to_string(), 2)]);
+    }
+}
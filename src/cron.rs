@@ -0,0 +1,290 @@
+// Cron-style recurrence ("0 9 * * MON-FRI"): parsing, occurrences in local time
+// or the task's time zone and best-effort translation to an RRULE for calendar sync
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+
+use crate::zone::Zone;
+
+// Days searched for the next match before giving up, e.g. on "0 0 30 2 *"
+const SEARCH_DAYS: i64 = 5 * 366;
+
+const WEEKDAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+const MONTHS: [&str; 12] = ["JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC"];
+const RRULE_WEEKDAYS: [&str; 7] = ["SU", "MO", "TU", "WE", "TH", "FR", "SA"];
+
+struct Field {
+    values: BTreeSet<u32>,
+    /// Written as "*", every value
+    any: bool,
+    /// Starting with "*", e.g. "*/2", which leaves a day of the month or week
+    /// field out when the two are combined, as in Vixie cron
+    star: bool,
+}
+
+pub struct Cron {
+    minutes: Field,
+    hours: Field,
+    days: Field,
+    months: Field,
+    /// 0 is Sunday
+    weekdays: Field,
+}
+
+fn parse_value(text: &str, first_name: u32, names: &[&str]) -> Result<u32, String> {
+    if let Some(index) = names.iter().position(|name| name.eq_ignore_ascii_case(text)) {
+        return Ok(index as u32 + first_name);
+    }
+    text.parse().map_err(|_| format!("'{}' is not a number", text))
+}
+
+// One field: "*", "5", "1-5", "MON-FRI", "*/15", "0-30/10", or a comma-separated list of those
+fn parse_field(text: &str, min: u32, max: u32, names: &[&str]) -> Result<Field, String> {
+    let mut values = BTreeSet::new();
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<usize>().ok().filter(|step| *step > 0).ok_or_else(|| format!("bad step in '{}'", part))?),
+            None => (part, 1),
+        };
+        let (first, last) = if range == "*" {
+            (min, max)
+        } else if let Some((first, last)) = range.split_once('-') {
+            (parse_value(first, min, names)?, parse_value(last, min, names)?)
+        } else {
+            let value = parse_value(range, min, names)?;
+            // "5/15" means from 5 to the end in steps of 15
+            (value, if step > 1 { max } else { value })
+        };
+        if first < min || last > max || first > last {
+            return Err(format!("'{}' is outside {}-{}", part, min, max));
+        }
+        values.extend((first..=last).step_by(step));
+    }
+    Ok(Field { values, any: text == "*", star: text.starts_with('*') })
+}
+
+// The step between the values if they are spread evenly over the whole cycle
+fn spacing(field: &Field, cycle: u32) -> Option<u32> {
+    let values: Vec<u32> = field.values.iter().copied().collect();
+    let step = match values.as_slice() {
+        [_] => cycle,
+        [first, second, ..] => second - first,
+        [] => return None,
+    };
+    let even = values.windows(2).all(|pair| pair[1] - pair[0] == step);
+    (even && step * values.len() as u32 == cycle).then_some(step)
+}
+
+impl Cron {
+    pub fn parse(expression: &str) -> Result<Cron, String> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * SUN",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            expression => expression,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields.as_slice() else {
+            return Err(format!("expected 5 fields (minute hour day month weekday), got {}", fields.len()));
+        };
+        let mut weekdays = parse_field(weekdays, 0, 7, &WEEKDAYS)?;
+        // Both 0 and 7 are Sunday
+        if weekdays.values.remove(&7) {
+            weekdays.values.insert(0);
+        }
+        Ok(Cron {
+            minutes: parse_field(minutes, 0, 59, &[])?,
+            hours: parse_field(hours, 0, 23, &[])?,
+            days: parse_field(days, 1, 31, &[])?,
+            months: parse_field(months, 1, 12, &MONTHS)?,
+            weekdays,
+        })
+    }
+
+    // Like cron, a day matches on its day of the month or its weekday when both
+    // are restricted, and on both otherwise; a field starting with "*" doesn't
+    // count as restricted
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let day = self.days.values.contains(&date.day());
+        let weekday = self.weekdays.values.contains(&date.weekday().num_days_from_sunday());
+        let either = if self.days.star || self.weekdays.star { day && weekday } else { day || weekday };
+        either && self.months.values.contains(&date.month())
+    }
+
+    // First matching time after `time`, in `zone`
+    pub fn next_after(&self, time: DateTime<Utc>, zone: &Zone) -> Option<DateTime<Utc>> {
+        let first_day = zone.naive(time).date();
+        for offset in 0..SEARCH_DAYS {
+            let date = first_day + Duration::days(offset);
+            if !self.day_matches(date) {
+                continue;
+            }
+            for hour in &self.hours.values {
+                for minute in &self.minutes.values {
+                    // Times skipped by a daylight saving change don't occur
+                    let Some(candidate) = date.and_hms_opt(*hour, *minute, 0).and_then(|naive| zone.resolve(naive)) else {
+                        continue;
+                    };
+                    if candidate > time {
+                        return Some(candidate);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    // The RRULE for the series, for an event whose days are `shift` days from
+    // the ones in the expression, as for one kept in UTC
+    pub fn to_rrule(&self, shift: i64) -> Result<String, String> {
+        let every_day = self.days.any && self.weekdays.any && self.months.any;
+        if self.minutes.values.len() > 1 || self.hours.values.len() > 1 {
+            if !every_day {
+                return Err("it repeats several times a day on some days only".to_string());
+            }
+            if self.minutes.values.len() == 1
+                && let Some(step) = spacing(&self.hours, 24)
+            {
+                return Ok(format!("FREQ=HOURLY;INTERVAL={}", step));
+            }
+            if self.hours.any
+                && let Some(step) = spacing(&self.minutes, 60)
+            {
+                return Ok(format!("FREQ=MINUTELY;INTERVAL={}", step));
+            }
+            return Err("its times of day aren't evenly spaced".to_string());
+        }
+        if every_day {
+            return Ok("FREQ=DAILY".to_string());
+        }
+        if !self.days.any && !self.weekdays.any {
+            return Err(match self.days.star || self.weekdays.star {
+                true => "it matches days of the month that are also certain weekdays",
+                false => "it matches either a day of the month or a weekday",
+            }
+            .to_string());
+        }
+        if shift != 0 && (!self.days.any || !self.months.any) {
+            return Err("its dates fall on other days in UTC".to_string());
+        }
+        let list = |values: &BTreeSet<u32>| values.iter().map(u32::to_string).collect::<Vec<_>>().join(",");
+        let mut parts = Vec::new();
+        if !self.weekdays.any {
+            let weekdays: Vec<&str> = self.weekdays.values.iter().map(|day| RRULE_WEEKDAYS[(*day as i64 + shift).rem_euclid(7) as usize]).collect();
+            parts.push("FREQ=WEEKLY".to_string());
+            parts.push(format!("BYDAY={}", weekdays.join(",")));
+        } else if !self.days.any {
+            parts.push(if self.months.any { "FREQ=MONTHLY" } else { "FREQ=YEARLY" }.to_string());
+            parts.push(format!("BYMONTHDAY={}", list(&self.days.values)));
+        } else {
+            parts.push("FREQ=DAILY".to_string());
+        }
+        if !self.months.any {
+            parts.push(format!("BYMONTH={}", list(&self.months.values)));
+        }
+        Ok(parts.join(";"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        Utc.from_utc_datetime(&chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap())
+    }
+
+    // The next `count` times after `from`, in UTC
+    fn next(expression: &str, from: &str, count: usize) -> Vec<String> {
+        let cron = Cron::parse(expression).unwrap();
+        let zone = crate::zone::parse("UTC").unwrap();
+        let mut time = utc(from);
+        (0..count)
+            .map(|_| {
+                time = cron.next_after(time, &zone).unwrap();
+                time.format("%Y-%m-%d %H:%M %a").to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn days_of_month_and_week_match_either_when_both_are_restricted() {
+        // 2024-06-01 is a Saturday
+        assert_eq!(next("0 9 1,15 * MON", "2024-06-01 10:00", 3), ["2024-06-03 09:00 Mon", "2024-06-10 09:00 Mon", "2024-06-15 09:00 Sat"]);
+    }
+
+    #[test]
+    fn days_of_month_and_week_match_both_when_one_is_a_star() {
+        assert_eq!(next("0 9 * * MON", "2024-06-01 10:00", 2), ["2024-06-03 09:00 Mon", "2024-06-10 09:00 Mon"]);
+        assert_eq!(next("0 9 13 * *", "2024-06-01 10:00", 2), ["2024-06-13 09:00 Thu", "2024-07-13 09:00 Sat"]);
+    }
+
+    #[test]
+    fn stepped_star_is_unrestricted_when_combining_days() {
+        // Odd days that are Mondays, not odd days or Mondays
+        assert_eq!(next("0 9 */2 * MON", "2024-06-01 10:00", 3), ["2024-06-03 09:00 Mon", "2024-06-17 09:00 Mon", "2024-07-01 09:00 Mon"]);
+    }
+
+    #[test]
+    fn seven_is_sunday() {
+        assert_eq!(next("0 9 * * 7", "2024-06-01 10:00", 1), ["2024-06-02 09:00 Sun"]);
+        assert_eq!(next("0 9 * * FRI-7", "2024-06-01 10:00", 2), ["2024-06-02 09:00 Sun", "2024-06-07 09:00 Fri"]);
+    }
+
+    #[test]
+    fn steps() {
+        assert_eq!(next("*/20 9 * * *", "2024-06-01 08:00", 4), ["2024-06-01 09:00 Sat", "2024-06-01 09:20 Sat", "2024-06-01 09:40 Sat", "2024-06-02 09:00 Sun"]);
+        assert_eq!(next("5/30 10-11 * * *", "2024-06-01 08:00", 3), ["2024-06-01 10:05 Sat", "2024-06-01 10:35 Sat", "2024-06-01 11:05 Sat"]);
+    }
+
+    #[test]
+    fn times_skipped_by_daylight_saving_do_not_occur() {
+        // Clocks in Berlin went from 02:00 to 03:00 on 2024-03-31
+        let cron = Cron::parse("30 2 * * *").unwrap();
+        let zone = crate::zone::parse("Europe/Berlin").unwrap();
+        let next = cron.next_after(utc("2024-03-30 02:00"), &zone).unwrap();
+        assert_eq!(next, utc("2024-04-01 00:30"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Cron::parse("0 9 * *").err().unwrap().contains("expected 5 fields"));
+        assert!(Cron::parse("60 9 * * *").err().unwrap().contains("outside 0-59"));
+        assert!(Cron::parse("0 9 * * FUNDAY").err().unwrap().contains("not a number"));
+        assert!(Cron::parse("*/0 9 * * *").err().unwrap().contains("bad step"));
+        assert!(Cron::parse("0 9 5-1 * *").err().unwrap().contains("outside 1-31"));
+    }
+
+    #[test]
+    fn rrules() {
+        let rrule = |expression: &str, shift| Cron::parse(expression).unwrap().to_rrule(shift);
+        assert_eq!(rrule("0 9 * * *", 0).unwrap(), "FREQ=DAILY");
+        assert_eq!(rrule("@hourly", 0).unwrap(), "FREQ=HOURLY;INTERVAL=1");
+        assert_eq!(rrule("0 */6 * * *", 0).unwrap(), "FREQ=HOURLY;INTERVAL=6");
+        assert_eq!(rrule("*/15 * * * *", 0).unwrap(), "FREQ=MINUTELY;INTERVAL=15");
+        assert_eq!(rrule("0 9 * * MON-FRI", 0).unwrap(), "FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR");
+        assert_eq!(rrule("0 9 1,15 * *", 0).unwrap(), "FREQ=MONTHLY;BYMONTHDAY=1,15");
+        assert_eq!(rrule("0 9 25 12 *", 0).unwrap(), "FREQ=YEARLY;BYMONTHDAY=25;BYMONTH=12");
+        assert_eq!(rrule("0 9 * 6-8 *", 0).unwrap(), "FREQ=DAILY;BYMONTH=6,7,8");
+    }
+
+    #[test]
+    fn rrule_weekdays_rotate_with_shift() {
+        let cron = Cron::parse("0 1 * * SUN,SAT").unwrap();
+        assert_eq!(cron.to_rrule(-1).unwrap(), "FREQ=WEEKLY;BYDAY=SA,FR");
+        assert_eq!(cron.to_rrule(1).unwrap(), "FREQ=WEEKLY;BYDAY=MO,SU");
+    }
+
+    #[test]
+    fn expressions_without_an_rrule() {
+        let error = |expression: &str, shift| Cron::parse(expression).unwrap().to_rrule(shift).unwrap_err();
+        assert_eq!(error("0 9,17 * * MON", 0), "it repeats several times a day on some days only");
+        assert_eq!(error("0 1,2,7 * * *", 0), "its times of day aren't evenly spaced");
+        assert_eq!(error("0 9 1 * MON", 0), "it matches either a day of the month or a weekday");
+        assert_eq!(error("0 9 */2 * MON", 0), "it matches days of the month that are also certain weekdays");
+        assert_eq!(error("0 23 1 * *", 1), "its dates fall on other days in UTC");
+    }
+}
@@ -0,0 +1,206 @@
+// Dependencies between tasks and subtasks: cycle checks and the graph in
+// Graphviz DOT or Mermaid
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{Task, TaskStatus};
+
+// Whether `to` can be reached from `from` by following `next`
+fn reaches(tasks: &HashMap<u32, Task>, from: u32, to: u32, next: impl Fn(&Task) -> Vec<u32>) -> bool {
+    let mut pending = vec![from];
+    let mut seen = Vec::new();
+    while let Some(id) = pending.pop() {
+        if id == to {
+            return true;
+        }
+        if seen.contains(&id) {
+            continue;
+        }
+        seen.push(id);
+        if let Some(task) = tasks.get(&id) {
+            pending.extend(next(task));
+        }
+    }
+    false
+}
+
+// Why task `id` can't depend on `dependency`, if it can't
+pub fn check_dependency(tasks: &HashMap<u32, Task>, id: u32, dependency: u32) -> Result<(), String> {
+    if !tasks.contains_key(&dependency) {
+        return Err(format!("there is no task #{}", dependency));
+    }
+    if dependency == id {
+        return Err("a task can't depend on itself".to_string());
+    }
+    if reaches(tasks, dependency, id, |task| task.depends_on.clone()) {
+        return Err(format!("#{} already depends on #{}", dependency, id));
+    }
+    Ok(())
+}
+
+// Why task `id` can't be a subtask of `parent`, if it can't
+pub fn check_parent(tasks: &HashMap<u32, Task>, id: u32, parent: u32) -> Result<(), String> {
+    if !tasks.contains_key(&parent) {
+        return Err(format!("there is no task #{}", parent));
+    }
+    if parent == id {
+        return Err("a task can't be its own subtask".to_string());
+    }
+    if reaches(tasks, parent, id, |task| task.parent.into_iter().collect()) {
+        return Err(format!("#{} is a subtask of #{}", parent, id));
+    }
+    Ok(())
+}
+
+// Percent done of task `id`: the average of its subtasks' when it has any,
+// otherwise its own progress, 100 once done. None for open tasks without any.
+pub fn progress<'a, I>(tasks: I, id: u32) -> Option<u8>
+where
+    I: IntoIterator<Item = &'a Task>,
+    I::IntoIter: Clone,
+{
+    fn percent<'a>(tasks: impl Iterator<Item = &'a Task> + Clone, task: &Task, depth: usize) -> Option<u8> {
+        let subtasks: Vec<&Task> = tasks.clone().filter(|other| other.parent == Some(task.id)).collect();
+        if subtasks.is_empty() || depth > tasks.clone().count() {
+            return if task.status == TaskStatus::Done { Some(100) } else { task.progress };
+        }
+        let total: u32 = subtasks.iter().map(|subtask| percent(tasks.clone(), subtask, depth + 1).unwrap_or(0) as u32).sum();
+        Some((total / subtasks.len() as u32) as u8)
+    }
+    let tasks = tasks.into_iter();
+    percent(tasks.clone(), tasks.clone().find(|task| task.id == id)?, 0)
+}
+
+// "[######----] 60%"
+pub fn progress_bar(percent: u8) -> String {
+    let filled = (percent as usize).div_ceil(10).min(10);
+    format!("[{}{}] {}%", "#".repeat(filled), "-".repeat(10 - filled), percent)
+}
+
+// Tasks grouped by project, tasks without one under ""
+fn by_project(tasks: &[Task]) -> BTreeMap<&str, Vec<&Task>> {
+    let mut projects: BTreeMap<&str, Vec<&Task>> = BTreeMap::new();
+    for task in tasks {
+        projects.entry(task.project.as_deref().unwrap_or("")).or_default().push(task);
+    }
+    projects
+}
+
+// From and to task IDs
+type Edge = (u32, u32);
+
+// Dependency edges (prerequisite first) and subtask edges (parent first) between known tasks
+fn edges(tasks: &[Task]) -> (Vec<Edge>, Vec<Edge>) {
+    let known = |id: &u32| tasks.iter().any(|task| task.id == *id);
+    let dependencies = tasks
+        .iter()
+        .flat_map(|task| task.depends_on.iter().filter(|id| known(id)).map(|id| (*id, task.id)))
+        .collect();
+    let subtasks = tasks
+        .iter()
+        .filter_map(|task| Some((task.parent.filter(known)?, task.id)))
+        .collect();
+    (dependencies, subtasks)
+}
+
+// Graphviz DOT: one cluster per project, done tasks greyed out, dependencies
+// as solid arrows and subtasks as dashed ones
+pub fn dot(tasks: &[Task]) -> String {
+    let label = |task: &Task| format!("#{} {}", task.id, task.title).replace('\\', "\\\\").replace('"', "\\\"");
+    let mut out = String::from("digraph tasks {\n    rankdir=LR;\n    node [shape=box];\n");
+    for (project, tasks) in by_project(tasks) {
+        let indent = if project.is_empty() { "    " } else { "        " };
+        if !project.is_empty() {
+            out.push_str(&format!("    subgraph \"cluster_{}\" {{\n        label=\"{}\";\n", project.replace('"', "\\\""), project.replace('"', "\\\"")));
+        }
+        for task in tasks {
+            let style = if task.status == TaskStatus::Done { ", style=filled, fillcolor=lightgray, fontcolor=gray40" } else { "" };
+            out.push_str(&format!("{}t{} [label=\"{}\"{}];\n", indent, task.id, label(task), style));
+        }
+        if !project.is_empty() {
+            out.push_str("    }\n");
+        }
+    }
+    let (dependencies, subtasks) = edges(tasks);
+    for (from, to) in dependencies {
+        out.push_str(&format!("    t{} -> t{};\n", from, to));
+    }
+    for (parent, child) in subtasks {
+        out.push_str(&format!("    t{} -> t{} [style=dashed, arrowhead=none];\n", parent, child));
+    }
+    out.push_str("}\n");
+    out
+}
+
+// Mermaid flowchart with the same layout as `dot`
+pub fn mermaid(tasks: &[Task]) -> String {
+    let label = |task: &Task| format!("#{} {}", task.id, task.title).replace('"', "#quot;");
+    let mut out = String::from("flowchart LR\n");
+    for (index, (project, tasks)) in by_project(tasks).into_iter().enumerate() {
+        let indent = if project.is_empty() { "    " } else { "        " };
+        if !project.is_empty() {
+            out.push_str(&format!("    subgraph project{} [\"{}\"]\n", index, project.replace('"', "#quot;")));
+        }
+        for task in tasks {
+            let class = if task.status == TaskStatus::Done { ":::done" } else { "" };
+            out.push_str(&format!("{}t{}[\"{}\"]{}\n", indent, task.id, label(task), class));
+        }
+        if !project.is_empty() {
+            out.push_str("    end\n");
+        }
+    }
+    let (dependencies, subtasks) = edges(tasks);
+    for (from, to) in dependencies {
+        out.push_str(&format!("    t{} --> t{}\n", from, to));
+    }
+    for (parent, child) in subtasks {
+        out.push_str(&format!("    t{} -.- t{}\n", parent, child));
+    }
+    out.push_str("    classDef done fill:#eee,color:#999\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tasks(links: &[(u32, &[u32], Option<u32>)]) -> HashMap<u32, Task> {
+        links
+            .iter()
+            .map(|(id, depends_on, parent)| (*id, Task { id: *id, depends_on: depends_on.to_vec(), parent: *parent, ..Default::default() }))
+            .collect()
+    }
+
+    #[test]
+    fn dependency_cycles_are_refused() {
+        // 3 depends on 2, which depends on 1
+        let tasks = tasks(&[(1, &[], None), (2, &[1], None), (3, &[2], None)]);
+        assert!(check_dependency(&tasks, 3, 1).is_ok());
+        assert!(check_dependency(&tasks, 1, 3).is_err_and(|e| e.contains("#3 already depends on #1")));
+        assert!(check_dependency(&tasks, 1, 1).is_err());
+        assert!(check_dependency(&tasks, 1, 4).is_err_and(|e| e.contains("no task #4")));
+    }
+
+    #[test]
+    fn parent_cycles_are_refused() {
+        let tasks = tasks(&[(1, &[], None), (2, &[], Some(1)), (3, &[], Some(2))]);
+        assert!(check_parent(&tasks, 3, 1).is_ok());
+        assert!(check_parent(&tasks, 1, 3).is_err_and(|e| e.contains("#3 is a subtask of #1")));
+        assert!(check_parent(&tasks, 2, 2).is_err());
+    }
+
+    #[test]
+    fn parents_average_their_subtasks() {
+        let tasks = vec![
+            Task { id: 1, progress: Some(10), ..Default::default() },
+            Task { id: 2, parent: Some(1), status: TaskStatus::Done, ..Default::default() },
+            Task { id: 3, parent: Some(1), progress: Some(50), ..Default::default() },
+            Task { id: 4, parent: Some(1), ..Default::default() },
+            Task { id: 5, ..Default::default() },
+        ];
+        assert_eq!(progress(&tasks, 1), Some(50));
+        assert_eq!(progress(&tasks, 3), Some(50));
+        assert_eq!(progress(&tasks, 5), None);
+        assert_eq!(progress_bar(60), "[######----] 60%");
+        assert_eq!(progress_bar(100), "[##########] 100%");
+    }
+}
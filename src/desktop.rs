@@ -0,0 +1,53 @@
+// Desktop notifications for the reminders the server fires, through the
+// platform's own tools: toasts on Windows, Notification Center on macOS
+#[cfg(any(windows, target_os = "macos"))]
+use tokio::process::Command;
+
+#[cfg(any(windows, target_os = "macos"))]
+use crate::i18n::t;
+
+// Toast through the WinRT notification API, shown as coming from PowerShell
+// since an unpackaged program has no app ID of its own. The text goes through
+// the environment so it needs no quoting.
+#[cfg(windows)]
+const TOAST: &str = "[void][Windows.UI.Notifications.ToastNotificationManager,Windows.UI.Notifications,ContentType=WindowsRuntime]; \
+    $xml = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+    $text = $xml.GetElementsByTagName('text'); \
+    [void]$text.Item(0).AppendChild($xml.CreateTextNode($env:TODO_TASK_TITLE)); \
+    [void]$text.Item(1).AppendChild($xml.CreateTextNode($env:TODO_TASK_MESSAGE)); \
+    $app = '{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\\WindowsPowerShell\\v1.0\\powershell.exe'; \
+    [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier($app).Show([Windows.UI.Notifications.ToastNotification]::new($xml))";
+
+#[cfg(windows)]
+pub async fn show(title: &str, message: &str) {
+    let status = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-WindowStyle", "Hidden", "-Command", TOAST])
+        .env("TODO_TASK_TITLE", title)
+        .env("TODO_TASK_MESSAGE", message)
+        .stdout(std::process::Stdio::null())
+        .status()
+        .await;
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("{}", t!("desktop-powershell-failed", status = status)),
+        Err(e) => eprintln!("{}", t!("desktop-powershell-missing", error = e)),
+    }
+}
+
+// AppleScript's notifications; the text is passed as arguments so it needs no quoting
+#[cfg(target_os = "macos")]
+pub async fn show(title: &str, message: &str) {
+    let status = Command::new("osascript")
+        .args(["-e", "on run argv", "-e", "display notification (item 2 of argv) with title (item 1 of argv)", "-e", "end run"])
+        .args([title, message])
+        .status()
+        .await;
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("{}", t!("desktop-osascript-failed", status = status)),
+        Err(e) => eprintln!("{}", t!("desktop-osascript-missing", error = e)),
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+pub async fn show(_title: &str, _message: &str) {}
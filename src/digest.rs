@@ -0,0 +1,93 @@
+// Digest of each assignee's open tasks: overdue, coming up and undated, printed
+// or emailed through the system's sendmail
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use chrono::{DateTime, Duration, Local, Utc};
+
+use crate::{Task, TaskStatus};
+
+pub struct Digest<'a> {
+    pub name: String,
+    pub address: Option<String>,
+    pub overdue: Vec<&'a Task>,
+    /// Starting within the days the digest covers
+    pub upcoming: Vec<&'a Task>,
+    pub undated: Vec<&'a Task>,
+}
+
+impl Digest<'_> {
+    pub fn count(&self) -> usize {
+        self.overdue.len() + self.upcoming.len() + self.undated.len()
+    }
+
+    pub fn subject(&self) -> String {
+        format!("Your tasks: {} overdue, {} in the coming days", self.overdue.len(), self.upcoming.len())
+    }
+
+    pub fn text(&self) -> String {
+        let mut out = format!("Tasks assigned to {}\n", self.name);
+        let line = |task: &Task| match task.start_time {
+            Some(start_time) => format!("  #{} {} ({})\n", task.id, task.title, start_time.with_timezone(&Local).format("%a %m-%d %H:%M")),
+            None => format!("  #{} {}\n", task.id, task.title),
+        };
+        for (heading, tasks) in [("Overdue", &self.overdue), ("Coming up", &self.upcoming), ("No date", &self.undated)] {
+            if !tasks.is_empty() {
+                out.push_str(&format!("\n{}:\n", heading));
+                out.extend(tasks.iter().map(|task| line(task)));
+            }
+        }
+        out
+    }
+}
+
+// Digests of the open one-off tasks of each assignee, by name, covering the
+// `days` days from `now`. Recurring tasks are left out since they come due again.
+pub fn build(tasks: &[Task], days: i64, now: DateTime<Utc>) -> BTreeMap<String, Digest<'_>> {
+    let mut digests: BTreeMap<String, Digest> = BTreeMap::new();
+    let mut open: Vec<&Task> = tasks.iter().filter(|task| task.status == TaskStatus::Todo && !task.is_recurring).collect();
+    open.sort_by_key(|task| (task.start_time, task.id));
+    for task in open {
+        let Some(assignee) = &task.assignee else { continue };
+        let digest = digests.entry(assignee.name.to_lowercase()).or_insert_with(|| Digest {
+            name: assignee.name.clone(),
+            address: None,
+            overdue: Vec::new(),
+            upcoming: Vec::new(),
+            undated: Vec::new(),
+        });
+        digest.address = digest.address.take().or_else(|| assignee.address.clone());
+        match task.start_time {
+            None => digest.undated.push(task),
+            Some(start_time) if start_time < now => digest.overdue.push(task),
+            Some(start_time) if start_time < now + Duration::days(days) => digest.upcoming.push(task),
+            Some(_) => {}
+        }
+    }
+    digests.retain(|_, digest| digest.count() > 0);
+    digests
+}
+
+// Hand an email to sendmail, which reads the recipients from the headers
+pub fn send(from: Option<&str>, to: &str, subject: &str, body: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut message = String::new();
+    if let Some(from) = from {
+        message.push_str(&format!("From: {}\n", from));
+    }
+    message.push_str(&format!("To: {}\nSubject: {}\nContent-Type: text/plain; charset=utf-8\n\n{}", to, subject, body));
+    let mut child = Command::new("sendmail")
+        .args(["-t", "-i"])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Can't run sendmail ({}); install a mail transfer agent such as msmtp or postfix", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(message.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!("sendmail failed: {}", String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(())
+}
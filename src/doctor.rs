@@ -0,0 +1,316 @@
+// `doctor`: diagnose the setup and suggest a fix for every problem found
+use std::collections::HashSet;
+use std::path::Path;
+
+use hyper::{Method, Request};
+
+use crate::caldav::CalDavBackend;
+use crate::config::{self, BackendKind, Config};
+use crate::graph;
+use crate::google;
+use crate::http;
+use crate::i18n::t;
+use crate::notify;
+use crate::paths;
+use crate::schema;
+use crate::serve::{self, ServerInfo};
+use crate::STORE_PATH;
+
+enum Outcome {
+    Ok(String),
+    /// Something is missing but the tool still works
+    Warn(String, String),
+    Fail(String, String),
+}
+
+struct Report {
+    failures: usize,
+}
+
+impl Report {
+    fn print(&mut self, check: &str, outcome: Outcome) {
+        match outcome {
+            Outcome::Ok(detail) => println!("{}", t!("doctor-ok", check = check, detail = detail)),
+            Outcome::Warn(detail, fix) => {
+                println!("{}", t!("doctor-warn", check = check, detail = detail));
+                println!("       {}", t!("doctor-fix", fix = fix));
+            }
+            Outcome::Fail(detail, fix) => {
+                self.failures += 1;
+                println!("{}", t!("doctor-fail", check = check, detail = detail));
+                println!("       {}", t!("doctor-fix", fix = fix));
+            }
+        }
+    }
+}
+
+fn check_config() -> (Outcome, Config) {
+    if !Path::new(&paths::config(config::CONFIG_PATH)).exists() {
+        let detail = t!("doctor-config-missing", path = paths::config(config::CONFIG_PATH));
+        let fix = t!("doctor-config-missing-fix", path = paths::config(config::CONFIG_PATH));
+        return (Outcome::Warn(detail, fix), Config::default());
+    }
+    match Config::load(&paths::config(config::CONFIG_PATH)) {
+        Ok(config) => (Outcome::Ok(t!("doctor-config-valid", path = paths::config(config::CONFIG_PATH))), config),
+        Err(e) => (
+            Outcome::Fail(
+                t!("doctor-config-invalid", path = paths::config(config::CONFIG_PATH), error = e),
+                t!("doctor-config-invalid-fix"),
+            ),
+            Config::default(),
+        ),
+    }
+}
+
+fn check_storage() -> Outcome {
+    let contents = match std::fs::read_to_string(paths::data(STORE_PATH)) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Outcome::Ok(t!("doctor-store-missing", path = paths::data(STORE_PATH)));
+        }
+        Err(e) => return Outcome::Fail(t!("doctor-store-unreadable", path = paths::data(STORE_PATH), error = e), t!("doctor-store-unreadable-fix")),
+    };
+    if let Ok(store) = serde_json::from_str::<serde_json::Value>(&contents)
+        && schema::version(&store) > schema::VERSION
+    {
+        return Outcome::Fail(
+            t!("doctor-store-newer", path = paths::data(STORE_PATH), version = schema::version(&store), supported = schema::VERSION),
+            t!("doctor-store-newer-fix"),
+        );
+    }
+    let (store, version) = match schema::read(&contents) {
+        Ok(read) => read,
+        Err(e) => {
+            return Outcome::Fail(
+                t!("doctor-store-corrupt", path = paths::data(STORE_PATH), error = e),
+                t!("doctor-store-corrupt-fix", path = paths::data(STORE_PATH)),
+            );
+        }
+    };
+
+    let mut problems = Vec::new();
+    let mut ids = HashSet::new();
+    let mut uuids = HashSet::new();
+    for task in &store.tasks {
+        if !ids.insert(task.id) {
+            problems.push(t!("doctor-store-duplicate-id", id = task.id));
+        }
+        if !uuids.insert(task.uuid.as_str()) {
+            problems.push(t!("doctor-store-duplicate-uuid", id = task.id));
+        }
+        if task.start_time.is_some() != task.end_time.is_some() {
+            problems.push(t!("doctor-store-half-scheduled", id = task.id));
+        }
+        if let Some((start_time, end_time)) = task.schedule()
+            && end_time < start_time
+        {
+            problems.push(t!("doctor-store-ends-early", id = task.id));
+        }
+    }
+    if let Some(max_id) = store.tasks.iter().map(|task| task.id).max()
+        && store.next_id <= max_id
+    {
+        problems.push(t!("doctor-store-next-id", next_id = store.next_id));
+    }
+    if problems.is_empty() && version < schema::VERSION {
+        Outcome::Ok(t!("doctor-store-upgradable", count = store.tasks.len(), version = version, latest = schema::VERSION))
+    } else if problems.is_empty() {
+        Outcome::Ok(t!("doctor-store-ok", count = store.tasks.len()))
+    } else {
+        Outcome::Fail(problems.join("; "), t!("doctor-store-problems-fix", path = paths::data(STORE_PATH)))
+    }
+}
+
+fn check_file(path: &str, missing: Outcome) -> Outcome {
+    if Path::new(path).exists() {
+        Outcome::Ok(t!("doctor-file-found", path = path))
+    } else {
+        missing
+    }
+}
+
+fn check_notifiers(config: &Config) -> Outcome {
+    let fix = t!("doctor-notifiers-invalid-fix", path = paths::config(config::CONFIG_PATH));
+    if let Err(e) = notify::Notifiers::new(config) {
+        return Outcome::Fail(e, fix);
+    }
+    if config.notifiers.is_empty() {
+        return Outcome::Ok(t!("doctor-notifiers-none"));
+    }
+    let on_path = |program: &str| {
+        std::env::var_os("PATH").is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file() || dir.join(format!("{}.exe", program)).is_file()))
+    };
+    let missing: Vec<String> = notify::plugins(config).into_iter().filter(|program| !on_path(program)).collect();
+    if !missing.is_empty() {
+        return Outcome::Warn(t!("doctor-plugins-missing", programs = missing.join(", ")), t!("doctor-plugins-missing-fix"));
+    }
+    let kinds: Vec<&str> = config.notifiers.iter().map(|notifier| notifier.kind.as_str()).collect();
+    Outcome::Ok(kinds.join(", "))
+}
+
+fn missing_setting(section: &str, field: &str) -> Outcome {
+    Outcome::Fail(
+        t!("doctor-setting-empty", field = field, section = section),
+        t!("doctor-setting-empty-fix", field = field, section = section, path = paths::config(config::CONFIG_PATH)),
+    )
+}
+
+fn check_credentials(config: &Config, report: &mut Report) {
+    let uses_google = config.backend == BackendKind::Google || config.google_tasks.is_some();
+    if uses_google {
+        let credentials = google::credentials_path(config);
+        let missing = Outcome::Fail(t!("doctor-file-missing", path = credentials), t!("doctor-google-credentials-fix", path = credentials));
+        report.print(&t!("doctor-check-google-credentials"), check_file(&credentials, missing));
+    }
+    match config.backend {
+        BackendKind::Google => {}
+        BackendKind::Caldav => match &config.caldav {
+            Some(caldav) if caldav.url.is_empty() => report.print(&t!("doctor-check-caldav-credentials"), missing_setting("caldav", "url")),
+            Some(caldav) if caldav.username.is_empty() => report.print(&t!("doctor-check-caldav-credentials"), missing_setting("caldav", "username")),
+            Some(_) => report.print(&t!("doctor-check-caldav-credentials"), Outcome::Ok(t!("doctor-caldav-set"))),
+            None => report.print(
+                &t!("doctor-check-caldav-credentials"),
+                Outcome::Fail(t!("doctor-section-missing", section = "caldav"), t!("doctor-caldav-missing-fix", path = paths::config(config::CONFIG_PATH))),
+            ),
+        },
+        BackendKind::Outlook => match &config.outlook {
+            Some(outlook) if outlook.client_id.is_empty() => report.print(&t!("doctor-check-outlook-credentials"), missing_setting("outlook", "client_id")),
+            Some(_) => report.print(&t!("doctor-check-outlook-credentials"), Outcome::Ok(t!("doctor-outlook-set"))),
+            None => report.print(
+                &t!("doctor-check-outlook-credentials"),
+                Outcome::Fail(t!("doctor-section-missing", section = "outlook"), t!("doctor-outlook-missing-fix", path = paths::config(config::CONFIG_PATH))),
+            ),
+        },
+    }
+    if let Some(todoist) = &config.todoist
+        && todoist.token.is_empty()
+    {
+        report.print(&t!("doctor-check-todoist-credentials"), missing_setting("todoist", "token"));
+    }
+    if let Some(github) = &config.github
+        && github.token.is_empty()
+    {
+        report.print(&t!("doctor-check-github-credentials"), missing_setting("github", "token"));
+    }
+    if let Some(jira) = &config.jira
+        && jira.api_token.is_empty()
+    {
+        report.print(&t!("doctor-check-jira-credentials"), missing_setting("jira", "api_token"));
+    }
+}
+
+async fn check_tokens(config: &Config, report: &mut Report) {
+    let sign_in = t!("doctor-sign-in-again");
+    let keyring_hint = t!("doctor-keyring-fix");
+    if (config.backend == BackendKind::Google || config.google_tasks.is_some()) && Path::new(&google::credentials_path(config)).exists() {
+        let outcome = match google::has_token(config.token_store) {
+            Err(e) => Outcome::Fail(t!("doctor-token-unreadable", error = e), keyring_hint.clone()),
+            Ok(false) => Outcome::Warn(t!("doctor-signed-out"), t!("doctor-sign-in")),
+            Ok(true) => match google::check_token(config.token_store, &google::credentials_path(config)).await {
+                Ok(()) => Outcome::Ok(t!("doctor-google-token-ok")),
+                Err(e) => Outcome::Fail(
+                    t!("doctor-token-refresh-failed", error = e),
+                    t!("doctor-google-token-fix", location = google::token_location(config.token_store)),
+                ),
+            },
+        };
+        report.print(&t!("doctor-check-google-token"), outcome);
+    }
+    if config.backend == BackendKind::Outlook
+        && let Some(outlook) = &config.outlook
+    {
+        let outcome = match graph::has_token(config.token_store) {
+            Err(e) => Outcome::Fail(t!("doctor-token-unreadable", error = e), keyring_hint),
+            Ok(false) => Outcome::Warn(t!("doctor-signed-out"), sign_in),
+            Ok(true) => match graph::check_token(outlook, config.token_store).await {
+                Ok(()) => Outcome::Ok(t!("doctor-outlook-token-ok")),
+                Err(e) => Outcome::Fail(t!("doctor-token-refresh-failed", error = e), sign_in),
+            },
+        };
+        report.print(&t!("doctor-check-outlook-token"), outcome);
+    }
+}
+
+// Any HTTP response at all means the service is reachable
+async fn reachable(url: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let request = Request::builder().method(Method::GET).uri(url).body(String::new())?;
+    let client = http::client()?;
+    tokio::time::timeout(std::time::Duration::from_secs(10), http::send(&client, request))
+        .await
+        .map_err(|_| "timed out after 10 seconds")??;
+    Ok(())
+}
+
+async fn check_network(config: &Config) -> Outcome {
+    let network_fix = t!("doctor-network-fix");
+    match config.backend {
+        BackendKind::Google => match reachable("https://www.googleapis.com/calendar/v3/colors").await {
+            Ok(()) => Outcome::Ok(t!("doctor-reachable", service = "Google Calendar API")),
+            Err(e) => Outcome::Fail(t!("doctor-unreachable", service = "Google Calendar API", error = e), network_fix),
+        },
+        BackendKind::Outlook => match reachable("https://graph.microsoft.com/v1.0/").await {
+            Ok(()) => Outcome::Ok(t!("doctor-reachable", service = "Microsoft Graph")),
+            Err(e) => Outcome::Fail(t!("doctor-unreachable", service = "Microsoft Graph", error = e), network_fix),
+        },
+        BackendKind::Caldav => {
+            let Some(caldav) = &config.caldav else {
+                return Outcome::Warn(t!("doctor-caldav-skipped"), t!("doctor-caldav-skipped-fix"));
+            };
+            let result = match CalDavBackend::new(caldav) {
+                Ok(backend) => backend.check().await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => Outcome::Ok(t!("doctor-caldav-ok", url = caldav.url)),
+                Err(e) => Outcome::Fail(t!("doctor-caldav-failed", url = caldav.url, error = e), t!("doctor-caldav-failed-fix")),
+            }
+        }
+    }
+}
+
+async fn check_server() -> Outcome {
+    let start = t!("doctor-server-start");
+    let Ok(contents) = std::fs::read_to_string(paths::data(serve::SERVER_INFO_PATH)) else {
+        return Outcome::Warn(t!("doctor-server-down"), start);
+    };
+    let Ok(info) = serde_json::from_str::<ServerInfo>(&contents) else {
+        let path = paths::data(serve::SERVER_INFO_PATH);
+        return Outcome::Fail(t!("doctor-server-info-corrupt", path = path), t!("doctor-server-info-corrupt-fix", path = path));
+    };
+    let mut addr = info.addr;
+    if addr.ip().is_unspecified() {
+        addr.set_ip(std::net::Ipv4Addr::LOCALHOST.into());
+    }
+    match reachable(&format!("http://{}/health", addr)).await {
+        Ok(()) => Outcome::Ok(t!("doctor-server-ok", pid = info.pid, address = info.addr, since = info.started_at.format("%Y-%m-%d %H:%M"))),
+        Err(e) => Outcome::Fail(
+            t!("doctor-server-unresponsive", since = info.started_at.format("%Y-%m-%d %H:%M"), pid = info.pid, address = info.addr, error = e),
+            t!("doctor-server-unresponsive-fix", path = paths::data(serve::SERVER_INFO_PATH)),
+        ),
+    }
+}
+
+// Run every check and print the results. Returns whether all of them passed.
+pub async fn run() -> bool {
+    let mut report = Report { failures: 0 };
+    let folders: Vec<String> = paths::describe().iter().map(|(kind, dir)| t!("doctor-folder", kind = kind, path = dir)).collect();
+    report.print(&t!("doctor-check-folders"), Outcome::Ok(folders.join(", ")));
+    let (outcome, config) = check_config();
+    report.print(&t!("doctor-check-config"), outcome);
+    let overrides = config::env_names();
+    if !overrides.is_empty() {
+        report.print(&t!("doctor-check-environment"), Outcome::Ok(t!("doctor-environment", names = overrides.join(", "))));
+    }
+    report.print(&t!("doctor-check-storage"), check_storage());
+    check_credentials(&config, &mut report);
+    report.print(&t!("doctor-check-notifiers"), check_notifiers(&config));
+    check_tokens(&config, &mut report).await;
+    report.print(&t!("doctor-check-network"), check_network(&config).await);
+    report.print(&t!("doctor-check-server"), check_server().await);
+    if report.failures == 0 {
+        println!("{}", t!("doctor-no-problems"));
+    } else {
+        println!("{}", t!("doctor-problems", count = report.failures));
+    }
+    report.failures == 0
+}
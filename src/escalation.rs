@@ -0,0 +1,80 @@
+// Priority escalation near deadlines: rules from the config that raise the
+// priority of one-off tasks and remind more often as their start comes near
+use chrono::{DateTime, Duration, Utc};
+
+use crate::config::EscalationRule;
+use crate::i18n::t;
+use crate::{Priority, Task, TaskStatus};
+
+// Start of an open task that isn't recurring; escalation leaves other tasks alone
+fn one_off_start(task: &Task) -> Option<DateTime<Utc>> {
+    task.start_time.filter(|_| task.status == TaskStatus::Todo && !task.is_recurring)
+}
+
+// The task's own priority, raised by the rules that apply at `now`
+pub fn priority(rules: &[EscalationRule], task: &Task, now: DateTime<Utc>) -> Priority {
+    let Some(start_time) = one_off_start(task) else { return task.priority };
+    rules
+        .iter()
+        .filter(|rule| now >= start_time - Duration::hours(rule.within_hours))
+        .filter_map(|rule| rule.priority)
+        .fold(task.priority, Ord::max)
+}
+
+// Times and messages of the extra reminders that come due in (since, until],
+// counted back from the task's start in steps of each rule's interval
+pub fn reminders(rules: &[EscalationRule], task: &Task, since: DateTime<Utc>, until: DateTime<Utc>) -> Vec<(DateTime<Utc>, String)> {
+    let Some(start_time) = one_off_start(task) else { return Vec::new() };
+    let mut due = Vec::new();
+    for rule in rules {
+        let Some(step) = rule.remind_every_minutes.filter(|minutes| *minutes > 0).map(Duration::minutes) else { continue };
+        let window_start = start_time - Duration::hours(rule.within_hours);
+        // The first step back from the start that isn't after `until`
+        let mut steps = ((start_time - until).num_seconds() + step.num_seconds() - 1).div_euclid(step.num_seconds()).max(1);
+        loop {
+            let time = start_time - step * steps as i32;
+            if time <= since || time < window_start {
+                break;
+            }
+            due.push(time);
+            steps += 1;
+        }
+    }
+    due.sort();
+    due.dedup();
+    due.into_iter().map(|time| (time, t!("remind-starts-in", length = crate::describe_length(start_time - time)))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, hour, minute, 0).unwrap()
+    }
+
+    fn rule(within_hours: i64, priority: Option<Priority>, remind_every_minutes: Option<i64>) -> EscalationRule {
+        EscalationRule { within_hours, priority, remind_every_minutes }
+    }
+
+    #[test]
+    fn priority_rises_as_the_start_nears() {
+        let task = Task { start_time: Some(at(12, 0)), priority: Priority::Low, ..Default::default() };
+        let rules = [rule(24, Some(Priority::Normal), None), rule(2, Some(Priority::Urgent), None)];
+        assert_eq!(priority(&rules, &task, Utc.with_ymd_and_hms(2026, 3, 1, 11, 0, 0).unwrap()), Priority::Low);
+        assert_eq!(priority(&rules, &task, at(9, 0)), Priority::Normal);
+        assert_eq!(priority(&rules, &task, at(13, 0)), Priority::Urgent);
+        let recurring = Task { is_recurring: true, ..task };
+        assert_eq!(priority(&rules, &recurring, at(13, 0)), Priority::Low);
+    }
+
+    #[test]
+    fn reminders_step_back_from_the_start() {
+        let task = Task { start_time: Some(at(12, 0)), ..Default::default() };
+        let rules = [rule(2, None, Some(30))];
+        let times: Vec<_> = reminders(&rules, &task, at(9, 0), at(11, 0)).into_iter().map(|(time, _)| time).collect();
+        assert_eq!(times, [at(10, 0), at(10, 30), at(11, 0)]);
+        assert!(reminders(&rules, &task, at(11, 0), at(11, 15)).is_empty());
+    }
+}
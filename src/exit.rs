@@ -0,0 +1,187 @@
+// Exit codes, so scripts can tell what went wrong: a command that fails
+// records why and the process exits with that code once the command is done
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+use hyper::StatusCode;
+
+use crate::http::StatusError;
+
+pub const SUCCESS: i32 = 0;
+/// Anything not covered below, e.g. a file that can't be written
+pub const ERROR: i32 = 1;
+/// Invalid arguments, as clap exits with for unknown options
+pub const USAGE: i32 = 2;
+/// No task, view, context or profile by that name
+pub const NOT_FOUND: i32 = 3;
+/// Sign-in missing, refused or expired
+pub const AUTH: i32 = 4;
+/// A service couldn't be reached or is failing
+pub const NETWORK: i32 = 5;
+
+static CODE: AtomicI32 = AtomicI32::new(SUCCESS);
+/// Set by `--format json`
+static JSON: AtomicBool = AtomicBool::new(false);
+
+// Print an error and record the exit code for it
+macro_rules! fail {
+    ($code:expr, $($message:tt)*) => {{
+        $crate::exit::report($code, &format!($($message)*));
+        $crate::exit::set($code)
+    }};
+}
+pub(crate) use fail;
+
+// Record that the command failed; the first failure decides the exit code
+pub fn set(code: i32) {
+    let _ = CODE.compare_exchange(SUCCESS, code, Ordering::SeqCst, Ordering::SeqCst);
+}
+
+pub fn code() -> i32 {
+    CODE.load(Ordering::SeqCst)
+}
+
+// Print errors as JSON objects from now on
+pub fn print_json(json: bool) {
+    JSON.store(json, Ordering::SeqCst);
+}
+
+// Whether `--format json` was given
+pub fn json() -> bool {
+    JSON.load(Ordering::SeqCst)
+}
+
+// Name of an exit code in JSON errors
+fn name(code: i32) -> &'static str {
+    match code {
+        USAGE => "usage",
+        NOT_FOUND => "not_found",
+        AUTH => "auth",
+        NETWORK => "network",
+        _ => "error",
+    }
+}
+
+// Print an error on stderr, as it reads or as one line of JSON
+pub fn report(code: i32, message: &str) {
+    if json() {
+        let message = message.strip_prefix("Error: ").unwrap_or(message);
+        eprintln!("{}", serde_json::json!({ "error": { "code": name(code), "message": message } }));
+    } else {
+        eprintln!("{}", message);
+    }
+}
+
+// An error followed by what caused it, "outer: inner", for messages that need
+// the detail a Debug dump has without the dump
+pub struct Chain<'a>(pub &'a (dyn Error + 'static));
+
+impl fmt::Display for Chain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut shown = self.0.to_string();
+        f.write_str(&shown)?;
+        let mut next = self.0.source();
+        while let Some(error) = next {
+            // Some errors repeat their cause in their own message
+            let text = error.to_string();
+            if !shown.contains(&text) {
+                write!(f, ": {}", text)?;
+                shown = text;
+            }
+            next = error.source();
+        }
+        Ok(())
+    }
+}
+
+// A failed sign-in, for errors that don't come with a status code
+#[derive(Debug)]
+pub struct AuthError(pub String);
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for AuthError {}
+
+fn status_code(status: u16) -> Option<i32> {
+    match StatusCode::from_u16(status).ok()? {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Some(AUTH),
+        StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS => Some(NETWORK),
+        status if status.is_server_error() => Some(NETWORK),
+        _ => None,
+    }
+}
+
+// The code for one error in a chain, if it tells
+fn classify(error: &(dyn Error + 'static)) -> Option<i32> {
+    if let Some(error) = error.downcast_ref::<StatusError>() {
+        return status_code(error.status.as_u16());
+    }
+    if error.is::<AuthError>() || error.is::<yup_oauth2::error::AuthError>() {
+        return Some(AUTH);
+    }
+    if error.is::<hyper::Error>() || error.is::<hyper_util::client::legacy::Error>() {
+        return Some(NETWORK);
+    }
+    if let Some(error) = error.downcast_ref::<std::io::Error>() {
+        use std::io::ErrorKind::*;
+        return matches!(
+            error.kind(),
+            ConnectionRefused | ConnectionReset | ConnectionAborted | NotConnected | TimedOut | HostUnreachable | NetworkUnreachable
+        )
+        .then_some(NETWORK);
+    }
+    if let Some(error) = error.downcast_ref::<yup_oauth2::Error>() {
+        return match error {
+            yup_oauth2::Error::HttpError(_) | yup_oauth2::Error::HttpClientError(_) => Some(NETWORK),
+            yup_oauth2::Error::AuthError(_) | yup_oauth2::Error::MissingAccessToken => Some(AUTH),
+            _ => None,
+        };
+    }
+    if let Some(error) = error.downcast_ref::<google_calendar3::Error>() {
+        return match error {
+            google_calendar3::Error::HttpError(_) => Some(NETWORK),
+            google_calendar3::Error::Failure(response) => status_code(response.status().as_u16()),
+            google_calendar3::Error::BadRequest(body) => body["error"]["code"].as_u64().and_then(|status| status_code(status as u16)),
+            google_calendar3::Error::MissingToken(error) => Some(match code_of(&**error) {
+                ERROR => AUTH,
+                code => code,
+            }),
+            _ => None,
+        };
+    }
+    None
+}
+
+// The exit code for a failure caused by `error`
+pub fn code_of(error: &(dyn Error + 'static)) -> i32 {
+    let mut next = Some(error);
+    while let Some(error) = next {
+        if let Some(code) = classify(error) {
+            return code;
+        }
+        next = error.source();
+    }
+    ERROR
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_are_classified() {
+        let status = |status: StatusCode| -> Box<dyn Error> { Box::new(StatusError { status, message: String::new() }) };
+        assert_eq!(code_of(&*status(StatusCode::UNAUTHORIZED)), AUTH);
+        assert_eq!(code_of(&*status(StatusCode::TOO_MANY_REQUESTS)), NETWORK);
+        assert_eq!(code_of(&*status(StatusCode::BAD_GATEWAY)), NETWORK);
+        assert_eq!(code_of(&*status(StatusCode::NOT_FOUND)), ERROR);
+        assert_eq!(code_of(&std::io::Error::from(std::io::ErrorKind::ConnectionRefused)), NETWORK);
+        assert_eq!(code_of(&std::io::Error::from(std::io::ErrorKind::PermissionDenied)), ERROR);
+        assert_eq!(code_of(&AuthError("signed out".to_string())), AUTH);
+    }
+}
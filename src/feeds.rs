@@ -0,0 +1,91 @@
+// Read-only ICS subscription feeds, cached between runs
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use hyper::{Method, Request};
+use serde::{Deserialize, Serialize};
+
+use crate::config::FeedConfig;
+use crate::atomic;
+use crate::http;
+use crate::ics;
+
+// File fetched feed events are cached in
+pub const FEEDS_PATH: &str = "feeds.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeedEvent {
+    pub title: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CachedFeed {
+    pub fetched_at: DateTime<Utc>,
+    pub events: Vec<FeedEvent>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct FeedCache {
+    pub feeds: BTreeMap<String, CachedFeed>,
+}
+
+impl FeedCache {
+    pub fn load(path: &str) -> Result<FeedCache, Box<dyn std::error::Error>> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(FeedCache::default()),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        atomic::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+async fn fetch(url: &str) -> Result<Vec<FeedEvent>, Box<dyn std::error::Error>> {
+    // webcal:// is just HTTPS with a different scheme
+    let url = match url.strip_prefix("webcal://") {
+        Some(rest) => format!("https://{}", rest),
+        None => url.to_string(),
+    };
+    let request = Request::builder().method(Method::GET).uri(url).body(String::new())?;
+    let (status, body) = http::send(&http::client()?, request).await?;
+    http::check(status, &body, "ICS feed download")?;
+    Ok(ics::parse_events(&body)
+        .into_iter()
+        .filter_map(|event| {
+            let start_time = event.start?;
+            Some(FeedEvent {
+                title: event.summary,
+                start_time,
+                end_time: event.end.unwrap_or(start_time),
+            })
+        })
+        .collect())
+}
+
+// Refresh every feed whose cached copy is older than its refresh interval,
+// dropping feeds that are no longer configured. Returns the names refreshed.
+pub async fn refresh(feeds: &[FeedConfig], cache: &mut FeedCache) -> Vec<(String, Result<usize, Box<dyn std::error::Error>>)> {
+    cache.feeds.retain(|name, _| feeds.iter().any(|feed| &feed.name == name));
+    let mut results = Vec::new();
+    for feed in feeds {
+        let fresh = cache.feeds.get(&feed.name).is_some_and(|cached| {
+            Utc::now() - cached.fetched_at < chrono::Duration::minutes(feed.refresh_minutes)
+        });
+        if fresh {
+            continue;
+        }
+        let result = fetch(&feed.url).await.map(|events| {
+            let count = events.len();
+            cache.feeds.insert(feed.name.clone(), CachedFeed { fetched_at: Utc::now(), events });
+            count
+        });
+        results.push((feed.name.clone(), result));
+    }
+    results
+}
@@ -0,0 +1,581 @@
+// Filter expressions for `list` and saved views, e.g.
+// "priority>=high and (tag:work or project:ops) and due<friday"
+//
+// Terms are "key:value" (or =, !=, <, <=, >, >= where they make sense) and bare
+// words searched for in the title and details. Terms next to each other have
+// to match together, as with "and"; "or", "not" and parentheses work as usual.
+use std::cmp::Ordering;
+
+use std::ops::Bound;
+
+use chrono::{Datelike, Duration, Local, NaiveDate, Weekday};
+
+use crate::store::{self, Lookup};
+use crate::{Priority, Task, TaskStatus, local_midnight};
+
+// Longest first, so "<=" isn't read as "<"
+const OPERATORS: [&str; 7] = ["!=", "<=", ">=", ":", "=", "<", ">"];
+const KEYS: &str = "status, tag, project, priority, due, waiting, assignee, meta.<name>";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Operator(&'static str),
+    Word(String),
+    /// Text in double quotes, always a value or a search
+    Quoted(String),
+}
+
+struct Lexeme {
+    token: Token,
+    /// 1-based, in characters
+    column: usize,
+}
+
+// Where an expression went wrong
+struct Error {
+    message: String,
+    column: usize,
+}
+
+fn error<T>(message: impl Into<String>, column: usize) -> Result<T, Error> {
+    Err(Error { message: message.into(), column })
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Comparison {
+    Equal,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Comparison::Equal => ordering == Ordering::Equal,
+            Comparison::Less => ordering == Ordering::Less,
+            Comparison::LessOrEqual => ordering != Ordering::Greater,
+            Comparison::Greater => ordering == Ordering::Greater,
+            Comparison::GreaterOrEqual => ordering != Ordering::Less,
+        }
+    }
+}
+
+enum Test {
+    Status(TaskStatus),
+    /// Open and waiting on someone, whose name contains the text when set
+    Waiting(Option<String>),
+    Tag(String),
+    /// None for tasks without a project
+    Project(Option<String>),
+    Priority(Comparison, Priority),
+    /// Local start date compared with a day
+    Due(Comparison, NaiveDate),
+    Undated,
+    /// Assigned to someone by that name or address; None for unassigned tasks
+    Assignee(Option<String>),
+    /// Custom attribute compared with a value, as numbers when both are; None
+    /// for tasks without the attribute
+    Meta(String, Comparison, Option<String>),
+    /// Found in the title or details
+    Text(String),
+}
+
+enum Expr {
+    All(Vec<Expr>),
+    Any(Vec<Expr>),
+    Not(Box<Expr>),
+    Test(Test),
+}
+
+pub struct Filter {
+    expr: Expr,
+}
+
+fn is_operator_start(chars: &[char], index: usize) -> bool {
+    match chars[index] {
+        ':' | '=' | '<' | '>' => true,
+        // "!" on its own negates a value, as in "tag:!work"
+        '!' => chars.get(index + 1) == Some(&'='),
+        _ => false,
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Lexeme>, Error> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut lexemes = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let column = index + 1;
+        let token = match chars[index] {
+            c if c.is_whitespace() => {
+                index += 1;
+                continue;
+            }
+            '(' => {
+                index += 1;
+                Token::Open
+            }
+            ')' => {
+                index += 1;
+                Token::Close
+            }
+            '"' => {
+                let Some(length) = chars[index + 1..].iter().position(|c| *c == '"') else {
+                    return error("this quote is never closed", column);
+                };
+                let quoted = chars[index + 1..index + 1 + length].iter().collect();
+                index += length + 2;
+                Token::Quoted(quoted)
+            }
+            _ if is_operator_start(&chars, index) => {
+                let rest: String = chars[index..].iter().take(2).collect();
+                let operator = OPERATORS.iter().find(|operator| rest.starts_with(**operator)).copied().unwrap_or(":");
+                index += operator.len();
+                Token::Operator(operator)
+            }
+            _ => {
+                let start = index;
+                while index < chars.len() && !chars[index].is_whitespace() && !"()\"".contains(chars[index]) && !is_operator_start(&chars, index) {
+                    index += 1;
+                }
+                Token::Word(chars[start..index].iter().collect())
+            }
+        };
+        lexemes.push(Lexeme { token, column });
+    }
+    Ok(lexemes)
+}
+
+// "today", "tomorrow", "yesterday", a weekday (the coming one, so "friday"
+// on a Friday is next week's) or YYYY-MM-DD
+pub fn parse_day(value: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match value.to_lowercase().as_str() {
+        "today" => return Some(today),
+        "tomorrow" => return Some(today + Duration::days(1)),
+        "yesterday" => return Some(today - Duration::days(1)),
+        _ => {}
+    }
+    if let Ok(weekday) = value.parse::<Weekday>() {
+        let ahead = (weekday.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64).rem_euclid(7);
+        return Some(today + Duration::days(if ahead == 0 { 7 } else { ahead }));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+// The test for one "key<operator>value" term; Err for a value that doesn't fit
+fn test(key: &str, comparison: Comparison, value: &str) -> Result<Test, String> {
+    let equal_only = |name: &str| match comparison {
+        Comparison::Equal => Ok(()),
+        _ => Err(format!("{} can only be compared with ':', '=' or '!='", name)),
+    };
+    Ok(match key {
+        "status" => {
+            equal_only("status")?;
+            match value.to_lowercase().as_str() {
+                "todo" => Test::Status(TaskStatus::Todo),
+                "done" => Test::Status(TaskStatus::Done),
+                "waiting" => Test::Waiting(None),
+                _ => return Err(format!("unknown status '{}'; use todo, done or waiting", value)),
+            }
+        }
+        "tag" => {
+            equal_only("tag")?;
+            Test::Tag(value.trim_start_matches('#').to_string())
+        }
+        "project" => {
+            equal_only("project")?;
+            Test::Project(Some(value.to_string()).filter(|project| !project.eq_ignore_ascii_case("none")))
+        }
+        "waiting" => {
+            equal_only("waiting")?;
+            Test::Waiting(Some(value.to_lowercase()))
+        }
+        "assignee" => {
+            equal_only("assignee")?;
+            Test::Assignee(Some(value.to_string()).filter(|who| !who.eq_ignore_ascii_case("none")))
+        }
+        "priority" => {
+            let priority = <Priority as clap::ValueEnum>::from_str(value, true)
+                .map_err(|_| format!("unknown priority '{}'; use low, normal, high or urgent", value))?;
+            Test::Priority(comparison, priority)
+        }
+        "due" | "start" if value.eq_ignore_ascii_case("none") => {
+            equal_only("due:none")?;
+            Test::Undated
+        }
+        "due" | "start" => {
+            let day = parse_day(value, Local::now().date_naive())
+                .ok_or_else(|| format!("'{}' is not a day; use today, tomorrow, a weekday or YYYY-MM-DD", value))?;
+            Test::Due(comparison, day)
+        }
+        _ if let Some(name) = key.strip_prefix("meta.") => {
+            let value = Some(value.to_string()).filter(|value| !value.eq_ignore_ascii_case("none"));
+            if value.is_none() {
+                equal_only("meta.<name>:none")?;
+            }
+            Test::Meta(name.to_string(), comparison, value)
+        }
+        _ => return Err(format!("unknown key '{}'; use {}", key, KEYS)),
+    })
+}
+
+struct Parser {
+    lexemes: Vec<Lexeme>,
+    position: usize,
+    /// Column just past the end, for errors about something missing
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.lexemes.get(self.position).map(|lexeme| &lexeme.token)
+    }
+
+    fn column(&self) -> usize {
+        self.lexemes.get(self.position).map_or(self.end, |lexeme| lexeme.column)
+    }
+
+    // Consume the keyword if it comes next
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Expr, Error> {
+        let mut any = vec![self.and()?];
+        while self.keyword("or") {
+            any.push(self.and()?);
+        }
+        Ok(if any.len() == 1 { any.remove(0) } else { Expr::Any(any) })
+    }
+
+    fn and(&mut self) -> Result<Expr, Error> {
+        let mut all = vec![self.not()?];
+        loop {
+            if self.keyword("and") {
+                all.push(self.not()?);
+                continue;
+            }
+            let starts_term = match self.peek() {
+                Some(Token::Word(word)) => !word.eq_ignore_ascii_case("or"),
+                Some(Token::Open | Token::Quoted(_)) => true,
+                _ => false,
+            };
+            if !starts_term {
+                break;
+            }
+            all.push(self.not()?);
+        }
+        Ok(if all.len() == 1 { all.remove(0) } else { Expr::All(all) })
+    }
+
+    fn not(&mut self) -> Result<Expr, Error> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.term()
+    }
+
+    fn term(&mut self) -> Result<Expr, Error> {
+        let column = self.column();
+        let Some(lexeme) = self.lexemes.get(self.position) else {
+            return error("expected a term here", column);
+        };
+        let token = lexeme.token.clone();
+        self.position += 1;
+        match token {
+            Token::Open => {
+                let expr = self.or()?;
+                if self.peek() != Some(&Token::Close) {
+                    return error("this '(' is never closed", column);
+                }
+                self.position += 1;
+                Ok(expr)
+            }
+            Token::Close => error("this ')' has no matching '('", column),
+            Token::Operator(operator) => error(format!("'{}' needs a key before it, e.g. priority>=high", operator), column),
+            Token::Quoted(text) => Ok(Expr::Test(Test::Text(text.to_lowercase()))),
+            Token::Word(word) => match self.peek() {
+                Some(Token::Operator(operator)) => {
+                    let operator = *operator;
+                    self.position += 1;
+                    self.comparison(&word, column, operator)
+                }
+                _ => Ok(Expr::Test(Test::Text(word.to_lowercase()))),
+            },
+        }
+    }
+
+    fn comparison(&mut self, key: &str, key_column: usize, operator: &str) -> Result<Expr, Error> {
+        let key = key.to_lowercase();
+        let meta = key.strip_prefix("meta.").is_some_and(|name| !name.is_empty());
+        if !meta && !["status", "tag", "project", "priority", "due", "start", "waiting", "assignee"].contains(&key.as_str()) {
+            return error(format!("unknown key '{}'; use {}", key, KEYS), key_column);
+        }
+        let column = self.column();
+        let value = match self.peek() {
+            Some(Token::Word(value) | Token::Quoted(value)) => value.clone(),
+            _ => return error(format!("expected a value after '{}{}'", key, operator), column),
+        };
+        self.position += 1;
+        // "!=" and "key:!value" both negate
+        let (value, negated) = match value.strip_prefix('!') {
+            Some(value) if matches!(operator, ":" | "=") => (value.to_string(), true),
+            _ => (value, operator == "!="),
+        };
+        let comparison = match operator {
+            "<" => Comparison::Less,
+            "<=" => Comparison::LessOrEqual,
+            ">" => Comparison::Greater,
+            ">=" => Comparison::GreaterOrEqual,
+            _ => Comparison::Equal,
+        };
+        let test = test(&key, comparison, &value).map_err(|message| Error { message, column })?;
+        Ok(if negated { Expr::Not(Box::new(Expr::Test(test))) } else { Expr::Test(test) })
+    }
+}
+
+impl Test {
+    fn matches(&self, task: &Task) -> bool {
+        match self {
+            Test::Status(status) => task.status == *status,
+            Test::Waiting(on) => {
+                let waiting = task.waiting.as_ref().filter(|_| task.status == TaskStatus::Todo);
+                waiting.is_some_and(|waiting| on.as_ref().is_none_or(|on| waiting.on.to_lowercase().contains(on)))
+            }
+            // Compared as the index has them, so lookups find every match
+            Test::Tag(tag) => task.tags.iter().any(|own| store::tag_key(own) == store::tag_key(tag)),
+            Test::Project(None) => task.project.is_none(),
+            Test::Project(Some(project)) => task.project.as_deref().is_some_and(|own| own.eq_ignore_ascii_case(project)),
+            Test::Priority(comparison, priority) => comparison.holds(task.priority.cmp(priority)),
+            Test::Due(comparison, day) => task
+                .start_time
+                .is_some_and(|start_time| comparison.holds(start_time.with_timezone(&Local).date_naive().cmp(day))),
+            Test::Undated => task.start_time.is_none(),
+            Test::Assignee(None) => task.assignee.is_none(),
+            Test::Assignee(Some(who)) => task.assignee.as_ref().is_some_and(|assignee| assignee.is(who)),
+            Test::Meta(name, comparison, value) => {
+                let own = task.meta.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, own)| own);
+                match (own, value) {
+                    (own, None) => own.is_none(),
+                    (None, Some(_)) => false,
+                    (Some(own), Some(value)) => {
+                        let ordering = match (own.parse::<f64>(), value.parse::<f64>()) {
+                            (Ok(own), Ok(value)) => own.partial_cmp(&value).unwrap_or(Ordering::Equal),
+                            _ => own.to_lowercase().cmp(&value.to_lowercase()),
+                        };
+                        comparison.holds(ordering)
+                    }
+                }
+            }
+            Test::Text(text) => task.title.to_lowercase().contains(text) || task.details.to_lowercase().contains(text),
+        }
+    }
+}
+
+impl Test {
+    // The index lookup covering every task this matches, if there is one
+    fn lookup(&self) -> Option<Lookup> {
+        let day = |day: NaiveDate| local_midnight(day);
+        let next = |day: NaiveDate| local_midnight(day + Duration::days(1));
+        Some(match self {
+            Test::Status(status) => Lookup::Status(*status),
+            Test::Waiting(_) => Lookup::Status(TaskStatus::Todo),
+            Test::Tag(tag) => Lookup::Tag(tag.clone()),
+            Test::Due(Comparison::Equal, on) => Lookup::Start(Bound::Included(day(*on)), Bound::Excluded(next(*on))),
+            Test::Due(Comparison::Less, on) => Lookup::Start(Bound::Unbounded, Bound::Excluded(day(*on))),
+            Test::Due(Comparison::LessOrEqual, on) => Lookup::Start(Bound::Unbounded, Bound::Excluded(next(*on))),
+            Test::Due(Comparison::Greater, on) => Lookup::Start(Bound::Included(next(*on)), Bound::Unbounded),
+            Test::Due(Comparison::GreaterOrEqual, on) => Lookup::Start(Bound::Included(day(*on)), Bound::Unbounded),
+            Test::Undated => Lookup::Undated,
+            _ => return None,
+        })
+    }
+}
+
+impl Expr {
+    // Lookups every match is found by: those of the terms that all have to match
+    fn lookups(&self, lookups: &mut Vec<Lookup>) {
+        match self {
+            Expr::All(all) => all.iter().for_each(|expr| expr.lookups(lookups)),
+            Expr::Test(test) => lookups.extend(test.lookup()),
+            Expr::Any(_) | Expr::Not(_) => {}
+        }
+    }
+
+    fn matches(&self, task: &Task) -> bool {
+        match self {
+            Expr::All(all) => all.iter().all(|expr| expr.matches(task)),
+            Expr::Any(any) => any.iter().any(|expr| expr.matches(task)),
+            Expr::Not(expr) => !expr.matches(task),
+            Expr::Test(test) => test.matches(task),
+        }
+    }
+}
+
+impl Filter {
+    // Parse an expression; the error points at the problem in the text
+    pub fn parse(text: &str) -> Result<Filter, String> {
+        let parse = || {
+            let lexemes = tokenize(text)?;
+            let mut parser = Parser { lexemes, position: 0, end: text.chars().count() + 1 };
+            if parser.lexemes.is_empty() {
+                return Ok(Expr::All(Vec::new()));
+            }
+            let expr = parser.or()?;
+            match parser.peek() {
+                None => Ok(expr),
+                Some(Token::Close) => error("this ')' has no matching '('", parser.column()),
+                Some(Token::Operator(operator)) => error(format!("'{}' needs a key before it, e.g. priority>=high", operator), parser.column()),
+                Some(_) => error("expected 'and' or 'or' here", parser.column()),
+            }
+        };
+        parse().map(|expr| Filter { expr }).map_err(|e| format!("{} at column {}\n  {}\n  {}^", e.message, e.column, text, " ".repeat(e.column - 1)))
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        self.expr.matches(task)
+    }
+
+    // What to look up in the store's indexes to find the tasks this can match
+    pub fn lookups(&self) -> Vec<Lookup> {
+        let mut lookups = Vec::new();
+        self.expr.lookups(&mut lookups);
+        lookups
+    }
+
+    // Tasks assigned to `who`, as "assignee:<who>" would match
+    pub fn assignee(who: &str) -> Filter {
+        Filter { expr: Expr::Test(Test::Assignee(Some(who.to_string()))) }
+    }
+
+    // Tasks tagged `tag`, as "tag:<tag>" would match
+    pub fn tag(tag: &str) -> Filter {
+        Filter { expr: Expr::Test(Test::Tag(tag.trim_start_matches('#').to_string())) }
+    }
+
+    // Tasks matching both filters
+    pub fn and(self, other: Filter) -> Filter {
+        Filter { expr: Expr::All(vec![self.expr, other.expr]) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::TaskStore;
+    use chrono::{TimeZone, Utc};
+
+    fn task(id: u32, title: &str, tags: &[&str], priority: Priority) -> Task {
+        Task { id, title: title.to_string(), tags: tags.iter().map(|tag| tag.to_string()).collect(), priority, ..Default::default() }
+    }
+
+    // Titles of the tasks the expression matches
+    fn matching(text: &str, tasks: &[Task]) -> Vec<String> {
+        let filter = Filter::parse(text).unwrap_or_else(|e| panic!("{}: {}", text, e));
+        tasks.iter().filter(|task| filter.matches(task)).map(|task| task.title.clone()).collect()
+    }
+
+    // The first line of the error for the expression
+    fn error(text: &str) -> String {
+        Filter::parse(text).err().expect(text).lines().next().unwrap().to_string()
+    }
+
+    fn letters() -> Vec<Task> {
+        vec![task(1, "a", &[], Priority::Normal), task(2, "b", &[], Priority::Normal), task(3, "c", &[], Priority::Normal), task(4, "b c", &[], Priority::Normal)]
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(matching("a or b and c", &letters()), ["a", "b c"]);
+        assert_eq!(matching("(a or b) and c", &letters()), ["b c"]);
+    }
+
+    #[test]
+    fn terms_next_to_each_other_all_match() {
+        assert_eq!(matching("b c", &letters()), ["b c"]);
+        assert_eq!(matching("b AND c", &letters()), ["b c"]);
+    }
+
+    #[test]
+    fn negation() {
+        let tasks = [task(1, "one", &["work"], Priority::High), task(2, "two", &["#Home"], Priority::Low), task(3, "three", &[], Priority::Normal)];
+        assert_eq!(matching("not tag:work", &tasks), ["two", "three"]);
+        assert_eq!(matching("tag:!work", &tasks), ["two", "three"]);
+        assert_eq!(matching("tag!=work", &tasks), ["two", "three"]);
+        assert_eq!(matching("tag:home", &tasks), ["two"]);
+        assert_eq!(matching("priority!=normal", &tasks), ["one", "two"]);
+        assert_eq!(matching("not not priority>=high", &tasks), ["one"]);
+    }
+
+    #[test]
+    fn quoted_text() {
+        let tasks = [task(1, "Call Bob (re: or)", &[], Priority::Normal), task(2, "Call", &[], Priority::Normal)];
+        assert_eq!(matching("\"bob (re: or)\"", &tasks), ["Call Bob (re: or)"]);
+        assert_eq!(matching("tag:\"a b\" or call", &tasks), ["Call Bob (re: or)", "Call"]);
+    }
+
+    #[test]
+    fn errors_point_at_the_column() {
+        assert_eq!(error("tag:a \"open"), "this quote is never closed at column 7");
+        assert_eq!(error("(tag:a or b"), "this '(' is never closed at column 1");
+        assert_eq!(error("tag:a )"), "this ')' has no matching '(' at column 7");
+        assert_eq!(error(") tag:a"), "this ')' has no matching '(' at column 1");
+        assert_eq!(error(">=high"), "'>=' needs a key before it, e.g. priority>=high at column 1");
+        assert_eq!(error("tag:a >=high"), "'>=' needs a key before it, e.g. priority>=high at column 7");
+        assert_eq!(error("colour:red"), format!("unknown key 'colour'; use {} at column 1", KEYS));
+        assert_eq!(error("a tag:"), "expected a value after 'tag:' at column 7");
+        assert_eq!(error("a or"), "expected a term here at column 5");
+        assert_eq!(error("priority:extreme"), "unknown priority 'extreme'; use low, normal, high or urgent at column 10");
+        assert_eq!(error("status>todo"), "status can only be compared with ':', '=' or '!=' at column 8");
+        assert_eq!(error("due:someday"), "'someday' is not a day; use today, tomorrow, a weekday or YYYY-MM-DD at column 5");
+    }
+
+    #[test]
+    fn error_marks_the_column() {
+        assert_eq!(Filter::parse("a )").err().unwrap(), "this ')' has no matching '(' at column 3\n  a )\n    ^");
+    }
+
+    #[test]
+    fn lookups_find_every_match() {
+        let dated = |id, title: &str, day: u32| Task {
+            start_time: Some(Utc.with_ymd_and_hms(2030, 1, day, 12, 0, 0).unwrap()),
+            ..task(id, title, &["Été"], Priority::Normal)
+        };
+        let tasks = vec![
+            task(1, "one", &["work", "ÉTÉ"], Priority::High),
+            task(2, "two", &["#Work"], Priority::Low),
+            Task { status: TaskStatus::Done, ..task(3, "three", &["été"], Priority::Normal) },
+            dated(4, "four", 1),
+            dated(5, "five", 2),
+        ];
+        let store = TaskStore::new(0, tasks.clone());
+        let filters = [
+            "tag:work",
+            "tag:été",
+            "tag:#ÉTÉ",
+            "status:done tag:Été",
+            "status:todo and (tag:work or due:none)",
+            "due:2030-01-01",
+            "due<=2030-01-02 tag:été",
+            "due>2030-01-01",
+            "due:none priority:high",
+        ];
+        for text in filters {
+            let filter = Filter::parse(text).unwrap();
+            let candidates: Vec<u32> = store.candidates(&filter.lookups()).iter().map(|task| task.id).collect();
+            let matches: Vec<&Task> = tasks.iter().filter(|task| filter.matches(task)).collect();
+            assert!(!matches.is_empty(), "{} matches nothing", text);
+            for task in matches {
+                assert!(candidates.contains(&task.id), "{} matches #{} but its lookups don't find it", text, task.id);
+            }
+        }
+    }
+}
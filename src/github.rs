@@ -0,0 +1,145 @@
+// GitHub issues: tasks from assigned issues, closing issues of completed tasks
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, Utc};
+use hyper::{Method, Request};
+use serde_json::{json, Value};
+
+use crate::config::GitHubConfig;
+use crate::http::{self, HttpClient};
+use crate::{AppState, Task, TaskStatus};
+
+const GITHUB_URL: &str = "https://api.github.com";
+
+#[derive(Debug, Default)]
+pub struct GitHubSummary {
+    pub added: usize,
+    pub updated: usize,
+    /// Local tasks marked done because their issue was closed
+    pub completed: usize,
+    /// Issues closed because their task was completed here
+    pub closed: usize,
+}
+
+struct GitHubClient {
+    client: HttpClient,
+    token: String,
+}
+
+impl GitHubClient {
+    fn new(config: &GitHubConfig) -> Result<GitHubClient, Box<dyn std::error::Error>> {
+        Ok(GitHubClient { client: http::client()?, token: config.token.clone() })
+    }
+
+    async fn call(&self, method: Method, path: &str, body: Option<Value>) -> Result<Value, Box<dyn std::error::Error>> {
+        let request = Request::builder()
+            .method(method.clone())
+            .uri(format!("{}{}", GITHUB_URL, path))
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            // GitHub rejects requests without a user agent
+            .header("User-Agent", "todo_task")
+            .body(body.map(|body| body.to_string()).unwrap_or_default())?;
+        let (status, body) = http::send(&self.client, request).await?;
+        http::check(status, &body, &format!("GitHub {} {}", method, path))?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    async fn close(&self, issue: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let (repo, number) = parse_issue(issue).ok_or("invalid issue reference")?;
+        let path = format!("/repos/{}/issues/{}", repo, number);
+        self.call(Method::PATCH, &path, Some(json!({ "state": "closed" }))).await?;
+        Ok(())
+    }
+
+    async fn is_closed(&self, issue: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let (repo, number) = parse_issue(issue).ok_or("invalid issue reference")?;
+        let item = self.call(Method::GET, &format!("/repos/{}/issues/{}", repo, number), None).await?;
+        Ok(item["state"].as_str() == Some("closed"))
+    }
+}
+
+// Split "owner/repo#123" into the repository and issue number
+pub fn parse_issue(issue: &str) -> Option<(&str, u64)> {
+    let (repo, number) = issue.split_once('#')?;
+    let (owner, name) = repo.split_once('/')?;
+    if owner.is_empty() || name.is_empty() || name.contains('/') {
+        return None;
+    }
+    Some((repo, number.parse().ok()?))
+}
+
+// The milestone's due date, if any, becomes the task's start time
+fn issue_task(item: &Value) -> Option<Task> {
+    let repo = item["repository"]["full_name"].as_str()?;
+    let issue = format!("{}#{}", repo, item["number"].as_u64()?);
+    let due: Option<DateTime<Utc>> = item["milestone"]["due_on"].as_str().and_then(|due| due.parse().ok());
+    Some(Task {
+        id: 0,
+        title: item["title"].as_str()?.to_string(),
+        details: item["html_url"].as_str().unwrap_or_default().to_string(),
+        start_time: due,
+        end_time: due.map(|due| due + Duration::minutes(30)),
+        status: TaskStatus::Todo,
+        project: Some(repo.to_string()),
+        tags: item["labels"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|label| label["name"].as_str().map(str::to_string))
+            .collect(),
+        github_issue: Some(issue),
+        ..Default::default()
+    })
+}
+
+pub async fn close_issue(config: &GitHubConfig, issue: &str) -> Result<(), Box<dyn std::error::Error>> {
+    GitHubClient::new(config)?.close(issue).await
+}
+
+// Pull open assigned issues, then reconcile closed issues and completed tasks
+pub async fn sync(config: &GitHubConfig, state: &AppState) -> Result<GitHubSummary, Box<dyn std::error::Error>> {
+    let client = GitHubClient::new(config)?;
+    let mut summary = GitHubSummary::default();
+    let mut open = HashSet::new();
+    let mut page = 1;
+    loop {
+        let path = format!("/issues?filter=assigned&state=open&per_page=100&page={}", page);
+        let items = client.call(Method::GET, &path, None).await?;
+        let items = items.as_array().cloned().unwrap_or_default();
+        for item in &items {
+            // The issues endpoint includes pull requests
+            if item.get("pull_request").is_some() {
+                continue;
+            }
+            let Some(task) = issue_task(item) else { continue };
+            open.extend(task.github_issue.clone());
+            if state.upsert_imported(task, |task| task.github_issue.clone()).await {
+                summary.updated += 1;
+            } else {
+                summary.added += 1;
+            }
+        }
+        if items.len() < 100 {
+            break;
+        }
+        page += 1;
+    }
+
+    for task in state.list_tasks().await {
+        let Some(issue) = &task.github_issue else { continue };
+        match task.status {
+            TaskStatus::Done if open.contains(issue) => {
+                client.close(issue).await?;
+                summary.closed += 1;
+            }
+            // No longer listed: either closed or unassigned, so ask
+            TaskStatus::Todo if !open.contains(issue) && client.is_closed(issue).await? => {
+                state.complete_task(task.id).await;
+                summary.completed += 1;
+            }
+            _ => {}
+        }
+    }
+    Ok(summary)
+}
@@ -0,0 +1,127 @@
+// Sync of the task store between devices through a Git repository: each device
+// commits its own change journal to the repository, under ops/<device>.jsonl, and
+// applies the journals the others pushed
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+use chrono::{DateTime, SubsecRound, Utc};
+
+use crate::config::GitConfig;
+use crate::paths;
+use crate::{StoreFile, atomic, audit, crdt, schema};
+
+// Folder of the journals inside the repository
+const OPS_DIR: &str = "ops";
+
+#[derive(Default)]
+pub struct Summary {
+    /// Changes received from other devices
+    pub received: usize,
+    /// Tasks added on another device
+    pub added: usize,
+    /// Tasks here changed by another device
+    pub updated: usize,
+    /// Tasks removed on another device
+    pub removed: usize,
+    /// Whether a new version was pushed
+    pub pushed: bool,
+}
+
+// Run git in `dir`, returning its output
+fn git(dir: &Path, args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Can't run git ({}); is it installed?", e))?;
+    if !output.status.success() {
+        return Err(format!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+fn read_store(path: &str) -> Result<StoreFile, Box<dyn std::error::Error>> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => Ok(schema::read(&contents)?.0),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(StoreFile::default()),
+        Err(e) => Err(Box::new(e)),
+    }
+}
+
+// Pull the journals other devices pushed, apply them to the store at `path`, and
+// commit and push this device's journal
+pub fn sync(config: &GitConfig, path: &str) -> Result<Summary, Box<dyn std::error::Error>> {
+    // A relative folder is kept with the tasks
+    let clone = paths::data(&config.dir);
+    let dir = Path::new(&clone);
+    if !dir.join(".git").exists() {
+        git(Path::new("."), &["clone", "--quiet", &config.remote, &clone])?;
+    }
+    git(dir, &["fetch", "--quiet", "origin"])?;
+    // Start from what was pushed, so the new commit goes on top of it
+    let remote = format!("origin/{}", config.branch);
+    if git(dir, &["rev-parse", "--verify", "--quiet", &remote]).is_ok() {
+        git(dir, &["checkout", "--quiet", "--force", "-B", &config.branch, &remote])?;
+    } else {
+        git(dir, &["symbolic-ref", "HEAD", &format!("refs/heads/{}", config.branch)])?;
+    }
+
+    // Nobody else saves the store until it's merged
+    let _lock = atomic::lock(path)?;
+    // Changes made to the store by hand are recorded first
+    let (who, command, now) = (audit::user(), audit::command(), Utc::now().trunc_subsecs(0));
+    let before = read_store(path)?;
+    audit::log(crdt::record(&before)?, &[], &before.tasks, &who, &command, now)?;
+    let mut ops = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(dir.join(OPS_DIR)) {
+        for entry in entries {
+            ops.extend(crdt::parse(&std::fs::read_to_string(entry?.path())?)?);
+        }
+    }
+    let received = crdt::merge(ops)?;
+    let mut summary = Summary { received: received.len(), ..Default::default() };
+    if summary.received > 0 {
+        let after = crdt::materialize(before.next_id)?;
+        for (stamp, change) in received {
+            let time = DateTime::from_timestamp(stamp.time.div_euclid(1000), 0).unwrap_or(now);
+            let device = format!("device {}", stamp.device.chars().take(8).collect::<String>());
+            audit::log(vec![change], &before.tasks, &after.tasks, &device, &command, time)?;
+        }
+        let mut old = HashMap::new();
+        for task in &before.tasks {
+            old.insert(task.uuid.as_str(), serde_json::to_value(task)?);
+        }
+        for task in &after.tasks {
+            match old.get(task.uuid.as_str()) {
+                None => summary.added += 1,
+                Some(value) if *value != serde_json::to_value(task)? => summary.updated += 1,
+                Some(_) => {}
+            }
+        }
+        summary.removed = (before.tasks.len() + summary.added).saturating_sub(after.tasks.len());
+        atomic::write(path, serde_json::to_string_pretty(&after)?)?;
+        // IDs given to tasks that came with one already used here are recorded too
+        audit::log(crdt::record(&after)?, &before.tasks, &after.tasks, &who, &command, now)?;
+    }
+
+    // This device's own operations
+    let device = crdt::Device::load(&paths::data(crdt::DEVICE_PATH))?;
+    let mut own = String::new();
+    for op in crdt::load(&paths::data(crdt::JOURNAL_PATH))?.iter().filter(|op| op.stamp.device == device.id) {
+        own.push_str(&serde_json::to_string(op)?);
+        own.push('\n');
+    }
+    let file = format!("{}/{}.jsonl", OPS_DIR, device.id);
+    std::fs::create_dir_all(dir.join(OPS_DIR))?;
+    std::fs::write(dir.join(&file), own)?;
+    git(dir, &["add", &file])?;
+    if git(dir, &["diff", "--cached", "--quiet"]).is_err() {
+        git(dir, &["commit", "--quiet", "-m", "Update task journal"])?;
+        git(dir, &["push", "--quiet", "origin", &config.branch])
+            .map_err(|e| format!("{}\nAnother device may have pushed in the meantime; run `sync --git` again.", e))?;
+        summary.pushed = true;
+    }
+    Ok(summary)
+}
@@ -0,0 +1,509 @@
+use chrono::{DateTime, Duration, Utc};
+use google_calendar3::{api::Event, CalendarHub};
+use serde::{Deserialize, Serialize};
+use yup_oauth2::authenticator::Authenticator;
+use yup_oauth2::storage::{TokenInfo, TokenStorage};
+use yup_oauth2::{InstalledFlowAuthenticator, InstalledFlowReturnMethod};
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+
+use crate::config::{CalendarConfig, Config, TokenStore};
+use crate::http::{self, HyperConnector};
+use crate::i18n::t;
+use crate::ics;
+use crate::keyring;
+use crate::paths;
+use crate::sync::{self, CalendarBackend, RemoteEvent, Rsvp};
+use crate::Task;
+
+// OAuth client downloaded from the Google Cloud console
+pub const CREDENTIALS_PATH: &str = "credentials.json";
+// File the Google OAuth tokens are persisted to with `"token_store": "file"`
+pub const TOKEN_STORE_PATH: &str = "token_store.json";
+// Keyring entry the tokens are kept in otherwise
+const KEYRING_ACCOUNT: &str = "google";
+
+// Name of the calendar `calendars create` and the first sync offer to make
+pub const TODO_CALENDAR: &str = "Todo CLI";
+
+const BATCH_URL: &str = "https://www.googleapis.com/batch/calendar/v3";
+// Most requests Google accepts in one batch
+pub const BATCH_SIZE: usize = 50;
+const CALENDAR_SCOPE: &str = "https://www.googleapis.com/auth/calendar";
+
+// A token and the scopes it was granted for, laid out as in yup-oauth2's token file
+#[derive(Serialize, Deserialize)]
+struct StoredToken {
+    scopes: Vec<String>,
+    token: TokenInfo,
+}
+
+// Token storage in the keyring or token_store.json, as configured
+struct Storage {
+    store: TokenStore,
+    /// Keyring entry and file of the sign-in
+    account: String,
+    path: String,
+}
+
+impl Storage {
+    // The main sign-in, or with `calendar` that of the calendar of that name
+    // in "calendars", kept apart so it can be another Google account
+    fn of(store: TokenStore, calendar: Option<&str>) -> Storage {
+        match calendar {
+            None => Storage { store, account: KEYRING_ACCOUNT.to_string(), path: paths::config(TOKEN_STORE_PATH) },
+            Some(name) => {
+                let name = paths::safe_name(name);
+                Storage { store, account: format!("{}-{}", KEYRING_ACCOUNT, name), path: paths::config(&format!("token_store-{}.json", name)) }
+            }
+        }
+    }
+
+    fn tokens(&self) -> Result<Vec<StoredToken>, Box<dyn std::error::Error>> {
+        match keyring::load(self.store, &self.account, &self.path)? {
+            Some(contents) => Ok(serde_json::from_str(&contents)?),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl TokenStorage for Storage {
+    async fn set(&self, scopes: &[&str], token: TokenInfo) -> anyhow::Result<()> {
+        let save = || -> Result<(), Box<dyn std::error::Error>> {
+            let mut tokens = self.tokens().unwrap_or_default();
+            tokens.retain(|stored| stored.scopes.len() != scopes.len() || !scopes.iter().all(|scope| stored.scopes.iter().any(|own| own == scope)));
+            tokens.push(StoredToken { scopes: scopes.iter().map(|scope| scope.to_string()).collect(), token });
+            keyring::save(self.store, &self.account, &self.path, &serde_json::to_string_pretty(&tokens)?)
+        };
+        save().map_err(|e| anyhow::anyhow!(e.to_string()))
+    }
+
+    // A token granted for all of `scopes`, maybe more
+    async fn get(&self, scopes: &[&str]) -> Option<TokenInfo> {
+        let tokens = self.tokens().inspect_err(|e| eprintln!("{}", t!("google-token-unreadable", error = e))).ok()?;
+        tokens
+            .into_iter()
+            .find(|stored| scopes.iter().all(|scope| stored.scopes.iter().any(|own| own == scope)))
+            .map(|stored| stored.token)
+    }
+}
+
+// Where the token is, for messages
+pub fn token_location(store: TokenStore) -> String {
+    keyring::location(store, KEYRING_ACCOUNT, &paths::config(TOKEN_STORE_PATH))
+}
+
+// Whether a Google token is stored
+pub fn has_token(store: TokenStore) -> Result<bool, Box<dyn std::error::Error>> {
+    Ok(!Storage::of(store, None).tokens()?.is_empty())
+}
+
+// The OAuth client file: "credentials" in the config, relative to the config
+// folder, or credentials.json there
+pub fn credentials_path(config: &Config) -> String {
+    paths::config(config.credentials.as_deref().unwrap_or(CREDENTIALS_PATH))
+}
+
+// OAuth authenticator shared by the Calendar and Tasks APIs
+pub async fn authenticator(store: TokenStore, credentials: &str) -> Result<Authenticator<HyperConnector>, Box<dyn std::error::Error>> {
+    sign_in(Storage::of(store, None), credentials).await
+}
+
+async fn sign_in(storage: Storage, credentials: &str) -> Result<Authenticator<HyperConnector>, Box<dyn std::error::Error>> {
+    let secret = yup_oauth2::read_application_secret(credentials)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", credentials, e))?;
+    Ok(InstalledFlowAuthenticator::builder(secret, InstalledFlowReturnMethod::HTTPRedirect)
+        .with_storage(Box::new(storage))
+        .build()
+        .await?)
+}
+
+// Exchange the stored refresh token without falling back to the browser flow
+pub async fn check_token(store: TokenStore, credentials: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let secret = yup_oauth2::read_application_secret(credentials).await?;
+    let tokens = Storage::of(store, None).tokens()?;
+    let refresh_token = tokens
+        .iter()
+        .find_map(|stored| stored.token.refresh_token.as_deref())
+        .ok_or("no refresh token is stored")?;
+    let body = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("client_id", &secret.client_id)
+        .append_pair("client_secret", &secret.client_secret)
+        .append_pair("refresh_token", refresh_token)
+        .append_pair("grant_type", "refresh_token")
+        .finish();
+    let request = hyper::Request::builder()
+        .method(hyper::Method::POST)
+        .uri(&secret.token_uri)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(body)?;
+    let (status, body) = http::send(&http::client()?, request).await?;
+    http::check(status, &body, "Google token refresh")
+}
+
+async fn authenticate(storage: Storage, credentials: &str) -> Result<CalendarHub<HyperConnector>, Box<dyn std::error::Error>> {
+    let hyper_client = Client::builder(TokioExecutor::new()).build(http::connector()?);
+    let auth = sign_in(storage, credentials).await?;
+    Ok(CalendarHub::new(hyper_client, auth))
+}
+
+// A calendar the account can see
+pub struct CalendarInfo {
+    pub id: String,
+    /// The user's own name for it, or else its title
+    pub name: String,
+    /// "owner", "writer", "reader" or "freeBusyReader"
+    pub access_role: String,
+    pub primary: bool,
+}
+
+impl CalendarInfo {
+    // Whether events can be added to it
+    pub fn writable(&self) -> bool {
+        self.access_role == "owner" || self.access_role == "writer"
+    }
+
+    // Whether it's the calendar with ID `id`, "primary" being the primary one's
+    pub fn is(&self, id: &str) -> bool {
+        self.id == id || (id == "primary" && self.primary)
+    }
+}
+
+// A push channel Google announces changes to the calendar's events on
+pub struct WatchChannel {
+    pub id: String,
+    /// Google's ID of the events being watched, needed to stop the channel
+    pub resource_id: String,
+    pub expires: DateTime<Utc>,
+}
+
+pub struct GoogleBackend {
+    hub: CalendarHub<HyperConnector>,
+    /// ID of the calendar events are kept in
+    calendar: String,
+}
+
+impl GoogleBackend {
+    pub async fn new(config: &Config) -> Result<GoogleBackend, Box<dyn std::error::Error>> {
+        let hub = authenticate(Storage::of(config.token_store, None), &credentials_path(config)).await?;
+        Ok(GoogleBackend { hub, calendar: config.calendar_id.clone().unwrap_or_else(|| "primary".to_string()) })
+    }
+
+    // The calendars in the account's calendar list
+    pub async fn calendars(&self) -> Result<Vec<CalendarInfo>, Box<dyn std::error::Error>> {
+        let mut calendars = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut call = self.hub.calendar_list().list();
+            if let Some(token) = &page_token {
+                call = call.page_token(token);
+            }
+            let (_, list) = call.doit().await?;
+            for entry in list.items.unwrap_or_default() {
+                let Some(id) = entry.id else { continue };
+                calendars.push(CalendarInfo {
+                    name: entry.summary_override.or(entry.summary).unwrap_or_else(|| id.clone()),
+                    id,
+                    access_role: entry.access_role.unwrap_or_default(),
+                    primary: entry.primary == Some(true),
+                });
+            }
+            page_token = list.next_page_token;
+            if page_token.is_none() {
+                return Ok(calendars);
+            }
+        }
+    }
+
+    // Create a secondary calendar named `name` in the computer's time zone
+    pub async fn create_calendar(&self, name: &str) -> Result<CalendarInfo, Box<dyn std::error::Error>> {
+        let calendar = google_calendar3::api::Calendar {
+            summary: Some(name.to_string()),
+            time_zone: Some(crate::zone::Zone::Local.name().unwrap_or_else(|| "UTC".to_string())),
+            ..Default::default()
+        };
+        let (_, created) = self.hub.calendars().insert(calendar).doit().await?;
+        let id = created.id.ok_or("Google returned the new calendar without an ID")?;
+        Ok(CalendarInfo { id, name: name.to_string(), access_role: "owner".to_string(), primary: false })
+    }
+
+    // Ask Google to call `address` whenever an event in the calendar changes,
+    // sending `token` along so the calls can be told from others
+    pub async fn watch(&self, address: &str, token: &str) -> Result<WatchChannel, Box<dyn std::error::Error>> {
+        let request = google_calendar3::api::Channel {
+            id: Some(uuid::Uuid::new_v4().to_string()),
+            type_: Some("web_hook".to_string()),
+            address: Some(address.to_string()),
+            token: Some(token.to_string()),
+            ..Default::default()
+        };
+        let (_, channel) = self.hub.events().watch(request, &self.calendar).doit().await?;
+        Ok(WatchChannel {
+            id: channel.id.ok_or("Google returned the channel without an ID")?,
+            resource_id: channel.resource_id.ok_or("Google returned the channel without a resource ID")?,
+            // Google keeps channels about a week unless it says otherwise
+            expires: channel.expiration.and_then(DateTime::from_timestamp_millis).unwrap_or_else(|| Utc::now() + Duration::days(7)),
+        })
+    }
+
+    // Stop the calls from a channel opened with `watch`
+    pub async fn stop_watch(&self, channel: &WatchChannel) -> Result<(), Box<dyn std::error::Error>> {
+        let request = google_calendar3::api::Channel {
+            id: Some(channel.id.clone()),
+            resource_id: Some(channel.resource_id.clone()),
+            ..Default::default()
+        };
+        self.hub.channels().stop(request).doit().await?;
+        Ok(())
+    }
+
+    // A calendar from "calendars", signed in on its own, maybe as another account
+    pub async fn for_calendar(config: &Config, calendar: &CalendarConfig) -> Result<GoogleBackend, Box<dyn std::error::Error>> {
+        let hub = authenticate(Storage::of(config.token_store, Some(&calendar.name)), &credentials_path(config)).await?;
+        Ok(GoogleBackend { hub, calendar: calendar.id.clone().unwrap_or_else(|| "primary".to_string()) })
+    }
+}
+
+// Build the Google Calendar event for a task
+fn task_event(task: &Task) -> Result<Event, Box<dyn std::error::Error>> {
+    let (start_time, end_time) = task.schedule().ok_or("Only dated tasks can be added to Google Calendar")?;
+    // Google repeats events by the clock in their zone
+    let zone = task.zone().name().unwrap_or_else(|| "UTC".to_string());
+    Ok(Event {
+        summary: Some(task.title.clone()),
+        description: Some(task.details.clone()),
+        start: Some(google_calendar3::api::EventDateTime {
+            date_time: Some(start_time),
+            time_zone: Some(zone.clone()),
+            ..Default::default()
+        }),
+        end: Some(google_calendar3::api::EventDateTime {
+            date_time: Some(end_time),
+            time_zone: Some(zone),
+            ..Default::default()
+        }),
+        recurrence: task.rrule().map(|rrule| vec![format!("RRULE:{}", rrule)]),
+        color_id: task.color.clone(),
+        // Private to this app's view of the calendar, unlike shared properties
+        extended_properties: Some(google_calendar3::api::EventExtendedProperties {
+            private: Some(sync::task_properties(task).into_iter().collect()),
+            ..Default::default()
+        }),
+        // The assignee is invited as a guest
+        attendees: task.assignee.as_ref().and_then(|assignee| {
+            let email = assignee.address.clone()?;
+            Some(vec![google_calendar3::api::EventAttendee {
+                email: Some(email),
+                display_name: Some(assignee.name.clone()),
+                ..Default::default()
+            }])
+        }),
+        ..Default::default()
+    })
+}
+
+// Split a multipart/mixed batch response into (item number, status, body),
+// using the delimiter its first line starts with
+fn batch_responses(body: &str) -> Vec<(usize, u16, String)> {
+    let Some(delimiter) = body.lines().map(str::trim).find(|line| line.starts_with("--")) else {
+        return Vec::new();
+    };
+    body.split(delimiter)
+        .filter_map(|part| {
+            let item = part
+                .lines()
+                .find_map(|line| line.trim().strip_prefix("Content-ID:").or_else(|| line.trim().strip_prefix("Content-Id:")))?
+                .trim()
+                .trim_start_matches("<response-item-")
+                .trim_end_matches('>')
+                .parse()
+                .ok()?;
+            let response = &part[part.find("HTTP/")?..];
+            let status = response.split_whitespace().nth(1)?.parse().ok()?;
+            let body = response.replace("\r\n", "\n");
+            let body = body.split_once("\n\n").map_or("", |(_, body)| body).trim().to_string();
+            Some((item, status, body))
+        })
+        .collect()
+}
+
+impl GoogleBackend {
+    // Insert up to BATCH_SIZE events in one request to the batch endpoint,
+    // returning each task's event ID or error in order
+    pub async fn push_batch(&self, tasks: &[Task]) -> Result<Vec<Result<String, Box<dyn std::error::Error>>>, Box<dyn std::error::Error>> {
+        let token = self
+            .hub
+            .auth
+            .get_token(&[CALENDAR_SCOPE])
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or("No Google access token")?;
+        let boundary = format!("batch_{}", uuid::Uuid::new_v4().simple());
+        let mut body = String::new();
+        let mut results: Vec<Result<String, Box<dyn std::error::Error>>> = Vec::new();
+        for (item, task) in tasks.iter().enumerate() {
+            let event = match task_event(task) {
+                Ok(event) => serde_json::to_string(&event)?,
+                Err(e) => {
+                    results.push(Err(e));
+                    continue;
+                }
+            };
+            results.push(Err("Missing from the batch response".into()));
+            body.push_str(&format!(
+                "--{}\r\nContent-Type: application/http\r\nContent-ID: <item-{}>\r\n\r\n\
+                 POST /calendar/v3/calendars/{}/events HTTP/1.1\r\nContent-Type: application/json\r\n\r\n{}\r\n",
+                boundary, item, url::form_urlencoded::byte_serialize(self.calendar.as_bytes()).collect::<String>(), event
+            ));
+        }
+        body.push_str(&format!("--{}--\r\n", boundary));
+        let request = hyper::Request::builder()
+            .method(hyper::Method::POST)
+            .uri(BATCH_URL)
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Content-Type", format!("multipart/mixed; boundary={}", boundary))
+            .body(body)?;
+        let (status, body) = http::send(&http::client()?, request).await?;
+        http::check(status, &body, "Google Calendar batch")?;
+        for (item, status, body) in batch_responses(&body) {
+            let Some(result) = results.get_mut(item) else { continue };
+            *result = if (200..300).contains(&status) {
+                serde_json::from_str::<serde_json::Value>(&body)?["id"]
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| "The batch response has no event ID".into())
+            } else {
+                Err(format!("Failed to add task to Google Calendar ({}): {}", status, body).into())
+            };
+        }
+        Ok(results)
+    }
+}
+
+impl CalendarBackend for GoogleBackend {
+    fn name(&self) -> &'static str {
+        "Google Calendar"
+    }
+
+    async fn push(&self, task: &Task) -> Result<String, Box<dyn std::error::Error>> {
+        let event = task_event(task)?;
+        match self.hub.events().insert(event, &self.calendar).send_updates("all").doit().await {
+            Ok((_, event)) => Ok(event.id.unwrap_or_default()),
+            Err(e) => Err(Box::new(std::io::Error::other(
+                format!("Failed to add task to Google Calendar: {:?}", e),
+            ))),
+        }
+    }
+
+    async fn update(&self, event_id: &str, task: &Task) -> Result<(), Box<dyn std::error::Error>> {
+        self.hub.events().update(task_event(task)?, &self.calendar, event_id).send_updates("all").doit().await?;
+        Ok(())
+    }
+
+    async fn delete(&self, event_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.hub.events().delete(&self.calendar, event_id).doit().await?;
+        Ok(())
+    }
+
+    async fn set_color(&self, event_id: &str, _task: &Task, color: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        // Color 8 is graphite
+        let patch = Event { color_id: Some(color.unwrap_or("8").to_string()), ..Default::default() };
+        self.hub.events().patch(patch, &self.calendar, event_id).doit().await?;
+        Ok(())
+    }
+
+    async fn pull(&self) -> Result<Vec<RemoteEvent>, Box<dyn std::error::Error>> {
+        // Deleted items are needed to see cancelled occurrences of recurring events
+        let result = self.hub.events().list(&self.calendar).max_results(10).show_deleted(true).doit().await?;
+        let calendar = result.1.summary;
+        let mut events = Vec::new();
+        for event in result.1.items.unwrap_or_default() {
+            let original_start = event.original_start_time.as_ref().and_then(|time| time.date_time);
+            if event.status.as_deref() == Some("cancelled") {
+                if let (Some(id), Some(series_id), Some(original_start)) = (&event.id, &event.recurring_event_id, original_start) {
+                    events.push(RemoteEvent {
+                        id: id.clone(),
+                        series_id: Some(series_id.clone()),
+                        original_start: Some(original_start),
+                        cancelled: true,
+                        ..Default::default()
+                    });
+                }
+                continue;
+            }
+            if let (Some(id), Some(summary), Some(start), Some(end)) = (
+                event.id.as_ref(),
+                event.summary.as_ref(),
+                event.start.as_ref().and_then(|s| s.date_time),
+                event.end.as_ref().and_then(|e| e.date_time),
+            ) {
+                let frequency_minutes = event
+                    .recurrence
+                    .iter()
+                    .flatten()
+                    .find_map(|rule| rule.strip_prefix("RRULE:").and_then(ics::rrule_to_minutes));
+                let own_reply = event
+                    .attendees
+                    .iter()
+                    .flatten()
+                    .find(|attendee| attendee.self_ == Some(true))
+                    .and_then(|attendee| attendee.response_status.as_deref());
+                // Apps that add events through the API usually leave a source behind
+                let creator = event
+                    .source
+                    .as_ref()
+                    .and_then(|source| source.title.clone())
+                    .or_else(|| event.creator.as_ref().and_then(|creator| creator.email.clone()));
+                events.push(RemoteEvent {
+                    id: id.clone(),
+                    title: summary.clone(),
+                    details: event.description.clone().unwrap_or_default(),
+                    start_time: Some(start),
+                    end_time: Some(end),
+                    frequency_minutes,
+                    declined: own_reply == Some("declined"),
+                    awaiting_reply: own_reply == Some("needsAction"),
+                    creator,
+                    calendar: calendar.clone(),
+                    etag: event.etag.clone(),
+                    updated: event.updated,
+                    series_id: event.recurring_event_id.clone(),
+                    original_start,
+                    cancelled: false,
+                    meta: event
+                        .extended_properties
+                        .as_ref()
+                        .and_then(|properties| properties.private.clone())
+                        .unwrap_or_default()
+                        .into_iter()
+                        .collect(),
+                    link: event.html_link.clone(),
+                });
+            }
+        }
+        Ok(events)
+    }
+
+    async fn respond(&self, event_id: &str, reply: Rsvp) -> Result<(), Box<dyn std::error::Error>> {
+        let (_, event) = self.hub.events().get(&self.calendar, event_id).doit().await?;
+        let mut attendees = event.attendees.unwrap_or_default();
+        let attendee = attendees
+            .iter_mut()
+            .find(|attendee| attendee.self_ == Some(true))
+            .ok_or("You aren't an attendee of this event")?;
+        attendee.response_status = Some(
+            match reply {
+                Rsvp::Accept => "accepted",
+                Rsvp::Decline => "declined",
+                Rsvp::Tentative => "tentative",
+            }
+            .to_string(),
+        );
+        // Only the attendee list is patched, so other edits to the event are kept
+        let patch = Event { attendees: Some(attendees), ..Default::default() };
+        self.hub.events().patch(patch, &self.calendar, event_id).send_updates("all").doit().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+// Google Tasks backend for undated tasks
+use chrono::{DateTime, Utc};
+use hyper::{Method, Request};
+use serde_json::{json, Value};
+
+use crate::config::{GoogleTasksConfig, TokenStore};
+use crate::google;
+use crate::http::{self, HttpClient};
+use crate::sync::{CalendarBackend, RemoteEvent};
+use crate::{Task, TaskStatus};
+
+const TASKS_URL: &str = "https://tasks.googleapis.com/tasks/v1";
+const TASKS_SCOPE: &str = "https://www.googleapis.com/auth/tasks";
+
+pub struct GoogleTasksBackend {
+    client: HttpClient,
+    access_token: String,
+    tasklist: String,
+}
+
+impl GoogleTasksBackend {
+    pub async fn new(config: &GoogleTasksConfig, store: TokenStore, credentials: &str) -> Result<GoogleTasksBackend, Box<dyn std::error::Error>> {
+        let auth = google::authenticator(store, credentials).await?;
+        let token = auth.token(&[TASKS_SCOPE]).await?;
+        Ok(GoogleTasksBackend {
+            client: http::client()?,
+            access_token: token.token().ok_or("Google did not return an access token")?.to_string(),
+            tasklist: config.tasklist.clone(),
+        })
+    }
+
+    async fn call(&self, method: Method, path: &str, body: Option<Value>) -> Result<Value, Box<dyn std::error::Error>> {
+        let url = format!("{}/lists/{}/tasks{}", TASKS_URL, self.tasklist, path);
+        let request = Request::builder()
+            .method(method.clone())
+            .uri(url)
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Content-Type", "application/json")
+            .body(body.map(|body| body.to_string()).unwrap_or_default())?;
+        let (status, body) = http::send(&self.client, request).await?;
+        http::check(status, &body, &format!("Google Tasks {}", method))?;
+        if body.trim().is_empty() {
+            return Ok(Value::Null);
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+}
+
+fn task_item(task: &Task) -> Value {
+    json!({
+        "title": task.title,
+        "notes": task.details,
+        "status": if task.status == TaskStatus::Done { "completed" } else { "needsAction" },
+    })
+}
+
+impl CalendarBackend for GoogleTasksBackend {
+    fn name(&self) -> &'static str {
+        "Google Tasks"
+    }
+
+    fn accepts(&self, task: &Task) -> bool {
+        task.start_time.is_none()
+    }
+
+    async fn push(&self, task: &Task) -> Result<String, Box<dyn std::error::Error>> {
+        let item = self.call(Method::POST, "", Some(task_item(task))).await?;
+        Ok(item["id"].as_str().unwrap_or_default().to_string())
+    }
+
+    async fn update(&self, event_id: &str, task: &Task) -> Result<(), Box<dyn std::error::Error>> {
+        self.call(Method::PATCH, &format!("/{}", event_id), Some(task_item(task))).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, event_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.call(Method::DELETE, &format!("/{}", event_id), None).await?;
+        Ok(())
+    }
+
+    async fn pull(&self) -> Result<Vec<RemoteEvent>, Box<dyn std::error::Error>> {
+        let response = self.call(Method::GET, "?showCompleted=false&maxResults=100", None).await?;
+        Ok(response["items"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|item| {
+                Some(RemoteEvent {
+                    id: item["id"].as_str()?.to_string(),
+                    title: item["title"].as_str().unwrap_or_default().to_string(),
+                    details: item["notes"].as_str().unwrap_or_default().to_string(),
+                    etag: item["etag"].as_str().map(str::to_string),
+                    updated: item["updated"]
+                        .as_str()
+                        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+                        .map(|time| time.with_timezone(&Utc)),
+                    link: item["webViewLink"].as_str().map(str::to_string),
+                    ..Default::default()
+                })
+            })
+            .collect())
+    }
+}
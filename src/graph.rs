@@ -0,0 +1,376 @@
+// Microsoft Graph backend for Outlook / Office 365 calendars
+use chrono::{DateTime, NaiveDateTime, Utc};
+use hyper::{Method, Request};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::config::{GraphConfig, TokenStore};
+use crate::exit::AuthError;
+use crate::http::{self, HttpClient};
+use crate::i18n::t;
+use crate::ics;
+use crate::keyring;
+use crate::paths;
+use crate::sync::{CalendarBackend, RemoteEvent, Rsvp};
+use crate::Task;
+
+// File the Graph OAuth tokens are persisted to
+pub const TOKEN_PATH: &str = "graph_token.json";
+// Keyring entry the token is kept in unless `"token_store": "file"`
+const KEYRING_ACCOUNT: &str = "outlook";
+const GRAPH_URL: &str = "https://graph.microsoft.com/v1.0";
+const SCOPES: &str = "offline_access Calendars.ReadWrite";
+
+#[derive(Serialize, Deserialize)]
+struct StoredToken {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_at: DateTime<Utc>,
+}
+
+pub struct GraphBackend {
+    client: HttpClient,
+    access_token: String,
+}
+
+impl GraphBackend {
+    pub async fn new(config: &GraphConfig, store: TokenStore) -> Result<GraphBackend, Box<dyn std::error::Error>> {
+        let client = http::client()?;
+        let access_token = access_token(&client, config, &TokenKey::of(store, None)).await?;
+        Ok(GraphBackend { client, access_token })
+    }
+
+    // A calendar from "calendars", signed in on its own, maybe as another account
+    pub async fn for_calendar(config: &GraphConfig, store: TokenStore, name: &str) -> Result<GraphBackend, Box<dyn std::error::Error>> {
+        let client = http::client()?;
+        let access_token = access_token(&client, config, &TokenKey::of(store, Some(name))).await?;
+        Ok(GraphBackend { client, access_token })
+    }
+
+    async fn call(&self, method: Method, path: &str, body: Option<Value>) -> Result<Value, Box<dyn std::error::Error>> {
+        let request = Request::builder()
+            .method(method.clone())
+            .uri(format!("{}{}", GRAPH_URL, path))
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Content-Type", "application/json")
+            // Have Graph report event times in UTC
+            .header("Prefer", "outlook.timezone=\"UTC\"")
+            .body(body.map(|body| body.to_string()).unwrap_or_default())?;
+        let (status, body) = http::send(&self.client, request).await?;
+        http::check(status, &body, &format!("Graph {} {}", method, path))?;
+        if body.trim().is_empty() {
+            return Ok(Value::Null);
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+}
+
+// Where a sign-in's token is kept: the keyring entry and file
+struct TokenKey {
+    store: TokenStore,
+    account: String,
+    path: String,
+}
+
+impl TokenKey {
+    // The main sign-in, or with `calendar` that of the calendar of that name
+    // in "calendars"
+    fn of(store: TokenStore, calendar: Option<&str>) -> TokenKey {
+        match calendar {
+            None => TokenKey { store, account: KEYRING_ACCOUNT.to_string(), path: paths::config(TOKEN_PATH) },
+            Some(name) => {
+                let name = paths::safe_name(name);
+                TokenKey { store, account: format!("{}-{}", KEYRING_ACCOUNT, name), path: paths::config(&format!("graph_token-{}.json", name)) }
+            }
+        }
+    }
+
+    fn load(&self) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        keyring::load(self.store, &self.account, &self.path)
+    }
+
+    fn save(&self, token: &StoredToken) -> Result<(), Box<dyn std::error::Error>> {
+        keyring::save(self.store, &self.account, &self.path, &serde_json::to_string_pretty(token)?)
+    }
+}
+
+// Reuse the stored token, refresh it, or fall back to the device code flow
+async fn access_token(client: &HttpClient, config: &GraphConfig, key: &TokenKey) -> Result<String, Box<dyn std::error::Error>> {
+    let stored: Option<StoredToken> = key.load()?.and_then(|contents| serde_json::from_str(&contents).ok());
+    if let Some(token) = &stored
+        && token.expires_at > Utc::now() + chrono::Duration::minutes(1)
+    {
+        return Ok(token.access_token.clone());
+    }
+
+    let refreshed = match stored.and_then(|token| token.refresh_token) {
+        Some(refresh_token) => token_request(client, config, &[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", &refresh_token),
+        ])
+        .await
+        .ok(),
+        None => None,
+    };
+    let token = match refreshed {
+        Some(token) => token,
+        None => device_code_flow(client, config).await?,
+    };
+    key.save(&token)?;
+    Ok(token.access_token)
+}
+
+// Whether an Outlook token is stored
+pub fn has_token(store: TokenStore) -> Result<bool, Box<dyn std::error::Error>> {
+    Ok(keyring::load(store, KEYRING_ACCOUNT, &paths::config(TOKEN_PATH))?.is_some())
+}
+
+// Check the stored sign-in, refreshing it if needed, without starting the device code flow
+pub async fn check_token(config: &GraphConfig, store: TokenStore) -> Result<(), Box<dyn std::error::Error>> {
+    let token: StoredToken = serde_json::from_str(&keyring::load(store, KEYRING_ACCOUNT, &paths::config(TOKEN_PATH))?.ok_or("no token is stored")?)?;
+    if token.expires_at > Utc::now() + chrono::Duration::minutes(1) {
+        return Ok(());
+    }
+    let refresh_token = token.refresh_token.ok_or("the stored token has expired and has no refresh token")?;
+    let token = token_request(&http::client()?, config, &[
+        ("grant_type", "refresh_token"),
+        ("refresh_token", &refresh_token),
+    ])
+    .await?;
+    keyring::save(store, KEYRING_ACCOUNT, &paths::config(TOKEN_PATH), &serde_json::to_string_pretty(&token)?)?;
+    Ok(())
+}
+
+fn oauth_url(config: &GraphConfig, endpoint: &str) -> String {
+    format!("https://login.microsoftonline.com/{}/oauth2/v2.0/{}", config.tenant, endpoint)
+}
+
+async fn post_form(client: &HttpClient, url: &str, fields: &[(&str, &str)]) -> Result<(hyper::StatusCode, Value), Box<dyn std::error::Error>> {
+    let body = url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(fields)
+        .finish();
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(url)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(body)?;
+    let (status, body) = http::send(client, request).await?;
+    Ok((status, serde_json::from_str(&body)?))
+}
+
+async fn token_request(client: &HttpClient, config: &GraphConfig, grant: &[(&str, &str)]) -> Result<StoredToken, Box<dyn std::error::Error>> {
+    let mut fields = vec![("client_id", config.client_id.as_str()), ("scope", SCOPES)];
+    fields.extend_from_slice(grant);
+    let (status, response) = post_form(client, &oauth_url(config, "token"), &fields).await?;
+    if !status.is_success() {
+        return Err(Box::new(AuthError(response["error"].as_str().unwrap_or("token request failed").to_string())));
+    }
+    Ok(stored_token(&response))
+}
+
+fn stored_token(response: &Value) -> StoredToken {
+    StoredToken {
+        access_token: response["access_token"].as_str().unwrap_or_default().to_string(),
+        refresh_token: response["refresh_token"].as_str().map(str::to_string),
+        expires_at: Utc::now() + chrono::Duration::seconds(response["expires_in"].as_i64().unwrap_or(3600)),
+    }
+}
+
+// OAuth device authorization grant: the user signs in on another device
+async fn device_code_flow(client: &HttpClient, config: &GraphConfig) -> Result<StoredToken, Box<dyn std::error::Error>> {
+    let (status, device) = post_form(client, &oauth_url(config, "devicecode"), &[
+        ("client_id", config.client_id.as_str()),
+        ("scope", SCOPES),
+    ])
+    .await?;
+    if !status.is_success() {
+        return Err(format!("Device code request failed: {}", device["error_description"]).into());
+    }
+    println!("{}", device["message"].as_str().unwrap_or("Complete the sign-in in your browser."));
+
+    let device_code = device["device_code"].as_str().unwrap_or_default().to_string();
+    let mut interval = device["interval"].as_u64().unwrap_or(5);
+    loop {
+        tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+        let (status, response) = post_form(client, &oauth_url(config, "token"), &[
+            ("client_id", config.client_id.as_str()),
+            ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+            ("device_code", &device_code),
+        ])
+        .await?;
+        if status.is_success() {
+            return Ok(stored_token(&response));
+        }
+        match response["error"].as_str() {
+            Some("authorization_pending") => {}
+            Some("slow_down") => interval += 5,
+            _ => return Err(Box::new(AuthError(format!("Sign-in failed: {}", response["error_description"])))),
+        }
+    }
+}
+
+// Graph has no minute-level recurrence; map whole days and weeks, drop anything else.
+// `start_time` is the task's start in its own zone, which days repeat in.
+fn recurrence(task: &Task, start_time: NaiveDateTime) -> Option<Value> {
+    let minutes = if task.has_rule() {
+        // Only cron expressions and rules that come down to a fixed interval carry over
+        match task.rrule().filter(|rrule| !rrule.contains(";BY")).as_deref().and_then(ics::rrule_to_minutes) {
+            Some(minutes) => minutes,
+            None => {
+                let repeat = task.recurrence_text().unwrap_or_default();
+                eprintln!("{}", t!("outlook-recurrence-unsupported", repeat = repeat, title = task.title));
+                return None;
+            }
+        }
+    } else {
+        task.frequency_minutes.filter(|_| task.is_recurring)?
+    };
+    let pattern = if minutes % (7 * 24 * 60) == 0 {
+        json!({
+            "type": "weekly",
+            "interval": minutes / (7 * 24 * 60),
+            "daysOfWeek": [start_time.format("%A").to_string().to_lowercase()],
+        })
+    } else if minutes % (24 * 60) == 0 {
+        json!({ "type": "daily", "interval": minutes / (24 * 60) })
+    } else {
+        eprintln!("{}", t!("outlook-frequency-unsupported", minutes = minutes, title = task.title));
+        return None;
+    };
+    let start_date = start_time.format("%Y-%m-%d").to_string();
+    // A task paused with --truncate ends its series on the day it was paused
+    let range = match task.paused.as_ref().filter(|pause| pause.truncated) {
+        Some(pause) => json!({ "type": "endDate", "startDate": start_date, "endDate": pause.since.format("%Y-%m-%d").to_string() }),
+        None => json!({ "type": "noEnd", "startDate": start_date }),
+    };
+    Some(json!({ "pattern": pattern, "range": range }))
+}
+
+fn task_event(task: &Task) -> Result<Value, Box<dyn std::error::Error>> {
+    let (start_time, end_time) = task.schedule().ok_or("Only dated tasks can be added to Outlook")?;
+    // Events in the task's zone keep their clock time there across DST changes
+    let zone = task.zone();
+    let (zone, start_time, end_time) = match zone.name() {
+        Some(name) => (name, zone.naive(start_time), zone.naive(end_time)),
+        None => ("UTC".to_string(), start_time.naive_utc(), end_time.naive_utc()),
+    };
+    let mut event = json!({
+        "subject": task.title,
+        "body": { "contentType": "text", "content": task.details },
+        "start": { "dateTime": start_time.format("%Y-%m-%dT%H:%M:%S").to_string(), "timeZone": zone },
+        "end": { "dateTime": end_time.format("%Y-%m-%dT%H:%M:%S").to_string(), "timeZone": zone },
+    });
+    if let Some(recurrence) = recurrence(task, start_time) {
+        event["recurrence"] = recurrence;
+    }
+    // Outlook colors events through categories
+    if let Some(color) = &task.color {
+        event["categories"] = json!([color]);
+    }
+    // The assignee is invited, which Outlook emails them about
+    if let Some(assignee) = &task.assignee
+        && let Some(address) = &assignee.address
+    {
+        event["attendees"] = json!([{ "emailAddress": { "address": address, "name": assignee.name }, "type": "required" }]);
+    }
+    Ok(event)
+}
+
+fn frequency_minutes(recurrence: &Value) -> Option<i64> {
+    let interval = recurrence["pattern"]["interval"].as_i64().unwrap_or(1);
+    match recurrence["pattern"]["type"].as_str()? {
+        "daily" => Some(interval * 24 * 60),
+        "weekly" if recurrence["pattern"]["daysOfWeek"].as_array().is_some_and(|days| days.len() == 1) => {
+            Some(interval * 7 * 24 * 60)
+        }
+        _ => None,
+    }
+}
+
+fn parse_graph_time(value: &Value) -> Option<DateTime<Utc>> {
+    let text = value["dateTime"].as_str()?;
+    // Graph returns seven fractional digits, which chrono's %.f accepts
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f")
+        .ok()
+        .map(|naive| naive.and_utc())
+}
+
+impl CalendarBackend for GraphBackend {
+    fn name(&self) -> &'static str {
+        "Outlook"
+    }
+
+    async fn push(&self, task: &Task) -> Result<String, Box<dyn std::error::Error>> {
+        let event = self.call(Method::POST, "/me/events", Some(task_event(task)?)).await?;
+        Ok(event["id"].as_str().unwrap_or_default().to_string())
+    }
+
+    async fn update(&self, event_id: &str, task: &Task) -> Result<(), Box<dyn std::error::Error>> {
+        self.call(Method::PATCH, &format!("/me/events/{}", event_id), Some(task_event(task)?)).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, event_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.call(Method::DELETE, &format!("/me/events/{}", event_id), None).await?;
+        Ok(())
+    }
+
+    // Outlook colors events through categories
+    async fn set_color(&self, event_id: &str, _task: &Task, color: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        let body = json!({ "categories": [color.unwrap_or("Completed")] });
+        self.call(Method::PATCH, &format!("/me/events/{}", event_id), Some(body)).await?;
+        Ok(())
+    }
+
+    async fn pull(&self) -> Result<Vec<RemoteEvent>, Box<dyn std::error::Error>> {
+        let filter = format!("end/dateTime ge '{}'", Utc::now().format("%Y-%m-%dT%H:%M:%S"));
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("$filter", &filter)
+            .append_pair("$top", "50")
+            .append_pair("$select", "id,subject,bodyPreview,start,end,recurrence,responseStatus,organizer,lastModifiedDateTime")
+            .finish();
+        let response = self.call(Method::GET, &format!("/me/events?{}", query), None).await?;
+        let mut events = Vec::new();
+        for event in response["value"].as_array().into_iter().flatten() {
+            if let (Some(id), Some(start_time), Some(end_time)) = (
+                event["id"].as_str(),
+                parse_graph_time(&event["start"]),
+                parse_graph_time(&event["end"]),
+            ) {
+                events.push(RemoteEvent {
+                    id: id.to_string(),
+                    title: event["subject"].as_str().unwrap_or_default().to_string(),
+                    details: event["bodyPreview"].as_str().unwrap_or_default().to_string(),
+                    start_time: Some(start_time),
+                    end_time: Some(end_time),
+                    frequency_minutes: frequency_minutes(&event["recurrence"]),
+                    declined: event["responseStatus"]["response"] == "declined",
+                    awaiting_reply: event["responseStatus"]["response"] == "notResponded",
+                    creator: event["organizer"]["emailAddress"]["address"].as_str().map(str::to_string),
+                    // /me/events doesn't say which calendar an event is in
+                    calendar: None,
+                    etag: event["@odata.etag"].as_str().map(str::to_string),
+                    updated: event["lastModifiedDateTime"]
+                        .as_str()
+                        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+                        .map(|time| time.with_timezone(&Utc)),
+                    link: event["webLink"].as_str().map(str::to_string),
+                    // /me/events lists series masters but not their exceptions
+                    ..Default::default()
+                });
+            }
+        }
+        Ok(events)
+    }
+
+    async fn respond(&self, event_id: &str, reply: Rsvp) -> Result<(), Box<dyn std::error::Error>> {
+        let action = match reply {
+            Rsvp::Accept => "accept",
+            Rsvp::Decline => "decline",
+            Rsvp::Tentative => "tentativelyAccept",
+        };
+        let body = json!({ "sendResponse": true });
+        self.call(Method::POST, &format!("/me/events/{}/{}", event_id, action), Some(body)).await?;
+        Ok(())
+    }
+}
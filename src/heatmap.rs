@@ -0,0 +1,78 @@
+// Heatmap: scheduled hours per day (task occurrences and calendar and feed
+// events) laid out as a calendar, shaded against the length of a working day,
+// so overloaded days stand out when picking a date
+use chrono::{Datelike, Duration, NaiveDate};
+
+use crate::agenda::AgendaItem;
+use crate::capacity;
+use crate::config::WorkHours;
+use crate::week;
+
+// Shades from nothing scheduled up to more than a working day
+const SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+
+// Time scheduled on `date`: the parts of the items falling in it, added up, so
+// double-booked time counts twice. Items covering the whole day, such as
+// all-day events, are left out.
+fn scheduled(items: &[AgendaItem], date: NaiveDate) -> Duration {
+    let midnight = crate::local_midnight(date);
+    let next = crate::local_midnight(date + Duration::days(1));
+    items
+        .iter()
+        .filter(|item| item.start_time > midnight || item.end_time < next)
+        .map(|item| item.end_time.min(next) - item.start_time.max(midnight))
+        .filter(|length| *length > Duration::zero())
+        .fold(Duration::zero(), |total, length| total + length)
+}
+
+// Shade of `time` scheduled in a day of `day` working time: a quarter, half or
+// all of it, or more
+fn shade(time: Duration, day: Duration) -> char {
+    match time {
+        time if time <= Duration::zero() => SHADES[0],
+        time if time * 4 <= day => SHADES[1],
+        time if time * 2 <= day => SHADES[2],
+        time if time <= day => SHADES[3],
+        _ => SHADES[4],
+    }
+}
+
+// The `days` days from `first` as weeks, each day with its number and shade,
+// and each week's total, led by the week's number when week numbers are on
+pub fn render(items: &[AgendaItem], hours: &WorkHours, first: NaiveDate, days: i64) -> String {
+    let day = (hours.end - hours.start).max(Duration::hours(1));
+    let last = first + Duration::days(days.max(1) - 1);
+    let numbered = week::number(first).is_some();
+    let mut out: String = if numbered { "   ".to_string() } else { String::new() };
+    for name in week::day_names().iter().map(String::as_str).chain(["Week"]) {
+        out.push_str(&format!("   {:<5}", name));
+    }
+    out = format!("{}\n", out.trim_end());
+    let mut start = week::first_day(first);
+    while start <= last {
+        if let Some(number) = week::number(start) {
+            out.push_str(&format!("{:>2} ", number));
+        }
+        let mut total = Duration::zero();
+        for date in start.iter_days().take(7) {
+            if date < first || date > last {
+                out.push_str("        ");
+                continue;
+            }
+            let time = scheduled(items, date);
+            total += time;
+            let cell = shade(time, day).to_string().repeat(3);
+            out.push_str(&format!("{:>2} {}  ", date.day(), cell));
+        }
+        out.push_str(&format!("{}\n", capacity::format_length(total)));
+        start += Duration::days(7);
+    }
+    let quarter = capacity::format_length(day / 4);
+    let half = capacity::format_length(day / 2);
+    let whole = capacity::format_length(day);
+    out.push_str(&format!(
+        "\n{} nothing  {} up to {}  {} up to {}  {} up to {}  {} over {}\n",
+        SHADES[0], SHADES[1], quarter, SHADES[2], half, SHADES[3], whole, SHADES[4], whole
+    ));
+    out
+}
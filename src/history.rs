@@ -0,0 +1,150 @@
+// Per-occurrence history of recurring tasks: what was completed, skipped or missed
+use chrono::{DateTime, Duration, Local, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::Task;
+use crate::i18n::t;
+
+// How far back `menu --complete` and `skip` look for an occurrence left open
+const LOOKBACK_DAYS: i64 = 7;
+
+// What became of an occurrence, as recorded by the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Completed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Completed,
+    Skipped,
+    /// Ended without being completed or skipped
+    Missed,
+    /// Cancelled on the calendar
+    Cancelled,
+    /// Started but not over yet
+    Due,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Status::Completed => "completed",
+            Status::Skipped => "skipped",
+            Status::Missed => "missed",
+            Status::Cancelled => "cancelled",
+            Status::Due => "due",
+        }
+    }
+}
+
+// Every occurrence of the task that started in [from, now), oldest first
+pub fn entries(task: &Task, from: DateTime<Utc>, now: DateTime<Utc>) -> Vec<(DateTime<Utc>, Status)> {
+    task.occurrences(from, now)
+        .into_iter()
+        .filter(|(start_time, _)| *start_time >= from)
+        .map(|(start_time, end_time)| {
+            let status = match task.history.get(&start_time) {
+                Some(Outcome::Completed) => Status::Completed,
+                Some(Outcome::Skipped) => Status::Skipped,
+                None if task.overrides.get(&start_time).is_some_and(|occurrence| occurrence.cancelled) => Status::Cancelled,
+                None if end_time > now => Status::Due,
+                None => Status::Missed,
+            };
+            (start_time, status)
+        })
+        .collect()
+}
+
+// The occurrence an outcome is recorded for: the latest one that started in the
+// last week and has none yet, or else the next one
+pub fn open_occurrence(task: &Task, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let started = entries(task, now - Duration::days(LOOKBACK_DAYS), now)
+        .into_iter()
+        .rev()
+        .find(|(_, status)| matches!(status, Status::Missed | Status::Due))
+        .map(|(start_time, _)| start_time);
+    started.or_else(|| {
+        task.occurrences(now, now + Duration::days(400))
+            .into_iter()
+            .map(|(start_time, _)| start_time)
+            .find(|start_time| *start_time >= now && !task.history.contains_key(start_time))
+    })
+}
+
+// Counts of completed, skipped and missed occurrences
+pub fn counts(entries: &[(DateTime<Utc>, Status)]) -> (usize, usize, usize) {
+    let count = |wanted: Status| entries.iter().filter(|(_, status)| *status == wanted).count();
+    (count(Status::Completed), count(Status::Skipped), count(Status::Missed))
+}
+
+// Completed occurrences in a row up to now; skipped and cancelled ones don't
+// break the streak and one still due doesn't either
+fn streak(entries: &[(DateTime<Utc>, Status)]) -> usize {
+    entries
+        .iter()
+        .rev()
+        .filter(|(_, status)| !matches!(status, Status::Skipped | Status::Cancelled | Status::Due))
+        .take_while(|(_, status)| *status == Status::Completed)
+        .count()
+}
+
+pub fn print(task: &Task, days: i64) {
+    let now = Utc::now();
+    let entries = entries(task, now - Duration::days(days), now);
+    println!("#{} '{}', {}", task.id, task.title, task.recurrence_text().unwrap_or_default());
+    if entries.is_empty() {
+        println!("{}", t!("history-empty", days = days));
+        return;
+    }
+    for (start_time, status) in &entries {
+        println!("  {}  {}", start_time.with_timezone(&Local).format("%a %Y-%m-%d %H:%M"), status.label());
+    }
+    let (completed, skipped, missed) = counts(&entries);
+    let summary = match completed + missed {
+        0 => t!("history-summary", days = days, completed = completed, skipped = skipped, missed = missed, streak = streak(&entries)),
+        total => t!(
+            "history-summary-rate",
+            days = days,
+            completed = completed,
+            skipped = skipped,
+            missed = missed,
+            percent = completed * 100 / total,
+            streak = streak(&entries)
+        ),
+    };
+    println!("{}", summary);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn occurrences_are_told_apart_by_outcome() {
+        let task = Task {
+            start_time: Some(at(1, 9, 0)),
+            end_time: Some(at(1, 9, 30)),
+            is_recurring: true,
+            frequency_minutes: Some(24 * 60),
+            timezone: Some("UTC".to_string()),
+            history: [(at(1, 9, 0), Outcome::Completed), (at(2, 9, 0), Outcome::Skipped), (at(4, 9, 0), Outcome::Completed)].into(),
+            ..Default::default()
+        };
+        let now = at(5, 9, 15);
+        let entries = entries(&task, at(1, 0, 0), now);
+        let statuses: Vec<_> = entries.iter().map(|(_, status)| *status).collect();
+        assert_eq!(statuses, [Status::Completed, Status::Skipped, Status::Missed, Status::Completed, Status::Due]);
+        assert_eq!(counts(&entries), (2, 1, 1));
+        // The miss on the 3rd ends the streak; the skip and the one due don't count
+        assert_eq!(streak(&entries), 1);
+        assert_eq!(open_occurrence(&task, now), Some(at(5, 9, 0)));
+    }
+}
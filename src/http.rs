@@ -0,0 +1,54 @@
+use http_body_util::BodyExt;
+use hyper::{Request, StatusCode};
+use hyper_rustls::HttpsConnectorBuilder;
+use hyper_util::client::legacy::{Client, connect::HttpConnector};
+use hyper_util::rt::TokioExecutor;
+
+pub type HyperConnector = hyper_rustls::HttpsConnector<HttpConnector>;
+pub type HttpClient = Client<HyperConnector, String>;
+
+pub fn connector() -> Result<HyperConnector, Box<dyn std::error::Error>> {
+    Ok(HttpsConnectorBuilder::new()
+        .with_native_roots()?
+        .https_or_http()
+        .enable_http1()
+        .enable_http2()
+        .build())
+}
+
+pub fn client() -> Result<HttpClient, Box<dyn std::error::Error>> {
+    Ok(Client::builder(TokioExecutor::new()).build(connector()?))
+}
+
+// Send a request and read the whole response body as text
+pub async fn send(client: &HttpClient, request: Request<String>) -> Result<(StatusCode, String), Box<dyn std::error::Error>> {
+    let response = client.request(request).await?;
+    let status = response.status();
+    let body = response.into_body().collect().await?.to_bytes();
+    Ok((status, String::from_utf8_lossy(&body).into_owned()))
+}
+
+// A request answered with a non-success status
+#[derive(Debug)]
+pub struct StatusError {
+    pub status: StatusCode,
+    /// What failed and the response body
+    pub message: String,
+}
+
+impl std::fmt::Display for StatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+// Turn a non-success status into an error carrying the response body
+pub fn check(status: StatusCode, body: &str, what: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if status.is_success() {
+        Ok(())
+    } else {
+        Err(Box::new(StatusError { status, message: format!("{} failed with {}: {}", what, status, body.trim()) }))
+    }
+}
@@ -0,0 +1,39 @@
+// Terminal hyperlinks (OSC 8): text that opens a URL when clicked in terminals
+// that support them and shows as plain text in the rest. Only written to a
+// terminal, unless "hyperlinks" in the config says otherwise.
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set from the config, or whether stdout is a terminal
+static ON: AtomicBool = AtomicBool::new(false);
+
+pub fn set(on: bool) {
+    ON.store(on, Ordering::SeqCst);
+}
+
+// `text` linking to `url`, or just `text` when hyperlinks are off
+pub fn link(url: &str, text: &str) -> String {
+    match ON.load(Ordering::SeqCst) {
+        true => format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text),
+        false => text.to_string(),
+    }
+}
+
+// `text` with each http(s) URL in it linking to itself
+pub fn urls(text: &str) -> String {
+    if !ON.load(Ordering::SeqCst) {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("https://").into_iter().chain(rest.find("http://")).min() {
+        out.push_str(&rest[..start]);
+        let url = &rest[start..];
+        let end = url.find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '\'')).unwrap_or(url.len());
+        // Punctuation ending a sentence isn't part of the URL
+        let url = url[..end].trim_end_matches(['.', ',', ';', ':', '!', '?', ')']);
+        out.push_str(&link(url, url));
+        rest = &rest[start + url.len()..];
+    }
+    out.push_str(rest);
+    out
+}
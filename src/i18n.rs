@@ -0,0 +1,209 @@
+// Translations of user-facing messages, kept in Fluent files (.ftl), read
+// with the `fluent-bundle` crate, so translations get its selectors and
+// plural forms. English is built in; other languages are read from
+// "locales/<language>.ftl" in the config folder, which can also override
+// built-in messages. The language comes from LC_ALL, LC_MESSAGES
+// or LANG, and day and month names in dates from LC_ALL, LC_TIME or LANG.
+// Messages missing from a translation are shown in English.
+use std::sync::{LazyLock, OnceLock};
+
+use chrono::format::{Item, StrftimeItems};
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::types::FluentNumber;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use unic_langid::LanguageIdentifier;
+
+use crate::paths;
+
+// Catalogs built in, by language
+const BUILT_IN: [(&str, &str); 1] = [("en", include_str!("../locales/en.ftl"))];
+
+type Catalog = FluentBundle<FluentResource>;
+
+static ENGLISH: LazyLock<Catalog> = LazyLock::new(|| catalog("en", [parse(BUILT_IN[0].1)]));
+
+struct Languages {
+    messages: Option<Catalog>,
+    dates: Option<Catalog>,
+}
+
+static LANGUAGES: OnceLock<Languages> = OnceLock::new();
+
+// A message with its values filled in
+macro_rules! t {
+    ($key:literal $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::i18n::message($key, &[$((stringify!($name), $value.to_string())),*])
+    };
+}
+pub(crate) use t;
+
+// The messages of a Fluent file. Entries with mistakes are left out, and the
+// rest still read, as Fluent does.
+fn parse(text: &str) -> FluentResource {
+    FluentResource::try_new(text.to_string()).unwrap_or_else(|(resource, _)| resource)
+}
+
+// "de_DE.UTF-8" as ["de-DE", "de"], from the first of the variables that's set
+fn language(variables: &[&str]) -> Vec<String> {
+    let Some(value) = variables.iter().filter_map(|name| std::env::var(name).ok()).find(|value| !value.is_empty()) else {
+        return Vec::new();
+    };
+    let tag = value.split(['.', '@']).next().unwrap_or_default().replace('_', "-");
+    if tag == "C" || tag == "POSIX" {
+        return Vec::new();
+    }
+    let mut tags = vec![tag.clone()];
+    if let Some((base, _)) = tag.split_once('-') {
+        tags.push(base.to_string());
+    }
+    tags
+}
+
+// The catalog for the first of `tags` there is one for, the config folder's
+// file laid over the built-in one
+fn load(tags: &[String]) -> Option<Catalog> {
+    tags.iter().find_map(|tag| {
+        let built_in = BUILT_IN.iter().find(|(language, _)| language == tag).map(|(_, text)| parse(text));
+        let own = std::fs::read_to_string(paths::config(&format!("locales/{}.ftl", tag))).ok().map(|text| parse(&text));
+        (built_in.is_some() || own.is_some()).then(|| catalog(tag, built_in.into_iter().chain(own)))
+    })
+}
+
+// A catalog of `resources` in language `tag`, later ones overriding messages
+// of earlier ones
+fn catalog(tag: &str, resources: impl IntoIterator<Item = FluentResource>) -> Catalog {
+    let mut catalog = Catalog::new_concurrent(vec![tag.parse::<LanguageIdentifier>().unwrap_or_default()]);
+    // Terminals show the marks Fluent puts around values as they are
+    catalog.set_use_isolating(false);
+    for resource in resources {
+        catalog.add_resource_overriding(resource);
+    }
+    catalog
+}
+
+// Pick the languages; runs after `paths::init` so translations can be found
+pub fn init() {
+    LANGUAGES.get_or_init(|| Languages {
+        messages: load(&language(&["LC_ALL", "LC_MESSAGES", "LANG"])),
+        dates: load(&language(&["LC_ALL", "LC_TIME", "LANG"])),
+    });
+}
+
+// `key` from `catalog` with `values` filled in. A value that's a number as
+// written goes in as one, for selectors to pick plural forms by.
+fn lookup(catalog: &Catalog, key: &str, values: &[(&str, String)]) -> Option<String> {
+    let pattern = catalog.get_message(key)?.value()?;
+    let mut args = FluentArgs::new();
+    for (name, value) in values {
+        match value.parse::<FluentNumber>() {
+            Ok(number) if number.as_string() == value.as_str() => args.set(*name, number),
+            _ => args.set(*name, FluentValue::from(value.as_str())),
+        }
+    }
+    let mut errors = Vec::new();
+    Some(catalog.format_pattern(pattern, Some(&args), &mut errors).into_owned())
+}
+
+// The message for `key` in the user's language, English if it has none
+pub fn message(key: &str, values: &[(&str, String)]) -> String {
+    LANGUAGES
+        .get()
+        .and_then(|languages| languages.messages.as_ref())
+        .and_then(|catalog| lookup(catalog, key, values))
+        .or_else(|| lookup(&ENGLISH, key, values))
+        .unwrap_or_else(|| key.to_string())
+}
+
+// A date format with %A, %a, %B and %b spelled out in the LC_TIME language,
+// for chrono to fill in the rest; unchanged without a translation of the names
+pub fn localize_format(format: &str, time: &impl chrono::Datelike) -> String {
+    let Some(dates) = LANGUAGES.get().and_then(|languages| languages.dates.as_ref()) else {
+        return format.to_string();
+    };
+    let name = |key: &str, index: u32| {
+        lookup(dates, key, &[]).and_then(|names| names.split(',').nth(index as usize).map(|name| name.trim().replace('%', "%%")))
+    };
+    let weekday = time.weekday().num_days_from_monday();
+    let month = time.month0();
+    let mut localized = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            localized.push(c);
+            continue;
+        }
+        let Some(spec) = chars.next() else {
+            localized.push(c);
+            break;
+        };
+        let replacement = match spec {
+            'A' => name("date-weekdays", weekday),
+            'a' => name("date-weekdays-short", weekday),
+            'B' => name("date-months", month),
+            'b' | 'h' => name("date-months-short", month),
+            _ => None,
+        };
+        match replacement {
+            Some(name) => localized.push_str(&name),
+            None => {
+                localized.push(c);
+                localized.push(spec);
+            }
+        }
+    }
+    localized
+}
+
+// `time` in a strftime format, with names in the LC_TIME language
+pub fn format<Tz: chrono::TimeZone>(time: &chrono::DateTime<Tz>, format: &str) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    time.format(&localize_format(format, time)).to_string()
+}
+
+// The LC_TIME language's format for times, if it has a valid one
+pub fn time_format() -> Option<&'static str> {
+    static FORMAT: OnceLock<Option<String>> = OnceLock::new();
+    let format = FORMAT.get_or_init(|| {
+        let format = lookup(LANGUAGES.get()?.dates.as_ref()?, "date-time-format", &[])?;
+        (!StrftimeItems::new(&format).any(|item| item == Item::Error)).then_some(format)
+    });
+    format.as_deref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn english_has_no_mistakes() {
+        assert!(FluentResource::try_new(BUILT_IN[0].1.to_string()).is_ok());
+    }
+
+    #[test]
+    fn counts_pick_plural_forms() {
+        let days = |days: &str| lookup(&ENGLISH, "time-days", &[("days", days.to_string())]);
+        assert_eq!(days("1").as_deref(), Some("1 day"));
+        assert_eq!(days("3").as_deref(), Some("3 days"));
+        let undo = |redo: bool| lookup(&ENGLISH, "undo-nothing", &[("redo", redo.to_string())]);
+        assert_eq!(undo(true).as_deref(), Some("Nothing to redo."));
+        assert_eq!(undo(false).as_deref(), Some("Nothing to undo."));
+    }
+
+    #[test]
+    fn translations_fall_back_to_english() {
+        let german = catalog("de", [parse("task-kept = Aufgabe { $id } behalten.\nbroken = { $id")]);
+        assert_eq!(lookup(&german, "task-kept", &[("id", "7".to_string())]).as_deref(), Some("Aufgabe 7 behalten."));
+        assert_eq!(lookup(&german, "task-removed", &[]), None);
+        assert_eq!(message("task-kept", &[("id", "7".to_string())]), "Kept task 7.");
+        assert_eq!(message("no-such-message", &[]), "no-such-message");
+    }
+
+    #[test]
+    fn own_file_overrides_built_in_messages() {
+        let english = catalog("en", [parse(BUILT_IN[0].1), parse("task-kept = Left task { $id } alone.")]);
+        assert_eq!(lookup(&english, "task-kept", &[("id", "7".to_string())]).as_deref(), Some("Left task 7 alone."));
+        assert_eq!(lookup(&english, "task-removed", &[("id", "7".to_string()), ("title", "Call".to_string())]).as_deref(), Some("Removed task 7 'Call'."));
+    }
+}
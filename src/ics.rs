@@ -0,0 +1,327 @@
+// Minimal iCalendar (RFC 5545) reading and writing for VEVENTs
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use crate::zone::{self, Zone};
+use crate::Task;
+
+#[derive(Debug, Clone, Default)]
+pub struct VEvent {
+    pub uid: String,
+    pub summary: String,
+    pub description: String,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub rrule: Option<String>,
+    /// Time zone DTSTART and DTEND are written in, so the series repeats by
+    /// its clock; UTC when None
+    pub tzid: Option<String>,
+    /// (address, PARTSTAT) of each attendee, the address as a "mailto:" URI
+    pub attendees: Vec<(String, String)>,
+    /// PRODID of the enclosing calendar; only filled in when parsing
+    pub prodid: Option<String>,
+    /// CSS color name (RFC 7986)
+    pub color: Option<String>,
+    /// Original start of the occurrence this VEVENT overrides; only filled in when parsing
+    pub recurrence_id: Option<DateTime<Utc>>,
+    /// Starts of cancelled occurrences; only filled in when parsing
+    pub exdates: Vec<DateTime<Utc>>,
+    /// Custom task attributes, as X-TODO-TASK-META:key=value lines
+    pub meta: BTreeMap<String, String>,
+}
+
+pub fn format_datetime(time: &DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+// Parse a DATE or DATE-TIME value; floating times are read as local time
+pub fn parse_datetime(value: &str) -> Option<DateTime<Utc>> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(Utc.from_utc_datetime(&naive));
+    }
+    let naive = match NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        Ok(naive) => naive,
+        Err(_) => NaiveDate::parse_from_str(value, "%Y%m%d").ok()?.and_hms_opt(0, 0, 0)?,
+    };
+    chrono::Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+// Parse a DTSTART or DTEND value, in the zone its TZID parameter names if any
+fn parse_zoned(value: &str, params: &str) -> Option<DateTime<Utc>> {
+    let tzid = params.split(';').find_map(|param| param.split_once('=').filter(|(key, _)| key.eq_ignore_ascii_case("TZID")));
+    match tzid.map(|(_, name)| zone::parse(name.trim_matches('"'))) {
+        Some(Ok(zone)) if !value.ends_with('Z') => zone.resolve(NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?),
+        _ => parse_datetime(value),
+    }
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+// Fold content lines longer than 75 octets as required by RFC 5545
+fn push_line(out: &mut String, line: &str) {
+    let mut current = 0;
+    for (index, c) in line.char_indices() {
+        if index - current + c.len_utf8() > 75 {
+            out.push_str(&line[current..index]);
+            out.push_str("\r\n ");
+            current = index;
+        }
+    }
+    out.push_str(&line[current..]);
+    out.push_str("\r\n");
+}
+
+impl VEvent {
+    fn write(&self, out: &mut String) {
+        push_line(out, "BEGIN:VEVENT");
+        push_line(out, &format!("UID:{}", self.uid));
+        push_line(out, &format!("DTSTAMP:{}", format_datetime(&Utc::now())));
+        for (name, time) in [("DTSTART", &self.start), ("DTEND", &self.end)] {
+            let Some(time) = time else { continue };
+            match &self.tzid {
+                Some(tzid) => {
+                    let local = Zone::of(Some(tzid)).naive(*time);
+                    push_line(out, &format!("{};TZID={}:{}", name, tzid, local.format("%Y%m%dT%H%M%S")));
+                }
+                None => push_line(out, &format!("{}:{}", name, format_datetime(time))),
+            }
+        }
+        push_line(out, &format!("SUMMARY:{}", escape_text(&self.summary)));
+        if !self.description.is_empty() {
+            push_line(out, &format!("DESCRIPTION:{}", escape_text(&self.description)));
+        }
+        if let Some(rrule) = &self.rrule {
+            push_line(out, &format!("RRULE:{}", rrule));
+        }
+        if let Some(color) = &self.color {
+            push_line(out, &format!("COLOR:{}", color));
+        }
+        for (address, partstat) in &self.attendees {
+            push_line(out, &format!("ATTENDEE;ROLE=REQ-PARTICIPANT;PARTSTAT={};RSVP=TRUE:{}", partstat, address));
+        }
+        for (key, value) in &self.meta {
+            push_line(out, &format!("X-TODO-TASK-META:{}", escape_text(&format!("{}={}", key, value))));
+        }
+        push_line(out, "END:VEVENT");
+    }
+}
+
+// The VEVENT for a dated task
+pub fn task_event(task: &Task, uid: &str) -> Option<VEvent> {
+    let (start_time, end_time) = task.schedule()?;
+    Some(VEvent {
+        uid: uid.to_string(),
+        summary: task.title.clone(),
+        description: task.details.clone(),
+        start: Some(start_time),
+        end: Some(end_time),
+        rrule: task.rrule(),
+        // A series is written in the task's zone to repeat by its clock there
+        tzid: task.zone().name().filter(|name| task.is_recurring && name != "UTC"),
+        meta: task.meta.clone(),
+        // The assignee is invited
+        attendees: task
+            .assignee
+            .as_ref()
+            .and_then(|assignee| assignee.address.as_ref())
+            .map(|address| (format!("mailto:{}", address), "NEEDS-ACTION".to_string()))
+            .into_iter()
+            .collect(),
+        ..Default::default()
+    })
+}
+
+// Wrap events in a VCALENDAR object
+pub fn write_calendar(events: &[VEvent]) -> String {
+    let mut out = String::new();
+    push_line(&mut out, "BEGIN:VCALENDAR");
+    push_line(&mut out, "VERSION:2.0");
+    push_line(&mut out, "PRODID:-//todo_task//EN");
+    for event in events {
+        event.write(&mut out);
+    }
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+// Extract every VEVENT from an iCalendar document, ignoring other components
+pub fn parse_events(text: &str) -> Vec<VEvent> {
+    // Unfold continuation lines first
+    let unfolded = text.replace("\r\n", "\n").replace("\n ", "").replace("\n\t", "");
+    let mut events = Vec::new();
+    let mut current: Option<VEvent> = None;
+    let mut prodid = None;
+    let mut depth = 0;
+    for line in unfolded.lines() {
+        let Some((name, value)) = line.split_once(':') else { continue };
+        let (name, params) = name.split_once(';').unwrap_or((name, ""));
+        match (name.to_ascii_uppercase().as_str(), current.as_mut()) {
+            ("PRODID", None) => prodid = Some(value.to_string()),
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VEVENT") => {
+                current = Some(VEvent { prodid: prodid.clone(), ..Default::default() })
+            }
+            // Nested components such as VALARM
+            ("BEGIN", Some(_)) => depth += 1,
+            ("END", Some(_)) if depth > 0 => depth -= 1,
+            ("END", Some(_)) => events.extend(current.take()),
+            (_, Some(_)) if depth > 0 => {}
+            ("UID", Some(event)) => event.uid = value.to_string(),
+            ("SUMMARY", Some(event)) => event.summary = unescape_text(value),
+            ("DESCRIPTION", Some(event)) => event.description = unescape_text(value),
+            ("DTSTART", Some(event)) => event.start = parse_zoned(value, params),
+            ("DTEND", Some(event)) => event.end = parse_zoned(value, params),
+            ("RRULE", Some(event)) => event.rrule = Some(value.to_string()),
+            ("RECURRENCE-ID", Some(event)) => event.recurrence_id = parse_datetime(value),
+            ("X-TODO-TASK-META", Some(event)) => {
+                if let Some((key, value)) = unescape_text(value).split_once('=') {
+                    event.meta.insert(key.to_string(), value.to_string());
+                }
+            }
+            ("EXDATE", Some(event)) => event.exdates.extend(value.split(',').filter_map(parse_datetime)),
+            ("ATTENDEE", Some(event)) => {
+                let partstat = params
+                    .split(';')
+                    .find_map(|param| param.split_once('=').filter(|(key, _)| key.eq_ignore_ascii_case("PARTSTAT")))
+                    .map_or("NEEDS-ACTION", |(_, partstat)| partstat);
+                event.attendees.push((value.to_string(), partstat.to_string()));
+            }
+            _ => {}
+        }
+    }
+    events
+}
+
+// Rewrite the PARTSTAT of the attendee with the given address (with or without
+// "mailto:"), keeping the rest of the document. None when there is no such attendee.
+pub fn set_partstat(text: &str, address: &str, partstat: &str) -> Option<String> {
+    let unfolded = text.replace("\r\n", "\n").replace("\n ", "").replace("\n\t", "");
+    let mut out = String::new();
+    let mut found = false;
+    for line in unfolded.lines() {
+        let attendee = line.split_once(':').and_then(|(name, value)| {
+            let (name, params) = name.split_once(';').unwrap_or((name, ""));
+            let value_address = value.strip_prefix("mailto:").unwrap_or(value);
+            (name.eq_ignore_ascii_case("ATTENDEE") && value_address.eq_ignore_ascii_case(address)).then_some((params, value))
+        });
+        match attendee {
+            Some((params, value)) => {
+                found = true;
+                let mut rewritten = "ATTENDEE".to_string();
+                for param in params.split(';').filter(|param| !param.is_empty()) {
+                    if !param.to_ascii_uppercase().starts_with("PARTSTAT=") {
+                        rewritten.push(';');
+                        rewritten.push_str(param);
+                    }
+                }
+                push_line(&mut out, &format!("{};PARTSTAT={}:{}", rewritten, partstat, value));
+            }
+            None => push_line(&mut out, line),
+        }
+    }
+    found.then_some(out)
+}
+
+// Translate an RRULE into the fixed-interval minutes tasks use, if it has one
+pub fn rrule_to_minutes(rrule: &str) -> Option<i64> {
+    let mut unit = None;
+    let mut interval = 1;
+    for part in rrule.split(';') {
+        match part.split_once('=')? {
+            ("FREQ", "MINUTELY") => unit = Some(1),
+            ("FREQ", "HOURLY") => unit = Some(60),
+            ("FREQ", "DAILY") => unit = Some(24 * 60),
+            ("FREQ", "WEEKLY") => unit = Some(7 * 24 * 60),
+            ("INTERVAL", n) => interval = n.parse().ok()?,
+            _ => {}
+        }
+    }
+    unit.map(|unit| unit * interval)
+}
+
+pub fn minutes_to_rrule(minutes: i64) -> String {
+    format!("FREQ=MINUTELY;INTERVAL={}", minutes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_read_back_as_written() {
+        let start = Utc.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap();
+        let event = VEvent {
+            uid: "task-1".to_string(),
+            summary: "Call Ann; then Bob, maybe".to_string(),
+            description: format!("Notes\n{}", "x".repeat(100)),
+            start: Some(start),
+            end: Some(start + chrono::Duration::minutes(30)),
+            rrule: Some("FREQ=WEEKLY;BYDAY=MO".to_string()),
+            attendees: vec![("mailto:ann@example.com".to_string(), "NEEDS-ACTION".to_string())],
+            meta: BTreeMap::from([("ticket".to_string(), "T-1".to_string())]),
+            ..Default::default()
+        };
+        let text = write_calendar(std::slice::from_ref(&event));
+        assert!(text.lines().all(|line| line.len() <= 75));
+        let read = &parse_events(&text)[0];
+        assert_eq!(read.uid, event.uid);
+        assert_eq!(read.summary, event.summary);
+        assert_eq!(read.description, event.description);
+        assert_eq!((read.start, read.end), (event.start, event.end));
+        assert_eq!(read.rrule, event.rrule);
+        assert_eq!(read.attendees, event.attendees);
+        assert_eq!(read.meta, event.meta);
+        assert_eq!(read.prodid.as_deref(), Some("-//todo_task//EN"));
+    }
+
+    #[test]
+    fn alarms_inside_events_are_skipped() {
+        let text = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:a\r\nSUMMARY:Dentist\r\nBEGIN:VALARM\r\nSUMMARY:Soon\r\nEND:VALARM\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let events = parse_events(text);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Dentist");
+    }
+
+    #[test]
+    fn replies_rewrite_only_the_attendee() {
+        let text = "BEGIN:VEVENT\r\nATTENDEE;CN=Ann;PARTSTAT=NEEDS-ACTION:mailto:ann@example.com\r\nATTENDEE;PARTSTAT=ACCEPTED:mailto:bob@example.com\r\nEND:VEVENT\r\n";
+        let replied = set_partstat(text, "ANN@example.com", "DECLINED").unwrap();
+        assert!(replied.contains("ATTENDEE;CN=Ann;PARTSTAT=DECLINED:mailto:ann@example.com"));
+        assert!(replied.contains("ATTENDEE;PARTSTAT=ACCEPTED:mailto:bob@example.com"));
+        assert!(set_partstat(text, "eve@example.com", "DECLINED").is_none());
+    }
+
+    #[test]
+    fn fixed_intervals_translate_both_ways() {
+        assert_eq!(rrule_to_minutes("FREQ=DAILY;INTERVAL=2"), Some(2 * 24 * 60));
+        assert_eq!(rrule_to_minutes("FREQ=WEEKLY"), Some(7 * 24 * 60));
+        assert_eq!(rrule_to_minutes(&minutes_to_rrule(90)), Some(90));
+        assert_eq!(rrule_to_minutes("FREQ=MONTHLY;BYMONTHDAY=1"), None);
+    }
+}
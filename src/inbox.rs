@@ -0,0 +1,91 @@
+// Inbox: thoughts captured with `in` as they come, without dates or anything
+// else to decide, and `triage` to turn them into scheduled or someday tasks
+// or drop them later. Tasks in the inbox aren't pushed anywhere.
+use chrono::{Duration, Utc};
+
+use crate::config::Config;
+use crate::conflicts::{prompt, read_answer};
+use crate::i18n::t;
+use crate::{capacity, format_time, when, AppState, Task, TaskStatus};
+
+// Open tasks still in the inbox, oldest first
+pub fn items(tasks: &[Task]) -> Vec<&Task> {
+    let mut items: Vec<&Task> = tasks.iter().filter(|task| task.inbox && task.status == TaskStatus::Todo).collect();
+    items.sort_by_key(|task| (task.created_at, task.id));
+    items
+}
+
+// Take the task out of the inbox, as it is now, and push it to the calendar or
+// task service it belongs on
+async fn file(config: &Config, state: &AppState, task: &Task) {
+    if state.set_inbox(task.id, false).await.is_none() {
+        return;
+    }
+    crate::save_state(state).await;
+    if let Err(e) = crate::push_to_calendar(config, state, task.id).await {
+        eprintln!("  {}", t!("inbox-push-failed", error = format!("{:?}", e)));
+    }
+}
+
+async fn schedule(config: &Config, state: &AppState, task: &Task) {
+    let Some(answer) = read_answer("  Start (e.g. \"tomorrow 9am\", \"friday 14:30\" or 2024-12-31T15:00:00Z):") else { return };
+    let start_time = match when::parse(&answer) {
+        Ok(start_time) if start_time > Utc::now() => start_time,
+        Ok(_) => {
+            println!("  {}", t!("inbox-passed", answer = answer));
+            return;
+        }
+        Err(e) => {
+            println!("  {}", t!("inbox-left", error = e));
+            return;
+        }
+    };
+    let default = task.estimate_minutes.unwrap_or(30);
+    let question = format!("  Length [{}]:", capacity::format_length(Duration::minutes(default)));
+    let Some(answer) = read_answer(&question) else { return };
+    let minutes = match answer.as_str() {
+        "" => default,
+        answer => match capacity::parse_estimate(answer) {
+            Ok(minutes) => minutes,
+            Err(e) => {
+                println!("  {}", t!("inbox-left", error = e));
+                return;
+            }
+        },
+    };
+    let Some(updated) = state.reschedule_task(task.id, start_time, start_time + Duration::minutes(minutes)).await else { return };
+    file(config, state, &updated).await;
+    println!("  {}", t!("inbox-scheduled", start = format_time(updated.start_time)));
+}
+
+// Ask what to do with each item in the inbox
+pub async fn triage(config: &Config, state: &AppState, items: &[&Task]) {
+    for (index, task) in items.iter().enumerate() {
+        println!();
+        println!("({}/{}) #{} '{}'", index + 1, items.len(), task.id, task.title);
+        if !task.details.is_empty() {
+            println!("  {}", task.details);
+        }
+        let answer = loop {
+            let answer = prompt("[s]chedule, some[d]ay, delete with [x], [k]eep in the inbox or [q]uit?");
+            if ["s", "d", "x", "k", "q"].contains(&answer.as_str()) {
+                break answer;
+            }
+        };
+        match answer.as_str() {
+            "s" => schedule(config, state, task).await,
+            "d" => {
+                file(config, state, task).await;
+                println!("  {}", t!("inbox-someday"));
+            }
+            "x" => {
+                let deleted = crate::remove_task(config, state, task.id).await;
+                if deleted.is_some() {
+                    println!("  {}", t!("inbox-deleted"));
+                }
+            }
+            "q" => break,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,173 @@
+// Jira Cloud: tasks from issues matching a JQL filter, updates on completion
+use std::collections::HashSet;
+
+use base64::Engine;
+use chrono::{Duration, NaiveDate};
+use hyper::{Method, Request};
+use serde_json::{json, Value};
+
+use crate::config::JiraConfig;
+use crate::http::{self, HttpClient};
+use crate::{date_only_start, AppState, Priority, Task, TaskStatus};
+
+#[derive(Debug, Default)]
+pub struct JiraSummary {
+    pub added: usize,
+    pub updated: usize,
+    /// Local tasks marked done because their issue was resolved
+    pub completed: usize,
+    /// Issues commented on or transitioned because their task was completed here
+    pub closed: usize,
+}
+
+struct JiraClient<'a> {
+    client: HttpClient,
+    config: &'a JiraConfig,
+    authorization: String,
+}
+
+impl<'a> JiraClient<'a> {
+    fn new(config: &'a JiraConfig) -> Result<JiraClient<'a>, Box<dyn std::error::Error>> {
+        let credentials = format!("{}:{}", config.email, config.api_token);
+        Ok(JiraClient {
+            client: http::client()?,
+            config,
+            authorization: format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(credentials)),
+        })
+    }
+
+    async fn call(&self, method: Method, path: &str, body: Option<Value>) -> Result<Value, Box<dyn std::error::Error>> {
+        let request = Request::builder()
+            .method(method.clone())
+            .uri(format!("{}/rest/api/3{}", self.config.url.trim_end_matches('/'), path))
+            .header("Authorization", &self.authorization)
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json")
+            .body(body.map(|body| body.to_string()).unwrap_or_default())?;
+        let (status, body) = http::send(&self.client, request).await?;
+        http::check(status, &body, &format!("Jira {} {}", method, path))?;
+        if body.trim().is_empty() {
+            return Ok(Value::Null);
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    // Post the configured comment and apply the configured transition
+    async fn complete(&self, key: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(comment) = &self.config.done_comment {
+            let body = json!({
+                "body": {
+                    "type": "doc",
+                    "version": 1,
+                    "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": comment }] }],
+                }
+            });
+            self.call(Method::POST, &format!("/issue/{}/comment", key), Some(body)).await?;
+        }
+        if let Some(name) = &self.config.done_transition {
+            let transitions = self.call(Method::GET, &format!("/issue/{}/transitions", key), None).await?;
+            let transition = transitions["transitions"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .find(|transition| transition["name"].as_str().is_some_and(|n| n.eq_ignore_ascii_case(name)))
+                .and_then(|transition| transition["id"].as_str())
+                .ok_or_else(|| format!("Issue {} has no transition named '{}'", key, name))?;
+            let body = json!({ "transition": { "id": transition } });
+            self.call(Method::POST, &format!("/issue/{}/transitions", key), Some(body)).await?;
+        }
+        Ok(())
+    }
+
+    async fn is_done(&self, key: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let issue = self.call(Method::GET, &format!("/issue/{}?fields=status", key), None).await?;
+        Ok(issue["fields"]["status"]["statusCategory"]["key"].as_str() == Some("done"))
+    }
+}
+
+fn priority_from_jira(name: Option<&str>) -> Priority {
+    match name {
+        Some("Highest") | Some("Blocker") => Priority::Urgent,
+        Some("High") | Some("Critical") => Priority::High,
+        Some("Low") | Some("Lowest") | Some("Minor") | Some("Trivial") => Priority::Low,
+        _ => Priority::Normal,
+    }
+}
+
+fn issue_task(issue: &Value) -> Option<Task> {
+    let fields = &issue["fields"];
+    let start_time = fields["duedate"]
+        .as_str()
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .and_then(date_only_start);
+    Some(Task {
+        id: 0,
+        title: format!("{} {}", issue["key"].as_str()?, fields["summary"].as_str()?),
+        start_time,
+        end_time: start_time.map(|start| start + Duration::minutes(30)),
+        status: TaskStatus::Todo,
+        project: fields["project"]["key"].as_str().map(str::to_string),
+        tags: fields["labels"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|label| label.as_str().map(str::to_string))
+            .collect(),
+        priority: priority_from_jira(fields["priority"]["name"].as_str()),
+        jira_issue: Some(issue["key"].as_str()?.to_string()),
+        ..Default::default()
+    })
+}
+
+pub async fn complete_issue(config: &JiraConfig, key: &str) -> Result<(), Box<dyn std::error::Error>> {
+    JiraClient::new(config)?.complete(key).await
+}
+
+// Pull issues matching the JQL, then reconcile resolved issues and completed tasks
+pub async fn sync(config: &JiraConfig, state: &AppState) -> Result<JiraSummary, Box<dyn std::error::Error>> {
+    let client = JiraClient::new(config)?;
+    let mut summary = JiraSummary::default();
+    let mut matching = HashSet::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let mut query = json!({
+            "jql": config.jql,
+            "fields": ["summary", "duedate", "priority", "project", "labels"],
+            "maxResults": 100,
+        });
+        if let Some(token) = &page_token {
+            query["nextPageToken"] = json!(token);
+        }
+        let page = client.call(Method::POST, "/search/jql", Some(query)).await?;
+        for issue in page["issues"].as_array().into_iter().flatten() {
+            let Some(task) = issue_task(issue) else { continue };
+            matching.extend(task.jira_issue.clone());
+            if state.upsert_imported(task, |task| task.jira_issue.clone()).await {
+                summary.updated += 1;
+            } else {
+                summary.added += 1;
+            }
+        }
+        match page["nextPageToken"].as_str() {
+            Some(token) if page["isLast"].as_bool() != Some(true) => page_token = Some(token.to_string()),
+            _ => break,
+        }
+    }
+
+    for task in state.list_tasks().await {
+        let Some(key) = &task.jira_issue else { continue };
+        match task.status {
+            // Without a transition the issue would keep matching and be commented on every sync
+            TaskStatus::Done if matching.contains(key) && config.done_transition.is_some() => {
+                client.complete(key).await?;
+                summary.closed += 1;
+            }
+            TaskStatus::Todo if !matching.contains(key) && client.is_done(key).await? => {
+                state.complete_task(task.id).await;
+                summary.completed += 1;
+            }
+            _ => {}
+        }
+    }
+    Ok(summary)
+}
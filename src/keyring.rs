@@ -0,0 +1,211 @@
+// OAuth tokens kept in the system keyring (Secret Service, macOS Keychain or
+// Windows Credential Manager) through the platform's own command-line tool, or
+// in a plaintext file when `token_store` is "file"
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::config::TokenStore;
+use crate::i18n::t;
+use crate::{atomic, paths};
+
+// Service the keyring entries are filed under
+const SERVICE: &str = "todo_task";
+
+/// Set once the keyring tool was found missing and the warning printed
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+// The keyring tool couldn't be started, e.g. as it isn't installed
+#[derive(Debug)]
+struct Unreachable {
+    program: String,
+    error: std::io::Error,
+}
+
+impl std::fmt::Display for Unreachable {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&t!("keyring-unreachable", program = self.program, error = self.error))
+    }
+}
+
+impl std::error::Error for Unreachable {}
+
+// Run a keyring tool, feeding it `input`. None when it exits with `missing`,
+// the tool's code for "no such entry".
+fn run(mut command: Command, input: Option<&str>, missing: i32) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| Unreachable { program: program.clone(), error })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.unwrap_or_default().as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    match output.status.code() {
+        Some(0) => Ok(Some(String::from_utf8(output.stdout)?.trim_end_matches(['\r', '\n']).to_string())),
+        Some(code) if code == missing => Ok(None),
+        _ => Err(t!("keyring-failed", program = program, error = String::from_utf8_lossy(&output.stderr).trim()).into()),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn get(account: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let mut command = Command::new("security");
+    command.args(["find-generic-password", "-s", SERVICE, "-a", account, "-w"]);
+    run(command, None, 44)
+}
+
+// The token goes to `security -i` on stdin, as a hex string so it needs no
+// quoting, and never on a command line other users can see
+#[cfg(target_os = "macos")]
+fn set(account: &str, secret: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let hex: String = secret.bytes().map(|byte| format!("{:02x}", byte)).collect();
+    let quoted = account.replace('\\', "\\\\").replace('"', "\\\"");
+    let input = format!("add-generic-password -U -s {} -a \"{}\" -X {}\n", SERVICE, quoted, hex);
+    let mut command = Command::new("security");
+    command.arg("-i");
+    run(command, Some(&input), -1)?;
+    // `security -i` exits with 0 when a command in it fails
+    match get(account)?.as_deref() == Some(secret) {
+        true => Ok(()),
+        false => Err(t!("keyring-not-stored").into()),
+    }
+}
+
+// PowerShell reaching the Credential Manager through the WinRT password vault.
+// The account, which has the profile's name in it, is handed over in an
+// environment variable rather than written into the script.
+#[cfg(windows)]
+fn powershell(script: &str, account: &str) -> Command {
+    let vault = "[void][Windows.Security.Credentials.PasswordVault,Windows.Security.Credentials,ContentType=WindowsRuntime]; \
+                 $vault = New-Object Windows.Security.Credentials.PasswordVault; ";
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-NonInteractive", "-Command", &format!("{}{}", vault, script)]);
+    command.env("TODO_TASK_KEYRING_ACCOUNT", account);
+    command
+}
+
+#[cfg(windows)]
+fn get(account: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let script = format!(
+        "try {{ $credential = $vault.Retrieve('{}', $env:TODO_TASK_KEYRING_ACCOUNT) }} catch {{ exit 44 }}; $credential.RetrievePassword(); $credential.Password",
+        SERVICE
+    );
+    run(powershell(&script, account), None, 44)
+}
+
+#[cfg(windows)]
+fn set(account: &str, secret: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let script = format!(
+        "$secret = [Console]::In.ReadToEnd(); $vault.Add((New-Object Windows.Security.Credentials.PasswordCredential('{}', $env:TODO_TASK_KEYRING_ACCOUNT, $secret)))",
+        SERVICE
+    );
+    run(powershell(&script, account), Some(secret), -1).map(|_| ())
+}
+
+// The Secret Service (GNOME Keyring, KWallet) through libsecret's secret-tool
+#[cfg(not(any(target_os = "macos", windows)))]
+fn get(account: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let mut command = Command::new("secret-tool");
+    command.args(["lookup", "service", SERVICE, "account", account]);
+    run(command, None, 1)
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn set(account: &str, secret: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut command = Command::new("secret-tool");
+    let label = format!("{} {}", SERVICE, account);
+    command.args(["store", "--label", &label, "service", SERVICE, "account", account]);
+    run(command, Some(secret), -1).map(|_| ())
+}
+
+// The entry for `account`; a profile's entries have its name in front
+fn entry(account: &str) -> String {
+    match paths::profile() {
+        Some(profile) => format!("{}/{}", profile, account),
+        None => account.to_string(),
+    }
+}
+
+// The stored token, if any. With the keyring, a token file left from before is
+// moved into it.
+pub fn load(store: TokenStore, account: &str, path: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let file = match std::fs::read_to_string(path) {
+        Ok(contents) => Some(contents),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(Box::new(e)),
+    };
+    if store == TokenStore::File {
+        return Ok(file);
+    }
+    let account = &entry(account);
+    match get(account) {
+        Ok(Some(secret)) => return Ok(Some(secret)),
+        Ok(None) => {}
+        Err(e) if e.is::<Unreachable>() => {
+            warn_file_instead(&*e, path);
+            return Ok(file);
+        }
+        Err(e) => return Err(e),
+    }
+    let Some(contents) = file else { return Ok(None) };
+    set(account, &contents)?;
+    std::fs::remove_file(path)?;
+    eprintln!("{}", t!("keyring-moved", path = path));
+    Ok(Some(contents))
+}
+
+// Where the token is, for messages
+pub fn location(store: TokenStore, account: &str, path: &str) -> String {
+    match store {
+        TokenStore::File => path.to_string(),
+        TokenStore::Keyring => t!("keyring-entry", service = SERVICE, entry = entry(account)),
+    }
+}
+
+pub fn save(store: TokenStore, account: &str, path: &str, contents: &str) -> Result<(), Box<dyn std::error::Error>> {
+    match store {
+        TokenStore::File => Ok(atomic::write_private(path, contents)?),
+        TokenStore::Keyring => match set(&entry(account), contents) {
+            Err(e) if e.is::<Unreachable>() => {
+                warn_file_instead(&*e, path);
+                Ok(atomic::write_private(path, contents)?)
+            }
+            result => result,
+        },
+    }
+}
+
+// Say, once, that tokens go to the file at `path` as the keyring can't be reached
+fn warn_file_instead(error: &dyn std::error::Error, path: &str) {
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        eprintln!("{}", t!("keyring-file-instead", error = error.to_string(), path = path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_tools_are_told_apart() {
+        let missing = run(Command::new("todo-task-no-such-keyring-tool"), None, 1);
+        assert!(missing.is_err_and(|e| e.is::<Unreachable>()));
+    }
+
+    #[test]
+    fn token_files_are_private() {
+        let path = std::env::temp_dir().join(format!("todo-task-token-{}.json", std::process::id())).to_string_lossy().into_owned();
+        save(TokenStore::File, "google", &path, "{\"token\": \"secret\"}").unwrap();
+        assert_eq!(load(TokenStore::File, "google", &path).unwrap().as_deref(), Some("{\"token\": \"secret\"}"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,72 @@
+// Links between tasks outside the dependency and subtask tree: "relates to"
+// and "duplicates"
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{Task, TaskStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Relation {
+    RelatesTo,
+    /// The linking task is a duplicate of the linked one
+    Duplicates,
+}
+
+// A link kept on the task it was made from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Link {
+    /// ID of the linked task
+    pub id: u32,
+    pub relation: Relation,
+}
+
+// Links from and to `task` as "relates to #12 'Title'" lines
+pub fn describe(task: &Task, tasks: &[Task]) -> Vec<String> {
+    let title = |id: u32| tasks.iter().find(|other| other.id == id).map_or(String::new(), |other| format!(" '{}'", other.title));
+    let outgoing = task.links.iter().map(|link| {
+        let verb = match link.relation {
+            Relation::RelatesTo => "relates to",
+            Relation::Duplicates => "duplicates",
+        };
+        format!("{} #{}{}", verb, link.id, title(link.id))
+    });
+    let incoming = tasks.iter().flat_map(|other| {
+        other.links.iter().filter(|link| link.id == task.id).map(move |link| {
+            let verb = match link.relation {
+                Relation::RelatesTo => "relates to",
+                Relation::Duplicates => "duplicated by",
+            };
+            format!("{} #{} '{}'", verb, other.id, other.title)
+        })
+    });
+    outgoing.chain(incoming).collect()
+}
+
+// Open tasks that `task` is a duplicate of
+pub fn open_duplicates<'a>(task: &Task, tasks: &'a [Task]) -> Vec<&'a Task> {
+    task.links
+        .iter()
+        .filter(|link| link.relation == Relation::Duplicates)
+        .filter_map(|link| tasks.iter().find(|other| other.id == link.id && other.status == TaskStatus::Todo))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_are_described_from_both_ends() {
+        let tasks = vec![
+            Task { id: 1, title: "Book flights".to_string(), ..Default::default() },
+            Task { id: 2, title: "Book flight".to_string(), links: vec![Link { id: 1, relation: Relation::Duplicates }], ..Default::default() },
+            Task { id: 3, title: "Pack".to_string(), links: vec![Link { id: 1, relation: Relation::RelatesTo }], ..Default::default() },
+        ];
+        assert_eq!(describe(&tasks[1], &tasks), ["duplicates #1 'Book flights'"]);
+        assert_eq!(describe(&tasks[0], &tasks), ["duplicated by #2 'Book flight'", "relates to #3 'Pack'"]);
+        assert_eq!(open_duplicates(&tasks[1], &tasks).len(), 1);
+        let done = vec![Task { status: TaskStatus::Done, ..tasks[0].clone() }, tasks[1].clone()];
+        assert!(open_duplicates(&done[1], &done).is_empty());
+    }
+}
@@ -1,338 +1,4442 @@
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use chrono::{Datelike, DateTime, DurationRound, Local, SubsecRound, TimeZone, Utc};
 use tokio::sync::Mutex;
-use tokio::time::sleep;
-use tokio::runtime::Runtime;
-use google_calendar3::{api::Event, CalendarHub};
-use yup_oauth2::{InstalledFlowAuthenticator, InstalledFlowReturnMethod};
-use clap::{Parser, Subcommand};
-use hyper_rustls::HttpsConnectorBuilder;
-use hyper_util::client::legacy::{Client, connect::HttpConnector};
-use hyper::body::Body;
-
-type HyperClient = Client<hyper_rustls::HttpsConnector<HttpConnector>, Body>;
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use clap::{Parser, Subcommand, ValueEnum};
+
+mod agenda;
+mod atomic;
+mod audit;
+mod block;
+mod bulk;
+mod burndown;
+mod caldav;
+mod capacity;
+mod clipboard;
+mod config;
+mod conflicts;
+mod confirm;
+mod context;
+mod crdt;
+mod cron;
+mod deps;
+mod desktop;
+mod digest;
+mod doctor;
+mod escalation;
+mod exit;
+mod feeds;
+mod filter;
+mod github;
+mod gitstore;
+mod google;
+mod google_tasks;
+mod graph;
+mod heatmap;
+mod history;
+mod hyperlink;
+mod i18n;
+mod inbox;
+mod http;
+mod ics;
+mod jira;
+mod keyring;
+mod links;
+mod maintenance;
+mod metrics;
+mod mqtt;
+mod notify;
+mod org;
+mod paths;
+mod pick;
+mod picker;
+mod progress;
+mod quota;
+mod quiet;
+mod recurrence;
+mod reload;
+mod schema;
+mod serve;
+mod service;
+mod shutdown;
+mod stale;
+mod store;
+mod sync;
+mod taskwarrior;
+mod timeline;
+mod todoist;
+mod trello;
+mod undo;
+mod webhook;
+mod week;
+mod when;
+mod zone;
+
+use config::{CompletedEvents, Config, MqttConfig};
+use exit::fail;
+use feeds::FeedCache;
+use i18n::t;
+use quiet::say;
+use google_tasks::GoogleTasksBackend;
+use store::{Lookup, TaskStore};
+use sync::{Backend, CalendarBackend, RemoteEvent, Rsvp, ServiceState, Snapshot, SyncFilter, SyncState};
+
+// File the task list is persisted to between runs
+const STORE_PATH: &str = "tasks.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "lowercase")]
+enum TaskStatus {
+    #[default]
+    Todo,
+    Done,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+// How output meant for other programs is printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+enum OutputFormat {
+    #[default]
+    Text,
+    /// Errors as `{"error": {"code": ..., "message": ...}}`, one per line on stderr
+    Json,
+}
+
+// Order `list` shows tasks in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+enum SortKey {
+    #[default]
+    Id,
+    Start,
+    /// Highest priority first, escalation included, then soonest start
+    Urgency,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 struct Task {
     id: u32,
+    /// Stable identifier shared with other tools (Taskwarrior, calendar events)
+    #[serde(default = "new_uuid")]
+    uuid: String,
     title: String,
     details: String,
-    start_time: DateTime<Utc>,
-    end_time: DateTime<Utc>,
+    /// Unset for undated (someday) tasks
+    start_time: Option<DateTime<Utc>>,
+    end_time: Option<DateTime<Utc>>,
     is_recurring: bool,
     frequency_minutes: Option<i64>,
+    #[serde(default)]
+    status: TaskStatus,
+    /// ID of the linked event on the sync backend
+    #[serde(default)]
+    event_id: Option<String>,
+    /// ETag and last-modified time of the linked event as of the last sync
+    #[serde(default)]
+    event_etag: Option<String>,
+    #[serde(default)]
+    event_updated: Option<DateTime<Utc>>,
+    /// IANA time zone the task's times are in, e.g. "Asia/Tokyo"; local time when unset
+    #[serde(default)]
+    timezone: Option<String>,
+    /// Web page of the linked event or task on its service, as of the last sync
+    #[serde(default)]
+    event_link: Option<String>,
+    /// Private task that is never pushed to a calendar or task service
+    #[serde(default)]
+    local_only: bool,
+    /// Cron expression a recurring task repeats on instead of every frequency_minutes
+    #[serde(default)]
+    cron: Option<String>,
+    /// Rule a recurring task repeats by instead, from `add --every`
+    #[serde(default)]
+    recurrence: Option<recurrence::Rule>,
+    /// Set while a recurring task is paused
+    #[serde(default)]
+    paused: Option<Pause>,
+    /// Occurrences of a recurring task completed or skipped, keyed by their start
+    #[serde(default)]
+    history: BTreeMap<DateTime<Utc>, history::Outcome>,
+    /// Occurrences of a recurring task that differ from the series, keyed by the start they originally had
+    #[serde(default)]
+    overrides: BTreeMap<DateTime<Utc>, Occurrence>,
+    #[serde(default)]
+    project: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    priority: Priority,
+    /// ID of the Todoist task this one was imported from
+    #[serde(default)]
+    todoist_id: Option<String>,
+    /// Linked GitHub issue as "owner/repo#123"
+    #[serde(default)]
+    github_issue: Option<String>,
+    /// Linked Jira issue key, e.g. "OPS-42"
+    #[serde(default)]
+    jira_issue: Option<String>,
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+    /// Last change made to the task other than a snooze
+    #[serde(default)]
+    touched_at: Option<DateTime<Utc>>,
+    /// Times the task was snoozed since then
+    #[serde(default)]
+    snoozes: u32,
+    /// Set while the task is blocked on someone else
+    #[serde(default)]
+    waiting: Option<Waiting>,
+    /// IDs of the tasks that have to be done first
+    #[serde(default)]
+    depends_on: Vec<u32>,
+    /// ID of the task this one is a subtask of
+    #[serde(default)]
+    parent: Option<u32>,
+    /// Links to related and duplicate tasks made from this one
+    #[serde(default)]
+    links: Vec<links::Link>,
+    /// Minutes before the start to remind at; 5 when empty
+    #[serde(default)]
+    reminders: Vec<i64>,
+    /// Event color: a Google color ID, a CSS color name for CalDAV or an Outlook category
+    #[serde(default)]
+    color: Option<String>,
+    /// Minutes of work the task is expected to take
+    #[serde(default)]
+    estimate_minutes: Option<i64>,
+    /// When the task was marked done
+    #[serde(default)]
+    completed_at: Option<DateTime<Utc>>,
+    /// Percent done, as last reported with `progress`
+    #[serde(default)]
+    progress: Option<u8>,
+    /// Free-form attributes set with `--meta`, e.g. Taskwarrior UDAs
+    #[serde(default)]
+    meta: BTreeMap<String, String>,
+    /// Captured with `in` and not yet triaged
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    inbox: bool,
+    /// Who owns the task on a shared list
+    #[serde(default)]
+    assignee: Option<Assignee>,
+}
+
+// A paused recurring task has no occurrences from `since` until it is resumed
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct Pause {
+    since: DateTime<Utc>,
+    /// The calendar event's recurrence was ended at `since`
+    #[serde(default)]
+    truncated: bool,
+}
+
+// Who a task is waiting on; `serve` reminds to follow up every
+// `follow_up_days` from `since`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct Waiting {
+    /// Person or team, e.g. "Alice"
+    on: String,
+    since: DateTime<Utc>,
+}
+
+// Who a task is assigned to; the address is invited to the task's event and
+// gets the digest
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct Assignee {
+    /// Name, e.g. "bob"
+    name: String,
+    /// From `people` in config.json, or the name itself when it is an address
+    #[serde(default)]
+    address: Option<String>,
+}
+
+impl Assignee {
+    fn new(config: &Config, name: String) -> Assignee {
+        let known = config.people.iter().find(|(known, _)| known.eq_ignore_ascii_case(&name));
+        let address = known.map(|(_, address)| address.clone()).or_else(|| name.contains('@').then(|| name.clone()));
+        Assignee { name, address }
+    }
+
+    // Whether `who` is this assignee's name or address
+    fn is(&self, who: &str) -> bool {
+        self.name.eq_ignore_ascii_case(who) || self.address.as_deref().is_some_and(|address| address.eq_ignore_ascii_case(who))
+    }
+}
+
+// One occurrence of a recurring task that was moved, renamed or cancelled
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+struct Occurrence {
+    #[serde(default)]
+    cancelled: bool,
+    /// Set when the occurrence has its own title
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+}
+
+fn new_uuid() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+impl Task {
+    // Start and end of a dated task
+    fn schedule(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        Some((self.start_time?, self.end_time?))
+    }
+
+    // Minutes before the start the task reminds at, latest reminder last
+    fn reminder_offsets(&self) -> Vec<i64> {
+        let mut offsets = if self.reminders.is_empty() { vec![5] } else { self.reminders.clone() };
+        offsets.sort_by(|a, b| b.cmp(a));
+        offsets.dedup();
+        offsets
+    }
+
+    fn touch(&mut self) {
+        self.touched_at = Some(Utc::now());
+        self.snoozes = 0;
+    }
+
+    // Time zone the task's times are in
+    fn zone(&self) -> zone::Zone {
+        zone::Zone::of(self.timezone.as_deref())
+    }
+
+    // Display one of the task's times: in its own zone, named, when it's
+    // pinned to one, otherwise as `format_time` does
+    fn format_time(&self, time: Option<DateTime<Utc>>) -> String {
+        let zone = self.zone();
+        let (Some(time), zone::Zone::Named(_)) = (time, &zone) else { return format_time(time) };
+        let there = time.with_timezone(&zone.offset(time));
+        match TIME_FORMAT.get().map(String::as_str).or_else(i18n::time_format) {
+            Some(format) => format!("{} {}", i18n::format(&there, format), zone.abbreviation(time)),
+            None => format!("{} {}", there.format("%Y-%m-%d %H:%M:%S"), zone.abbreviation(time)),
+        }
+    }
+
+    // Whether the task repeats by a cron expression or rule rather than a fixed interval
+    fn has_rule(&self) -> bool {
+        self.cron.is_some() || self.recurrence.is_some()
+    }
+
+    // Start and end of every occurrence overlapping [from, until)
+    fn occurrences(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        let Some((start_time, end_time)) = self.schedule() else { return Vec::new() };
+        let until = self.paused.as_ref().map_or(until, |pause| until.min(pause.since));
+        if self.has_rule() || self.clock_rule().is_some() {
+            agenda::expand(start_time, end_time, from, until, |after| self.next_start(after))
+        } else {
+            agenda::occurrences(start_time, end_time, self.frequency_minutes.filter(|_| self.is_recurring), from, until)
+        }
+    }
+
+    // Start of the first occurrence after `after`, which for fixed intervals
+    // must itself be the start of an occurrence
+    fn next_start(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if !self.is_recurring {
+            return None;
+        }
+        match (&self.cron, &self.recurrence) {
+            (Some(expression), _) => cron::Cron::parse(expression).ok()?.next_after(after, &self.zone()),
+            (None, Some(rule)) => rule.next_after(self.start_time?, after, &self.zone()),
+            (None, None) => match self.clock_rule() {
+                Some(rule) => rule.next_after(self.start_time?, after, &self.zone()),
+                None => Some(after + chrono::Duration::minutes(self.frequency_minutes?)),
+            },
+        }
+    }
+
+    // The rule a task repeating every whole number of days or weeks follows,
+    // so it stays at the same time of day in its zone when the clocks change
+    fn clock_rule(&self) -> Option<recurrence::Rule> {
+        self.frequency_minutes.filter(|_| self.is_recurring && !self.has_rule()).and_then(recurrence::Rule::of_minutes)
+    }
+
+    // RRULE the task's calendar event repeats by, or why its cron expression or
+    // rule has none; None for single events
+    fn try_rrule(&self) -> Option<Result<String, String>> {
+        if !self.is_recurring {
+            return None;
+        }
+        let start_time = self.start_time?;
+        // Events are written in the task's zone and repeat by its clock there;
+        // one kept in UTC instead has its days shifted to the UTC ones
+        let zone = self.zone();
+        let shift = match zone.name() {
+            Some(_) => 0,
+            None => (start_time.date_naive() - zone.naive(start_time).date()).num_days(),
+        };
+        let rrule = match (&self.cron, &self.recurrence, &self.clock_rule()) {
+            (Some(expression), _, _) => cron::Cron::parse(expression).and_then(|cron| cron.to_rrule(shift)),
+            (None, Some(rule), _) | (None, None, Some(rule)) => rule.to_rrule(shift),
+            (None, None, None) => Ok(ics::minutes_to_rrule(self.frequency_minutes?)),
+        };
+        Some(match self.paused.as_ref().filter(|pause| pause.truncated) {
+            Some(pause) => rrule.map(|rrule| format!("{};UNTIL={}", rrule, pause.since.format("%Y%m%dT%H%M%SZ"))),
+            None => rrule,
+        })
+    }
+
+    fn rrule(&self) -> Option<String> {
+        self.try_rrule()?.ok()
+    }
+
+    // "every weekday 09:00", "cron '0 9 * * MON-FRI'", "every 90 minutes"
+    fn recurrence_text(&self) -> Option<String> {
+        if !self.is_recurring {
+            return None;
+        }
+        match (&self.cron, &self.recurrence) {
+            (Some(expression), _) => Some(format!("cron '{}'", expression)),
+            (None, Some(rule)) => Some(rule.describe()),
+            (None, None) => self.frequency_minutes.map(recurrence::describe_minutes),
+        }
+    }
+}
+
+// Services with date-only due dates place the task at 09:00 local time
+fn date_only_start(date: chrono::NaiveDate) -> Option<DateTime<Utc>> {
+    let naive = date.and_hms_opt(9, 0, 0)?;
+    chrono::Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+// Tasks `list` shows at a time unless told otherwise
+const DEFAULT_LIMIT: usize = 100;
+
+// Which part of a long listing to show
+#[derive(clap::Args, Debug, Clone, Copy)]
+struct Paging {
+    /// Tasks to show at most, 0 for no limit [default: 100, none with --all]
+    #[arg(long)]
+    limit: Option<usize>,
+    /// Page of --limit tasks to show, counting from 1
+    #[arg(long, default_value_t = 1)]
+    page: usize,
+}
+
+impl Paging {
+    fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_LIMIT)
+    }
+
+    // Tasks on the pages before this one
+    fn skipped(&self) -> usize {
+        self.page.saturating_sub(1).saturating_mul(self.limit())
+    }
+}
+
+// One page of a listing: the tasks on it, each with its progress if open, and
+// how many tasks matched in all
+struct Page {
+    tasks: Vec<(Task, Option<u8>)>,
+    total: usize,
+}
+
+// Print a page of tasks the way `list` does, as it goes, followed by how many
+// more there are
+fn print_tasks(config: &Config, page: &Page, paging: Paging, relative: bool) -> std::io::Result<()> {
+    let mut out = std::io::stdout().lock();
+    let now = Utc::now();
+    // Start and end, from now with `relative`
+    let show = |task: &Task, time: Option<DateTime<Utc>>| match time {
+        Some(time) if relative => relative_time(time, now),
+        time => task.format_time(time),
+    };
+    for (task, progress) in &page.tasks {
+        let priority = escalation::priority(&config.escalation, task, now);
+        let recurring = match (task.recurrence_text(), &task.paused) {
+            (Some(text), Some(pause)) => t!("list-paused", recurrence = text, since = format_time(Some(pause.since))),
+            (Some(text), None) => text,
+            (None, _) => t!("list-not-recurring"),
+        };
+        let priority = match priority == task.priority {
+            true => format!("{:?}", priority),
+            false => t!("list-priority-raised", priority = format!("{:?}", priority), from = format!("{:?}", task.priority)),
+        };
+        let synced = match () {
+            _ if task.local_only => "local",
+            _ if task.inbox => "inbox",
+            _ if task.event_id.is_some() => "yes",
+            _ => "no",
+        };
+        writeln!(
+            out,
+            "{}",
+            t!(
+                "list-task",
+                id = task.id,
+                title = task.event_link.as_deref().map_or(task.title.clone(), |url| hyperlink::link(url, &task.title)),
+                details = hyperlink::urls(&task.details),
+                start = show(task, task.start_time),
+                end = show(task, task.end_time),
+                recurring = recurring,
+                priority = priority,
+                project = task.project.as_deref().unwrap_or("-"),
+                tags = task.tags.join(", "),
+                synced = synced
+            )
+        )?;
+        if let Some(assignee) = &task.assignee {
+            writeln!(out, "    {}", t!("list-assigned", name = assignee.name))?;
+        }
+        if let Some(waiting) = &task.waiting {
+            writeln!(out, "    {}", t!("list-waiting", on = waiting.on, since = format_time(Some(waiting.since))))?;
+        }
+        if !task.depends_on.is_empty() {
+            let ids: Vec<String> = task.depends_on.iter().map(|id| format!("#{}", id)).collect();
+            writeln!(out, "    {}", t!("list-depends-on", ids = ids.join(", ")))?;
+        }
+        if let Some(parent) = task.parent {
+            writeln!(out, "    {}", t!("list-subtask-of", parent = parent))?;
+        }
+        if let Some(percent) = *progress {
+            writeln!(out, "    {}", t!("list-progress", bar = deps::progress_bar(percent)))?;
+        }
+        for (original_start, occurrence) in &task.overrides {
+            let original = format_time(Some(*original_start));
+            let line = match (occurrence.cancelled, &occurrence.title) {
+                (true, _) => t!("list-occurrence-cancelled", original = original),
+                (false, None) => t!("list-occurrence-moved", original = original, start = format_time(occurrence.start_time), end = format_time(occurrence.end_time)),
+                (false, Some(title)) => t!(
+                    "list-occurrence-moved-renamed",
+                    original = original,
+                    start = format_time(occurrence.start_time),
+                    end = format_time(occurrence.end_time),
+                    title = title
+                ),
+            };
+            writeln!(out, "    {}", line)?;
+        }
+    }
+    let shown = paging.skipped() + page.tasks.len();
+    if page.tasks.is_empty() && paging.skipped() > 0 {
+        writeln!(out, "{}", t!("list-past-end", page = paging.page, total = page.total))?;
+    } else if shown < page.total {
+        let more = page.total - shown;
+        writeln!(out, "{}", t!("list-more", more = more, next = paging.page.max(1) + 1, count = paging.limit().min(more)))?;
+    }
+    Ok(())
+}
+
+// The filter expression, if any, narrowed down by the current context's filter
+fn with_context(config: &Config, filter: Option<&str>) -> Result<Option<filter::Filter>, String> {
+    let filter = filter.map(filter::Filter::parse).transpose()?;
+    Ok(match (filter, context::filter(config)?) {
+        (Some(filter), Some(context)) => Some(context.and(filter)),
+        (filter, context) => filter.or(context),
+    })
+}
+
+// List the tasks matching a filter expression and the current context, or all of them
+async fn list_filtered(config: &Config, state: &AppState, filter: Option<&str>, assignee: Option<&str>, sort: SortKey, paging: Paging, relative: bool) {
+    let filter = match with_context(config, filter) {
+        Ok(filter) => match (filter, assignee.map(filter::Filter::assignee)) {
+            (Some(filter), Some(assignee)) => Some(filter.and(assignee)),
+            (filter, assignee) => filter.or(assignee),
+        },
+        Err(e) => {
+            fail!(exit::USAGE, "{}", t!("error-invalid-filter", error = e));
+            return;
+        }
+    };
+    let page = state.query(config, filter.as_ref(), sort, paging).await;
+    // Piped into `head`, the rest isn't wanted
+    if let Err(e) = print_tasks(config, &page, paging, relative)
+        && e.kind() != std::io::ErrorKind::BrokenPipe
+    {
+        fail!(exit::ERROR, "{}", t!("error-writing-list", error = e));
+    }
+}
+
+// Give new tasks in a project the defaults configured for it
+fn apply_project_defaults(config: &Config, task: &mut Task) {
+    let Some(defaults) = task.project.as_ref().and_then(|project| config.projects.get(project)) else { return };
+    for tag in &defaults.tags {
+        if !task.tags.contains(tag) {
+            task.tags.push(tag.clone());
+        }
+    }
+    if task.reminders.is_empty() {
+        task.reminders = defaults.reminders.clone();
+    }
+    if task.color.is_none() {
+        task.color = defaults.color.clone();
+    }
+}
+
+// "client=acme" as a key and value; keys are letters, digits, '-' and '_'
+// so they survive export and calendar properties
+fn parse_meta(text: &str) -> Result<(String, String), String> {
+    let (key, value) = text.split_once('=').ok_or_else(|| format!("'{}' is not KEY=VALUE", text))?;
+    let key = key.trim();
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("'{}' is not a valid key; use letters, digits, '-' and '_'", key));
+    }
+    Ok((key.to_string(), value.trim().to_string()))
+}
+
+// A task as given to `add`, on the command line or in a file, before it's checked
+struct NewTask {
+    title: String,
+    details: String,
+    start_time: Option<String>,
+    end_time: Option<String>,
+    recurring: bool,
+    frequency_minutes: Option<i64>,
+    cron: Option<String>,
+    every: Option<String>,
+    project: Option<String>,
+    tags: Vec<String>,
+    priority: Priority,
+    local_only: bool,
+    waiting_for: Option<String>,
+    depends_on: Vec<u32>,
+    parent: Option<u32>,
+    reminders: Vec<i64>,
+    color: Option<String>,
+    estimate: Option<i64>,
+    meta: Vec<(String, String)>,
+    assignee: Option<String>,
+    force: bool,
+    timezone: Option<String>,
+}
+
+impl NewTask {
+    // An entry of an `add --file` file
+    fn from_entry(entry: bulk::Entry) -> Result<NewTask, String> {
+        if entry.title.trim().is_empty() {
+            return Err(t!("add-file-no-title"));
+        }
+        let estimate = entry.estimate.as_deref().map(capacity::parse_estimate).transpose()?;
+        let meta = entry.meta.iter().map(|(key, value)| parse_meta(&format!("{}={}", key, value))).collect::<Result<_, _>>()?;
+        if let Some(name) = &entry.timezone {
+            zone::parse(name)?;
+        }
+        Ok(NewTask {
+            title: entry.title,
+            details: entry.details,
+            start_time: entry.start_time,
+            end_time: entry.end_time,
+            recurring: entry.recurring,
+            frequency_minutes: entry.frequency_minutes,
+            cron: entry.cron,
+            every: entry.every,
+            project: entry.project,
+            tags: entry.tags,
+            priority: entry.priority,
+            local_only: entry.local_only,
+            waiting_for: entry.waiting_for,
+            depends_on: entry.depends_on,
+            parent: entry.parent,
+            reminders: entry.reminders,
+            color: entry.color,
+            estimate,
+            meta,
+            assignee: entry.assignee,
+            force: entry.force,
+            timezone: entry.timezone,
+        })
+    }
+}
+
+// Check a new task's times and recurrence and build it, with the defaults of
+// the context and project in use; Err has the exit code and what's wrong
+fn new_task(config: &Config, new: NewTask) -> Result<Task, (i32, String)> {
+    let usage = |message: String| (exit::USAGE, message);
+    let schedule = new.cron.as_deref().map(cron::Cron::parse).transpose().map_err(|e| usage(t!("task-cron-invalid", error = e)))?;
+    let recurrence = new.every.as_deref().map(recurrence::Rule::parse).transpose().map_err(|e| usage(t!("task-recurrence-invalid", error = e)))?;
+    let zone = new.timezone.as_deref().map(zone::parse).transpose().map_err(usage)?.unwrap_or(zone::Zone::Local);
+    // Without a start time the task starts at the first match and lasts 30 minutes
+    let first = match (&schedule, &recurrence) {
+        (Some(schedule), _) => Some(schedule.next_after(Utc::now(), &zone).ok_or_else(|| t!("task-cron-never"))),
+        (None, Some(rule)) => Some(rule.first_after(Utc::now(), &zone).ok_or_else(|| t!("task-recurrence-no-time"))),
+        (None, None) => None,
+    };
+    let (start_time, end_time) = match (first, new.start_time) {
+        (Some(first), None) => {
+            let next = first.map_err(usage)?;
+            (Some(next.to_rfc3339()), Some((next + chrono::Duration::minutes(30)).to_rfc3339()))
+        }
+        (_, start_time) => (start_time, new.end_time),
+    };
+    let start_time = start_time.as_deref().map(|text| when::parse_in(text, &zone)).transpose().map_err(usage)?;
+    let end_time = end_time.as_deref().map(|text| when::parse_in(text, &zone)).transpose().map_err(usage)?;
+
+    // Validation for start and end times
+    match (start_time, end_time) {
+        (Some(start_time), Some(end_time)) => {
+            if start_time <= Utc::now() && !new.force {
+                return Err(usage(t!("task-start-past")));
+            }
+            if end_time <= start_time {
+                return Err(usage(t!("task-end-before-start")));
+            }
+        }
+        (Some(_), None) => return Err(usage(t!("task-end-missing"))),
+        (None, Some(_)) => return Err(usage(t!("task-start-missing"))),
+        (None, None) => {}
+    }
+
+    let mut task = Task {
+        id: 0,
+        title: new.title,
+        details: new.details,
+        start_time,
+        end_time,
+        is_recurring: new.recurring || new.cron.is_some() || recurrence.is_some(),
+        frequency_minutes: new.frequency_minutes,
+        cron: new.cron,
+        recurrence,
+        status: TaskStatus::Todo,
+        project: new.project,
+        tags: new.tags,
+        priority: new.priority,
+        local_only: new.local_only,
+        waiting: new.waiting_for.map(|on| Waiting { on, since: Utc::now().trunc_subsecs(0) }),
+        depends_on: new.depends_on,
+        parent: new.parent,
+        reminders: new.reminders,
+        color: new.color,
+        estimate_minutes: new.estimate,
+        meta: new.meta.into_iter().filter(|(_, value)| !value.is_empty()).collect(),
+        assignee: new.assignee.map(|name| Assignee::new(config, name)),
+        timezone: new.timezone.map(|name| name.trim().to_string()),
+        ..Default::default()
+    };
+    context::apply_defaults(config, &mut task);
+    apply_project_defaults(config, &mut task);
+    Ok(task)
+}
+
+// What a reminder this many minutes before the start says
+fn starts_in(minutes: i64) -> String {
+    match minutes {
+        0 => t!("remind-starts-now"),
+        minutes => t!("remind-starts-in", length = describe_length(chrono::Duration::minutes(minutes))),
+    }
 }
 
+fn describe_length(length: chrono::Duration) -> String {
+    match length.num_minutes() {
+        minutes if minutes % 60 == 0 && minutes != 0 => t!("length-hours", hours = minutes / 60),
+        minutes => t!("length-minutes", minutes = minutes),
+    }
+}
+
+// strftime format times are displayed in, in local time, from the config;
+// the LC_TIME language's format or UTC in RFC 3339 style when unset
+static TIME_FORMAT: OnceLock<String> = OnceLock::new();
+
+// Display an optional task time
+fn format_time(time: Option<DateTime<Utc>>) -> String {
+    let Some(time) = time else {
+        return t!("time-unscheduled");
+    };
+    match TIME_FORMAT.get().map(String::as_str).or_else(i18n::time_format) {
+        Some(format) => i18n::format(&time.with_timezone(&Local), format),
+        None => time.to_string(),
+    }
+}
+
+// How far `time` is from `now`, e.g. "in 2 h 15 m", "3 days ago" or "now"
+fn relative_time(time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = (time - now).num_minutes();
+    let length = match minutes.abs() {
+        0 => return t!("time-now"),
+        minutes if minutes < 60 => t!("time-minutes", minutes = minutes),
+        minutes if minutes % 60 == 0 && minutes < 24 * 60 => t!("time-hours", hours = minutes / 60),
+        minutes if minutes < 24 * 60 => t!("time-hours-minutes", hours = minutes / 60, minutes = minutes % 60),
+        // Whole days, rounded
+        minutes => t!("time-days", days = (minutes + 12 * 60) / (24 * 60)),
+    };
+    if minutes > 0 { t!("time-in", length = length) } else { t!("time-ago", length = length) }
+}
+
+// The tasks and the next free ID sit behind a single lock, so there's no lock
+// order to get wrong. Store methods take it once, do all their work on the
+// `TaskStore` and let go before anything slow: never hold it across network
+// calls or other awaits, and drop it before calling another store method.
 #[derive(Default)]
 struct AppState {
-    tasks: Mutex<HashMap<u32, Task>>,
-    next_id: Mutex<u32>,
+    tasks: Mutex<TaskStore>,
+}
+
+// On-disk layout of the task store
+#[derive(Serialize, Deserialize, Default)]
+struct StoreFile {
+    /// Layout version, see `schema`; 0 for files from before it was recorded
+    #[serde(default)]
+    version: u32,
+    next_id: u32,
+    tasks: Vec<Task>,
 }
 
 #[derive(Parser)]
 #[command(name = "Todo Task")]
 #[command(about = "A CLI tool to manage tasks and reminder, integrated with Google Calendar")]
-struct CLI {
+struct Cli {
+    /// Keep all files in DIR instead of the platform's config and data
+    /// folders; overrides TODO_HOME
+    #[arg(long, global = true, value_name = "DIR")]
+    home: Option<PathBuf>,
+    /// Use a profile's own tasks, settings, sign-ins and server instead of the
+    /// main ones; overrides TODO_PROFILE
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+    /// Print errors as JSON for wrappers and GUIs to present
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+    /// Don't ask before deleting tasks, events or profiles
+    #[arg(short, long, global = true)]
+    yes: bool,
+    /// Leave out messages about what was done, printing only results such as
+    /// the IDs of added tasks, warnings and errors
+    #[arg(short, long, global = true)]
+    quiet: bool,
+    /// Only merge this calendar or feed into the agenda and free time, by
+    /// name; repeat for several
+    #[arg(long, global = true, value_name = "NAME")]
+    calendar: Vec<String>,
+    /// Leave this calendar or feed out of the agenda and free time; repeat
+    /// for several
+    #[arg(long, global = true, value_name = "NAME", conflicts_with = "calendar")]
+    without: Vec<String>,
     #[command(subcommand)]
     command: Commands,
 }
 
+// Parsed once per run, so `add` having many more options than the rest costs nothing
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// Add a new Task
     Add {
         /// Title of the task
-        title: String,
+        #[arg(required_unless_present_any = ["file", "from_clipboard"])]
+        title: Option<String>,
         /// Details of the task
-        details: String,
-        /// start time (ISO 8601 format, e.g., "2024-12-31T15:00:06")
-        start_time: String,
-        /// End time (ISO format)
-        end_time: String,
+        #[arg(required_unless_present_any = ["file", "from_clipboard"])]
+        details: Option<String>,
+        /// Start time, e.g. "2024-12-31T15:00:00Z", "2024-12-31 15:00", "3pm", "tomorrow 9am"
+        /// or "12/31 3pm" (local time without an offset); omit for an undated task
+        start_time: Option<String>,
+        /// End time, in the same formats
+        end_time: Option<String>,
         /// Whether the task is recurring
         #[arg(long)]
         recurring: bool,
         /// Frequency of recurrence in minutes (only for recurring tasks)
         frequency_minutes: Option<i64>,
+        /// Repeat on a cron expression instead, e.g. "0 9 * * MON-FRI" (local time);
+        /// without a start time the task starts at the next match and lasts 30 minutes
+        #[arg(long, value_name = "EXPRESSION", conflicts_with_all = ["recurring", "frequency_minutes"])]
+        cron: Option<String>,
+        /// Repeat as described, e.g. "weekday at 9am" or "2 weeks on tuesday";
+        /// without a start time the phrase needs a time of day
+        #[arg(long, value_name = "PHRASE", conflicts_with_all = ["recurring", "frequency_minutes", "cron"])]
+        every: Option<String>,
+        /// Project the task belongs to
+        #[arg(long)]
+        project: Option<String>,
+        /// Tag to attach (repeatable)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        #[arg(long, value_enum, default_value_t = Priority::Normal)]
+        priority: Priority,
+        /// Keep the task private: never push it to the calendar or Google Tasks
+        #[arg(long)]
+        local_only: bool,
+        /// Mark the task as waiting on someone, e.g. "Alice"; `serve` reminds you to follow up
+        #[arg(long, value_name = "WHO")]
+        waiting_for: Option<String>,
+        /// ID of a task that has to be done first (repeatable)
+        #[arg(long, value_name = "ID")]
+        depends_on: Vec<u32>,
+        /// ID of the task this one is a subtask of
+        #[arg(long, value_name = "ID")]
+        parent: Option<u32>,
+        /// Remind this many minutes before the start (repeatable); 5 by default
+        #[arg(long = "remind", value_name = "MINUTES")]
+        reminders: Vec<i64>,
+        /// Event color: a Google color ID, a CSS color name for CalDAV or an Outlook category
+        #[arg(long)]
+        color: Option<String>,
+        /// Expected work, e.g. "2h", "90m" or "1h30m"; checked against free time by `agenda` and `capacity`
+        #[arg(long, value_name = "LENGTH", value_parser = capacity::parse_estimate)]
+        estimate: Option<i64>,
+        /// Custom attribute, e.g. client=acme (repeatable)
+        #[arg(long = "meta", value_name = "KEY=VALUE", value_parser = parse_meta)]
+        meta: Vec<(String, String)>,
+        /// Person the task is assigned to, a name from `people` in config.json or an address;
+        /// invited to the task's event
+        #[arg(long, value_name = "WHO")]
+        assignee: Option<String>,
+        /// Allow a start time in the past, e.g. to log something already done;
+        /// occurrences before the task was added get no reminders
+        #[arg(long, visible_alias = "past-ok")]
+        force: bool,
+        /// Time zone the task's times are in, e.g. Asia/Tokyo, so it stays at that
+        /// time wherever you are; the times given are read in it
+        #[arg(long, value_name = "ZONE")]
+        tz: Option<String>,
+        /// Print only the new task's ID, or the whole task as JSON with
+        /// `--format json`, for scripts; implies --quiet
+        #[arg(long, conflicts_with = "file")]
+        porcelain: bool,
+        /// Add every task in a YAML, TOML or JSON file ("-" for stdin) instead,
+        /// all or none of them
+        #[arg(long, value_name = "PATH", conflicts_with_all = [
+            "title", "recurring", "cron", "every", "project", "tags", "local_only", "waiting_for", "depends_on",
+            "parent", "reminders", "color", "estimate", "meta", "assignee", "force", "tz",
+        ])]
+        file: Option<String>,
+        /// Add the task written on the clipboard, read as `menu --add` reads
+        /// it: "<title> [@ <start>] [for <length>]"; further lines become the details
+        #[arg(long, conflicts_with_all = [
+            "title", "recurring", "cron", "every", "waiting_for", "depends_on", "parent", "reminders", "color",
+            "meta", "assignee", "force", "tz", "file",
+        ])]
+        from_clipboard: bool,
+    },
+    /// Change a task
+    Edit {
+        /// ID of the task to change; picked with a fuzzy finder when omitted
+        id: Option<u32>,
+        /// Keep the task off the calendar (true) or let sync push it again (false);
+        /// turning it on deletes the task's event
+        #[arg(long, value_name = "BOOL")]
+        local_only: Option<bool>,
+        /// Mark the task as waiting on someone, e.g. "Alice"
+        #[arg(long, value_name = "WHO")]
+        waiting_for: Option<String>,
+        /// The task is no longer waiting on anyone
+        #[arg(long, conflicts_with = "waiting_for")]
+        not_waiting: bool,
+        /// ID of a task that has to be done first (repeatable)
+        #[arg(long, value_name = "ID")]
+        depends_on: Vec<u32>,
+        /// ID of the task this one is a subtask of
+        #[arg(long, value_name = "ID")]
+        parent: Option<u32>,
+        /// Expected work, e.g. "2h", "90m" or "1h30m"
+        #[arg(long, value_name = "LENGTH", value_parser = capacity::parse_estimate)]
+        estimate: Option<i64>,
+        /// Drop the task's estimate
+        #[arg(long, conflicts_with = "estimate")]
+        no_estimate: bool,
+        /// Set a custom attribute, e.g. client=acme, or remove it with client= (repeatable)
+        #[arg(long = "meta", value_name = "KEY=VALUE", value_parser = parse_meta)]
+        meta: Vec<(String, String)>,
+        /// Assign the task to someone, a name from `people` in config.json or an address
+        #[arg(long, value_name = "WHO")]
+        assignee: Option<String>,
+        /// The task is no longer assigned to anyone
+        #[arg(long, conflicts_with = "assignee")]
+        unassign: bool,
+        /// Pin the task to a time zone, e.g. Asia/Tokyo, keeping its clock time:
+        /// 9:00 becomes 9:00 there
+        #[arg(long, value_name = "ZONE")]
+        tz: Option<String>,
+        /// Put the task back in local time, keeping its clock time
+        #[arg(long, conflicts_with = "tz")]
+        no_tz: bool,
+    },
+    /// Show everything about a task, including its links
+    Show {
+        /// ID of the task; picked with a fuzzy finder when omitted
+        id: Option<u32>,
+    },
+    /// Record how far along a task is
+    Progress {
+        /// ID of the task
+        id: u32,
+        /// Percent done, 0 to 100
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=100))]
+        percent: u8,
+    },
+    /// Link two tasks, e.g. as related or as duplicates
+    Link {
+        /// ID of the task to link from
+        id: u32,
+        /// ID of the task to link to
+        other: u32,
+        #[arg(long, value_enum, default_value_t = links::Relation::RelatesTo)]
+        relation: links::Relation,
+        /// Remove the link instead
+        #[arg(long)]
+        remove: bool,
     },
     /// List all tasks
-    List,
-    // Remove a task by its ID
+    List {
+        /// Only tasks matching a filter, e.g. "priority>=high and (tag:work or project:ops) and due<friday";
+        /// the default view when omitted
+        #[arg(conflicts_with = "waiting")]
+        filter: Option<String>,
+        /// Every task, even when a default view or a context is set, and with no limit
+        #[arg(long, conflicts_with_all = ["filter", "waiting"])]
+        all: bool,
+        /// Only tasks waiting on someone, longest waiting first
+        #[arg(long)]
+        waiting: bool,
+        /// Only tasks assigned to this person, by name or address
+        #[arg(long, value_name = "WHO", conflicts_with_all = ["all", "waiting"])]
+        assignee: Option<String>,
+        #[arg(long, value_enum, default_value_t = SortKey::Id, conflicts_with = "waiting")]
+        sort: SortKey,
+        /// Show start and end times from now, e.g. "in 2 h 15 m" or "3 days ago"
+        #[arg(long, conflicts_with = "waiting")]
+        relative: bool,
+        #[command(flatten)]
+        paging: Paging,
+    },
+    /// Switch to a context, e.g. "work", or manage contexts; without a name show the current one
+    #[command(args_conflicts_with_subcommands = true)]
+    Context {
+        /// Context to switch to, or "none" to leave the current one
+        name: Option<String>,
+        #[command(subcommand)]
+        command: Option<ContextCommands>,
+    },
+    /// List the tasks of a saved view, or manage views
+    #[command(args_conflicts_with_subcommands = true)]
+    View {
+        /// Name of the view to list
+        name: Option<String>,
+        #[arg(long, value_enum, default_value_t = SortKey::Id)]
+        sort: SortKey,
+        #[command(flatten)]
+        paging: Paging,
+        #[command(subcommand)]
+        command: Option<ViewCommands>,
+    },
+    /// Remove a task
     Remove {
-        /// ID of the task to be removed
+        /// ID of the task to be removed; picked with a fuzzy finder when omitted
+        id: Option<u32>,
+    },
+    /// Mark a task as done; for a recurring task, its current occurrence
+    Done {
+        /// ID of the task; picked with a fuzzy finder when omitted
+        id: Option<u32>,
+    },
+    /// Pause a recurring task, e.g. over a vacation, so no occurrences come due
+    Pause {
+        /// ID of the recurring task
+        id: u32,
+        /// Also end the calendar event's recurrence now
+        #[arg(long)]
+        truncate: bool,
+    },
+    /// Resume a paused recurring task from its next occurrence
+    Resume {
+        /// ID of the paused task
+        id: u32,
+    },
+    /// Skip the current occurrence of a recurring task, keeping its streak
+    Skip {
+        /// ID of the recurring task
+        id: u32,
+    },
+    /// List open tasks nobody has touched in a while
+    Stale {
+        /// Days without a change after which a task is stale
+        #[arg(long, default_value_t = 14)]
+        days: i64,
+        /// Ask whether to reschedule, delegate or drop each one
+        #[arg(long)]
+        interactive: bool,
+    },
+    /// Show which occurrences of a recurring task were completed, skipped or missed
+    History {
+        /// ID of the recurring task
+        id: u32,
+        /// Number of days to look back
+        #[arg(long, default_value_t = 30)]
+        days: i64,
+    },
+    /// Reply to the calendar invitation a task was created from
+    Rsvp {
+        /// ID of the task linked to the invitation
+        id: u32,
+        reply: Rsvp,
+    },
+    /// Sync tasks with the configured calendar (Google Calendar, CalDAV or Outlook)
+    /// and undated tasks with Google Tasks when enabled
+    Sync {
+        /// Ask how to settle each conflict between a task and its event
+        #[arg(long)]
+        interactive: bool,
+        /// Print what would be synced, including the project and tags of new tasks, without changing anything
+        #[arg(long, conflicts_with = "interactive")]
+        dry_run: bool,
+        /// Show calendar API requests and throttling per day instead of syncing
+        #[arg(long, conflicts_with_all = ["interactive", "dry_run"])]
+        stats: bool,
+        /// Merge the task store with other devices through the Git repository in config.json instead
+        #[arg(long, conflicts_with_all = ["interactive", "dry_run", "stats"])]
+        git: bool,
+        #[command(subcommand)]
+        command: Option<SyncCommands>,
+    },
+    /// Show upcoming tasks and subscribed calendar events day by day
+    Agenda {
+        /// The seven days from the first day of this week
+        #[arg(long, conflicts_with = "days")]
+        week: bool,
+        /// Number of days to show, starting today
+        #[arg(long, default_value_t = 7)]
+        days: i64,
+        /// Show when each item starts from now, e.g. "in 2 h 15 m", instead of its hours
+        #[arg(long)]
+        relative: bool,
+    },
+    /// Revert the last commands that changed tasks, one step per command
+    Undo {
+        /// Number of steps to undo
+        #[arg(default_value_t = 1)]
+        steps: usize,
+    },
+    /// Reapply steps taken back with `undo`
+    Redo {
+        /// Number of steps to redo
+        #[arg(default_value_t = 1)]
+        steps: usize,
+    },
+    /// Show who changed what and when, newest first, including changes made by sync
+    Log {
+        /// Only the changes to this task
+        id: Option<u32>,
+        /// Number of commands to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Each assignee's overdue, upcoming and undated tasks, printed or emailed to them
+    Digest {
+        /// Days ahead counted as upcoming
+        #[arg(long, default_value_t = 7)]
+        days: i64,
+        /// Only the digest of this person, by name or address
+        #[arg(long, value_name = "WHO")]
+        assignee: Option<String>,
+        /// Email each digest to its assignee through sendmail instead of printing it
+        #[arg(long)]
+        send: bool,
+    },
+    /// Compare estimated work with free working hours day by day
+    Capacity {
+        /// The seven days from the first day of this week
+        #[arg(long, conflicts_with = "days")]
+        week: bool,
+        /// Number of days to show, starting today
+        #[arg(long, default_value_t = 7)]
+        days: i64,
+    },
+    /// Put open, estimated tasks without a time into a day's free working
+    /// hours as back-to-back blocks, most urgent first
+    Block {
+        /// Day to fill: today, tomorrow, a weekday or YYYY-MM-DD
+        #[arg(long, default_value = "today")]
+        day: String,
+        /// Show the blocks without changing any task
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Move the rest of today's tasks later after the schedule slips, around
+    /// fixed events and within working hours
+    Replan {
+        /// Time from which the day is re-planned: now, or a time such as 14:30
+        #[arg(long, default_value = "now")]
+        from: String,
+        /// Show the new times without changing any task
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Show the hours scheduled each day as a calendar heatmap, to spot
+    /// overloaded days
+    Heatmap {
+        /// This calendar month
+        #[arg(long, conflicts_with = "weeks")]
+        month: bool,
+        /// Number of weeks to show, starting this week
+        #[arg(long, default_value_t = 4)]
+        weeks: i64,
+    },
+    /// Capture a thought into the inbox, to sort out later with `triage`;
+    /// without one, list the inbox
+    In {
+        /// What to capture; the words are joined with spaces
+        text: Vec<String>,
+    },
+    /// Go through the inbox one item at a time: schedule it, keep it for
+    /// someday or delete it
+    Triage,
+    /// Pick an actionable task at random, for a quick win
+    Pick {
+        /// Only tasks matching a filter, e.g. "project:home or priority>=high"
+        filter: Option<String>,
+        /// Only tasks expected to take at most this long, e.g. 15m; their
+        /// estimate, or the time they're scheduled for
+        #[arg(long, value_name = "LENGTH", value_parser = capacity::parse_estimate)]
+        max_duration: Option<i64>,
+        /// Only tasks with this tag; repeat for several
+        #[arg(long)]
+        tag: Vec<String>,
+    },
+    /// Draw tasks as bars across each day, so overlaps and long tasks stand out
+    Timeline {
+        /// The seven days from the first day of this week
+        #[arg(long, conflicts_with = "days")]
+        week: bool,
+        /// Number of days to show, starting today
+        #[arg(long, default_value_t = 7)]
+        days: i64,
+    },
+    /// Run as a server: subscription feed, metrics, reminders and background sync
+    #[command(group = clap::ArgGroup::new("endpoints").required(true).multiple(true))]
+    Serve {
+        /// Serve open tasks as a read-only iCalendar feed at a secret URL
+        #[arg(long, group = "endpoints")]
+        ics: bool,
+        /// Expose Prometheus metrics at /metrics
+        #[arg(long, group = "endpoints")]
+        metrics: bool,
+        /// Sync with every configured service this often
+        #[arg(long, value_name = "MINUTES")]
+        sync_minutes: Option<u64>,
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: std::net::SocketAddr,
+    },
+    /// Install the server as a background service started at login, with reminders
+    /// and metrics, or remove it again
+    #[command(group = clap::ArgGroup::new("action").required(true))]
+    Daemon {
+        /// Install and start a user-level systemd service running `serve`
+        #[arg(long, group = "action")]
+        install_systemd: bool,
+        /// Register `serve` with the Task Scheduler to start at logon, on Windows
+        #[arg(long, group = "action")]
+        install_windows_service: bool,
+        /// Write launchd agents running `serve` at login, on macOS
+        #[arg(long, group = "action")]
+        install_launchd: bool,
+        /// Also run `sync` this often
+        #[arg(long, value_name = "MINUTES", conflicts_with = "uninstall")]
+        sync_every: Option<u64>,
+        /// Address the service's metrics endpoint listens on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: std::net::SocketAddr,
+        /// Stop and remove the installed service and periodic sync
+        #[arg(long, group = "action")]
+        uninstall: bool,
+    },
+    /// Send a test reminder, or list the reminders coming up
+    Remind {
+        #[command(subcommand)]
+        command: RemindCommands,
+    },
+    /// Check the configuration, task store, credentials, network and server
+    Doctor,
+    /// Repair references between tasks and compact the change journal
+    Maintenance {
+        /// Report what would be repaired and compacted without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Show the Google calendars the account can see, or pick the one tasks sync with
+    Calendars {
+        #[command(subcommand)]
+        command: Option<CalendarCommands>,
+    },
+    /// Manage profiles, separate sets of tasks and settings used with `--profile`
+    Profiles {
+        #[command(subcommand)]
+        command: ProfileCommands,
+    },
+    /// Import tasks from another tool
+    #[command(group = clap::ArgGroup::new("source").required(true))]
+    Import {
+        /// Import active tasks from Todoist using the API token in config.json
+        #[arg(long, group = "source")]
+        todoist: bool,
+        /// Import the JSON written by `task export` ("-" reads stdin)
+        #[arg(long, group = "source", value_name = "FILE")]
+        taskwarrior: Option<String>,
+        /// Import TODO/DONE headings from an org-mode file ("-" reads stdin)
+        #[arg(long, group = "source", value_name = "FILE")]
+        org: Option<String>,
+        /// Import a Trello board by ID or URL, or from its JSON export file
+        #[arg(long, group = "source", value_name = "BOARD")]
+        trello: Option<String>,
+    },
+    /// Write the graph of dependencies and subtasks, one cluster per project
+    #[command(group = clap::ArgGroup::new("output").required(true))]
+    Graph {
+        /// Graphviz DOT, e.g. for `dot -Tsvg` (stdout by default)
+        #[arg(long, group = "output", value_name = "FILE", num_args = 0..=1, default_missing_value = "-")]
+        dot: Option<String>,
+        /// Mermaid flowchart, e.g. for Markdown docs
+        #[arg(long, group = "output", value_name = "FILE", num_args = 0..=1, default_missing_value = "-")]
+        mermaid: Option<String>,
+    },
+    /// Chart open against completed tasks of a project day by day
+    Burndown {
+        /// Project to chart
+        #[arg(long)]
+        project: String,
+        /// Number of days to chart, up to today
+        #[arg(long, default_value_t = 30)]
+        days: i64,
+        /// Write the chart as SVG instead (stdout by default)
+        #[arg(long, value_name = "FILE", num_args = 0..=1, default_missing_value = "-")]
+        svg: Option<String>,
+    },
+    /// Export tasks for another tool
+    #[command(group = clap::ArgGroup::new("output").required(true))]
+    Export {
+        /// Write Taskwarrior-compatible JSON for `task import` (stdout by default)
+        #[arg(long, group = "output", value_name = "FILE", num_args = 0..=1, default_missing_value = "-")]
+        taskwarrior: Option<String>,
+        /// Write org-mode headings with TODO keywords and SCHEDULED timestamps
+        #[arg(long, group = "output", value_name = "FILE", num_args = 0..=1, default_missing_value = "-")]
+        org: Option<String>,
+    },
+    /// GitHub issues integration
+    Github {
+        #[command(subcommand)]
+        command: GithubCommands,
+    },
+    /// Jira integration
+    Jira {
+        #[command(subcommand)]
+        command: JiraCommands,
+    },
+    /// Launcher (rofi/dmenu) integration
+    ///
+    /// Without flags, prints one line per open task. Pipe the line picked in the
+    /// launcher back with --complete or --snooze, or a new line with --add.
+    Menu {
+        /// Mark the selected task as done
+        #[arg(long, conflicts_with_all = ["snooze", "add"])]
+        complete: bool,
+        /// Push the selected task back by --minutes
+        #[arg(long, conflicts_with = "add")]
+        snooze: bool,
+        /// Quick-add a task, e.g. "Call the bank @ 2024-12-31T15:00:00Z for 15m"
+        #[arg(long)]
+        add: bool,
+        /// Snooze length in minutes
+        #[arg(long, default_value_t = 15)]
+        minutes: i64,
+        /// Selected line or quick-add text (read from stdin when omitted)
+        input: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum RemindCommands {
+    /// Send a reminder right away through every configured channel, or
+    /// through the channels reminding of task ID
+    Test { id: Option<u32> },
+    /// List the reminders `serve` will fire, with when and where they go
+    List {
+        /// Hours ahead to look
+        #[arg(long, default_value_t = 24)]
+        hours: i64,
+    },
+}
+
+#[derive(Subcommand)]
+enum CalendarCommands {
+    /// Sync tasks with this calendar, by name or ID, instead of the current one
+    Use { name: String },
+    /// Create a calendar of tasks' own and sync them with it
+    Create {
+        #[arg(default_value = google::TODO_CALENDAR)]
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfileCommands {
+    /// Show the profiles and how many tasks each has
+    List,
+    /// Create a profile, starting with the main settings and Google OAuth client
+    Create { name: String },
+    /// Delete a profile with its tasks, settings and sign-ins
+    Delete { name: String },
+}
+
+#[derive(Subcommand)]
+enum ContextCommands {
+    /// Define a context, replacing any of that name
+    Define {
+        name: String,
+        /// Filter expression applied to `list`, `view` and `menu`, as for `list`
+        #[arg(long)]
+        filter: Option<String>,
+        /// Project new tasks get unless given one
+        #[arg(long)]
+        project: Option<String>,
+        /// Tag new tasks get (repeatable)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+    },
+    /// Show the defined contexts
+    List,
+    /// Delete a context
+    Delete { name: String },
+}
+
+#[derive(Subcommand)]
+enum ViewCommands {
+    /// Save a filter expression under a name, replacing any view of that name
+    Save {
+        name: String,
+        /// Filter expression, as for `list`
+        filter: String,
+    },
+    /// Show the saved views
+    List,
+    /// Delete a saved view
+    Delete { name: String },
+    /// Make a view what `list` shows by default; without a name `list` shows everything again
+    Default { name: Option<String> },
+}
+
+#[derive(Subcommand)]
+enum SyncCommands {
+    /// Show the last sync of each service, pending changes and unresolved conflicts
+    Status,
+}
+
+#[derive(Subcommand)]
+enum GithubCommands {
+    /// Link a task to an issue so completing the task closes it
+    Link {
+        /// ID of the task
         id: u32,
+        /// Issue as "owner/repo#123"
+        issue: String,
     },
-    /// Sync tasks with Google Calendar
+    /// Create tasks from open issues assigned to you and close issues of completed tasks
     Sync,
 }
 
+#[derive(Subcommand)]
+enum JiraCommands {
+    /// Link a task to a Jira issue so completing the task updates it
+    Link {
+        /// ID of the task
+        id: u32,
+        /// Issue key, e.g. "OPS-42"
+        key: String,
+    },
+    /// Create tasks from issues matching the configured JQL and update issues of completed tasks
+    Sync,
+}
 
-// Implementation block for AppState struct
 impl AppState {
-    // intialize a add task to the state
     pub async fn add_task(&self, task: Task) -> u32 {
         let mut tasks = self.tasks.lock().await;
-        let mut next_id = self.next_id.lock().await;
-        // Assign task ID and increment next_id
-        let task_id = *next_id;
-        *next_id +=1;
+        let task_id = tasks.take_id();
+        let mut task = task;
+        task.id = task_id;
+        if task.uuid.is_empty() {
+            task.uuid = new_uuid();
+        }
+        task.created_at.get_or_insert_with(Utc::now);
         tasks.insert(task_id, task);
         task_id
     }
 
+    // Add several tasks at once, so nobody sees only some of them; their IDs in order
+    pub async fn add_tasks(&self, new: Vec<Task>) -> Vec<u32> {
+        let mut tasks = self.tasks.lock().await;
+        let now = Utc::now();
+        new.into_iter()
+            .map(|mut task| {
+                task.id = tasks.take_id();
+                if task.uuid.is_empty() {
+                    task.uuid = new_uuid();
+                }
+                task.created_at.get_or_insert(now);
+                let task_id = task.id;
+                tasks.insert(task_id, task);
+                task_id
+            })
+            .collect()
+    }
+
+    // Replace every task, e.g. with an earlier version from undo
+    pub async fn replace_tasks(&self, replacement: Vec<Task>) {
+        self.tasks.lock().await.replace(replacement);
+    }
+
+    // A page of the tasks matching `filter`, in `sort` order. Only the tasks on
+    // the page are cloned, so long lists stay cheap.
+    pub async fn query(&self, config: &Config, filter: Option<&filter::Filter>, sort: SortKey, paging: Paging) -> Page {
+        let mut tasks = self.tasks.lock().await;
+        let now = Utc::now();
+        tasks.refresh();
+        let lookups = filter.map(filter::Filter::lookups).unwrap_or_default();
+        let mut matching: Vec<&Task> = tasks.candidates(&lookups);
+        matching.retain(|task| filter.is_none_or(|filter| filter.matches(task)));
+        match sort {
+            SortKey::Id => matching.sort_unstable_by_key(|task| task.id),
+            // Undated tasks last
+            SortKey::Start => matching.sort_unstable_by_key(|task| (task.start_time.is_none(), task.start_time, task.id)),
+            SortKey::Urgency => matching.sort_by_cached_key(|task| {
+                let priority = escalation::priority(&config.escalation, task, now);
+                (std::cmp::Reverse(priority), task.start_time.is_none(), task.start_time, task.id)
+            }),
+        }
+        let limit = match paging.limit() {
+            0 => usize::MAX,
+            limit => limit,
+        };
+        let page = matching
+            .iter()
+            .skip(paging.skipped())
+            .take(limit)
+            .map(|task| {
+                let progress = if task.status == TaskStatus::Todo { deps::progress(tasks.values(), task.id) } else { None };
+                ((*task).clone(), progress)
+            })
+            .collect();
+        Page { tasks: page, total: matching.len() }
+    }
+
+    // The tasks found by the index lookups that pass `test`; only those are cloned
+    pub async fn find(&self, lookups: &[Lookup], test: impl Fn(&Task) -> bool) -> Vec<Task> {
+        let mut tasks = self.tasks.lock().await;
+        tasks.refresh();
+        tasks.candidates(lookups).into_iter().filter(|task| test(task)).cloned().collect()
+    }
+
     pub async fn list_tasks(&self) -> Vec<Task> {
         let tasks = self.tasks.lock().await;
         tasks.values().cloned().collect()
     }
 
-    // Adding the remove task method
+    pub async fn get_task(&self, task_id: u32) -> Option<Task> {
+        self.tasks.lock().await.get(&task_id).cloned()
+    }
+
+    // Remove the task and every dependency on it; its subtasks become top-level tasks
     pub async fn remove_task(&self, task_id: u32) -> Option<Task> {
         let mut tasks = self.tasks.lock().await;
-        tasks.remove(&task_id)
+        let removed = tasks.remove(&task_id)?;
+        for task in tasks.values_mut() {
+            task.depends_on.retain(|id| *id != task_id);
+            task.links.retain(|link| link.id != task_id);
+            if task.parent == Some(task_id) {
+                task.parent = None;
+            }
+        }
+        Some(removed)
+    }
+
+    pub async fn complete_task(&self, task_id: u32) -> Option<Task> {
+        let mut tasks = self.tasks.lock().await;
+        let mut task = tasks.get_mut(&task_id)?;
+        task.status = TaskStatus::Done;
+        task.completed_at = Some(Utc::now().trunc_subsecs(0));
+        task.touch();
+        Some(task.clone())
+    }
+
+    // Record the outcome of the recurring task's open occurrence, returning the
+    // task and that occurrence's start
+    pub async fn record_occurrence(&self, task_id: u32, outcome: history::Outcome) -> Option<(Task, DateTime<Utc>)> {
+        let mut tasks = self.tasks.lock().await;
+        let mut task = tasks.get_mut(&task_id)?;
+        let start_time = history::open_occurrence(&task, Utc::now())?;
+        task.history.insert(start_time, outcome);
+        task.touch();
+        Some((task.clone(), start_time))
+    }
+
+    pub async fn reschedule_task(&self, task_id: u32, start_time: DateTime<Utc>, end_time: DateTime<Utc>) -> Option<Task> {
+        let mut tasks = self.tasks.lock().await;
+        let mut task = tasks.get_mut(&task_id)?;
+        task.start_time = Some(start_time);
+        task.end_time = Some(end_time);
+        task.touch();
+        Some(task.clone())
+    }
+
+    // Mark the task as waiting on someone, or no longer waiting when None
+    pub async fn set_waiting(&self, task_id: u32, on: Option<String>) -> Option<Task> {
+        let mut tasks = self.tasks.lock().await;
+        let mut task = tasks.get_mut(&task_id)?;
+        task.waiting = on.map(|on| Waiting { on, since: Utc::now().trunc_subsecs(0) });
+        task.touch();
+        Some(task.clone())
+    }
+
+    // Shift both start and end time, keeping the task's duration
+    pub async fn snooze_task(&self, task_id: u32, minutes: i64) -> Option<Task> {
+        let mut tasks = self.tasks.lock().await;
+        let mut task = tasks.get_mut(&task_id)?;
+        task.start_time = task.start_time.map(|time| time + chrono::Duration::minutes(minutes));
+        task.end_time = task.end_time.map(|time| time + chrono::Duration::minutes(minutes));
+        task.snoozes += 1;
+        Some(task.clone())
+    }
+
+    pub async fn pause_task(&self, task_id: u32, truncate: bool) -> Option<Task> {
+        let mut tasks = self.tasks.lock().await;
+        let mut task = tasks.get_mut(&task_id)?;
+        task.paused = Some(Pause { since: Utc::now().trunc_subsecs(0), truncated: truncate });
+        task.touch();
+        Some(task.clone())
+    }
+
+    // Lift the pause and move the task to its first occurrence from now on,
+    // skipping the ones that fell in the pause
+    pub async fn resume_task(&self, task_id: u32) -> Option<Task> {
+        let mut tasks = self.tasks.lock().await;
+        let mut task = tasks.get_mut(&task_id)?;
+        task.paused = None;
+        let now = Utc::now();
+        if let Some((start_time, end_time)) = task.occurrences(now, now + chrono::Duration::days(400)).into_iter().find(|(start_time, _)| *start_time >= now) {
+            task.start_time = Some(start_time);
+            task.end_time = Some(end_time);
+        }
+        task.touch();
+        Some(task.clone())
+    }
+
+    pub async fn link_event(&self, task_id: u32, event_id: String) {
+        let mut tasks = self.tasks.lock().await;
+        if let Some(mut task) = tasks.get_mut(&task_id) {
+            task.event_id = Some(event_id);
+        }
+    }
+
+    // Insert or refresh a task imported from another service, matched on the
+    // service's ID for it. Returns true if it already existed; status is kept.
+    pub async fn upsert_imported<K: PartialEq>(&self, imported: Task, key: impl Fn(&Task) -> Option<K>) -> bool {
+        let mut tasks = self.tasks.lock().await;
+        let imported_key = key(&imported);
+        let existing = tasks
+            .values_mut()
+            .find(|task| imported_key.is_some() && key(task) == imported_key);
+        if let Some(task) = existing {
+            task.title = imported.title;
+            task.details = imported.details;
+            task.start_time = imported.start_time;
+            task.end_time = imported.end_time;
+            task.project = imported.project;
+            task.tags = imported.tags;
+            task.priority = imported.priority;
+            return true;
+        }
+        drop(tasks);
+        self.add_task(imported).await;
+        false
+    }
+
+    // Replace the task with the same UUID (keeping its ID and calendar link) or
+    // add a new one, returning true if it already existed
+    pub async fn upsert_uuid(&self, imported: Task) -> bool {
+        let mut tasks = self.tasks.lock().await;
+        if let Some(task) = tasks.values_mut().find(|task| task.uuid == imported.uuid) {
+            *task = Task {
+                id: task.id,
+                event_id: task.event_id.take(),
+                ..imported
+            };
+            return true;
+        }
+        drop(tasks);
+        self.add_task(imported).await;
+        false
+    }
+
+    // Apply a remote event to the task linked to it, returning false if there is none
+    pub async fn update_from_event(&self, event: &RemoteEvent) -> bool {
+        let mut tasks = self.tasks.lock().await;
+        let Some(task) = tasks
+            .values_mut()
+            .find(|task| task.event_id.as_deref() == Some(event.id.as_str()))
+        else {
+            return false;
+        };
+        Snapshot::of_event(event).apply_to(task);
+        task.touch();
+        true
+    }
+
+    // Overwrite the synced fields, e.g. with the outcome of a conflict resolution
+    pub async fn set_override(&self, task_id: u32, original_start: DateTime<Utc>, occurrence: Occurrence) {
+        let mut tasks = self.tasks.lock().await;
+        if let Some(mut task) = tasks.get_mut(&task_id) {
+            task.overrides.insert(original_start, occurrence);
+        }
+    }
+
+    // Add dependencies and set the parent, refusing anything that makes a cycle
+    pub async fn link_task(&self, task_id: u32, depends_on: &[u32], parent: Option<u32>) -> Result<Task, String> {
+        let mut tasks = self.tasks.lock().await;
+        if !tasks.contains_key(&task_id) {
+            return Err(format!("there is no task #{}", task_id));
+        }
+        for dependency in depends_on {
+            deps::check_dependency(&tasks, task_id, *dependency)?;
+        }
+        if let Some(parent) = parent {
+            deps::check_parent(&tasks, task_id, parent)?;
+        }
+        let mut task = tasks.get_mut(&task_id).ok_or("the task went away")?;
+        for dependency in depends_on {
+            if !task.depends_on.contains(dependency) {
+                task.depends_on.push(*dependency);
+            }
+        }
+        task.parent = parent.or(task.parent);
+        task.touch();
+        Ok(task.clone())
+    }
+
+    // Link the task to another one, replacing any link between them made from
+    // this side, or remove that link
+    pub async fn link_tasks(&self, task_id: u32, other: u32, relation: links::Relation, remove: bool) -> Result<Task, String> {
+        let mut tasks = self.tasks.lock().await;
+        if task_id == other {
+            return Err("a task can't be linked to itself".to_string());
+        }
+        if !tasks.contains_key(&other) {
+            return Err(format!("there is no task #{}", other));
+        }
+        let mut task = tasks.get_mut(&task_id).ok_or(format!("there is no task #{}", task_id))?;
+        task.links.retain(|link| link.id != other);
+        if !remove {
+            task.links.push(links::Link { id: other, relation });
+        }
+        task.touch();
+        Ok(task.clone())
+    }
+
+    pub async fn set_local_only(&self, task_id: u32, local_only: bool) -> Option<Task> {
+        let mut tasks = self.tasks.lock().await;
+        let mut task = tasks.get_mut(&task_id)?;
+        task.local_only = local_only;
+        task.touch();
+        Some(task.clone())
+    }
+
+    pub async fn set_inbox(&self, task_id: u32, inbox: bool) -> Option<Task> {
+        let mut tasks = self.tasks.lock().await;
+        let mut task = tasks.get_mut(&task_id)?;
+        task.inbox = inbox;
+        task.touch();
+        Some(task.clone())
+    }
+
+    // Move the task to another time zone, or back to local time, at the same
+    // clock time; a time skipped there when the clocks go forward keeps its instant
+    pub async fn set_timezone(&self, task_id: u32, timezone: Option<String>) -> Option<Task> {
+        let mut tasks = self.tasks.lock().await;
+        let mut task = tasks.get_mut(&task_id)?;
+        let (from, to) = (task.zone(), zone::Zone::of(timezone.as_deref()));
+        let moved = |time: DateTime<Utc>| to.resolve(from.naive(time)).unwrap_or(time);
+        task.start_time = task.start_time.map(moved);
+        task.end_time = task.end_time.map(moved);
+        task.timezone = timezone;
+        task.touch();
+        Some(task.clone())
+    }
+
+    pub async fn set_estimate(&self, task_id: u32, estimate_minutes: Option<i64>) -> Option<Task> {
+        let mut tasks = self.tasks.lock().await;
+        let mut task = tasks.get_mut(&task_id)?;
+        task.estimate_minutes = estimate_minutes;
+        task.touch();
+        Some(task.clone())
+    }
+
+    // Assign the task, or unassign it when None
+    pub async fn set_assignee(&self, task_id: u32, assignee: Option<Assignee>) -> Option<Task> {
+        let mut tasks = self.tasks.lock().await;
+        let mut task = tasks.get_mut(&task_id)?;
+        task.assignee = assignee;
+        task.touch();
+        Some(task.clone())
+    }
+
+    // Set each attribute, removing those with an empty value
+    pub async fn set_meta(&self, task_id: u32, meta: Vec<(String, String)>) -> Option<Task> {
+        let mut tasks = self.tasks.lock().await;
+        let mut task = tasks.get_mut(&task_id)?;
+        for (key, value) in meta {
+            if value.is_empty() {
+                task.meta.remove(&key);
+            } else {
+                task.meta.insert(key, value);
+            }
+        }
+        task.touch();
+        Some(task.clone())
+    }
+
+    pub async fn set_progress(&self, task_id: u32, percent: u8) -> Option<Task> {
+        let mut tasks = self.tasks.lock().await;
+        let mut task = tasks.get_mut(&task_id)?;
+        task.progress = Some(percent);
+        task.touch();
+        Some(task.clone())
+    }
+
+    pub async fn unlink_event(&self, task_id: u32) {
+        let mut tasks = self.tasks.lock().await;
+        if let Some(mut task) = tasks.get_mut(&task_id) {
+            task.event_id = None;
+            task.event_etag = None;
+            task.event_updated = None;
+            task.event_link = None;
+        }
+    }
+
+    pub async fn set_event_version(&self, task_id: u32, etag: Option<String>, updated: Option<DateTime<Utc>>, link: Option<String>) {
+        let mut tasks = self.tasks.lock().await;
+        if let Some(mut task) = tasks.get_mut(&task_id) {
+            task.event_etag = etag;
+            task.event_updated = updated;
+            task.event_link = link;
+        }
+    }
+
+    pub async fn apply_snapshot(&self, task_id: u32, snapshot: &Snapshot) -> Option<Task> {
+        let mut tasks = self.tasks.lock().await;
+        let mut task = tasks.get_mut(&task_id)?;
+        snapshot.apply_to(&mut task);
+        task.touch();
+        Some(task.clone())
+    }
+
+    pub async fn link_github_issue(&self, task_id: u32, issue: String) -> Option<Task> {
+        let mut tasks = self.tasks.lock().await;
+        let mut task = tasks.get_mut(&task_id)?;
+        task.github_issue = Some(issue);
+        task.touch();
+        Some(task.clone())
+    }
+
+    pub async fn link_jira_issue(&self, task_id: u32, key: String) -> Option<Task> {
+        let mut tasks = self.tasks.lock().await;
+        let mut task = tasks.get_mut(&task_id)?;
+        task.jira_issue = Some(key);
+        task.touch();
+        Some(task.clone())
+    }
+
+    // Load the store from disk to work on, starting empty if the file doesn't
+    // exist yet. A store from an older version is upgraded, and the file kept
+    // as it was beside it ("tasks.json.v0") for going back to that version.
+    pub fn load(path: &str) -> Result<AppState, Box<dyn std::error::Error>> {
+        back_up_older(path)?;
+        AppState::read(path)
+    }
+
+    // The store on disk only to look at: one from an older version is upgraded
+    // in memory, but nothing is written
+    pub fn read(path: &str) -> Result<AppState, Box<dyn std::error::Error>> {
+        let store = match std::fs::read_to_string(path) {
+            Ok(contents) => schema::read(&contents)?.0,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => StoreFile::default(),
+            Err(e) => return Err(Box::new(e)),
+        };
+        Ok(AppState {
+            tasks: Mutex::new(TaskStore::new(store.next_id, store.tasks)),
+        })
+    }
+
+    // The ID a task added here has after saving: another process may have
+    // given out the one it had first
+    pub async fn saved_id(&self, id: u32) -> u32 {
+        self.tasks.lock().await.moved_id(id)
     }
+
+    // Whether tasks changed since the store was loaded or last saved
+    pub async fn has_unsaved_changes(&self) -> bool {
+        self.tasks.lock().await.is_changed()
+    }
+
+    // Write the tasks back. The file is locked from reading what's there to
+    // writing the new version, and what other processes saved since the tasks
+    // were loaded is merged in first, so their changes aren't lost. Waiting
+    // for the file lock is left to a blocking thread and done before taking
+    // the tasks, so other work carries on meanwhile.
+    pub async fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let locked = path.to_string();
+        let _lock = tokio::task::spawn_blocking(move || atomic::lock(&locked)).await??;
+        back_up_older(path)?;
+        let mut tasks = self.tasks.lock().await;
+        // The version there now, also for the tasks removed since
+        let previous = match std::fs::read_to_string(path) {
+            Ok(contents) => {
+                let (previous, _) = schema::read(&contents)?;
+                // Tasks there without a UUID get a new one each time they're
+                // read, so they can't be matched up; they're from a file not
+                // saved by this version since it was loaded, though, and have
+                // nothing to merge
+                let identified = serde_json::from_str::<serde_json::Value>(&contents)?["tasks"]
+                    .as_array()
+                    .is_some_and(|tasks| tasks.iter().all(|task| task.get("uuid").is_some()));
+                if identified {
+                    tasks.rebase(previous.tasks.clone(), previous.next_id);
+                }
+                previous.tasks
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(Box::new(e)),
+        };
+        let mut store = StoreFile {
+            version: schema::VERSION,
+            next_id: tasks.next_id(),
+            tasks: tasks.values().cloned().collect(),
+        };
+        store.tasks.sort_by_key(|task| task.id);
+        atomic::write(path, serde_json::to_string_pretty(&store)?)?;
+        tasks.mark_saved();
+        let changes = crdt::record(&store)?;
+        audit::log(changes, &previous, &store.tasks, &audit::user(), &audit::command(), Utc::now().trunc_subsecs(0))
+    }
+}
+
+// Keep a store from an older version as it is beside it, as "tasks.json.v0"
+// and so on, unless it was kept already
+fn back_up_older(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(Box::new(e)),
+    };
+    let version = schema::version(&serde_json::from_str(&contents)?);
+    let backup = format!("{}.v{}", path, version);
+    if version < schema::VERSION && !std::path::Path::new(&backup).exists() {
+        std::fs::write(&backup, contents)?;
+    }
+    Ok(())
 }
 
-// Send reminder at 5 mins before start and 2 mins before end
-async fn schedule_reminders(task: Task, state: Arc<AppState>) {
-    let reminder_time_start = task.start_time - chrono::Duration::minutes(5);
-    let reminder_time_end = task.end_time - chrono::Duration::minutes(2);
-    let now = Utc::now();
+// Push a new task to the configured calendar and remember the event it created
+async fn push_to_calendar(config: &Config, state: &AppState, task_id: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(task) = state.get_task(task_id).await else {
+        return Ok(());
+    };
+    if !SyncFilter::new(&config.sync)?.pushes(&task) {
+        return Ok(());
+    }
+    let Some(backend) = Backend::for_task(config, &task).await? else {
+        return Ok(());
+    };
+    let event_id = backend.push(&task).await?;
+    state.link_event(task_id, event_id).await;
+    save_state(state).await;
+    say!("{}", t!("add-pushed"));
+    Ok(())
+}
+
+// Push tasks just added, as `add` does, but over one connection per backend;
+// the number pushed
+async fn push_added(config: &Config, state: &AppState, ids: &[u32]) -> Result<usize, Box<dyn std::error::Error>> {
+    let filter = SyncFilter::new(&config.sync)?;
+    let mut added = Vec::new();
+    for id in ids {
+        if let Some(task) = state.get_task(*id).await.filter(|task| filter.pushes(task)) {
+            added.push(task);
+        }
+    }
+    // Dated tasks go to the calendar, undated ones to Google Tasks if enabled
+    let (dated, undated): (Vec<Task>, Vec<Task>) = added.into_iter().partition(|task| task.start_time.is_some());
+    let mut pushed = 0;
+    for tasks in [dated, undated] {
+        let Some(first) = tasks.first() else { continue };
+        let Some(backend) = Backend::for_task(config, first).await? else { continue };
+        let tasks: Vec<Task> = tasks.into_iter().filter(|task| backend.accepts(task)).collect();
+        let progress = progress::Progress::bar(&t!("sync-pushing", service = backend.name()), tasks.len());
+        let results = backend.push_all(&tasks, &progress).await;
+        progress.finish();
+        let mut first_error = None;
+        for (task, result) in tasks.iter().zip(results) {
+            match result {
+                Ok(event_id) => {
+                    state.link_event(task.id, event_id).await;
+                    pushed += 1;
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        save_state(state).await;
+        // Tasks pushed before the failure stay linked; sync pushes the rest
+        if let Some(e) = first_error {
+            return Err(e);
+        }
+    }
+    Ok(pushed)
+}
+
+// Add the tasks in a file: all of them, or none if any of them is invalid
+async fn add_file(config: &Config, state: &AppState, path: &str) {
+    let text = match read_input(path) {
+        Ok(text) => text,
+        Err(e) => {
+            fail!(exit::ERROR, "{}", t!("error-reading", path = path, error = exit::Chain(&e)));
+            return;
+        }
+    };
+    let entries = match bulk::parse(path, &text) {
+        Ok(entries) => entries,
+        Err(e) => {
+            fail!(exit::USAGE, "{}", t!("add-file-unreadable", path = path, error = e));
+            return;
+        }
+    };
+    let known: std::collections::HashSet<u32> = state.list_tasks().await.iter().map(|task| task.id).collect();
+    let mut tasks = Vec::new();
+    let mut invalid = 0;
+    for (i, entry) in entries.into_iter().enumerate() {
+        let title = match &entry {
+            Ok(entry) if !entry.title.is_empty() => format!(" '{}'", entry.title),
+            _ => String::new(),
+        };
+        let checked = entry.and_then(NewTask::from_entry).map_err(|e| (exit::USAGE, e)).and_then(|new| {
+            match new.depends_on.iter().chain(&new.parent).find(|id| !known.contains(id)) {
+                Some(id) => Err((exit::NOT_FOUND, format!("There is no task #{}", id))),
+                None => new_task(config, new),
+            }
+        });
+        match checked {
+            Ok(task) => tasks.push(task),
+            Err((code, message)) => {
+                fail!(code, "{}", t!("add-file-invalid-task", number = i + 1, title = title, path = path, message = message));
+                invalid += 1;
+            }
+        }
+    }
+    if invalid > 0 {
+        eprintln!("{}", t!("add-file-none-added", path = path, invalid = invalid));
+        return;
+    }
+    if tasks.is_empty() {
+        say!("{}", t!("add-file-empty", path = path));
+        return;
+    }
+    for task in &tasks {
+        if let Some(Err(reason)) = task.try_rrule() {
+            eprintln!("{}", t!("add-file-recurrence-unsynced", title = task.title, reason = reason));
+        }
+    }
+    let ids = state.add_tasks(tasks).await;
+    save_state(state).await;
+    let mut saved = Vec::new();
+    for id in ids {
+        saved.push(state.saved_id(id).await);
+    }
+    let ids = saved;
+    if quiet::on() {
+        for id in &ids {
+            println!("{}", id);
+        }
+    } else {
+        let range = match (ids.first(), ids.last()) {
+            (Some(first), Some(last)) if first != last => t!("add-file-ids", first = first, last = last),
+            _ => t!("add-file-id", id = ids[0]),
+        };
+        println!("{}", t!("add-file-added", count = ids.len(), path = path, range = range));
+    }
+    match push_added(config, state, &ids).await {
+        Ok(0) => {}
+        Ok(pushed) => say!("{}", t!("add-file-pushed", count = pushed)),
+        Err(e) => fail!(exit::code_of(&*e), "{}", t!("add-file-push-failed", error = exit::Chain(&*e))),
+    }
+    for id in &ids {
+        if let Some(task) = state.get_task(*id).await {
+            publish_event(config.mqtt.as_ref(), "added", &task, None).await;
+        }
+    }
+}
+
+// Publish a task event to the MQTT broker, if one is configured
+async fn publish_event(mqtt: Option<&MqttConfig>, event: &str, task: &Task, message: Option<&str>) {
+    if let Some(mqtt) = mqtt
+        && let Err(e) = mqtt::publish(mqtt, event, task, message).await
+    {
+        eprintln!("{}", t!("mqtt-publish-failed", event = event, error = format!("{:?}", e)));
+    }
+}
+
+// Propagate a completion to the calendar, any linked issue tracker and MQTT
+async fn on_task_completed(config: &Config, state: &AppState, task: &Task) {
+    publish_event(config.mqtt.as_ref(), "completed", task, None).await;
+    if let Err(e) = mark_done_on_calendar(config, state, task).await {
+        fail!(exit::code_of(&*e), "{}", t!("error-event-update", error = exit::Chain(&*e)));
+    }
+    if let (Some(github), Some(issue)) = (&config.github, &task.github_issue) {
+        match github::close_issue(github, issue).await {
+            Ok(()) => say!("{}", t!("done-github-closed", issue = issue)),
+            Err(e) => fail!(exit::code_of(&*e), "{}", t!("done-github-failed", issue = issue, error = exit::Chain(&*e))),
+        }
+    }
+    if let (Some(jira), Some(key)) = (&config.jira, &task.jira_issue) {
+        match jira::complete_issue(jira, key).await {
+            Ok(()) => say!("{}", t!("done-jira-updated", key = key)),
+            Err(e) => fail!(exit::code_of(&*e), "{}", t!("done-jira-failed", key = key, error = exit::Chain(&*e))),
+        }
+    }
+}
+
+// Mirror a local change onto the task's calendar event, if it has one
+async fn update_on_calendar(config: &Config, task: &Task) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(event_id) = &task.event_id else { return Ok(()) };
+    match Backend::for_task(config, task).await? {
+        Some(backend) => backend.update(event_id, task).await,
+        None => Ok(()),
+    }
+}
+
+// Mark a completed task's event as configured in `sync.completed`. Undated tasks
+// on Google Tasks are simply updated, which completes them there.
+async fn mark_done_on_calendar(config: &Config, state: &AppState, task: &Task) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(event_id) = &task.event_id else { return Ok(()) };
+    let Some(backend) = Backend::for_task(config, task).await? else { return Ok(()) };
+    if task.start_time.is_none() {
+        return backend.update(event_id, task).await;
+    }
+    match config.sync.completed {
+        CompletedEvents::Keep => backend.update(event_id, task).await,
+        CompletedEvents::Prefix => {
+            let mut marked = task.clone();
+            marked.title = format!("{}{}", config.sync.completed_prefix.as_deref().unwrap_or("✔ "), task.title);
+            backend.update(event_id, &marked).await
+        }
+        CompletedEvents::Color => backend.set_color(event_id, task, config.sync.completed_color.as_deref()).await,
+        CompletedEvents::Delete => {
+            backend.delete(event_id).await?;
+            forget_event(task);
+            state.unlink_event(task.id).await;
+            save_state(state).await;
+            Ok(())
+        }
+    }
+}
+
+// Remove a task from the store, MQTT subscribers and the calendar
+async fn remove_task(config: &Config, state: &AppState, id: u32) -> Option<Task> {
+    let removed_task = state.remove_task(id).await?;
+    save_state(state).await;
+    publish_event(config.mqtt.as_ref(), "removed", &removed_task, None).await;
+    match delete_from_calendar(config, &removed_task).await {
+        Ok(()) => forget_event(&removed_task),
+        // Sync deletes the event once the calendar is reachable again
+        Err(e) => fail!(exit::code_of(&*e), "{}", t!("error-event-remove", error = exit::Chain(&*e))),
+    }
+    Some(removed_task)
+}
+
+async fn delete_from_calendar(config: &Config, task: &Task) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(event_id) = &task.event_id else { return Ok(()) };
+    match Backend::for_task(config, task).await? {
+        Some(backend) => backend.delete(event_id).await,
+        None => Ok(()),
+    }
+}
+
+// Reply to the invitation the task's event came from
+async fn respond_to_invitation(config: &Config, task: &Task, reply: Rsvp) -> Result<(), Box<dyn std::error::Error>> {
+    let event_id = task
+        .event_id
+        .as_deref()
+        .filter(|_| task.start_time.is_some())
+        .ok_or_else(|| format!("Task {} isn't linked to a calendar event", task.id))?;
+    Backend::from_config(config).await?.respond(event_id, reply).await
+}
+
+// Read a file, or stdin for "-"
+fn read_input(path: &str) -> std::io::Result<String> {
+    if path == "-" {
+        let mut buffer = String::new();
+        std::io::stdin().read_to_string(&mut buffer)?;
+        return Ok(buffer);
+    }
+    std::fs::read_to_string(path)
+}
+
+// Write a file, or stdout for "-"
+fn write_output(path: &str, contents: &str) -> std::io::Result<()> {
+    if path == "-" {
+        println!("{}", contents);
+        return Ok(());
+    }
+    std::fs::write(path, contents)
+}
+
+// Start of a local day, in UTC
+fn local_midnight(date: chrono::NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|midnight| Local.from_local_datetime(&midnight).earliest())
+        .map_or_else(Utc::now, |midnight| midnight.with_timezone(&Utc))
+}
+
+// Tasks, feed events and cached calendar events of the `days` days from `from`
+fn agenda_items(tasks: &[Task], calendars: &SyncState, from: DateTime<Utc>, days: i64) -> Vec<agenda::AgendaItem> {
+    let feeds = FeedCache::load(&paths::cache(feeds::FEEDS_PATH)).unwrap_or_else(|e| {
+        eprintln!("{}", t!("error-reading", path = paths::cache(feeds::FEEDS_PATH), error = format!("{:?}", e)));
+        FeedCache::default()
+    });
+    agenda::collect(tasks, &feeds, calendars, from, from + chrono::Duration::days(days))
+}
+
+// Re-download subscribed ICS feeds that are due for a refresh
+async fn refresh_feeds(config: &Config) {
+    if config.ics_feeds.is_empty() {
+        return;
+    }
+    let mut cache = FeedCache::load(&paths::cache(feeds::FEEDS_PATH)).unwrap_or_default();
+    for (name, result) in feeds::refresh(&config.ics_feeds, &mut cache).await {
+        match result {
+            Ok(count) => say!("{}", t!("feeds-refreshed", name = name, count = count)),
+            Err(e) => fail!(exit::code_of(&*e), "{}", t!("feeds-failed", name = name, error = exit::Chain(&*e))),
+        }
+    }
+    if let Err(e) = cache.save(&paths::cache(feeds::FEEDS_PATH)) {
+        fail!(exit::ERROR, "{}", t!("error-saving", path = paths::cache(feeds::FEEDS_PATH), error = exit::Chain(&*e)));
+    }
+}
+
+// Record the outcome of syncing with a service in its sync state
+fn record_sync<T>(service: &mut ServiceState, result: &Result<T, Box<dyn std::error::Error>>) {
+    let now = Utc::now();
+    service.last_attempt = Some(now);
+    match result {
+        Ok(_) => {
+            service.last_success = Some(now);
+            service.last_error = None;
+        }
+        Err(e) => service.last_error = Some(e.to_string()),
+    }
+}
+
+// Synchronize tasks with the configured calendar, undated ones with Google Tasks,
+// and Todoist when enabled. With `interactive`, asks how to settle each conflict;
+// with `dry_run`, only prints what would change and leaves the sync state alone;
+// with `confirm`, asks before deleting events whose tasks were removed here.
+// Returns the services that failed.
+async fn sync_all(config: &Config, state: &AppState, interactive: bool, dry_run: bool, confirm: bool) -> Vec<&'static str> {
+    let mut sync_state = SyncState::load(&paths::data(sync::SYNC_STATE_PATH)).unwrap_or_else(|e| {
+        eprintln!("{}", t!("error-reading", path = paths::data(sync::SYNC_STATE_PATH), error = format!("{:?}", e)));
+        SyncState::default()
+    });
+    let mut failed = Vec::new();
+    let filter = match SyncFilter::new(&config.sync) {
+        Ok(filter) => filter,
+        Err(e) => {
+            fail!(exit::USAGE, "{}", t!("sync-rules-invalid", path = paths::config(config::CONFIG_PATH), error = e));
+            return vec!["Sync rules"];
+        }
+    };
+    let mut backends = Vec::new();
+    let calendar_name = sync::calendar_name(config.backend);
+    match Backend::from_config(config).await {
+        Ok(backend) => backends.push(backend),
+        Err(e) => {
+            fail!(exit::code_of(&*e), "{}", t!("sync-connect-failed", service = calendar_name, error = exit::Chain(&*e)));
+            record_sync::<()>(sync_state.service(calendar_name), &Err(e));
+            failed.push(calendar_name);
+        }
+    }
+    if let Some(google_tasks) = &config.google_tasks {
+        match GoogleTasksBackend::new(google_tasks, config.token_store, &google::credentials_path(config)).await {
+            Ok(backend) => backends.push(Backend::GoogleTasks(backend)),
+            Err(e) => {
+                fail!(exit::code_of(&*e), "{}", t!("sync-google-tasks-connect-failed", error = exit::Chain(&*e)));
+                record_sync::<()>(sync_state.service("Google Tasks"), &Err(e));
+                failed.push("Google Tasks");
+            }
+        }
+    }
+    for backend in &backends {
+        let service = sync_state.service(backend.name());
+        if dry_run {
+            println!("{}:", backend.name());
+        }
+        let deletes = sync::pending(service, &state.list_tasks().await).deleted;
+        if confirm && !dry_run && deletes > 0 {
+            let question = t!("sync-confirm-deletes", count = deletes, service = backend.name());
+            if !confirm::ask(&question) {
+                say!("{}", t!("sync-skipped", service = backend.name()));
+                continue;
+            }
+        }
+        let result = sync::sync(backend, state, service, &filter, dry_run, confirm).await;
+        if dry_run {
+            if let Err(e) = result {
+                fail!(exit::code_of(&*e), "{}", t!("sync-failed", service = backend.name(), error = exit::Chain(&*e)));
+                failed.push(backend.name());
+            }
+            continue;
+        }
+        record_sync(service, &result);
+        match result {
+            Ok(summary) => {
+                say!(
+                    "{}",
+                    t!(
+                        "sync-done",
+                        service = backend.name(),
+                        pushed = summary.pushed,
+                        added = summary.added,
+                        updated = summary.updated,
+                        sent = summary.sent,
+                        deleted = summary.deleted,
+                        skipped = summary.skipped,
+                        exceptions = summary.exceptions
+                    )
+                );
+                if summary.adopted > 0 {
+                    say!("{}", t!("sync-adopted", count = summary.adopted));
+                }
+                for (task_id, title) in &summary.invitations {
+                    say!("{}", t!("sync-invitation", id = task_id, title = title));
+                }
+                if interactive && !service.conflicts.is_empty() {
+                    let resolved = conflicts::resolve(backend, state, service).await;
+                    say!("{}", t!("sync-conflicts-resolved", resolved = resolved, left = service.conflicts.len()));
+                } else if summary.conflicts > 0 {
+                    say!("{}", t!("sync-conflicts-left", count = summary.conflicts));
+                }
+            }
+            Err(e) => {
+                fail!(exit::code_of(&*e), "{}", t!("sync-failed", service = backend.name(), error = exit::Chain(&*e)));
+                failed.push(backend.name());
+            }
+        }
+    }
+    if dry_run {
+        return failed;
+    }
+    for calendar in &config.calendars {
+        let result = match Backend::for_calendar(config, calendar).await {
+            Ok(backend) => progress::spin(&t!("sync-fetching", service = calendar.name), sync::fetch(&backend, sync_state.service(&calendar.name))).await,
+            Err(e) => Err(e),
+        };
+        record_sync(sync_state.service(&calendar.name), &result);
+        match result {
+            Ok(count) => say!("{}", t!("sync-fetched", name = calendar.name, count = count)),
+            Err(e) => {
+                fail!(exit::code_of(&*e), "{}", t!("sync-fetch-failed", name = calendar.name, error = exit::Chain(&*e)));
+                failed.push("Calendars");
+            }
+        }
+    }
+    if let Some(todoist) = config.todoist.as_ref().filter(|todoist| todoist.sync) {
+        let result = progress::spin(&t!("sync-todoist-syncing"), todoist::sync(todoist, state)).await;
+        record_sync(sync_state.service("Todoist"), &result);
+        match result {
+            Ok(summary) => say!(
+                "{}",
+                t!("sync-todoist-done", added = summary.added, updated = summary.updated, completed = summary.completed, closed = summary.closed)
+            ),
+            Err(e) => {
+                fail!(exit::code_of(&*e), "{}", t!("sync-todoist-failed", error = exit::Chain(&*e)));
+                failed.push("Todoist");
+            }
+        }
+    }
+    if let Err(e) = sync_state.save(&paths::data(sync::SYNC_STATE_PATH)) {
+        fail!(exit::ERROR, "{}", t!("error-saving", path = paths::data(sync::SYNC_STATE_PATH), error = exit::Chain(&*e)));
+    }
+    failed
+}
+
+// Print when each service was last synced and what is waiting to be synced
+fn print_sync_status(config: &Config, tasks: &[Task]) {
+    let sync_state = match SyncState::load(&paths::data(sync::SYNC_STATE_PATH)) {
+        Ok(sync_state) => sync_state,
+        Err(e) => {
+            eprintln!("{}", t!("error-reading", path = paths::data(sync::SYNC_STATE_PATH), error = format!("{:?}", e)));
+            return;
+        }
+    };
+    let filter = SyncFilter::new(&config.sync).ok();
+    let unpushed = tasks
+        .iter()
+        .filter(|task| task.status == TaskStatus::Todo && task.event_id.is_none() && !task.local_only)
+        .filter(|task| task.start_time.is_some() || config.google_tasks.is_some())
+        .filter(|task| filter.as_ref().is_none_or(|filter| filter.pushes(task)))
+        .count();
+    println!("{}", t!("sync-status-unpushed", count = unpushed));
+    if sync_state.services.is_empty() {
+        println!("{}", t!("sync-status-never"));
+    }
+    for (name, service) in &sync_state.services {
+        println!("{}", name);
+        println!("  {}", t!("sync-status-last-success", time = service.last_success.map_or_else(|| t!("sync-status-never-run"), |time| format_time(Some(time)))));
+        if let (Some(attempt), Some(error)) = (service.last_attempt, &service.last_error) {
+            println!("  {}", t!("sync-status-last-failure", time = format_time(Some(attempt)), error = error));
+        }
+        println!("  {}", service.sync_token.as_ref().map_or_else(|| t!("sync-status-no-token"), |token| t!("sync-status-token", token = token)));
+        let pending = sync::pending(service, tasks);
+        println!("  {}", t!("sync-status-pending", changed = pending.changed, deleted = pending.deleted));
+        if let Some(fetched_at) = service.events_fetched_at {
+            println!("  {}", t!("sync-status-cached", count = service.events.len(), time = format_time(Some(fetched_at))));
+        }
+        println!("  {}", t!("sync-status-conflicts", count = service.conflicts.len()));
+        for conflict in &service.conflicts {
+            println!(
+                "    {}",
+                t!(
+                    "sync-status-conflict",
+                    id = conflict.task_id,
+                    local = conflict.local.title,
+                    local_start = format_time(conflict.local.start_time),
+                    remote = conflict.remote.title,
+                    remote_start = format_time(conflict.remote.start_time)
+                )
+            );
+        }
+    }
+    print_background_sync();
+}
+
+// How the server syncs in the background, as it last recorded
+fn print_background_sync() {
+    let Some(info) = serve::ServerInfo::load() else {
+        println!("{}", t!("sync-status-background-off", path = config::CONFIG_PATH));
+        return;
+    };
+    let Some(background) = info.sync else {
+        println!("{}", t!("sync-status-background-no-interval", pid = info.pid));
+        return;
+    };
+    println!("{}", t!("sync-status-background", pid = info.pid));
+    let mut schedule = Vec::new();
+    if let Some(minutes) = background.every_minutes {
+        schedule.push(match background.jitter_minutes {
+            0 => t!("sync-status-every", minutes = minutes),
+            jitter => t!("sync-status-every-jitter", minutes = minutes, jitter = jitter),
+        });
+    }
+    if background.on_push {
+        schedule.push(t!("sync-status-on-push"));
+    }
+    println!("  {}", t!("sync-status-runs", schedule = schedule.join(", and ")));
+    let time = background.last_run.map_or_else(|| t!("sync-status-not-yet"), |time| format_time(Some(time)));
+    match background.last_failed.as_slice() {
+        [] => println!("  {}", t!("sync-status-last-run", time = time)),
+        failed => println!("  {}", t!("sync-status-last-run-failed", time = time, services = failed.join(", "))),
+    }
+    if let Some(next) = background.next_run {
+        println!("  {}", t!("sync-status-next-run", time = format_time(Some(next))));
+    }
+}
+
+// Stop tracking the event of a task whose event was just deleted
+fn forget_event(task: &Task) {
+    let Some(event_id) = &task.event_id else { return };
+    let result = SyncState::load(&paths::data(sync::SYNC_STATE_PATH)).and_then(|mut sync_state| {
+        sync_state.forget_event(event_id);
+        sync_state.save(&paths::data(sync::SYNC_STATE_PATH))
+    });
+    if let Err(e) = result {
+        fail!(exit::ERROR, "{}", t!("error-updating", path = paths::data(sync::SYNC_STATE_PATH), error = exit::Chain(&*e)));
+    }
+}
+
+// The calendars of the signed-in Google account, for `calendars`
+async fn google_calendars(config: &Config) -> Option<Vec<google::CalendarInfo>> {
+    if config.backend != config::BackendKind::Google {
+        fail!(exit::USAGE, "{}", t!("calendars-google-only"));
+        return None;
+    }
+    let backend = match google::GoogleBackend::new(config).await {
+        Ok(backend) => backend,
+        Err(e) => {
+            fail!(exit::code_of(&*e), "{}", t!("calendars-sign-in-failed", error = exit::Chain(&*e)));
+            return None;
+        }
+    };
+    match quota::call("Google Calendar", || backend.calendars()).await {
+        Ok(calendars) => Some(calendars),
+        Err(e) => {
+            fail!(exit::code_of(&*e), "{}", t!("calendars-list-failed", error = exit::Chain(&*e)));
+            None
+        }
+    }
+}
+
+// Sync tasks with `calendar` from now on. Events in the old calendar stay
+// there; the tasks get new ones at the next sync.
+async fn switch_calendar(config: &mut Config, state: &AppState, calendar: &google::CalendarInfo) {
+    config.calendar_id = Some(if calendar.primary { "primary".to_string() } else { calendar.id.clone() });
+    if let Err(e) = config.save(&paths::config(config::CONFIG_PATH)) {
+        fail!(exit::ERROR, "{}", t!("error-saving", path = paths::config(config::CONFIG_PATH), error = exit::Chain(&*e)));
+        return;
+    }
+    let linked: Vec<u32> = {
+        let tasks = state.tasks.lock().await;
+        tasks.values().filter(|task| task.start_time.is_some() && task.event_id.is_some()).map(|task| task.id).collect()
+    };
+    for &id in &linked {
+        state.unlink_event(id).await;
+    }
+    save_state(state).await;
+    let mut sync_state = SyncState::load(&paths::data(sync::SYNC_STATE_PATH)).unwrap_or_default();
+    *sync_state.service("Google Calendar") = Default::default();
+    if let Err(e) = sync_state.save(&paths::data(sync::SYNC_STATE_PATH)) {
+        fail!(exit::ERROR, "{}", t!("error-saving", path = paths::data(sync::SYNC_STATE_PATH), error = exit::Chain(&*e)));
+    }
+    say!("{}", t!("calendars-switched", name = calendar.name));
+    if !linked.is_empty() {
+        say!("{}", t!("calendars-switched-tasks", count = linked.len()));
+    }
+}
+
+// The first time tasks go to Google Calendar, offer to keep them in a
+// calendar of their own instead of among meetings. Either answer is kept in
+// the config, so it's asked once.
+async fn offer_todo_calendar(config: Config, state: &AppState) -> Config {
+    let first_run = config.backend == config::BackendKind::Google
+        && config.calendar_id.is_none()
+        && std::path::Path::new(&google::credentials_path(&config)).exists()
+        && std::io::stdin().is_terminal()
+        && std::io::stdout().is_terminal()
+        && !state.tasks.lock().await.values().any(|task| task.event_id.is_some());
+    if !first_run {
+        return config;
+    }
+    let question = t!("calendars-offer", name = google::TODO_CALENDAR);
+    let Some(answer) = conflicts::read_answer(&question) else { return config };
+    let mut config = config;
+    if matches!(answer.to_lowercase().as_str(), "n" | "no") {
+        config.calendar_id = Some("primary".to_string());
+        match config.save(&paths::config(config::CONFIG_PATH)) {
+            Ok(()) => say!("{}", t!("calendars-using-primary")),
+            Err(e) => fail!(exit::ERROR, "{}", t!("error-saving", path = paths::config(config::CONFIG_PATH), error = exit::Chain(&*e))),
+        }
+        return config;
+    }
+    let backend = match google::GoogleBackend::new(&config).await {
+        Ok(backend) => backend,
+        Err(e) => {
+            eprintln!("{}", t!("calendars-sign-in-failed-using-primary", error = exit::Chain(&*e)));
+            return config;
+        }
+    };
+    // One left from an earlier setup is used again rather than doubled
+    let existing = quota::call("Google Calendar", || backend.calendars())
+        .await
+        .ok()
+        .and_then(|calendars| calendars.into_iter().find(|calendar| calendar.name == google::TODO_CALENDAR && calendar.writable()));
+    let calendar = match existing {
+        Some(calendar) => calendar,
+        None => match quota::call("Google Calendar", || backend.create_calendar(google::TODO_CALENDAR)).await {
+            Ok(calendar) => {
+                say!("{}", t!("calendars-created", name = calendar.name));
+                calendar
+            }
+            Err(e) => {
+                eprintln!("{}", t!("calendars-create-failed-using-primary", error = exit::Chain(&*e)));
+                return config;
+            }
+        },
+    };
+    switch_calendar(&mut config, state, &calendar).await;
+    config
+}
+
+async fn save_state(state: &AppState) {
+    if let Err(e) = state.save(&paths::data(STORE_PATH)).await {
+        fail!(exit::ERROR, "{}", t!("store-unsaved", path = paths::data(STORE_PATH), error = exit::Chain(&*e)));
+    }
+}
+
+// One line per task, in a format launchers can display and hand back
+fn menu_line(task: &Task) -> String {
+    format!(
+        "[{}] {} ({})",
+        task.id,
+        task.title,
+        task.start_time
+            .map_or_else(|| t!("menu-someday"), |time| time.format("%Y-%m-%d %H:%M").to_string())
+    )
+}
+
+// Recover the task ID from a line produced by menu_line
+fn parse_menu_selection(line: &str) -> Option<u32> {
+    let rest = line.trim().strip_prefix('[')?;
+    let (id, _) = rest.split_once(']')?;
+    id.trim().parse().ok()
+}
+
+// Parse quick-add text: "<title> [@ <start>] [for <N>m|<N>h]"
+// Without a start time the task is placed at the next full hour, lasting 30 minutes.
+fn parse_quick_add(text: &str) -> Result<Task, String> {
+    let text = text.trim();
+    let (text, duration) = match text.rsplit_once(" for ") {
+        Some((rest, spec)) => (rest.trim(), parse_duration_spec(spec.trim())?),
+        None => (text, chrono::Duration::minutes(30)),
+    };
+    let (title, start_time) = match text.rsplit_once(" @ ") {
+        Some((title, start)) => {
+            let start = when::parse(start)?;
+            (title.trim(), start)
+        }
+        None => {
+            let next_hour = Utc::now() + chrono::Duration::hours(1);
+            let start = next_hour
+                .duration_trunc(chrono::Duration::hours(1))
+                .unwrap_or(next_hour);
+            (text, start)
+        }
+    };
+    if title.is_empty() {
+        return Err(t!("menu-no-title"));
+    }
+    Ok(Task {
+        id: 0,
+        title: title.to_string(),
+        details: String::new(),
+        start_time: Some(start_time),
+        end_time: Some(start_time + duration),
+        is_recurring: false,
+        frequency_minutes: None,
+        status: TaskStatus::Todo,
+        ..Default::default()
+    })
+}
+
+// Add a task made from quick-add text, with the context's and its project's defaults
+async fn add_quick(config: &Config, state: &AppState, mut task: Task) {
+    context::apply_defaults(config, &mut task);
+    apply_project_defaults(config, &mut task);
+    let task_id = state.add_task(task.clone()).await;
+    save_state(state).await;
+    let task_id = state.saved_id(task_id).await;
+    if quiet::on() {
+        println!("{}", task_id);
+    } else {
+        println!("{}", t!("task-added", title = task.title, id = task_id));
+    }
+    if let Err(e) = push_to_calendar(config, state, task_id).await {
+        fail!(exit::code_of(&*e), "{}", t!("error-event-add", error = exit::Chain(&*e)));
+    }
+    if let Some(added) = state.get_task(task_id).await {
+        publish_event(config.mqtt.as_ref(), "added", &added, None).await;
+    }
+}
+
+// Parse a duration such as "15m", "2h" or "45" (minutes)
+fn parse_duration_spec(spec: &str) -> Result<chrono::Duration, String> {
+    let invalid = || t!("menu-invalid-duration", duration = spec);
+    if let Some(hours) = spec.strip_suffix('h') {
+        hours.parse().map(chrono::Duration::hours).map_err(|_| invalid())
+    } else {
+        let minutes = spec.strip_suffix('m').unwrap_or(spec);
+        minutes.parse().map(chrono::Duration::minutes).map_err(|_| invalid())
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => usage_error(e),
+    };
+    exit::print_json(cli.format == OutputFormat::Json);
+    if let Err(e) = paths::init(cli.home.clone(), cli.profile.clone()) {
+        exit::report(exit::ERROR, &t!("error", error = e));
+        std::process::exit(exit::ERROR);
+    }
+    i18n::init();
+    // Runs before anything is loaded so it can report broken files
+    if let Commands::Doctor = cli.command {
+        if !doctor::run().await {
+            std::process::exit(exit::ERROR);
+        }
+        return;
+    }
+    let state = match AppState::load(&paths::data(STORE_PATH)) {
+        Ok(state) => Arc::new(state),
+        Err(e) => {
+            exit::report(exit::ERROR, &t!("error-reading-doctor", path = paths::data(STORE_PATH), error = e));
+            std::process::exit(exit::ERROR);
+        }
+    };
+    let config = match Config::load(&paths::config(config::CONFIG_PATH)) {
+        Ok(config) => config,
+        Err(e) => {
+            exit::report(exit::ERROR, &t!("error-reading-doctor", path = paths::config(config::CONFIG_PATH), error = e));
+            std::process::exit(exit::ERROR);
+        }
+    };
+    quota::set_daily_limit(config.sync.daily_request_limit);
+    sync::set_concurrency(config.sync.push_concurrency);
+    week::set(config.week_start, config.week_numbers);
+    agenda::select(agenda::Selection {
+        only: cli.calendar.clone(),
+        without: cli.without.clone(),
+        hidden: config.calendars.iter().filter(|calendar| !calendar.include).map(|calendar| calendar.name.clone()).collect(),
+    });
+    if let Some(format) = &config.time_format {
+        let _ = TIME_FORMAT.set(format.clone());
+    }
+    confirm::skip(cli.yes || config.confirm == Some(false));
+    quiet::set(cli.quiet || config.quiet == Some(true));
+    hyperlink::set(config.hyperlinks.unwrap_or_else(|| std::io::stdout().is_terminal() && std::env::var("TERM").is_ok_and(|term| term != "dumb")));
+
+    // The server stops itself cleanly; other commands keep what they changed
+    // before being stopped, e.g. events already created by a sync, unless
+    // they're dry runs. This runs on its own so it still works while a command
+    // waits on a prompt.
+    if !matches!(cli.command, Commands::Serve { .. }) {
+        let dry_run = matches!(
+            cli.command,
+            Commands::Sync { dry_run: true, .. } | Commands::Block { dry_run: true, .. } | Commands::Replan { dry_run: true, .. } | Commands::Maintenance { dry_run: true }
+        );
+        let state = Arc::clone(&state);
+        tokio::spawn(async move {
+            let signal = shutdown::signal().await;
+            if !dry_run && state.has_unsaved_changes().await {
+                eprintln!("{}", t!("stopped-saving", signal = signal));
+                save_state(&state).await;
+            }
+            std::process::exit(130);
+        });
+    }
+    let config = match cli.command {
+        Commands::Add { .. } | Commands::Sync { command: None, dry_run: false, stats: false, git: false, .. } => {
+            offer_todo_calendar(config, &state).await
+        }
+        _ => config,
+    };
+    run(cli.command, config, state).await;
+    std::process::exit(exit::code());
+}
+
+// Report arguments clap couldn't make sense of and exit. The format isn't
+// known then, so `--format json` is looked for among the raw arguments.
+fn usage_error(error: clap::Error) -> ! {
+    use clap::error::ErrorKind;
+    let json = std::env::args().collect::<Vec<_>>().windows(2).any(|pair| pair[0] == "--format" && pair[1] == "json")
+        || std::env::args().any(|arg| arg == "--format=json");
+    let shown = matches!(error.kind(), ErrorKind::DisplayHelp | ErrorKind::DisplayVersion | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand);
+    if !json || shown {
+        error.exit();
+    }
+    exit::print_json(true);
+    let text = error.render().to_string();
+    let message = text.lines().next().unwrap_or_default().trim_start_matches("error: ");
+    exit::report(exit::USAGE, message);
+    std::process::exit(exit::USAGE);
+}
+
+// Mark a task as done, or a recurring task's current occurrence
+async fn mark_done(config: &Config, state: &AppState, id: u32) {
+    // Completing a recurring task completes its current occurrence, not the series
+    if state.get_task(id).await.is_some_and(|task| task.is_recurring) {
+        match state.record_occurrence(id, history::Outcome::Completed).await {
+            Some((task, start_time)) => {
+                save_state(state).await;
+                say!("{}", t!("task-occurrence-done", title = task.title, start = task.format_time(Some(start_time))));
+                publish_event(config.mqtt.as_ref(), "completed", &task, Some("occurrence")).await;
+            }
+            None => say!("{}", t!("task-no-occurrence", id = id)),
+        }
+        return;
+    }
+    let Some(task) = state.complete_task(id).await else {
+        fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id));
+        return;
+    };
+    save_state(state).await;
+    say!("{}", t!("task-done", title = task.title));
+    let tasks = state.list_tasks().await;
+    for original in links::open_duplicates(&task, &tasks) {
+        eprintln!("{}", t!("done-duplicate-open", title = task.title, id = original.id, original_title = original.title));
+    }
+    on_task_completed(config, state, &task).await;
+}
+
+// The task ID given, or else one picked with the fuzzy finder from the open
+// tasks, or from all of them with `all`; None if none was picked
+async fn given_or_picked(state: &AppState, id: Option<u32>, all: bool) -> Option<u32> {
+    if id.is_some() {
+        return id;
+    }
+    let mut tasks: Vec<Task> = state.list_tasks().await.into_iter().filter(|task| all || task.status != TaskStatus::Done).collect();
+    tasks.sort_by_key(|task| (task.status == TaskStatus::Done, task.id));
+    match picker::pick(&tasks) {
+        Ok(picked) => picked,
+        Err(e) => {
+            fail!(exit::USAGE, "{}", t!("error-sentence", error = e));
+            None
+        }
+    }
+}
+
+// Exit code for a failure to link tasks: a missing task, or a link not allowed
+fn link_failure(error: &str) -> i32 {
+    if error.starts_with("there is no task") { exit::NOT_FOUND } else { exit::USAGE }
+}
+
+// Carry out a command
+async fn run(command: Commands, config: Config, state: Arc<AppState>) {
+    match command {
+        Commands::Add { file: Some(path), .. } => add_file(&config, &state, &path).await,
+        Commands::Add { from_clipboard: true, project, tags, priority, local_only, estimate, porcelain, .. } => {
+            if porcelain {
+                quiet::set(true);
+            }
+            let text = match clipboard::read() {
+                Ok(text) => text,
+                Err(e) => {
+                    fail!(exit::ERROR, "{}", t!("add-clipboard-unreadable", error = exit::Chain(&*e)));
+                    return;
+                }
+            };
+            // A line copied from an email or chat, maybe with more below it
+            let mut lines = text.lines().map(str::trim).skip_while(|line| line.is_empty());
+            let Some(first) = lines.next() else {
+                fail!(exit::USAGE, "{}", t!("add-clipboard-empty"));
+                return;
+            };
+            let mut task = match parse_quick_add(first) {
+                Ok(task) => task,
+                Err(e) => {
+                    fail!(exit::USAGE, "{}", t!("error", error = e));
+                    return;
+                }
+            };
+            task.details = lines.collect::<Vec<_>>().join("\n").trim().to_string();
+            task.project = project;
+            task.tags = tags;
+            task.priority = priority;
+            task.local_only = local_only;
+            task.estimate_minutes = estimate;
+            add_quick(&config, &state, task).await;
+        }
+        Commands::Add {
+            title,
+            details,
+            start_time,
+            end_time,
+            recurring,
+            frequency_minutes,
+            cron,
+            every,
+            project,
+            tags,
+            priority,
+            local_only,
+            waiting_for,
+            depends_on,
+            parent,
+            reminders,
+            color,
+            estimate,
+            meta,
+            assignee,
+            force,
+            tz,
+            porcelain,
+            file: None,
+            from_clipboard: false,
+        } => {
+            // Nothing but the ID or JSON printed below goes to stdout
+            if porcelain {
+                quiet::set(true);
+            }
+            for id in depends_on.iter().chain(&parent) {
+                if state.get_task(*id).await.is_none() {
+                    fail!(exit::NOT_FOUND, "{}", t!("add-no-such-parent", id = id));
+                    return;
+                }
+            }
+            let new = NewTask {
+                title: title.unwrap_or_default(),
+                details: details.unwrap_or_default(),
+                start_time,
+                end_time,
+                recurring,
+                frequency_minutes,
+                cron,
+                every,
+                project,
+                tags,
+                priority,
+                local_only,
+                waiting_for,
+                depends_on,
+                parent,
+                reminders,
+                color,
+                estimate,
+                meta,
+                assignee,
+                force,
+                timezone: tz,
+            };
+            let mut task = match new_task(&config, new) {
+                Ok(task) => task,
+                Err((code, message)) => {
+                    fail!(code, "{}", t!("error-sentence", error = message));
+                    return;
+                }
+            };
+            if let Some(Err(reason)) = task.try_rrule() {
+                eprintln!("{}", t!("add-recurrence-unsynced", reason = reason));
+            }
+
+            // Add the task to the state; the store assigns its ID
+            let task_id = state.add_task(task.clone()).await;
+            save_state(&state).await;
+            let task_id = state.saved_id(task_id).await;
+            task.id = task_id;
+            if quiet::on() && !porcelain {
+                println!("{}", task_id);
+            } else {
+                say!("{}", t!("task-added", title = task.title, id = task_id));
+            }
+
+            if let Err(e) = push_to_calendar(&config, &state, task_id).await {
+                fail!(exit::code_of(&*e), "{}", t!("error-event-add", error = exit::Chain(&*e)));
+            }
+            let added = state.get_task(task_id).await;
+            if let Some(added) = &added {
+                publish_event(config.mqtt.as_ref(), "added", added, None).await;
+            }
+            // After the push, so the JSON has the task's event
+            if porcelain {
+                match added.filter(|_| exit::json()) {
+                    Some(added) => println!("{}", serde_json::to_string(&added).unwrap_or_default()),
+                    None => println!("{}", task_id),
+                }
+            }
+        }
+        Commands::Edit { id, local_only, waiting_for, not_waiting, depends_on, parent, estimate, no_estimate, meta, assignee, unassign, tz, no_tz } => {
+            if local_only.is_none()
+                && waiting_for.is_none()
+                && !not_waiting
+                && depends_on.is_empty()
+                && parent.is_none()
+                && estimate.is_none()
+                && !no_estimate
+                && meta.is_empty()
+                && assignee.is_none()
+                && !unassign
+                && tz.is_none()
+                && !no_tz
+            {
+                say!("{}", t!("task-nothing-to-change"));
+                return;
+            }
+            let Some(id) = given_or_picked(&state, id, false).await else { return };
+            if !depends_on.is_empty() || parent.is_some() {
+                match state.link_task(id, &depends_on, parent).await {
+                    Ok(task) => {
+                        save_state(&state).await;
+                        say!("{}", t!("task-updated", title = task.title));
+                    }
+                    Err(e) => {
+                        fail!(link_failure(&e), "{}", t!("error-sentence", error = e));
+                        return;
+                    }
+                }
+            }
+            if waiting_for.is_some() || not_waiting {
+                let Some(task) = state.set_waiting(id, waiting_for).await else {
+                    fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id));
+                    return;
+                };
+                save_state(&state).await;
+                match &task.waiting {
+                    Some(waiting) => say!("{}", t!("edit-waiting", title = task.title, on = waiting.on)),
+                    None => say!("{}", t!("edit-not-waiting", title = task.title)),
+                }
+            }
+            if estimate.is_some() || no_estimate {
+                let Some(task) = state.set_estimate(id, estimate).await else {
+                    fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id));
+                    return;
+                };
+                save_state(&state).await;
+                match task.estimate_minutes {
+                    Some(minutes) => say!("{}", t!("edit-estimated", title = task.title, length = capacity::format_length(chrono::Duration::minutes(minutes)))),
+                    None => say!("{}", t!("edit-no-estimate", title = task.title)),
+                }
+            }
+            if !meta.is_empty() {
+                let Some(task) = state.set_meta(id, meta).await else {
+                    fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id));
+                    return;
+                };
+                save_state(&state).await;
+                say!("{}", t!("task-updated", title = task.title));
+                if let Err(e) = update_on_calendar(&config, &task).await {
+                    fail!(exit::code_of(&*e), "{}", t!("error-event-update", error = exit::Chain(&*e)));
+                }
+            }
+            if assignee.is_some() || unassign {
+                let Some(task) = state.set_assignee(id, assignee.map(|name| Assignee::new(&config, name))).await else {
+                    fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id));
+                    return;
+                };
+                save_state(&state).await;
+                match &task.assignee {
+                    Some(assignee) => say!("{}", t!("edit-assigned", title = task.title, name = assignee.name)),
+                    None => say!("{}", t!("edit-unassigned", title = task.title)),
+                }
+                if let Err(e) = update_on_calendar(&config, &task).await {
+                    fail!(exit::code_of(&*e), "{}", t!("error-event-update", error = exit::Chain(&*e)));
+                }
+            }
+            if tz.is_some() || no_tz {
+                if let Some(Err(e)) = tz.as_deref().map(zone::parse) {
+                    fail!(exit::USAGE, "{}", t!("error-sentence", error = e));
+                    return;
+                }
+                let Some(task) = state.set_timezone(id, tz.map(|name| name.trim().to_string())).await else {
+                    fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id));
+                    return;
+                };
+                save_state(&state).await;
+                match &task.timezone {
+                    Some(name) => say!("{}", t!("edit-time-zone", title = task.title, name = name, start = task.format_time(task.start_time))),
+                    None => say!("{}", t!("edit-local-time", title = task.title, start = task.format_time(task.start_time))),
+                }
+                if let Err(e) = update_on_calendar(&config, &task).await {
+                    fail!(exit::code_of(&*e), "{}", t!("error-event-update", error = exit::Chain(&*e)));
+                }
+            }
+            let Some(local_only) = local_only else { return };
+            let Some(task) = state.set_local_only(id, local_only).await else {
+                fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id));
+                return;
+            };
+            if local_only {
+                if task.event_id.is_some() {
+                    match delete_from_calendar(&config, &task).await {
+                        Ok(()) => forget_event(&task),
+                        // Sync deletes the unlinked event once the calendar is reachable again
+                        Err(e) => fail!(exit::code_of(&*e), "{}", t!("error-event-remove", error = exit::Chain(&*e))),
+                    }
+                    state.unlink_event(id).await;
+                }
+                save_state(&state).await;
+                say!("{}", t!("edit-local-only", title = task.title));
+            } else {
+                save_state(&state).await;
+                say!("{}", t!("edit-synced", title = task.title));
+                if let Err(e) = push_to_calendar(&config, &state, id).await {
+                    fail!(exit::code_of(&*e), "{}", t!("error-event-add", error = exit::Chain(&*e)));
+                }
+            }
+        }
+        Commands::Show { id } => {
+            let Some(id) = given_or_picked(&state, id, true).await else { return };
+            let tasks = state.list_tasks().await;
+            let Some(task) = tasks.iter().find(|task| task.id == id) else {
+                fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id));
+                return;
+            };
+            println!("#{} '{}'", task.id, task.title);
+            if !task.details.is_empty() {
+                println!("  {}", t!("show-details", details = hyperlink::urls(&task.details)));
+            }
+            let status = match task.status {
+                TaskStatus::Todo => "todo",
+                TaskStatus::Done => "done",
+            };
+            println!("  {}", t!("show-status", status = status));
+            println!("  {}", t!("show-start-end", start = task.format_time(task.start_time), end = task.format_time(task.end_time)));
+            if let Some(timezone) = &task.timezone {
+                println!("  {}", t!("show-time-zone", timezone = timezone));
+            }
+            if let Some(text) = task.recurrence_text() {
+                println!("  {}", t!("show-recurring", text = text));
+            }
+            println!("  {}", t!("show-priority", priority = format!("{:?}", escalation::priority(&config.escalation, task, Utc::now()))));
+            println!("  {}", t!("show-project-tags", project = task.project.as_deref().unwrap_or("-"), tags = task.tags.join(", ")));
+            if let Some(assignee) = &task.assignee {
+                match &assignee.address {
+                    Some(address) if *address != assignee.name => println!("  {}", t!("show-assigned-address", name = assignee.name, address = address)),
+                    _ => println!("  {}", t!("show-assigned", name = assignee.name)),
+                }
+            }
+            if let Some(waiting) = &task.waiting {
+                println!("  {}", t!("show-waiting", on = waiting.on, time = format_time(Some(waiting.since))));
+            }
+            if !task.meta.is_empty() {
+                let meta: Vec<String> = task.meta.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+                println!("  {}", t!("show-meta", meta = meta.join(", ")));
+            }
+            if let Some(minutes) = task.estimate_minutes {
+                println!("  {}", t!("show-estimate", length = capacity::format_length(chrono::Duration::minutes(minutes))));
+            }
+            if let Some(percent) = deps::progress(&tasks, task.id) {
+                let from_subtasks = tasks.iter().any(|other| other.parent == Some(task.id));
+                println!("  {}", t!("show-progress", bar = deps::progress_bar(percent), from_subtasks = from_subtasks));
+            }
+            if !task.depends_on.is_empty() {
+                let ids: Vec<String> = task.depends_on.iter().map(|id| format!("#{}", id)).collect();
+                println!("  {}", t!("show-depends-on", ids = ids.join(", ")));
+            }
+            if let Some(parent) = task.parent {
+                println!("  {}", t!("show-subtask-of", parent = parent));
+            }
+            if let Some(url) = &task.event_link {
+                println!("  {}", t!("show-event", url = hyperlink::link(url, url)));
+            }
+            if let Some(issue) = &task.github_issue {
+                let url = issue.split_once('#').map(|(repo, number)| format!("https://github.com/{}/issues/{}", repo, number));
+                println!("  {}", t!("show-github-issue", url = url.map_or(issue.clone(), |url| hyperlink::link(&url, issue))));
+            }
+            if let Some(key) = &task.jira_issue {
+                let url = config.jira.as_ref().map(|jira| format!("{}/browse/{}", jira.url.trim_end_matches('/'), key));
+                println!("  {}", t!("show-jira-issue", url = url.map_or(key.clone(), |url| hyperlink::link(&url, key))));
+            }
+            let links = links::describe(task, &tasks);
+            if !links.is_empty() {
+                println!("  {}", t!("show-links"));
+                for link in links {
+                    println!("    {}", link);
+                }
+            }
+        }
+        Commands::Progress { id, percent } => {
+            if state.list_tasks().await.iter().any(|task| task.parent == Some(id)) {
+                fail!(exit::USAGE, "{}", t!("progress-from-subtasks", id = id));
+                return;
+            }
+            let Some(task) = state.set_progress(id, percent).await else {
+                fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id));
+                return;
+            };
+            save_state(&state).await;
+            say!("{}", t!("progress-set", title = task.title, percent = percent));
+            if let Some(parent) = task.parent
+                && let Some(parent_percent) = deps::progress(&state.list_tasks().await, parent)
+            {
+                say!("{}", t!("progress-parent", parent = parent, percent = parent_percent));
+            }
+        }
+        Commands::Link { id, other, relation, remove } => match state.link_tasks(id, other, relation, remove).await {
+            Ok(task) => {
+                save_state(&state).await;
+                if remove {
+                    say!("{}", t!("link-removed", title = task.title, other = other));
+                } else {
+                    say!("{}", t!("link-added", title = task.title, other = other));
+                }
+            }
+            Err(e) => fail!(link_failure(&e), "{}", t!("error-sentence", error = e)),
+        },
+        Commands::List { waiting: true, .. } => {
+            let mut tasks = state.find(&[Lookup::Status(TaskStatus::Todo)], |task| task.waiting.is_some()).await;
+            tasks.sort_by_key(|task| task.waiting.as_ref().map(|waiting| waiting.since));
+            if tasks.is_empty() {
+                println!("{}", t!("list-waiting-none"));
+            }
+            let now = Utc::now();
+            for task in tasks {
+                let Some(waiting) = &task.waiting else { continue };
+                println!(
+                    "{}",
+                    t!(
+                        "list-waiting-task",
+                        id = task.id,
+                        title = task.title,
+                        on = waiting.on,
+                        days = (now - waiting.since).num_days(),
+                        time = format_time(Some(waiting.since)),
+                        start = task.format_time(task.start_time)
+                    )
+                );
+            }
+        }
+        Commands::List { filter, all, waiting: false, assignee, sort, mut paging, relative } => {
+            let filter = match filter {
+                Some(filter) => Some(filter),
+                None if all => {
+                    paging.limit.get_or_insert(0);
+                    let page = state.query(&config, None, sort, paging).await;
+                    if let Err(e) = print_tasks(&config, &page, paging, relative)
+                        && e.kind() != std::io::ErrorKind::BrokenPipe
+                    {
+                        fail!(exit::ERROR, "{}", t!("error-writing-list", error = e));
+                    }
+                    return;
+                }
+                None => match &config.default_view {
+                    Some(name) => match config.views.get(name) {
+                        Some(filter) => Some(filter.clone()),
+                        None => {
+                            eprintln!("{}", t!("list-default-view-missing", name = name));
+                            None
+                        }
+                    },
+                    None => None,
+                },
+            };
+            list_filtered(&config, &state, filter.as_deref(), assignee.as_deref(), sort, paging, relative).await;
+        }
+        Commands::Context { name, command } => match (name, command) {
+            (Some(name), _) => {
+                let current = match name.as_str() {
+                    "none" => None,
+                    _ if !config.contexts.contains_key(&name) => {
+                        fail!(exit::NOT_FOUND, "{}", t!("context-not-found-hint", name = name));
+                        return;
+                    }
+                    _ => Some(name),
+                };
+                let state = context::ContextState { current };
+                match (state.save(&paths::data(context::CONTEXT_PATH)), &state.current) {
+                    (Err(e), _) => fail!(exit::ERROR, "{}", t!("error-saving", path = paths::data(context::CONTEXT_PATH), error = exit::Chain(&*e))),
+                    (Ok(()), Some(name)) => say!("{}", t!("context-in-use", name = name)),
+                    (Ok(()), None) => say!("{}", t!("context-none-in-use")),
+                }
+            }
+            (None, Some(ContextCommands::Define { name, filter, project, tags })) => {
+                if let Some(Err(e)) = filter.as_deref().map(filter::Filter::parse) {
+                    fail!(exit::USAGE, "{}", t!("error-invalid-filter", error = e));
+                    return;
+                }
+                if name == "none" {
+                    fail!(exit::USAGE, "{}", t!("context-none-reserved"));
+                    return;
+                }
+                let mut config = config;
+                config.contexts.insert(name.clone(), config::ContextConfig { filter, project, tags });
+                match config.save(&paths::config(config::CONFIG_PATH)) {
+                    Ok(()) => say!("{}", t!("context-defined", name = name)),
+                    Err(e) => fail!(exit::ERROR, "{}", t!("error-saving", path = paths::config(config::CONFIG_PATH), error = exit::Chain(&*e))),
+                }
+            }
+            (None, Some(ContextCommands::List)) => {
+                if config.contexts.is_empty() {
+                    println!("{}", t!("context-none-defined"));
+                }
+                let current = context::current(&config).map(|(name, _)| name);
+                for (name, context) in &config.contexts {
+                    println!(
+                        "{}",
+                        t!(
+                            "context-line",
+                            name = name,
+                            in_use = current.as_ref() == Some(name),
+                            filter = context.filter.as_deref().unwrap_or("-"),
+                            project = context.project.as_deref().unwrap_or("-"),
+                            tags = if context.tags.is_empty() { "-".to_string() } else { context.tags.join(", ") }
+                        )
+                    );
+                }
+            }
+            (None, Some(ContextCommands::Delete { name })) => {
+                let mut config = config;
+                if config.contexts.remove(&name).is_none() {
+                    fail!(exit::NOT_FOUND, "{}", t!("context-not-found", name = name));
+                    return;
+                }
+                match config.save(&paths::config(config::CONFIG_PATH)) {
+                    Ok(()) => say!("{}", t!("context-deleted", name = name)),
+                    Err(e) => fail!(exit::ERROR, "{}", t!("error-saving", path = paths::config(config::CONFIG_PATH), error = exit::Chain(&*e))),
+                }
+                if context::ContextState::load(&paths::data(context::CONTEXT_PATH)).is_ok_and(|state| state.current == Some(name))
+                    && let Err(e) = context::ContextState::default().save(&paths::data(context::CONTEXT_PATH))
+                {
+                    fail!(exit::ERROR, "{}", t!("error-saving", path = paths::data(context::CONTEXT_PATH), error = exit::Chain(&*e)));
+                }
+            }
+            (None, None) => match context::current(&config) {
+                Some((name, _)) => println!("{}", t!("context-in-use", name = name)),
+                None => println!("{}", t!("context-none-in-use")),
+            },
+        },
+        Commands::View { name, sort, paging, command } => match (name, command) {
+            (Some(name), _) => match config.views.get(&name) {
+                Some(filter) => list_filtered(&config, &state, Some(filter), None, sort, paging, false).await,
+                None => fail!(exit::NOT_FOUND, "{}", t!("view-not-found-hint", name = name)),
+            },
+            (None, Some(ViewCommands::Save { name, filter })) => {
+                if let Err(e) = filter::Filter::parse(&filter) {
+                    fail!(exit::USAGE, "{}", t!("error-invalid-filter", error = e));
+                    return;
+                }
+                let mut config = config;
+                config.views.insert(name.clone(), filter);
+                match config.save(&paths::config(config::CONFIG_PATH)) {
+                    Ok(()) => say!("{}", t!("view-saved", name = name)),
+                    Err(e) => fail!(exit::ERROR, "{}", t!("error-saving", path = paths::config(config::CONFIG_PATH), error = exit::Chain(&*e))),
+                }
+            }
+            (None, Some(ViewCommands::List)) => {
+                if config.views.is_empty() {
+                    println!("{}", t!("view-none-saved"));
+                }
+                for (name, filter) in &config.views {
+                    println!("{}", t!("view-line", name = name, default = config.default_view.as_ref() == Some(name), filter = filter));
+                }
+            }
+            (None, Some(ViewCommands::Delete { name })) => {
+                let mut config = config;
+                if config.views.remove(&name).is_none() {
+                    fail!(exit::NOT_FOUND, "{}", t!("view-not-found", name = name));
+                    return;
+                }
+                if config.default_view.as_ref() == Some(&name) {
+                    config.default_view = None;
+                }
+                match config.save(&paths::config(config::CONFIG_PATH)) {
+                    Ok(()) => say!("{}", t!("view-deleted", name = name)),
+                    Err(e) => fail!(exit::ERROR, "{}", t!("error-saving", path = paths::config(config::CONFIG_PATH), error = exit::Chain(&*e))),
+                }
+            }
+            (None, Some(ViewCommands::Default { name })) => {
+                if let Some(name) = name.as_ref().filter(|name| !config.views.contains_key(*name)) {
+                    fail!(exit::NOT_FOUND, "{}", t!("view-not-found", name = name));
+                    return;
+                }
+                let mut config = config;
+                config.default_view = name;
+                match (config.save(&paths::config(config::CONFIG_PATH)), &config.default_view) {
+                    (Err(e), _) => fail!(exit::ERROR, "{}", t!("error-saving", path = paths::config(config::CONFIG_PATH), error = exit::Chain(&*e))),
+                    (Ok(()), Some(name)) => say!("{}", t!("view-default-set", name = name)),
+                    (Ok(()), None) => say!("{}", t!("view-default-cleared")),
+                }
+            }
+            (None, None) => fail!(exit::USAGE, "{}", t!("view-none-named")),
+        },
+
+        Commands::Pause { id, truncate } => {
+            match state.get_task(id).await {
+                None => fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id)),
+                Some(task) if !task.is_recurring => println!("{}", t!("task-not-recurring", id = id)),
+                Some(Task { paused: Some(pause), .. }) => println!("{}", t!("pause-already", id = id, time = format_time(Some(pause.since)))),
+                Some(_) => {
+                    let Some(task) = state.pause_task(id, truncate).await else { return };
+                    save_state(&state).await;
+                    say!("{}", t!("pause-done", title = task.title, id = id));
+                    if truncate && let Err(e) = update_on_calendar(&config, &task).await {
+                        fail!(exit::code_of(&*e), "{}", t!("error-event-update", error = exit::Chain(&*e)));
+                    }
+                }
+            }
+        }
 
-    // wait until 5 mins before start time
-    if reminder_time_start > now {
-        if let Ok(duration) = reminder_time_start.signed_duration_since(now).to_std() {
-            //tokio::time::
-            sleep(duration).await;
-            println!("Reminder: '{}' starts in 5 minutes!", task.title);
-        }
-    }
-
-    //wait until 2 mins before end time
-    if reminder_time_end > now {
-        if let Ok(duration) = reminder_time_end.signed_duration_since(now).to_std() {
-            //tokio::time::
-            sleep(duration).await;
-            println!("Reminder: '{}' ends in 2 minutes!", task.title);
-        }
-    }
-
-    // clone the title field to reuse it after move
-    let task_title = task.title.clone(); // clone the title
-    // mark task as completed
-    println!("Task '{}' is complete", task_title);
-
-    // if the task is a recurring, schedule the next instance
-    if task.is_recurring {
-        if let Some(frequency) = task.frequency_minutes {
-            let next_task = Task {
-                id: 0,
-                title: task.title.clone(),
-                details: task.details.clone(),
-                start_time: task.start_time + chrono::Duration::minutes(frequency),
-                end_time: task.end_time + chrono::Duration::minutes(frequency),
-                is_recurring: true,
-                frequency_minutes: Some(frequency),
-            };
-
-            // Schedule the next task after the frequency duration
-            let delay_until_next_task = next_task.start_time - Utc::now();
-            if let Ok(duration) = delay_until_next_task.to_std() {
-                sleep(duration).await; // Wait until the next task's start time
-            }
-
-            // Add the next task to the state
-            let task_id = state.add_task(next_task.clone()).await;
-            println!("Next recurring task scheduled with ID: {}", task_id);
- 
-            // Spawn a task to schedule the next reminder
-            //tokio::spawn(schedule_reminders(next_task, state.clone()));
-            tokio::task::spawn_blocking(move || {
-                let rt = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
-                rt.block_on(async move {
-                    schedule_reminders(next_task, Arc::clone(&state)).await;
-                });
-            }); 
-        }
-    }
-}
-async fn authenticate() -> Result<CalendarHub<HyperClient>, Box<dyn std::error::Error>> {
-    // Define the connector for hyper
-    let rt = Runtime::new().expect("Failed to create Tokio runtime");
-
-    let https_connector = HttpsConnectorBuilder::new()
-        .with_native_roots()?
-        .https_or_http()
-        .enable_http1()
-        .enable_http2()
-        .build();
-    
-    let hyper_client = HyperClient::builder(rt.handle().clone()).build();
-        
-    // Set up the authenticator
-    let secret = yup_oauth2::read_application_secret("credentials.json")
-        .await
-        .expect("Failed to read credentials.json");
-    let auth = InstalledFlowAuthenticator::builder(secret, InstalledFlowReturnMethod::HTTPRedirect)
-        .persist_tokens_to_disk("token_store.json")
-        .build()
-        .await?;
-
-    //Create the CalendarHub
-    Ok(CalendarHub::new(hyper_client, auth))
-}
-
-async fn add_to_google_calendar(task: &Task) -> Result<(), Box<dyn std::error::Error>> {
-    // Authenticate with Google Calendar
-    let hub = authenticate().await?;
-
-    // Create a Google Calendar event
-    let event = Event {
-        summary: Some(task.title.clone()),
-        description: Some(task.details.clone()),
-        start: Some(google_calendar3::api::EventDateTime {
-            date_time: Some(task.start_time),
-            time_zone: Some("UTC".to_string()),
-            ..Default::default()
-        }),
-        end: Some(google_calendar3::api::EventDateTime {
-            date_time: Some(task.end_time),
-            time_zone: Some("UTC".to_string()),
-            ..Default::default()
-        }),
-        recurrence: task.is_recurring.then(|| {
-            vec![format!("RRULE:FREQ=MINUTELY;INTERVAL={}", task.frequency_minutes.unwrap())]
-        }),
-        ..Default::default()
-    };
+        Commands::Resume { id } => {
+            match state.get_task(id).await {
+                None => fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id)),
+                Some(task) if task.paused.is_none() => println!("{}", t!("resume-not-paused", id = id)),
+                Some(_) => {
+                    let Some(task) = state.resume_task(id).await else { return };
+                    save_state(&state).await;
+                    say!("{}", t!("resume-done", title = task.title, start = task.format_time(task.start_time)));
+                    if let Err(e) = update_on_calendar(&config, &task).await {
+                        fail!(exit::code_of(&*e), "{}", t!("error-event-update", error = exit::Chain(&*e)));
+                    }
+                }
+            }
+        }
 
-    // Attempt to insert the event into Google Calendar
-    let result = hub.events().insert(event, "primary").doit().await?;
-    match result {
-        Ok(_) => Ok(println!("Task successfully added to Google Calendar.")),
-        Err(e) => Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("Failed to add task to Google Calendar: {:?}", e),
-        ))),
-    }
-    
-}
-
-async fn sync_from_google_calendar(hub: &CalendarHub<HyperClient>, state: &AppState) -> Result<(), Box<dyn std::error::Error>> {
-    let result = hub.events().list("primary").max_results(10).doit().await?;
-    if let Some(items) = result.1.items {
-        for event in items {
-            if let (Some(summary), Some(start), Some(end)) = (
-                event.summary.as_ref(),
-                event.start.as_ref().and_then(|s| s.date_time.as_ref()),
-                event.end.as_ref().and_then(|e| e.date_time.as_ref()),
-            ) {
-                let start_time = DateTime::parse_from_rfc3339(start)?.with_timezone(&Utc);
-                let end_time = DateTime::parse_from_rfc3339(end)?.with_timezone(&Utc);
-                let task = Task {
-                    id: 0,
-                    title: summary,
-                    details: event.description.unwrap_or_default(),
-                    start_time,
-                    end_time,
-                    is_recurring: false,
-                    frequency_minutes: None,
-                };
-                    state.add_task(task).await;
+        Commands::Skip { id } => match state.get_task(id).await {
+            None => fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id)),
+            Some(task) if !task.is_recurring => println!("{}", t!("task-not-recurring", id = id)),
+            Some(_) => match state.record_occurrence(id, history::Outcome::Skipped).await {
+                Some((task, start_time)) => {
+                    save_state(&state).await;
+                    say!("{}", t!("skip-done", title = task.title, start = task.format_time(Some(start_time))));
+                }
+                None => println!("{}", t!("skip-no-occurrence", id = id)),
+            },
+        },
+
+        Commands::Stale { days, interactive } => {
+            let tasks = state.list_tasks().await;
+            let stale = stale::find(&tasks, days, Utc::now());
+            if stale.is_empty() {
+                println!("{}", t!("stale-none"));
+            } else if interactive {
+                stale::review(&config, &state, &stale).await;
+            } else {
+                for (task, reason) in &stale {
+                    println!("#{} '{}': {}", task.id, task.title, reason);
+                }
+                println!("{}", t!("stale-hint"));
             }
         }
-    }
-    println!("Tasks synchronized from Google Calendar.");
-    Ok(())
-}
 
-//Main Application ENtry
-#[tokio::main]
-async fn main() {
-    let cli = CLI::parse();
-    let state = Arc::new(AppState::default());
-    
-    
-    match cli.command{
-        Commands::Add {
-            title,
-            details,
-            start_time,
-            end_time,
-            recurring,
-            frequency_minutes,
-        } => {
-            let start_time = start_time.parse::<DateTime<Utc>>()
-            .expect("Invalid start time format. Use ISO 8601 format, e.g., '2024-12-31T15:00:06'");
-            let end_time = end_time.parse::<DateTime<Utc>>()
-            .expect("Invalid end time format. Use ISO 8601 format, e.g., '2024-12-31T15:00:06'");
+        Commands::History { id, days } => match state.get_task(id).await {
+            None => fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id)),
+            Some(task) if !task.is_recurring => println!("{}", t!("task-not-recurring", id = id)),
+            Some(task) => history::print(&task, days),
+        },
+
+        Commands::Remove { id } => {
+            let Some(id) = given_or_picked(&state, id, true).await else { return };
+            let Some(task) = state.get_task(id).await else {
+                fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id));
+                return;
+            };
+            let question = match task.event_id {
+                Some(_) => t!("task-remove-with-event-question", id = id, title = task.title),
+                None => t!("task-remove-question", id = id, title = task.title),
+            };
+            if !confirm::ask(&question) {
+                say!("{}", t!("task-kept", id = id));
+                return;
+            }
+            if let Some(removed_task) = remove_task(&config, &state, id).await {
+                say!("{}", t!("task-removed", id = removed_task.id, title = removed_task.title));
+            } else {
+                fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id));
+            }
+        }
+
+        Commands::Done { id } => {
+            let Some(id) = given_or_picked(&state, id, false).await else { return };
+            mark_done(&config, &state, id).await;
+        }
+
+        Commands::Rsvp { id, reply } => {
+            if let Some(task) = state.get_task(id).await {
+                match respond_to_invitation(&config, &task, reply).await {
+                    Ok(()) => match reply {
+                        Rsvp::Accept => say!("{}", t!("rsvp-accepted", title = task.title)),
+                        Rsvp::Tentative => say!("{}", t!("rsvp-tentative", title = task.title)),
+                        Rsvp::Decline => say!("{}", t!("rsvp-declined", title = task.title, id = id)),
+                    },
+                    Err(e) => fail!(exit::code_of(&*e), "{}", t!("rsvp-failed", error = e)),
+                }
+            } else {
+                fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id));
+            }
+        }
+
+        Commands::Sync { command: Some(SyncCommands::Status), .. } => {
+            print_sync_status(&config, &state.list_tasks().await);
+        }
 
+        Commands::Sync { stats: true, command: None, .. } => {
+            quota::print_stats();
+        }
 
-            // Validation for start and end times
-            if start_time <= Utc::now() {
-                eprintln!("Error: Start time must be in the future.");
+        Commands::Sync { git: true, command: None, .. } => {
+            let Some(git) = &config.git else {
+                fail!(exit::USAGE, "{}", t!("sync-git-not-configured", path = paths::config(config::CONFIG_PATH)));
                 return;
+            };
+            match gitstore::sync(git, &paths::data(STORE_PATH)) {
+                Ok(summary) => {
+                    println!(
+                        "{}",
+                        t!(
+                            "sync-git-done",
+                            received = summary.received,
+                            added = summary.added,
+                            updated = summary.updated,
+                            removed = summary.removed,
+                            pushed = summary.pushed
+                        )
+                    );
+                }
+                Err(e) => fail!(exit::code_of(&*e), "{}", t!("sync-git-failed", remote = git.remote, error = e)),
             }
+        }
 
-            if end_time <= start_time {
-                eprintln!("Error: End time must be after the start time.");
+        Commands::Sync { dry_run: true, command: None, .. } => {
+            sync_all(&config, &state, false, true, false).await;
+        }
+
+        Commands::Sync { interactive, command: None, .. } => {
+            refresh_feeds(&config).await;
+            sync_all(&config, &state, interactive, false, true).await;
+            save_state(&state).await;
+        }
+
+        Commands::Agenda { week, days, relative } => {
+            let today = Local::now().date_naive();
+            let (first, days) = if week { (week::first_day(today), 7) } else { (today, days) };
+            let calendars = SyncState::load(&paths::data(sync::SYNC_STATE_PATH)).unwrap_or_else(|e| {
+                eprintln!("{}", t!("error-reading", path = paths::data(sync::SYNC_STATE_PATH), error = format!("{:?}", e)));
+                SyncState::default()
+            });
+            let tasks = state.list_tasks().await;
+            let items = agenda_items(&tasks, &calendars, local_midnight(first), days);
+            for note in agenda::stale_calendars(&calendars, chrono::Duration::hours(1)) {
+                println!("{}", note);
+            }
+            if let Some(label) = week::label(first).filter(|_| week) {
+                println!("{}", label);
+            }
+            if items.is_empty() {
+                println!("{}", t!("agenda-empty", days = days));
+            }
+            let capacity = capacity::days(&tasks, &items, &config.work_hours, first, days);
+            let colors = match std::io::stdout().is_terminal() {
+                true => config.calendars.iter().filter_map(|calendar| Some((calendar.name.clone(), agenda::color_code(calendar.color.as_deref()?)?))).collect(),
+                false => BTreeMap::new(),
+            };
+            agenda::render(&items, &capacity, relative, &colors);
+        }
+
+        Commands::Undo { steps: count } | Commands::Redo { steps: count } => {
+            let redo = matches!(command, Commands::Redo { .. });
+            let entries = match audit::load(&paths::data(audit::AUDIT_PATH)) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    fail!(exit::ERROR, "{}", t!("error-reading", path = paths::data(audit::AUDIT_PATH), error = exit::Chain(&*e)));
+                    return;
+                }
+            };
+            let mut position = undo::UndoState::load(&paths::data(undo::UNDO_PATH)).unwrap_or_default();
+            let steps = undo::steps(&entries, &mut position);
+            let mut tasks = state.list_tasks().await;
+            let mut done = 0;
+            for _ in 0..count {
+                let Some(next) = position.next(steps.len(), redo) else { break };
+                let step = &steps[next];
+                if let Err(e) = undo::apply(&mut tasks, step, !redo) {
+                    fail!(exit::ERROR, "{}", t!("undo-failed", redo = redo, command = step[0].command, error = e));
+                    break;
+                }
+                say!(
+                    "{}",
+                    t!("undo-done", redo = redo, command = step[0].command, time = step[0].time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"))
+                );
+                position.step(redo);
+                done += 1;
+            }
+            if done == 0 {
+                println!("{}", t!("undo-nothing", redo = redo));
                 return;
             }
+            state.replace_tasks(tasks).await;
+            save_state(&state).await;
+            if let Err(e) = position.save(&paths::data(undo::UNDO_PATH)) {
+                fail!(exit::ERROR, "{}", t!("error-saving", path = paths::data(undo::UNDO_PATH), error = exit::Chain(&*e)));
+            }
+            say!("{}", t!("undo-sync-hint"));
+        }
 
-            //Add task
-            let task = Task {
-                id: 0,
-                title,
-                details,
-                start_time,
-                end_time,
-                is_recurring: recurring,
-                frequency_minutes,
+        Commands::Log { id, limit } => {
+            let tasks = state.list_tasks().await;
+            let uuid = match id {
+                Some(id) => match tasks.iter().find(|task| task.id == id) {
+                    Some(task) => Some(task.uuid.as_str()),
+                    None => {
+                        fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id));
+                        return;
+                    }
+                },
+                None => None,
             };
+            match audit::load(&paths::data(audit::AUDIT_PATH)) {
+                Ok(entries) => audit::print(&entries, uuid, &tasks, limit),
+                Err(e) => fail!(exit::ERROR, "{}", t!("error-reading", path = paths::data(audit::AUDIT_PATH), error = exit::Chain(&*e))),
+            }
+        }
+
+        Commands::Digest { days, assignee, send } => {
+            let tasks = state.list_tasks().await;
+            let digests = digest::build(&tasks, days, Utc::now());
+            let digests: Vec<&digest::Digest> = digests
+                .values()
+                .filter(|digest| {
+                    assignee.as_ref().is_none_or(|who| {
+                        digest.name.eq_ignore_ascii_case(who) || digest.address.as_ref().is_some_and(|address| address.eq_ignore_ascii_case(who))
+                    })
+                })
+                .collect();
+            if digests.is_empty() {
+                println!("{}", t!("digest-empty"));
+            }
+            for digest in digests {
+                if !send {
+                    println!("{}", digest.text());
+                    continue;
+                }
+                let Some(address) = &digest.address else {
+                    eprintln!("{}", t!("digest-no-address", name = digest.name, path = paths::config(config::CONFIG_PATH)));
+                    continue;
+                };
+                match digest::send(config.mail_from.as_deref(), address, &digest.subject(), &digest.text()) {
+                    Ok(()) => {
+                        let recipient = if *address == digest.name { address.clone() } else { format!("{} <{}>", digest.name, address) };
+                        say!("{}", t!("digest-sent", count = digest.count(), recipient = recipient));
+                    }
+                    Err(e) => fail!(exit::code_of(&*e), "{}", t!("digest-send-failed", address = address, error = e)),
+                }
+            }
+        }
+
+        Commands::Capacity { week, days } => {
+            let today = Local::now().date_naive();
+            let (first, days) = if week { (week::first_day(today), 7) } else { (today, days) };
+            let calendars = SyncState::load(&paths::data(sync::SYNC_STATE_PATH)).unwrap_or_else(|e| {
+                eprintln!("{}", t!("error-reading", path = paths::data(sync::SYNC_STATE_PATH), error = format!("{:?}", e)));
+                SyncState::default()
+            });
+            let tasks = state.list_tasks().await;
+            let items = agenda_items(&tasks, &calendars, local_midnight(first), days);
+            if let Some(label) = week::label(first).filter(|_| week) {
+                println!("{}", label);
+            }
+            capacity::print(&capacity::days(&tasks, &items, &config.work_hours, first, days), &tasks);
+        }
 
-            // Add the task to the state and get the task_id
-            let _task_id = state.add_task(task.clone()).await;
-            println!("Task '{}' added with ID: {}", task.title, task.id);
+        Commands::Block { day, dry_run } => {
+            let now = Utc::now();
+            let Some(date) = filter::parse_day(&day, Local::now().date_naive()) else {
+                fail!(exit::USAGE, "{}", t!("block-invalid-day", day = day));
+                return;
+            };
+            let calendars = SyncState::load(&paths::data(sync::SYNC_STATE_PATH)).unwrap_or_else(|e| {
+                eprintln!("{}", t!("error-reading", path = paths::data(sync::SYNC_STATE_PATH), error = format!("{:?}", e)));
+                SyncState::default()
+            });
+            let tasks = state.list_tasks().await;
+            let items = agenda_items(&tasks, &calendars, local_midnight(date), 1);
+            let plan = block::plan(&config, &tasks, &items, date, now);
+            let title = |id: u32| tasks.iter().find(|task| task.id == id).map_or(String::new(), |task| task.title.clone());
+            let length = |id: u32| capacity::format_length(chrono::Duration::minutes(tasks.iter().find(|task| task.id == id).and_then(|task| task.estimate_minutes).unwrap_or(0)));
+            for block in &plan.blocks {
+                let (start, end) = (block.start_time.with_timezone(&Local), block.end_time.with_timezone(&Local));
+                println!("{}-{}  #{} '{}' ({})", start.format("%H:%M"), end.format("%H:%M"), block.id, title(block.id), length(block.id));
+            }
+            for id in &plan.left_over {
+                println!("{}", t!("block-didnt-fit", id = id, title = title(*id), length = length(*id)));
+            }
+            if plan.blocks.is_empty() {
+                println!("{}", t!("block-nothing", date = date.format("%a %Y-%m-%d")));
+                return;
+            }
+            if dry_run {
+                return;
+            }
+            let mut ids = Vec::new();
+            for block in &plan.blocks {
+                let before = tasks.iter().find(|task| task.id == block.id);
+                // An undated task on Google Tasks moves to the calendar
+                if let Some(before) = before.filter(|task| task.event_id.is_some()) {
+                    match delete_from_calendar(&config, before).await {
+                        Ok(()) => {
+                            forget_event(before);
+                            state.unlink_event(before.id).await;
+                        }
+                        Err(e) => {
+                            fail!(exit::code_of(&*e), "{}", t!("block-move-failed", id = before.id, error = exit::Chain(&*e)));
+                            continue;
+                        }
+                    }
+                }
+                state.reschedule_task(block.id, block.start_time, block.end_time).await;
+                ids.push(block.id);
+            }
+            save_state(&state).await;
+            say!("{}", t!("block-done", count = ids.len(), date = date.format("%a %Y-%m-%d")));
+            match push_added(&config, &state, &ids).await {
+                Ok(0) => {}
+                Ok(pushed) => say!("{}", t!("block-pushed", count = pushed)),
+                Err(e) => fail!(exit::code_of(&*e), "{}", t!("block-push-failed", error = exit::Chain(&*e))),
+            }
+        }
 
-            if let Err(e) = add_to_google_calendar(&task).await {
-                eprintln!("Error adding task to Google Calendar: {:?}", e);
+        Commands::Replan { from, dry_run } => {
+            let from = match from.trim() {
+                "now" => Ok(Utc::now()),
+                text => when::parse(text),
+            };
+            let from = match from {
+                Ok(from) => from,
+                Err(e) => {
+                    fail!(exit::USAGE, "{}", t!("error", error = e));
+                    return;
+                }
+            };
+            let calendars = SyncState::load(&paths::data(sync::SYNC_STATE_PATH)).unwrap_or_else(|e| {
+                eprintln!("{}", t!("error-reading", path = paths::data(sync::SYNC_STATE_PATH), error = format!("{:?}", e)));
+                SyncState::default()
+            });
+            let tasks = state.list_tasks().await;
+            let date = from.with_timezone(&Local).date_naive();
+            let items = agenda_items(&tasks, &calendars, local_midnight(date), 1);
+            let plan = block::replan(&config, &tasks, &items, from);
+            let moved: Vec<(&Task, &block::Block)> = plan
+                .blocks
+                .iter()
+                .filter_map(|block| Some((tasks.iter().find(|task| task.id == block.id)?, block)))
+                .filter(|(task, block)| task.start_time != Some(block.start_time))
+                .collect();
+            let span = |start: DateTime<Utc>, end: DateTime<Utc>| format!("{}-{}", start.with_timezone(&Local).format("%H:%M"), end.with_timezone(&Local).format("%H:%M"));
+            for (task, block) in &moved {
+                let (Some(start), Some(end)) = (task.start_time, task.end_time) else { continue };
+                println!("{} -> {}  #{} '{}'", span(start, end), span(block.start_time, block.end_time), task.id, task.title);
+            }
+            for id in &plan.left_over {
+                let title = tasks.iter().find(|task| task.id == *id).map_or("", |task| task.title.as_str());
+                println!("{}", t!("replan-didnt-fit", id = id, title = title));
             }
+            if moved.is_empty() && plan.left_over.is_empty() {
+                println!("{}", t!("replan-nothing", date = date.format("%a %Y-%m-%d")));
+            }
+            if moved.is_empty() {
+                return;
+            }
+            if dry_run {
+                return;
+            }
+            let mut rescheduled = Vec::new();
+            for (_, block) in &moved {
+                if let Some(task) = state.reschedule_task(block.id, block.start_time, block.end_time).await {
+                    rescheduled.push(task);
+                }
+            }
+            save_state(&state).await;
+            say!("{}", t!("replan-done", count = rescheduled.len()));
+            for task in &rescheduled {
+                if let Err(e) = update_on_calendar(&config, task).await {
+                    fail!(exit::code_of(&*e), "{}", t!("replan-update-failed", id = task.id, error = exit::Chain(&*e)));
+                }
+            }
+        }
 
-            tokio::spawn(async move {
-                schedule_reminders(task, Arc::clone(&state)).await;
+        Commands::Heatmap { month, weeks } => {
+            let today = Local::now().date_naive();
+            let (first, days) = match month {
+                true => {
+                    let first = today.with_day(1).unwrap_or(today);
+                    (first, first.checked_add_months(chrono::Months::new(1)).map_or(31, |next| (next - first).num_days()))
+                }
+                false => (week::first_day(today), weeks * 7),
+            };
+            let calendars = SyncState::load(&paths::data(sync::SYNC_STATE_PATH)).unwrap_or_else(|e| {
+                eprintln!("{}", t!("error-reading", path = paths::data(sync::SYNC_STATE_PATH), error = format!("{:?}", e)));
+                SyncState::default()
             });
+            let tasks = state.list_tasks().await;
+            let items = agenda_items(&tasks, &calendars, local_midnight(first), days);
+            if month {
+                println!("{}", first.format("%B %Y"));
+            }
+            print!("{}", heatmap::render(&items, &config.work_hours, first, days));
+        }
+
+        Commands::In { text } => {
+            let title = text.join(" ").trim().to_string();
+            if title.is_empty() {
+                let tasks = state.list_tasks().await;
+                let items = inbox::items(&tasks);
+                if items.is_empty() {
+                    println!("{}", t!("inbox-empty"));
+                }
+                for task in items {
+                    println!("#{} '{}'", task.id, task.title);
+                }
+                return;
+            }
+            let mut task = Task { title, status: TaskStatus::Todo, inbox: true, ..Default::default() };
+            context::apply_defaults(&config, &mut task);
+            let id = state.add_task(task).await;
+            save_state(&state).await;
+            let id = state.saved_id(id).await;
+            say!("{}", t!("inbox-captured", id = id));
         }
-        Commands::List => {
+
+        Commands::Triage => {
             let tasks = state.list_tasks().await;
-            for task in tasks {
-                println!(
-                    "ID: {}, Title: '{}', Details: '{}', Start: {}, End: {}, Recurring: {}",
-                    task.id,
-                    task.title,
-                    task.details,
-                    task.start_time,
-                    task.end_time,
-                    if task.is_recurring {"Yes"} else {"No"}
-                );
+            let items = inbox::items(&tasks);
+            if items.is_empty() {
+                println!("{}", t!("inbox-empty"));
+                return;
             }
+            inbox::triage(&config, &state, &items).await;
         }
 
-        Commands::Remove { id } => {
-            if let Some(removed_task) = state.remove_task(id).await {
-                println!("Removed task: {:?}", removed_task);
+        Commands::Pick { filter, max_duration, tag } => {
+            let filter = match with_context(&config, filter.as_deref()) {
+                Ok(filter) => tag.iter().map(|tag| filter::Filter::tag(tag)).fold(filter, |all, tag| match all {
+                    Some(all) => Some(all.and(tag)),
+                    None => Some(tag),
+                }),
+                Err(e) => {
+                    fail!(exit::USAGE, "{}", t!("error-invalid-filter", error = e));
+                    return;
+                }
+            };
+            let tasks = state.list_tasks().await;
+            let actionable = pick::actionable(&tasks, filter.as_ref(), max_duration.map(chrono::Duration::minutes), Utc::now());
+            let Some(task) = pick::choose(&actionable) else {
+                fail!(exit::NOT_FOUND, "{}", t!("pick-nothing"));
+                return;
+            };
+            let length = pick::length(task).map_or(String::new(), |length| format!(" ({})", capacity::format_length(length)));
+            println!("#{} '{}'{}", task.id, task.title, length);
+            if !task.details.is_empty() {
+                println!("  {}", task.details);
+            }
+            say!("{}", t!("pick-done", count = actionable.len()));
+        }
+
+        Commands::Timeline { week, days } => {
+            let today = Local::now().date_naive();
+            let (first, days) = if week { (week::first_day(today), 7) } else { (today, days) };
+            let tasks = state.list_tasks().await;
+            let mut items = agenda::collect(&tasks, &FeedCache::default(), &SyncState::default(), local_midnight(first), local_midnight(first + chrono::Duration::days(days)));
+            items.retain(|item| item.task_id.is_some());
+            if let Some(label) = week::label(first).filter(|_| week) {
+                println!("{}", label);
+            }
+            print!("{}", timeline::render(&items, first, days));
+        }
+
+        Commands::Serve { ics, metrics, sync_minutes, addr } => {
+            let mut config = config;
+            let ics_token = match config.serve_token.clone() {
+                _ if !ics => None,
+                Some(token) => Some(token),
+                None => {
+                    let token = new_uuid();
+                    config.serve_token = Some(token.clone());
+                    if let Err(e) = config.save(&paths::config(config::CONFIG_PATH)) {
+                        fail!(exit::ERROR, "{}", t!("serve-token-unsaved", path = paths::config(config::CONFIG_PATH), error = exit::Chain(&*e)));
+                    }
+                    Some(token)
+                }
+            };
+            let options = serve::ServeOptions { addr, ics_token, metrics, sync_minutes };
+            if let Err(e) = serve::run(config, options).await {
+                fail!(exit::code_of(&*e), "{}", t!("serve-failed", error = exit::Chain(&*e)));
+            }
+        }
+
+        Commands::Doctor => {}
+
+        Commands::Remind { command } => {
+            let notifiers = match notify::Notifiers::new(&config) {
+                Ok(notifiers) => notifiers,
+                Err(e) => {
+                    fail!(exit::ERROR, "{}", t!("error-invalid-notifier", path = paths::config(config::CONFIG_PATH), error = e));
+                    return;
+                }
+            };
+            match command {
+                RemindCommands::Test { id } => {
+                    let task = match id {
+                        Some(id) => match state.get_task(id).await {
+                            Some(task) => task,
+                            None => {
+                                fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id));
+                                return;
+                            }
+                        },
+                        None => Task { title: t!("remind-test-title"), ..Default::default() },
+                    };
+                    let message = t!("remind-test");
+                    let results = notifiers.test(&task, id.is_none(), &message, &t!("remind-message", title = task.title, message = message)).await;
+                    if results.is_empty() {
+                        println!("{}", t!("remind-no-channel", id = task.id));
+                    }
+                    for (name, result) in results {
+                        match result {
+                            Ok(()) => println!("{}", t!("remind-sent", name = name)),
+                            Err(e) => fail!(exit::ERROR, "{}", t!("remind-send-failed", name = name, error = e)),
+                        }
+                    }
+                }
+                RemindCommands::List { hours } => {
+                    let now = Utc::now();
+                    let mut due: Vec<(serve::Due, Task)> = Vec::new();
+                    for task in state.list_tasks().await {
+                        for reminder in serve::due_reminders(&config, &notifiers, &task, now, now + chrono::Duration::hours(hours.max(0))) {
+                            due.push((reminder, task.clone()));
+                        }
+                    }
+                    due.sort_by_key(|(reminder, task)| (reminder.time, task.id));
+                    if due.is_empty() {
+                        println!("{}", t!("remind-none-upcoming", hours = hours));
+                    }
+                    for (reminder, task) in &due {
+                        let channels = notifiers.names(task, reminder.before_start);
+                        let to = if channels.is_empty() { t!("remind-upcoming-no-channel") } else { channels.join(", ") };
+                        println!(
+                            "{}",
+                            t!("remind-upcoming", time = format_time(Some(reminder.time)), id = task.id, title = task.title, message = reminder.message, channels = to)
+                        );
+                    }
+                    if !due.is_empty() && !std::path::Path::new(&paths::data(serve::SERVER_INFO_PATH)).exists() {
+                        say!("{}", t!("remind-server-down"));
+                    }
+                }
+            }
+        }
+
+        Commands::Maintenance { dry_run } => {
+            let mut tasks = state.list_tasks().await;
+            let mut repairs = maintenance::repair(&mut tasks);
+            let sync_path = paths::data(sync::SYNC_STATE_PATH);
+            let mut sync_state = match SyncState::load(&sync_path) {
+                Ok(sync_state) => sync_state,
+                Err(e) => {
+                    fail!(exit::ERROR, "{}", t!("error-reading", path = sync_path, error = exit::Chain(&*e)));
+                    return;
+                }
+            };
+            let sync_repairs = maintenance::repair_sync_state(&mut sync_state, &tasks);
+            let conflicts_repaired = !sync_repairs.is_empty();
+            repairs.extend(sync_repairs);
+            for repair in &repairs {
+                println!("{}", t!("maintenance-problem", id = repair.id, problem = repair.problem));
+            }
+            if !dry_run && !repairs.is_empty() {
+                state.replace_tasks(tasks).await;
+                save_state(&state).await;
+                if conflicts_repaired && let Err(e) = sync_state.save(&sync_path) {
+                    fail!(exit::ERROR, "{}", t!("error-saving", path = sync_path, error = exit::Chain(&*e)));
+                }
+            }
+            // After saving, so the repairs are in the journal before it's compacted
+            let journal = paths::data(crdt::JOURNAL_PATH);
+            let (before, after) = match crdt::compact(config.git.is_some(), dry_run) {
+                Ok(counts) => counts,
+                Err(e) => {
+                    fail!(exit::ERROR, "{}", t!("maintenance-compact-failed", path = journal, error = exit::Chain(&*e)));
+                    return;
+                }
+            };
+            match repairs.len() {
+                0 => println!("{}", t!("maintenance-nothing-broken")),
+                count => println!("{}", t!("maintenance-repaired", count = count, dry_run = dry_run)),
+            }
+            match before - after {
+                0 => println!("{}", t!("maintenance-journal-compact", count = before)),
+                dropped => println!("{}", t!("maintenance-journal-compacted", dropped = dropped, before = before, after = after, dry_run = dry_run)),
+            }
+        }
+
+        Commands::Calendars { command: None } => {
+            let Some(calendars) = google_calendars(&config).await else { return };
+            let current = config.calendar_id.as_deref().unwrap_or("primary");
+            let width = calendars.iter().map(|calendar| calendar.name.chars().count()).max().unwrap_or(0).max(4);
+            println!("  {:<width$}  {:<14}  {:<7}  ID", "Name", "Access", "Primary", width = width);
+            for calendar in &calendars {
+                let primary = if calendar.primary { "yes" } else { "" };
+                let mark = if calendar.is(current) { "*" } else { " " };
+                println!("{} {:<width$}  {:<14}  {:<7}  {}", mark, calendar.name, calendar.access_role, primary, calendar.id, width = width);
+            }
+        }
+        Commands::Calendars { command: Some(CalendarCommands::Use { name }) } => {
+            let Some(calendars) = google_calendars(&config).await else { return };
+            let matches: Vec<&google::CalendarInfo> = match calendars.iter().find(|calendar| calendar.id == name) {
+                Some(calendar) => vec![calendar],
+                None => calendars.iter().filter(|calendar| calendar.name.to_lowercase() == name.to_lowercase()).collect(),
+            };
+            let calendar = match matches.as_slice() {
+                [calendar] => *calendar,
+                [] => {
+                    fail!(exit::NOT_FOUND, "{}", t!("calendars-not-found", name = name));
+                    return;
+                }
+                _ => {
+                    fail!(exit::USAGE, "{}", t!("calendars-ambiguous", name = name));
+                    return;
+                }
+            };
+            if !calendar.writable() {
+                fail!(exit::USAGE, "{}", t!("calendars-read-only", name = calendar.name));
+                return;
+            }
+            if calendar.is(config.calendar_id.as_deref().unwrap_or("primary")) {
+                say!("{}", t!("calendars-already-in-use", name = calendar.name));
+                return;
+            }
+            let mut config = config;
+            switch_calendar(&mut config, &state, calendar).await;
+        }
+        Commands::Calendars { command: Some(CalendarCommands::Create { name }) } => {
+            let Some(calendars) = google_calendars(&config).await else { return };
+            if calendars.iter().any(|calendar| calendar.name.to_lowercase() == name.to_lowercase()) {
+                fail!(exit::USAGE, "{}", t!("calendars-exists", name = name));
+                return;
+            }
+            let created = match google::GoogleBackend::new(&config).await {
+                Ok(backend) => quota::call("Google Calendar", || backend.create_calendar(&name)).await,
+                Err(e) => Err(e),
+            };
+            match created {
+                Ok(calendar) => {
+                    say!("{}", t!("calendars-created", name = calendar.name));
+                    let mut config = config;
+                    switch_calendar(&mut config, &state, &calendar).await;
+                }
+                Err(e) => fail!(exit::code_of(&*e), "{}", t!("calendars-create-failed", error = exit::Chain(&*e))),
+            }
+        }
+        Commands::Profiles { command: ProfileCommands::List } => {
+            let names = match paths::profiles() {
+                Ok(names) => names,
+                Err(e) => {
+                    fail!(exit::ERROR, "{}", t!("profiles-list-failed", error = e));
+                    return;
+                }
+            };
+            let current = paths::profile();
+            let mut profiles = vec![(None, paths::main_data(STORE_PATH))];
+            profiles.extend(names.iter().map(|name| (Some(name.as_str()), paths::profile_data(name, STORE_PATH))));
+            for (name, store) in profiles {
+                let tasks = match AppState::read(&store) {
+                    Ok(state) => t!("profiles-tasks", count = state.tasks.lock().await.len()),
+                    Err(e) => t!("profiles-unreadable", path = store, error = e),
+                };
+                let in_use = name == current;
+                let name = name.map_or_else(|| t!("profiles-main"), str::to_string);
+                println!("{}", t!("profiles-line", name = name, in_use = in_use, tasks = tasks));
+            }
+        }
+        Commands::Profiles { command: ProfileCommands::Create { name } } => match paths::create_profile(&name) {
+            Ok(dir) => {
+                // The OAuth client is the app's, not a sign-in, so the profile can share it
+                let credentials = paths::config(google::CREDENTIALS_PATH);
+                if std::path::Path::new(&credentials).exists()
+                    && let Err(e) = std::fs::copy(&credentials, paths::profile_config(&name, google::CREDENTIALS_PATH))
+                {
+                    fail!(exit::ERROR, "{}", t!("profiles-copy-failed", path = credentials, error = e));
+                }
+                say!("{}", t!("profiles-created", name = name, dir = dir.display()));
+            }
+            Err(e) => fail!(exit::USAGE, "{}", t!("error", error = e)),
+        },
+        Commands::Profiles { command: ProfileCommands::Delete { name } } => {
+            if paths::profile() == Some(name.as_str()) {
+                fail!(exit::USAGE, "{}", t!("profiles-in-use", name = name));
+                return;
+            }
+            if std::path::Path::new(&paths::profile_data(&name, serve::SERVER_INFO_PATH)).exists() {
+                fail!(exit::ERROR, "{}", t!("profiles-server-running", name = name));
+                return;
+            }
+            let question = t!("profiles-remove-question", name = name);
+            if !confirm::ask(&question) {
+                say!("{}", t!("profiles-kept", name = name));
+                return;
+            }
+            match paths::delete_profile(&name) {
+                Ok(removed) => {
+                    for dir in removed {
+                        say!("{}", t!("profiles-removed", dir = dir.display()));
+                    }
+                }
+                Err(e) => fail!(exit::NOT_FOUND, "{}", t!("error", error = e)),
+            }
+        }
+
+        Commands::Daemon { install_systemd: true, sync_every, addr, .. } => match service::install_systemd(addr, sync_every) {
+            Ok(written) => {
+                for path in written {
+                    say!("{}", t!("daemon-wrote", path = path.display()));
+                }
+                let unit = service::named("todo_task");
+                say!("{}", t!("daemon-systemd-started", unit = unit));
+                if let Some(minutes) = sync_every {
+                    say!("{}", t!("daemon-systemd-sync-every", minutes = minutes.max(1)));
+                }
+            }
+            Err(e) => fail!(exit::code_of(&*e), "{}", t!("daemon-systemd-install-failed", error = e)),
+        },
+        Commands::Daemon { install_windows_service: true, sync_every, addr, .. } => match service::install_windows(addr, sync_every) {
+            Ok(installed) => {
+                say!("{}", t!("daemon-windows-installed", count = installed.len(), names = installed.join(", ")));
+                if let Some(minutes) = sync_every {
+                    say!("{}", t!("daemon-sync-every", minutes = minutes.max(1)));
+                }
+            }
+            Err(e) => fail!(exit::code_of(&*e), "{}", t!("daemon-windows-install-failed", error = e)),
+        },
+        Commands::Daemon { install_launchd: true, sync_every, addr, .. } => match service::install_launchd(addr, sync_every) {
+            Ok(written) => {
+                for path in written {
+                    say!("{}", t!("daemon-launchd-loaded", path = path.display()));
+                }
+                if let Some(minutes) = sync_every {
+                    say!("{}", t!("daemon-sync-every", minutes = minutes.max(1)));
+                }
+            }
+            Err(e) => fail!(exit::code_of(&*e), "{}", t!("daemon-launchd-install-failed", error = e)),
+        },
+        Commands::Daemon { .. } if cfg!(target_os = "macos") => match service::uninstall_launchd() {
+            Ok(removed) if removed.is_empty() => println!("{}", t!("daemon-launchd-none")),
+            Ok(removed) => {
+                for path in removed {
+                    say!("{}", t!("daemon-removed", path = path.display()));
+                }
+            }
+            Err(e) => fail!(exit::code_of(&*e), "{}", t!("daemon-launchd-uninstall-failed", error = e)),
+        },
+        Commands::Daemon { .. } if cfg!(windows) => match service::uninstall_windows() {
+            Ok(removed) if removed.is_empty() => println!("{}", t!("daemon-windows-none")),
+            Ok(removed) => say!("{}", t!("daemon-windows-uninstalled", count = removed.len(), names = removed.join(", "))),
+            Err(e) => fail!(exit::code_of(&*e), "{}", t!("daemon-windows-uninstall-failed", error = e)),
+        },
+        Commands::Daemon { .. } => match service::uninstall_systemd() {
+            Ok(removed) if removed.is_empty() => println!("{}", t!("daemon-systemd-none")),
+            Ok(removed) => {
+                for path in removed {
+                    say!("{}", t!("daemon-removed", path = path.display()));
+                }
+            }
+            Err(e) => fail!(exit::code_of(&*e), "{}", t!("daemon-systemd-uninstall-failed", error = e)),
+        },
+
+        Commands::Import { todoist, taskwarrior, org, trello } => {
+            if let Some(board) = trello {
+                match progress::spin(&t!("import-trello-importing"), trello::import(config.trello.as_ref(), &board, &state)).await {
+                    Ok(summary) => say!("{}", t!("import-trello-done", added = summary.added, updated = summary.updated, skipped = summary.skipped)),
+                    Err(e) => fail!(exit::code_of(&*e), "{}", t!("import-trello-failed", error = exit::Chain(&*e))),
+                }
+                save_state(&state).await;
+            } else if let Some(path) = org {
+                let text = match read_input(&path) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        fail!(exit::ERROR, "{}", t!("error-reading", path = path, error = exit::Chain(&e)));
+                        return;
+                    }
+                };
+                let summary = progress::spin(&t!("import-org-importing"), org::import(&text, &state)).await;
+                say!("{}", t!("import-org-done", added = summary.added, updated = summary.updated));
+                save_state(&state).await;
+            } else if let Some(path) = taskwarrior {
+                let json = match read_input(&path) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        fail!(exit::ERROR, "{}", t!("error-reading", path = path, error = exit::Chain(&e)));
+                        return;
+                    }
+                };
+                match progress::spin(&t!("import-taskwarrior-importing"), taskwarrior::import(&json, &state)).await {
+                    Ok(summary) => say!("{}", t!("import-taskwarrior-done", added = summary.added, updated = summary.updated, skipped = summary.skipped)),
+                    Err(e) => fail!(exit::code_of(&*e), "{}", t!("import-taskwarrior-failed", error = exit::Chain(&*e))),
+                }
+                save_state(&state).await;
+            } else if todoist {
+                let Some(todoist) = &config.todoist else {
+                    fail!(exit::USAGE, "{}", t!("todoist-not-configured"));
+                    return;
+                };
+                match progress::spin(&t!("import-todoist-importing"), todoist::import(todoist, &state)).await {
+                    Ok(summary) => say!("{}", t!("import-todoist-done", added = summary.added, updated = summary.updated)),
+                    Err(e) => fail!(exit::code_of(&*e), "{}", t!("import-todoist-failed", error = exit::Chain(&*e))),
+                }
+                save_state(&state).await;
+            }
+        }
+
+        Commands::Graph { dot, mermaid } => {
+            let mut tasks = state.list_tasks().await;
+            tasks.sort_by_key(|task| task.id);
+            let (path, contents) = match (dot, mermaid) {
+                (Some(path), _) => (path, deps::dot(&tasks)),
+                (None, Some(path)) => (path, deps::mermaid(&tasks)),
+                (None, None) => return,
+            };
+            if let Err(e) = write_output(&path, &contents) {
+                fail!(exit::ERROR, "{}", t!("error-writing", path = path, error = exit::Chain(&e)));
+            }
+        }
+
+        Commands::Burndown { project, days, svg } => {
+            let tasks = state.list_tasks().await;
+            if !tasks.iter().any(|task| task.project.as_deref() == Some(project.as_str()) && !task.is_recurring) {
+                println!("{}", t!("burndown-empty", project = project));
+                return;
+            }
+            let points = burndown::points(&tasks, &project, days, Local::now().date_naive());
+            match svg {
+                Some(path) => {
+                    if let Err(e) = write_output(&path, &burndown::svg(&points, &project)) {
+                        fail!(exit::ERROR, "{}", t!("error-writing", path = path, error = exit::Chain(&e)));
+                    }
+                }
+                None => print!("{}", burndown::ascii(&points)),
+            }
+        }
+
+        Commands::Export { taskwarrior, org } => {
+            let mut tasks = state.list_tasks().await;
+            tasks.sort_by_key(|task| task.id);
+            let (path, contents) = if let Some(path) = taskwarrior {
+                match taskwarrior::export(&tasks) {
+                    Ok(json) => (path, json),
+                    Err(e) => {
+                        fail!(exit::ERROR, "{}", t!("export-failed", error = exit::Chain(&e)));
+                        return;
+                    }
+                }
+            } else if let Some(path) = org {
+                (path, org::export(&tasks))
             } else {
-                println!("Task with ID {} not found.", id);
+                return;
+            };
+            if let Err(e) = write_output(&path, &contents) {
+                fail!(exit::ERROR, "{}", t!("error-writing", path = path, error = exit::Chain(&e)));
+            }
+        }
+
+        Commands::Github { command } => {
+            let Some(github) = &config.github else {
+                fail!(exit::USAGE, "{}", t!("github-not-configured"));
+                return;
+            };
+            match command {
+                GithubCommands::Link { id, issue } => {
+                    if github::parse_issue(&issue).is_none() {
+                        fail!(exit::USAGE, "{}", t!("github-invalid-issue", issue = issue));
+                        return;
+                    }
+                    match state.link_github_issue(id, issue.clone()).await {
+                        Some(task) => {
+                            save_state(&state).await;
+                            say!("{}", t!("github-linked", title = task.title, issue = issue));
+                        }
+                        None => fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id)),
+                    }
+                }
+                GithubCommands::Sync => {
+                    match progress::spin(&t!("github-syncing"), github::sync(github, &state)).await {
+                        Ok(summary) => say!(
+                            "{}",
+                            t!("github-synced", added = summary.added, updated = summary.updated, completed = summary.completed, closed = summary.closed)
+                        ),
+                        Err(e) => fail!(exit::code_of(&*e), "{}", t!("github-sync-failed", error = exit::Chain(&*e))),
+                    }
+                    save_state(&state).await;
+                }
+            }
+        }
+
+        Commands::Jira { command } => {
+            let Some(jira) = &config.jira else {
+                fail!(exit::USAGE, "{}", t!("jira-not-configured"));
+                return;
+            };
+            match command {
+                JiraCommands::Link { id, key } => match state.link_jira_issue(id, key.clone()).await {
+                    Some(task) => {
+                        save_state(&state).await;
+                        say!("{}", t!("jira-linked", title = task.title, key = key));
+                    }
+                    None => fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id)),
+                },
+                JiraCommands::Sync => {
+                    match progress::spin(&t!("jira-syncing"), jira::sync(jira, &state)).await {
+                        Ok(summary) => say!(
+                            "{}",
+                            t!("jira-synced", added = summary.added, updated = summary.updated, completed = summary.completed, closed = summary.closed)
+                        ),
+                        Err(e) => fail!(exit::code_of(&*e), "{}", t!("jira-sync-failed", error = exit::Chain(&*e))),
+                    }
+                    save_state(&state).await;
+                }
             }
         }
 
-        Commands::Sync => {
-            // Synchronize tasks with Google Calendar
-            let hub = authenticate().await.unwrap();
-            if let Err(e) = sync_from_google_calendar(&hub, &state).await {
-                eprintln!("Failed to sync tasks from Google Calendar: {:?}", e);
+        Commands::Menu { complete, snooze, add, minutes, input } => {
+            if !(complete || snooze || add) {
+                let filter = match context::filter(&config) {
+                    Ok(filter) => filter,
+                    Err(e) => {
+                        fail!(exit::USAGE, "{}", t!("error-invalid-filter", error = e));
+                        return;
+                    }
+                };
+                let mut lookups = vec![Lookup::Status(TaskStatus::Todo)];
+                lookups.extend(filter.iter().flat_map(filter::Filter::lookups));
+                let mut tasks = state.find(&lookups, |task| filter.as_ref().is_none_or(|filter| filter.matches(task))).await;
+                // Undated tasks come last
+                tasks.sort_by_key(|task| (task.start_time.is_none(), task.start_time));
+                for task in tasks {
+                    println!("{}", menu_line(&task));
+                }
+                return;
+            }
+
+            // Launchers pipe the selection in, so fall back to stdin
+            let input = match input {
+                Some(input) => input,
+                None => {
+                    let mut buffer = String::new();
+                    if let Err(e) = std::io::stdin().read_to_string(&mut buffer) {
+                        fail!(exit::ERROR, "{}", t!("menu-unreadable", error = exit::Chain(&e)));
+                        return;
+                    }
+                    buffer
+                }
+            };
+            if input.trim().is_empty() {
+                // Launcher was dismissed without a selection
+                return;
+            }
+
+            if add {
+                let task = match parse_quick_add(&input) {
+                    Ok(task) => task,
+                    Err(e) => {
+                        fail!(exit::USAGE, "{}", t!("error", error = e));
+                        return;
+                    }
+                };
+                add_quick(&config, &state, task).await;
+                return;
+            }
+
+            let Some(id) = parse_menu_selection(&input) else {
+                fail!(exit::NOT_FOUND, "{}", t!("menu-no-id", line = input.trim()));
+                return;
+            };
+            if complete {
+                mark_done(&config, &state, id).await;
+                return;
             }
-            
+            let updated = state.snooze_task(id, minutes).await;
+            save_state(&state).await;
+            match updated {
+                Some(task) => {
+                    say!("{}", t!("menu-snoozed", title = task.title, start = task.format_time(task.start_time)));
+                    publish_event(config.mqtt.as_ref(), "snoozed", &task, None).await;
+                    if let Err(e) = update_on_calendar(&config, &task).await {
+                        fail!(exit::code_of(&*e), "{}", t!("error-event-update", error = exit::Chain(&*e)));
+                    }
+                }
+                None => fail!(exit::NOT_FOUND, "{}", t!("task-not-found", id = id)),
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, month, day, hour, minute, 0).unwrap()
+    }
+
+    // A task in Berlin repeating every `minutes` from `start`, lasting half an hour
+    fn berlin_task(start: DateTime<Utc>, minutes: i64) -> Task {
+        Task {
+            start_time: Some(start),
+            end_time: Some(start + chrono::Duration::minutes(30)),
+            is_recurring: true,
+            frequency_minutes: Some(minutes),
+            timezone: Some("Europe/Berlin".to_string()),
+            ..Default::default()
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn daily_tasks_keep_their_clock_time_across_clock_changes() {
+        // 9:00 in Berlin is 8:00 UTC until the clocks go forward on 29 March,
+        // and 7:00 UTC after
+        let task = berlin_task(utc(3, 28, 8, 0), 24 * 60);
+        assert!(task.clock_rule().is_some());
+        assert_eq!(task.next_start(utc(3, 28, 8, 0)), Some(utc(3, 29, 7, 0)));
+        assert_eq!(task.next_start(utc(3, 29, 7, 0)), Some(utc(3, 30, 7, 0)));
+        // And back on 25 October
+        let task = berlin_task(utc(10, 24, 7, 0), 24 * 60);
+        assert_eq!(task.next_start(utc(10, 24, 7, 0)), Some(utc(10, 25, 8, 0)));
+    }
+
+    #[test]
+    fn occurrences_at_skipped_times_are_left_out() {
+        // 2:30 doesn't exist in Berlin on 29 March
+        let task = berlin_task(utc(3, 28, 1, 30), 24 * 60);
+        assert_eq!(task.next_start(utc(3, 28, 1, 30)), Some(utc(3, 30, 0, 30)));
+    }
+
+    #[test]
+    fn intervals_under_a_day_ignore_the_clock() {
+        let task = berlin_task(utc(3, 29, 0, 30), 90);
+        assert!(task.clock_rule().is_none());
+        assert_eq!(task.next_start(utc(3, 29, 0, 30)), Some(utc(3, 29, 2, 0)));
+    }
+
+    #[test]
+    fn task_files_take_a_zone() {
+        let entry = |timezone: &str| bulk::Entry { title: "Stand-up".to_string(), timezone: Some(timezone.to_string()), ..Default::default() };
+        assert_eq!(NewTask::from_entry(entry("Europe/Berlin")).ok().and_then(|new| new.timezone).as_deref(), Some("Europe/Berlin"));
+        assert!(NewTask::from_entry(entry("Europe/Nowhere")).is_err());
+    }
+
+    #[test]
+    fn series_repeat_by_the_clock_of_their_zone() {
+        // 9:00 in Berlin, the day before the clocks go forward
+        let task = berlin_task(utc(3, 28, 8, 0), 24 * 60);
+        assert_eq!(task.rrule().as_deref(), Some("FREQ=DAILY;INTERVAL=1"));
+        let event = ics::task_event(&task, "stand-up").unwrap();
+        let text = ics::write_calendar(&[event]);
+        assert!(text.contains("DTSTART;TZID=Europe/Berlin:20260328T090000"));
+        let read = &ics::parse_events(&text)[0];
+        assert_eq!(read.start, Some(utc(3, 28, 8, 0)));
+        assert_eq!(read.rrule.as_deref(), Some("FREQ=DAILY;INTERVAL=1"));
+    }
+
+    #[test]
+    fn weekdays_are_the_zones_own() {
+        // Monday 00:30 in Berlin is still Sunday in UTC, but the rule is by
+        // Berlin's days
+        let task = Task { recurrence: recurrence::Rule::parse("every monday at 00:30").ok(), ..berlin_task(utc(3, 29, 22, 30), 0) };
+        assert!(task.rrule().is_some_and(|rrule| rrule.contains("BYDAY=MO")));
+    }
+
+    #[tokio::test]
+    async fn older_stores_are_kept_when_loaded_but_not_when_read() {
+        let path = std::env::temp_dir().join(format!("todo-task-schema-{}.json", std::process::id())).to_string_lossy().into_owned();
+        let backup = format!("{}.v0", path);
+        let task = Task { id: 1, title: "Pack".to_string(), ..Default::default() };
+        let contents = serde_json::json!({"next_id": 2, "tasks": [task]}).to_string();
+        std::fs::write(&path, &contents).unwrap();
+        let state = AppState::read(&path).unwrap();
+        assert_eq!(state.list_tasks().await.len(), 1);
+        assert!(!std::path::Path::new(&backup).exists());
+        AppState::load(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), contents);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&backup);
+    }
+
+    #[test]
+    fn an_end_needs_a_start() {
+        let entry = |start: Option<&str>, end: Option<&str>| bulk::Entry {
+            title: "Dentist".to_string(),
+            start_time: start.map(str::to_string),
+            end_time: end.map(str::to_string),
+            ..Default::default()
+        };
+        let refused = |entry| new_task(&Config::default(), NewTask::from_entry(entry).unwrap()).err();
+        assert_eq!(refused(entry(None, Some("2099-01-01T10:00:00Z"))), Some((exit::USAGE, t!("task-start-missing"))));
+        assert_eq!(refused(entry(Some("2099-01-01T09:00:00Z"), None)), Some((exit::USAGE, t!("task-end-missing"))));
+        assert_eq!(refused(entry(Some("2099-01-01T10:00:00Z"), Some("2099-01-01T09:00:00Z"))), Some((exit::USAGE, t!("task-end-before-start"))));
+    }
+}
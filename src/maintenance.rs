@@ -0,0 +1,153 @@
+// `maintenance`: references between tasks and to calendar events checked and
+// repaired, so hand edits, merges and crashes don't leave tasks pointing at
+// ones that are gone
+use std::collections::{HashMap, HashSet};
+
+use crate::i18n::t;
+use crate::sync::SyncState;
+use crate::Task;
+
+// A problem found, and what was (or would be) done about it
+pub struct Repair {
+    pub id: u32,
+    pub problem: String,
+}
+
+impl Repair {
+    fn new(task: &Task, problem: String) -> Repair {
+        Repair { id: task.id, problem }
+    }
+}
+
+// Repair references in `tasks`: dependencies, parents and links to tasks that
+// don't exist or to the task itself, loops of subtasks or dependencies, events
+// linked to several tasks (the lowest ID keeps it) and missing or repeated UUIDs
+pub fn repair(tasks: &mut [Task]) -> Vec<Repair> {
+    let ids: HashSet<u32> = tasks.iter().map(|task| task.id).collect();
+    let mut repairs = Vec::new();
+    tasks.sort_by_key(|task| task.id);
+    for task in tasks.iter_mut() {
+        let (id, before) = (task.id, task.depends_on.clone());
+        task.depends_on.retain(|dependency| *dependency != id && ids.contains(dependency));
+        let mut seen = HashSet::new();
+        task.depends_on.retain(|dependency| seen.insert(*dependency));
+        for dependency in before.iter().filter(|dependency| !task.depends_on.contains(dependency)) {
+            repairs.push(Repair::new(task, match *dependency == id {
+                true => t!("maintenance-depends-on-itself"),
+                false if ids.contains(dependency) => t!("maintenance-dependency-twice", dependency = dependency),
+                false => t!("maintenance-dependency-missing", dependency = dependency),
+            }));
+        }
+        if let Some(parent) = task.parent.filter(|parent| *parent == id || !ids.contains(parent)) {
+            task.parent = None;
+            repairs.push(Repair::new(task, match parent == id {
+                true => t!("maintenance-own-subtask"),
+                false => t!("maintenance-parent-missing", parent = parent),
+            }));
+        }
+        let before = task.links.clone();
+        task.links.retain(|link| link.id != id && ids.contains(&link.id));
+        for link in before.iter().filter(|link| !task.links.contains(link)) {
+            let problem = if link.id == id { t!("maintenance-linked-to-itself") } else { t!("maintenance-link-missing", other = link.id) };
+            repairs.push(Repair::new(task, problem));
+        }
+    }
+
+    // Subtasks going round in a loop: the loop is cut at its highest ID
+    let parents: HashMap<u32, u32> = tasks.iter().filter_map(|task| Some((task.id, task.parent?))).collect();
+    let mut cut = HashSet::new();
+    for &start in parents.keys() {
+        let mut path = vec![start];
+        let mut current = start;
+        while let Some(&parent) = parents.get(&current) {
+            if cut.contains(&current) || cut.contains(&parent) {
+                break;
+            }
+            if let Some(position) = path.iter().position(|id| *id == parent) {
+                cut.insert(*path[position..].iter().max().unwrap_or(&parent));
+                break;
+            }
+            path.push(parent);
+            current = parent;
+        }
+    }
+    for task in tasks.iter_mut().filter(|task| cut.contains(&task.id)) {
+        repairs.push(Repair::new(task, t!("maintenance-subtask-loop", parent = task.parent.unwrap_or_default())));
+        task.parent = None;
+    }
+
+    let graph: HashMap<u32, Vec<u32>> = tasks.iter().map(|task| (task.id, task.depends_on.clone())).collect();
+    let mut visited = HashMap::new();
+    let mut closing = Vec::new();
+    for task in tasks.iter() {
+        closing_dependencies(&graph, task.id, &mut visited, &mut closing);
+    }
+    for (id, dependency) in closing {
+        if let Some(task) = tasks.iter_mut().find(|task| task.id == id) {
+            task.depends_on.retain(|other| *other != dependency);
+            repairs.push(Repair::new(task, t!("maintenance-dependency-loop", dependency = dependency)));
+        }
+    }
+
+    let mut events: HashMap<String, u32> = HashMap::new();
+    let mut uuids = HashSet::new();
+    for task in tasks.iter_mut() {
+        if let Some(event_id) = &task.event_id {
+            match events.get(event_id) {
+                Some(owner) => {
+                    repairs.push(Repair::new(task, t!("maintenance-shared-event", owner = owner)));
+                    task.event_id = None;
+                    task.event_etag = None;
+                    task.event_updated = None;
+                    task.event_link = None;
+                }
+                None => {
+                    events.insert(event_id.clone(), task.id);
+                }
+            }
+        }
+        if task.uuid.is_empty() || !uuids.insert(task.uuid.clone()) {
+            let problem = if task.uuid.is_empty() { t!("maintenance-uuid-missing") } else { t!("maintenance-uuid-shared") };
+            repairs.push(Repair::new(task, problem));
+            task.uuid = crate::new_uuid();
+            uuids.insert(task.uuid.clone());
+        }
+    }
+    repairs
+}
+
+// Dependencies closing a loop, found depth first from `id`; `visited` is false
+// while a task's dependencies are still being followed
+fn closing_dependencies(graph: &HashMap<u32, Vec<u32>>, id: u32, visited: &mut HashMap<u32, bool>, closing: &mut Vec<(u32, u32)>) {
+    if visited.contains_key(&id) {
+        return;
+    }
+    visited.insert(id, false);
+    for &dependency in graph.get(&id).into_iter().flatten() {
+        match visited.get(&dependency) {
+            Some(false) => closing.push((id, dependency)),
+            Some(true) => {}
+            None => closing_dependencies(graph, dependency, visited, closing),
+        }
+    }
+    visited.insert(id, true);
+}
+
+// Drop sync conflicts of tasks that are gone or no longer linked to the
+// conflicting event
+pub fn repair_sync_state(sync_state: &mut SyncState, tasks: &[Task]) -> Vec<Repair> {
+    let events: HashMap<u32, Option<&str>> = tasks.iter().map(|task| (task.id, task.event_id.as_deref())).collect();
+    let mut repairs = Vec::new();
+    for (name, service) in sync_state.services.iter_mut() {
+        service.conflicts.retain(|conflict| {
+            let problem = match events.get(&conflict.task_id) {
+                None => t!("maintenance-conflict-task-missing", service = name),
+                Some(event_id) if *event_id != Some(conflict.event_id.as_str()) => t!("maintenance-conflict-unlinked", service = name),
+                Some(_) => return true,
+            };
+            repairs.push(Repair { id: conflict.task_id, problem });
+            false
+        });
+    }
+    repairs
+}
@@ -0,0 +1,90 @@
+// Prometheus metrics for `serve`, in the text exposition format
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::{history, quota};
+use crate::{Task, TaskStatus};
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    reminders_fired: AtomicU64,
+    sync_runs: AtomicU64,
+    /// Failed syncs keyed by service name
+    sync_errors: Mutex<BTreeMap<String, u64>>,
+    last_sync_seconds: Mutex<Option<f64>>,
+}
+
+impl Metrics {
+    pub fn reminder_fired(&self) {
+        self.reminders_fired.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn sync_finished(&self, duration: Duration, failed: &[&str]) {
+        self.sync_runs.fetch_add(1, Ordering::Relaxed);
+        *self.last_sync_seconds.lock().unwrap() = Some(duration.as_secs_f64());
+        let mut errors = self.sync_errors.lock().unwrap();
+        for service in failed {
+            *errors.entry(service.to_string()).or_default() += 1;
+        }
+    }
+
+    // Counters plus gauges computed from the current task list
+    pub fn render(&self, tasks: &[Task]) -> String {
+        let mut out = String::new();
+        let count = |status: TaskStatus| tasks.iter().filter(|task| task.status == status).count();
+        // Open dated tasks that haven't reached a calendar yet
+        let pending = tasks
+            .iter()
+            .filter(|task| task.status == TaskStatus::Todo && task.event_id.is_none() && !task.local_only && task.start_time.is_some())
+            .count();
+
+        let _ = writeln!(out, "# HELP todo_tasks Tasks in the store by status.");
+        let _ = writeln!(out, "# TYPE todo_tasks gauge");
+        let _ = writeln!(out, "todo_tasks{{status=\"todo\"}} {}", count(TaskStatus::Todo));
+        let _ = writeln!(out, "todo_tasks{{status=\"done\"}} {}", count(TaskStatus::Done));
+        let _ = writeln!(out, "# HELP todo_sync_pending_tasks Open dated tasks not yet pushed to the calendar.");
+        let _ = writeln!(out, "# TYPE todo_sync_pending_tasks gauge");
+        let _ = writeln!(out, "todo_sync_pending_tasks {}", pending);
+        // Outcomes of the last 30 days' occurrences of open recurring tasks
+        let now = Utc::now();
+        let (mut completed, mut skipped, mut missed) = (0, 0, 0);
+        for task in tasks.iter().filter(|task| task.status == TaskStatus::Todo && task.is_recurring) {
+            let (task_completed, task_skipped, task_missed) = history::counts(&history::entries(task, now - chrono::Duration::days(30), now));
+            completed += task_completed;
+            skipped += task_skipped;
+            missed += task_missed;
+        }
+        let _ = writeln!(out, "# HELP todo_recurring_occurrences Occurrences of recurring tasks in the last 30 days by outcome.");
+        let _ = writeln!(out, "# TYPE todo_recurring_occurrences gauge");
+        let _ = writeln!(out, "todo_recurring_occurrences{{outcome=\"completed\"}} {}", completed);
+        let _ = writeln!(out, "todo_recurring_occurrences{{outcome=\"skipped\"}} {}", skipped);
+        let _ = writeln!(out, "todo_recurring_occurrences{{outcome=\"missed\"}} {}", missed);
+        let _ = writeln!(out, "# HELP todo_reminders_fired_total Reminders fired since the server started.");
+        let _ = writeln!(out, "# TYPE todo_reminders_fired_total counter");
+        let _ = writeln!(out, "todo_reminders_fired_total {}", self.reminders_fired.load(Ordering::Relaxed));
+        let _ = writeln!(out, "# HELP todo_sync_runs_total Background sync runs since the server started.");
+        let _ = writeln!(out, "# TYPE todo_sync_runs_total counter");
+        let _ = writeln!(out, "todo_sync_runs_total {}", self.sync_runs.load(Ordering::Relaxed));
+        let _ = writeln!(out, "# HELP todo_sync_errors_total Failed syncs by service.");
+        let _ = writeln!(out, "# TYPE todo_sync_errors_total counter");
+        for (service, errors) in self.sync_errors.lock().unwrap().iter() {
+            let _ = writeln!(out, "todo_sync_errors_total{{service=\"{}\"}} {}", service, errors);
+        }
+        let _ = writeln!(out, "# HELP todo_api_errors_total Failed calendar API requests by service and HTTP status, or network, auth or other.");
+        let _ = writeln!(out, "# TYPE todo_api_errors_total counter");
+        for ((service, kind), errors) in quota::failures() {
+            let _ = writeln!(out, "todo_api_errors_total{{service=\"{}\",kind=\"{}\"}} {}", service, kind, errors);
+        }
+        if let Some(seconds) = *self.last_sync_seconds.lock().unwrap() {
+            let _ = writeln!(out, "# HELP todo_sync_duration_seconds Duration of the last background sync.");
+            let _ = writeln!(out, "# TYPE todo_sync_duration_seconds gauge");
+            let _ = writeln!(out, "todo_sync_duration_seconds {}", seconds);
+        }
+        out
+    }
+}
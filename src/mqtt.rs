@@ -0,0 +1,193 @@
+// Publish task events to an MQTT broker (MQTT 3.1.1, QoS 0)
+use std::time::Duration;
+
+use serde_json::json;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::config::MqttConfig;
+use crate::i18n::t;
+use crate::Task;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// Remaining length uses a base-128 varint
+fn push_length(packet: &mut Vec<u8>, mut length: usize) {
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+}
+
+// Strings are prefixed with their length in two bytes; `field` names the
+// setting a string too long for that came from
+fn push_string(body: &mut Vec<u8>, field: &str, value: &[u8]) -> Result<(), String> {
+    let length = u16::try_from(value.len()).map_err(|_| t!("mqtt-too-long", field = field, length = value.len()))?;
+    body.extend_from_slice(&length.to_be_bytes());
+    body.extend_from_slice(value);
+    Ok(())
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    push_length(&mut packet, body.len());
+    packet.extend_from_slice(body);
+    packet
+}
+
+fn connect_packet(config: &MqttConfig) -> Result<Vec<u8>, String> {
+    let mut flags = 0x02; // Clean session
+    if config.username.is_some() {
+        flags |= 0x80;
+    }
+    if config.password.is_some() {
+        flags |= 0x40;
+    }
+    let mut body = Vec::new();
+    push_string(&mut body, "protocol", b"MQTT")?;
+    body.push(4); // Protocol level 3.1.1
+    body.push(flags);
+    body.extend_from_slice(&60u16.to_be_bytes()); // Keep-alive seconds
+    push_string(&mut body, "client_id", config.client_id.as_bytes())?;
+    if let Some(username) = &config.username {
+        push_string(&mut body, "username", username.as_bytes())?;
+    }
+    if let Some(password) = &config.password {
+        push_string(&mut body, "password", password.as_bytes())?;
+    }
+    Ok(packet(0x10, &body))
+}
+
+fn publish_packet(topic: &str, payload: &[u8]) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    push_string(&mut body, "topic", topic.as_bytes())?;
+    body.extend_from_slice(payload);
+    Ok(packet(0x30, &body))
+}
+
+// Read a packet's fixed header and body: the header byte, then the body's
+// length as a varint of at most four bytes
+async fn read_packet<R: AsyncRead + Unpin>(stream: &mut R) -> Result<(u8, Vec<u8>), Box<dyn std::error::Error>> {
+    let header = stream.read_u8().await?;
+    let mut length = 0;
+    for shift in (0..4).map(|byte| byte * 7) {
+        let byte = stream.read_u8().await?;
+        length |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            let mut body = vec![0; length];
+            stream.read_exact(&mut body).await?;
+            return Ok((header, body));
+        }
+    }
+    Err(t!("mqtt-not-acknowledged").into())
+}
+
+// Check the broker's CONNACK: a 0x20 packet whose second byte is the return code
+async fn read_connack<R: AsyncRead + Unpin>(stream: &mut R) -> Result<(), Box<dyn std::error::Error>> {
+    match read_packet(stream).await? {
+        (0x20, body) if body.len() == 2 => match body[1] {
+            0 => Ok(()),
+            code => Err(t!("mqtt-refused", code = code).into()),
+        },
+        _ => Err(t!("mqtt-not-acknowledged").into()),
+    }
+}
+
+async fn send(config: &MqttConfig, topic: &str, payload: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let (connect, publish) = (connect_packet(config)?, publish_packet(topic, payload)?);
+    let mut stream = TcpStream::connect((config.host.as_str(), config.port)).await?;
+    stream.write_all(&connect).await?;
+    read_connack(&mut stream).await?;
+    stream.write_all(&publish).await?;
+    stream.write_all(&[0xE0, 0x00]).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+// Publish `event` ("added", "completed", "reminder", ...) for a task on <topic_prefix>/<event>
+pub async fn publish(config: &MqttConfig, event: &str, task: &Task, message: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let topic = format!("{}/{}", config.topic_prefix.trim_end_matches('/'), event);
+    let payload = json!({
+        "event": event,
+        "id": task.id,
+        "uuid": task.uuid,
+        "title": task.title,
+        "priority": task.priority,
+        "project": task.project,
+        "tags": task.tags,
+        "start_time": task.start_time,
+        "end_time": task.end_time,
+        "message": message,
+    });
+    tokio::time::timeout(TIMEOUT, send(config, &topic, payload.to_string().as_bytes()))
+        .await
+        .map_err(|_| t!("mqtt-timed-out"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn length(length: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        push_length(&mut bytes, length);
+        bytes
+    }
+
+    fn config(username: Option<&str>, password: Option<&str>) -> MqttConfig {
+        MqttConfig {
+            host: "localhost".to_string(),
+            port: 1883,
+            username: username.map(str::to_string),
+            password: password.map(str::to_string),
+            client_id: "todo".to_string(),
+            topic_prefix: "todo".to_string(),
+        }
+    }
+
+    #[test]
+    fn lengths_are_varints() {
+        assert_eq!(length(0), [0x00]);
+        assert_eq!(length(127), [0x7F]);
+        assert_eq!(length(128), [0x80, 0x01]);
+        assert_eq!(length(16383), [0xFF, 0x7F]);
+        assert_eq!(length(16384), [0x80, 0x80, 0x01]);
+    }
+
+    #[test]
+    fn connect_flags_follow_the_credentials() {
+        // Header, length, "MQTT", level, then the flags
+        let anonymous = connect_packet(&config(None, None)).unwrap();
+        assert_eq!(anonymous[..9], [0x10, 16, 0, 4, b'M', b'Q', b'T', b'T', 4]);
+        assert_eq!(anonymous[9], 0x02);
+        assert_eq!(anonymous[12..], [0, 4, b't', b'o', b'd', b'o']);
+        let signed_in = connect_packet(&config(Some("ann"), Some("pw"))).unwrap();
+        assert_eq!(signed_in[9], 0xC2);
+        assert_eq!(signed_in[18..], [0, 3, b'a', b'n', b'n', 0, 2, b'p', b'w']);
+        assert_eq!(connect_packet(&config(Some("ann"), None)).unwrap()[9], 0x82);
+    }
+
+    #[test]
+    fn overlong_strings_are_refused() {
+        let long = "x".repeat(u16::MAX as usize + 1);
+        assert!(publish_packet(&long, b"{}").is_err());
+        assert!(publish_packet(&long[1..], b"{}").is_ok());
+        assert!(connect_packet(&config(None, Some(&long))).is_err());
+    }
+
+    #[tokio::test]
+    async fn connacks_are_read_by_their_length() {
+        assert!(read_connack(&mut &[0x20, 0x02, 0x00, 0x00][..]).await.is_ok());
+        assert!(read_connack(&mut &[0x20, 0x02, 0x00, 0x05][..]).await.is_err_and(|e| e.to_string().contains('5')));
+        assert!(read_connack(&mut &[0x20, 0x03, 0x00, 0x00, 0x00][..]).await.is_err());
+        assert!(read_connack(&mut &[0x20, 0x02, 0x00][..]).await.is_err());
+        assert!(read_connack(&mut &[0x30, 0x02, 0x00, 0x00][..]).await.is_err());
+    }
+}
@@ -0,0 +1,356 @@
+// Channels reminders go out on. Each kind of channel is a `Notifier`, built from
+// its entry in the "notifiers" section of config.json; a reminder goes to the
+// channels of the first of the "notification_routes" its task's tags or
+// project take, or else to each channel whose filter matches the task. A type that isn't built in is
+// handed to the program "todo_task-notify-<type>" on the PATH, so new channels
+// need no change here: it reads the reminder as JSON on stdin.
+use std::process::Stdio;
+
+use hyper::{Method, Request};
+use serde_json::{Map, Value, json};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::config::{Config, NotifierConfig};
+use crate::filter::Filter;
+use crate::http::{self, HttpClient};
+use crate::i18n::t;
+use crate::{Task, desktop, digest};
+
+// A reminder about to go out
+pub struct Reminder<'a> {
+    pub task: &'a Task,
+    /// What's due, e.g. "starts in 5 minutes"
+    pub message: &'a str,
+    /// The whole reminder as one line of text
+    pub line: &'a str,
+}
+
+#[async_trait::async_trait]
+pub trait Notifier: Send + Sync {
+    // The channel in error messages, e.g. "Slack"
+    fn name(&self) -> String;
+    async fn notify(&self, reminder: &Reminder<'_>) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+type Build = fn(&NotifierConfig, &Config) -> Result<Box<dyn Notifier>, String>;
+
+// Channels built in, by type; a new one is a `Notifier` and an entry here
+const BUILT_IN: [(&str, Build); 5] = [
+    ("stdout", |_, _| Ok(Box::new(Stdout))),
+    ("desktop", |_, _| Ok(Box::new(Desktop))),
+    ("email", Email::build),
+    ("slack", Slack::build),
+    ("webhook", Webhook::build),
+];
+
+// A setting of a channel that must be there, as text
+fn required(notifier: &NotifierConfig, name: &str) -> Result<String, String> {
+    match notifier.settings.get(name).and_then(Value::as_str).filter(|value| !value.is_empty()) {
+        Some(value) => Ok(value.to_string()),
+        None => Err(format!("the {} notifier needs \"{}\"", notifier.kind, name)),
+    }
+}
+
+// Printed on stdout, as the server's log
+struct Stdout;
+
+#[async_trait::async_trait]
+impl Notifier for Stdout {
+    fn name(&self) -> String {
+        "stdout".to_string()
+    }
+
+    async fn notify(&self, reminder: &Reminder<'_>) -> Result<(), Box<dyn std::error::Error>> {
+        println!("{}", reminder.line);
+        Ok(())
+    }
+}
+
+// Shown by the desktop's notifications, on Windows and macOS
+struct Desktop;
+
+#[async_trait::async_trait]
+impl Notifier for Desktop {
+    fn name(&self) -> String {
+        "desktop".to_string()
+    }
+
+    async fn notify(&self, reminder: &Reminder<'_>) -> Result<(), Box<dyn std::error::Error>> {
+        desktop::show(&reminder.task.title, reminder.message).await;
+        Ok(())
+    }
+}
+
+// Mailed through sendmail, as `digest --send` does
+struct Email {
+    to: String,
+    from: Option<String>,
+}
+
+impl Email {
+    fn build(notifier: &NotifierConfig, config: &Config) -> Result<Box<dyn Notifier>, String> {
+        Ok(Box::new(Email { to: required(notifier, "to")?, from: config.mail_from.clone() }))
+    }
+}
+
+#[async_trait::async_trait]
+impl Notifier for Email {
+    fn name(&self) -> String {
+        format!("email to {}", self.to)
+    }
+
+    async fn notify(&self, reminder: &Reminder<'_>) -> Result<(), Box<dyn std::error::Error>> {
+        let subject = format!("Reminder: {} {}", reminder.task.title, reminder.message);
+        let body = format!("{}\n\n{}\n", reminder.line, reminder.task.details);
+        digest::send(self.from.as_deref(), &self.to, &subject, &body)
+    }
+}
+
+// Posted to a Slack incoming webhook
+struct Slack {
+    client: HttpClient,
+    webhook_url: String,
+}
+
+impl Slack {
+    fn build(notifier: &NotifierConfig, _: &Config) -> Result<Box<dyn Notifier>, String> {
+        let client = http::client().map_err(|e| e.to_string())?;
+        Ok(Box::new(Slack { client, webhook_url: required(notifier, "webhook_url")? }))
+    }
+}
+
+#[async_trait::async_trait]
+impl Notifier for Slack {
+    fn name(&self) -> String {
+        "Slack".to_string()
+    }
+
+    async fn notify(&self, reminder: &Reminder<'_>) -> Result<(), Box<dyn std::error::Error>> {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(&self.webhook_url)
+            .header("Content-Type", "application/json")
+            .body(json!({ "text": reminder.line }).to_string())?;
+        let (status, body) = http::send(&self.client, request).await?;
+        http::check(status, &body, "Slack webhook")
+    }
+}
+
+// The reminder and its task as JSON, for webhooks and plugins
+fn payload(reminder: &Reminder<'_>) -> Value {
+    json!({ "event": "reminder", "message": reminder.message, "text": reminder.line, "task": reminder.task })
+}
+
+// POSTed as JSON to any URL, with extra headers if given, e.g. for a token
+struct Webhook {
+    client: HttpClient,
+    url: String,
+    headers: Map<String, Value>,
+}
+
+impl Webhook {
+    fn build(notifier: &NotifierConfig, _: &Config) -> Result<Box<dyn Notifier>, String> {
+        let headers = match notifier.settings.get("headers") {
+            None => Map::new(),
+            Some(Value::Object(headers)) if headers.values().all(Value::is_string) => headers.clone(),
+            Some(_) => return Err("the webhook notifier's \"headers\" must map names to text".to_string()),
+        };
+        let client = http::client().map_err(|e| e.to_string())?;
+        Ok(Box::new(Webhook { client, url: required(notifier, "url")?, headers }))
+    }
+}
+
+#[async_trait::async_trait]
+impl Notifier for Webhook {
+    fn name(&self) -> String {
+        format!("webhook {}", self.url)
+    }
+
+    async fn notify(&self, reminder: &Reminder<'_>) -> Result<(), Box<dyn std::error::Error>> {
+        let mut request = Request::builder().method(Method::POST).uri(&self.url).header("Content-Type", "application/json");
+        for (name, value) in &self.headers {
+            request = request.header(name, value.as_str().unwrap_or_default());
+        }
+        let (status, body) = http::send(&self.client, request.body(payload(reminder).to_string())?).await?;
+        http::check(status, &body, &format!("Webhook {}", self.url))
+    }
+}
+
+// A program found on the PATH, given the channel's settings, the reminder and
+// its task as one JSON object on stdin
+struct Plugin {
+    program: String,
+    settings: Map<String, Value>,
+}
+
+#[async_trait::async_trait]
+impl Notifier for Plugin {
+    fn name(&self) -> String {
+        self.program.clone()
+    }
+
+    async fn notify(&self, reminder: &Reminder<'_>) -> Result<(), Box<dyn std::error::Error>> {
+        let mut input = payload(reminder);
+        input["settings"] = Value::Object(self.settings.clone());
+        let mut child = Command::new(&self.program)
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("can't run {} ({}); is it on the PATH?", self.program, e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input.to_string().as_bytes()).await?;
+        }
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            return Err(format!("{} exited with {}: {}", self.program, output.status, String::from_utf8_lossy(&output.stderr).trim()).into());
+        }
+        Ok(())
+    }
+}
+
+// The program a channel that isn't built in runs
+fn plugin_program(kind: &str) -> String {
+    format!("todo_task-notify-{}", kind)
+}
+
+// Programs the configured channels run, for `doctor` to look for
+pub fn plugins(config: &Config) -> Vec<String> {
+    config.notifiers.iter().filter(|notifier| !BUILT_IN.iter().any(|(kind, _)| *kind == notifier.kind)).map(|notifier| plugin_program(&notifier.kind)).collect()
+}
+
+// A channel, and the tasks whose reminders it takes
+struct Channel {
+    /// What routes call it: its "name", or else its type
+    name: String,
+    filter: Option<Filter>,
+    notifier: Box<dyn Notifier>,
+}
+
+// Where the reminders of tasks with some tags or in some projects go
+struct Route {
+    /// Lowercase, without a leading '#'
+    tags: Vec<String>,
+    projects: Vec<String>,
+    /// Channels by index, with the minutes before the start each reminds at;
+    /// None for the task's own reminders
+    to: Vec<(usize, Option<Vec<i64>>)>,
+}
+
+impl Route {
+    fn takes(&self, task: &Task) -> bool {
+        task.tags.iter().any(|tag| self.tags.contains(&tag.trim_start_matches('#').to_lowercase()))
+            || task.project.as_ref().is_some_and(|project| self.projects.iter().any(|other| other.eq_ignore_ascii_case(project)))
+    }
+}
+
+pub struct Notifiers {
+    channels: Vec<Channel>,
+    routes: Vec<Route>,
+}
+
+impl Notifiers {
+    // The channels and routes in config.json; stdout and the desktop when no
+    // channels are configured
+    pub fn new(config: &Config) -> Result<Notifiers, String> {
+        let mut channels = Vec::new();
+        if config.notifiers.is_empty() {
+            channels.push(Channel { name: "stdout".to_string(), filter: None, notifier: Box::new(Stdout) });
+            channels.push(Channel { name: "desktop".to_string(), filter: None, notifier: Box::new(Desktop) });
+        }
+        for notifier in &config.notifiers {
+            let name = notifier.name.clone().unwrap_or_else(|| notifier.kind.clone());
+            if channels.iter().any(|channel: &Channel| channel.name == name) {
+                return Err(format!("two notifiers are called \"{}\"; give one a different \"name\"", name));
+            }
+            let filter = match &notifier.filter {
+                Some(filter) => Some(Filter::parse(filter).map_err(|e| format!("the {} notifier's filter: {}", name, e))?),
+                None => None,
+            };
+            let notifier = match BUILT_IN.iter().find(|(kind, _)| *kind == notifier.kind) {
+                Some((_, build)) => build(notifier, config)?,
+                None => Box::new(Plugin { program: plugin_program(&notifier.kind), settings: notifier.settings.clone() }),
+            };
+            channels.push(Channel { name, filter, notifier });
+        }
+        let mut routes = Vec::new();
+        for route in &config.notification_routes {
+            let mut to = Vec::new();
+            for target in &route.to {
+                let Some(index) = channels.iter().position(|channel| channel.name == target.notifier) else {
+                    return Err(format!("a route goes to \"{}\", but no notifier is called that", target.notifier));
+                };
+                if target.remind.as_ref().is_some_and(|remind| remind.iter().any(|minutes| *minutes < 0)) {
+                    return Err(format!("the route to \"{}\" reminds a negative number of minutes before the start", target.notifier));
+                }
+                to.push((index, target.remind.clone()));
+            }
+            let tags = route.tags.iter().map(|tag| tag.trim_start_matches('#').to_lowercase()).collect();
+            routes.push(Route { tags, projects: route.projects.clone(), to });
+        }
+        Ok(Notifiers { channels, routes })
+    }
+
+    // The channels reminding of `task`, each with the minutes before the start
+    // it reminds at: those of the first route the task takes, or else every
+    // channel whose filter matches, at the task's own reminders
+    fn channels(&self, task: &Task) -> Vec<(&Channel, Vec<i64>)> {
+        match self.routes.iter().find(|route| route.takes(task)) {
+            Some(route) => route.to.iter().map(|(index, remind)| (&self.channels[*index], remind.clone().unwrap_or_else(|| task.reminder_offsets()))).collect(),
+            None => self
+                .channels
+                .iter()
+                .filter(|channel| channel.filter.as_ref().is_none_or(|filter| filter.matches(task)))
+                .map(|channel| (channel, task.reminder_offsets()))
+                .collect(),
+        }
+    }
+
+    // Minutes before the start `task` is reminded at on any channel, latest
+    // reminder last
+    pub fn offsets(&self, task: &Task) -> Vec<i64> {
+        let mut offsets: Vec<i64> = self.channels(task).into_iter().flat_map(|(_, offsets)| offsets).collect();
+        offsets.sort_by(|a, b| b.cmp(a));
+        offsets.dedup();
+        offsets
+    }
+
+    // The channels a reminder goes to: every channel that takes the task, or
+    // for the reminder `before_start` minutes before the start, those
+    // reminding then
+    fn targets(&self, task: &Task, before_start: Option<i64>) -> Vec<&Channel> {
+        self.channels(task)
+            .into_iter()
+            .filter(|(_, offsets)| before_start.is_none_or(|minutes| offsets.contains(&minutes)))
+            .map(|(channel, _)| channel)
+            .collect()
+    }
+
+    // Names of the channels a reminder goes to, for `remind list`
+    pub fn names(&self, task: &Task, before_start: Option<i64>) -> Vec<&str> {
+        self.targets(task, before_start).into_iter().map(|channel| channel.name.as_str()).collect()
+    }
+
+    // Send a reminder to the channels it goes to; one failing doesn't stop the
+    // others
+    pub async fn send(&self, task: &Task, before_start: Option<i64>, message: &str, line: &str) {
+        let reminder = Reminder { task, message, line };
+        for channel in self.targets(task, before_start) {
+            if let Err(e) = channel.notifier.notify(&reminder).await {
+                eprintln!("{}", t!("notify-failed", notifier = channel.notifier.name(), error = e));
+            }
+        }
+    }
+
+    // Send a reminder to the channels of `task`, or with `all` to every
+    // channel, returning how each went, for `remind test`
+    pub async fn test(&self, task: &Task, all: bool, message: &str, line: &str) -> Vec<(&str, Result<(), String>)> {
+        let reminder = Reminder { task, message, line };
+        let channels = if all { self.channels.iter().collect() } else { self.targets(task, None) };
+        let mut results = Vec::new();
+        for channel in channels {
+            results.push((channel.name.as_str(), channel.notifier.notify(&reminder).await.map_err(|e| e.to_string())));
+        }
+        results
+    }
+}
@@ -0,0 +1,270 @@
+// Emacs org-mode export and import
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+
+use crate::{AppState, Priority, Task, TaskStatus};
+
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub updated: usize,
+}
+
+// Org repeaters only go down to hours
+fn repeater(minutes: i64) -> Option<String> {
+    if minutes % (7 * 24 * 60) == 0 {
+        Some(format!(" +{}w", minutes / (7 * 24 * 60)))
+    } else if minutes % (24 * 60) == 0 {
+        Some(format!(" +{}d", minutes / (24 * 60)))
+    } else if minutes % 60 == 0 {
+        Some(format!(" +{}h", minutes / 60))
+    } else {
+        None
+    }
+}
+
+// <2024-12-31 Tue 15:00-16:00 +1d>, in local time as org expects
+fn timestamp(task: &Task) -> Option<String> {
+    let (start_time, end_time) = task.schedule()?;
+    let start = start_time.with_timezone(&Local);
+    let end = end_time.with_timezone(&Local);
+    let mut stamp = start.format("<%Y-%m-%d %a %H:%M").to_string();
+    if end.date_naive() == start.date_naive() {
+        stamp.push_str(&end.format("-%H:%M").to_string());
+    }
+    if let Some(minutes) = task.frequency_minutes.filter(|_| task.is_recurring) {
+        stamp.push_str(&repeater(minutes).unwrap_or_default());
+    }
+    stamp.push('>');
+    Some(stamp)
+}
+
+pub fn export(tasks: &[Task]) -> String {
+    let mut out = String::new();
+    for task in tasks {
+        let keyword = match task.status {
+            TaskStatus::Todo => "TODO",
+            TaskStatus::Done => "DONE",
+        };
+        let cookie = match task.priority {
+            Priority::Urgent | Priority::High => " [#A]",
+            Priority::Low => " [#C]",
+            Priority::Normal => "",
+        };
+        out.push_str(&format!("* {}{} {}", keyword, cookie, task.title));
+        if !task.tags.is_empty() {
+            out.push_str(&format!(" :{}:", task.tags.join(":")));
+        }
+        out.push('\n');
+        if let Some(stamp) = timestamp(task) {
+            out.push_str(&format!("  SCHEDULED: {}\n", stamp));
+        }
+        out.push_str("  :PROPERTIES:\n");
+        out.push_str(&format!("  :ID: {}\n", task.uuid));
+        if let Some(project) = &task.project {
+            out.push_str(&format!("  :CATEGORY: {}\n", project));
+        }
+        for (key, value) in &task.meta {
+            out.push_str(&format!("  :{}: {}\n", key, value));
+        }
+        out.push_str("  :END:\n");
+        for line in task.details.lines() {
+            out.push_str(&format!("  {}\n", line));
+        }
+    }
+    out
+}
+
+// Parse the "2024-12-31 Tue 15:00-16:00 +1d" inside an org timestamp
+fn parse_timestamp(stamp: &str) -> Option<(DateTime<Utc>, DateTime<Utc>, Option<i64>)> {
+    let inner = stamp.trim().trim_start_matches(['<', '[']).trim_end_matches(['>', ']']);
+    let mut parts = inner.split_whitespace();
+    let date = NaiveDate::parse_from_str(parts.next()?, "%Y-%m-%d").ok()?;
+    let mut start = NaiveTime::from_hms_opt(0, 0, 0)?;
+    let mut end = None;
+    let mut frequency = None;
+    for part in parts {
+        if let Some(repeat) = part.strip_prefix(['+', '.']).map(|rest| rest.trim_start_matches('+')) {
+            let (count, unit) = repeat.split_at(repeat.len().saturating_sub(1));
+            let count: i64 = count.parse().ok()?;
+            frequency = match unit {
+                "h" => Some(count * 60),
+                "d" => Some(count * 24 * 60),
+                "w" => Some(count * 7 * 24 * 60),
+                _ => None,
+            };
+        } else if part.contains(':') {
+            let (from, to) = part.split_once('-').map_or((part, None), |(from, to)| (from, Some(to)));
+            start = NaiveTime::parse_from_str(from, "%H:%M").ok()?;
+            end = to.and_then(|to| NaiveTime::parse_from_str(to, "%H:%M").ok());
+        }
+    }
+    let local = |time: NaiveTime| {
+        Local
+            .from_local_datetime(&date.and_time(time))
+            .earliest()
+            .map(|time| time.with_timezone(&Utc))
+    };
+    let start_time = local(start)?;
+    let end_time = match end {
+        Some(end) => local(end)?,
+        None => start_time + chrono::Duration::minutes(30),
+    };
+    Some((start_time, end_time, frequency))
+}
+
+// Split "TODO [#A] Title :tag:other:" into a task; plain headings aren't tasks
+fn parse_heading(heading: &str) -> Option<Task> {
+    let (keyword, rest) = heading.split_once(' ').unwrap_or((heading, ""));
+    let status = match keyword {
+        "TODO" => TaskStatus::Todo,
+        "DONE" => TaskStatus::Done,
+        _ => return None,
+    };
+    let mut rest = rest.trim();
+    let mut priority = Priority::Normal;
+    if let Some(after) = rest.strip_prefix("[#") {
+        priority = match after.chars().next() {
+            Some('A') => Priority::High,
+            Some('C') => Priority::Low,
+            _ => Priority::Normal,
+        };
+        rest = after.get(2..).unwrap_or("").trim();
+    }
+    let mut tags = Vec::new();
+    if let Some((title, tag_text)) = rest.rsplit_once(' ')
+        && tag_text.len() > 1
+        && tag_text.starts_with(':')
+        && tag_text.ends_with(':')
+    {
+        tags = tag_text.split(':').filter(|tag| !tag.is_empty()).map(str::to_string).collect();
+        rest = title.trim();
+    }
+    Some(Task {
+        title: rest.to_string(),
+        status,
+        priority,
+        tags,
+        ..Default::default()
+    })
+}
+
+pub fn parse(text: &str) -> Vec<Task> {
+    let mut tasks: Vec<Task> = Vec::new();
+    let mut current: Option<Task> = None;
+    let mut in_drawer = false;
+    let mut details: Vec<String> = Vec::new();
+    let mut finish = |task: Option<Task>, details: &mut Vec<String>| {
+        if let Some(mut task) = task {
+            task.details = details.join("\n").trim().to_string();
+            tasks.push(task);
+        }
+        details.clear();
+    };
+    for line in text.lines() {
+        if let Some(heading) = line.strip_prefix('*').map(|rest| rest.trim_start_matches('*'))
+            && heading.starts_with(' ')
+        {
+            finish(current.take(), &mut details);
+            current = parse_heading(heading.trim());
+            in_drawer = false;
+            continue;
+        }
+        let Some(task) = current.as_mut() else { continue };
+        let trimmed = line.trim();
+        if trimmed == ":PROPERTIES:" {
+            in_drawer = true;
+        } else if trimmed == ":END:" {
+            in_drawer = false;
+        } else if in_drawer {
+            if let Some(id) = trimmed.strip_prefix(":ID:") {
+                task.uuid = id.trim().to_string();
+            } else if let Some(category) = trimmed.strip_prefix(":CATEGORY:") {
+                task.project = Some(category.trim().to_string());
+            } else if let Some((key, value)) = trimmed.strip_prefix(':').and_then(|rest| rest.split_once(':')) {
+                task.meta.insert(key.to_string(), value.trim().to_string());
+            }
+        } else if trimmed.starts_with("SCHEDULED:") || trimmed.starts_with("DEADLINE:") {
+            // SCHEDULED wins over DEADLINE when a task has both
+            for (keyword, stamp) in [("SCHEDULED:", trimmed.find("SCHEDULED:")), ("DEADLINE:", trimmed.find("DEADLINE:"))] {
+                let Some(index) = stamp else { continue };
+                let rest = &trimmed[index + keyword.len()..];
+                let end = rest.find(['>', ']']).map_or(rest.len(), |end| end + 1);
+                if task.start_time.is_none()
+                    && let Some((start_time, end_time, frequency)) = parse_timestamp(&rest[..end])
+                {
+                    task.start_time = Some(start_time);
+                    task.end_time = Some(end_time);
+                    task.is_recurring = frequency.is_some();
+                    task.frequency_minutes = frequency;
+                }
+            }
+        } else {
+            details.push(trimmed.to_string());
+        }
+    }
+    finish(current.take(), &mut details);
+    tasks
+}
+
+pub async fn import(text: &str, state: &AppState) -> ImportSummary {
+    let mut summary = ImportSummary::default();
+    for task in parse(text) {
+        if task.uuid.is_empty() {
+            state.add_task(task).await;
+            summary.added += 1;
+        } else if state.upsert_uuid(task).await {
+            summary.updated += 1;
+        } else {
+            summary.added += 1;
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exported_tasks_read_back() {
+        let start = Local.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap().with_timezone(&Utc);
+        let task = Task {
+            uuid: "0d6c3f4e".to_string(),
+            title: "Water plants".to_string(),
+            details: "Balcony first".to_string(),
+            start_time: Some(start),
+            end_time: Some(start + chrono::Duration::minutes(45)),
+            is_recurring: true,
+            frequency_minutes: Some(7 * 24 * 60),
+            priority: Priority::High,
+            tags: vec!["home".to_string(), "garden".to_string()],
+            project: Some("House".to_string()),
+            meta: [("room".to_string(), "balcony".to_string())].into(),
+            ..Default::default()
+        };
+        let text = export(std::slice::from_ref(&task));
+        assert!(text.starts_with("* TODO [#A] Water plants :home:garden:\n  SCHEDULED: <2026-03-02 Mon 09:00-09:45 +1w>\n"));
+        let read = &parse(&text)[0];
+        assert_eq!((&read.uuid, &read.title, &read.details), (&task.uuid, &task.title, &task.details));
+        assert_eq!((read.start_time, read.end_time, read.frequency_minutes), (task.start_time, task.end_time, task.frequency_minutes));
+        assert_eq!((read.priority, &read.tags, &read.project, &read.meta), (task.priority, &task.tags, &task.project, &task.meta));
+    }
+
+    #[test]
+    fn only_todo_headings_are_tasks() {
+        let text = "* Notes\nSome text\n** DONE [#C] File taxes\n   DEADLINE: <2026-04-30 Thu>\n* TODO Call Ann :phone:\n";
+        let tasks = parse(text);
+        assert_eq!(tasks.len(), 2);
+        assert_eq!((tasks[0].title.as_str(), tasks[0].status, tasks[0].priority), ("File taxes", TaskStatus::Done, Priority::Low));
+        assert!(tasks[0].start_time.is_some());
+        assert_eq!((tasks[1].title.as_str(), tasks[1].tags.as_slice()), ("Call Ann", ["phone".to_string()].as_slice()));
+    }
+
+    #[test]
+    fn repeaters_only_go_down_to_hours() {
+        assert_eq!(repeater(14 * 24 * 60).as_deref(), Some(" +2w"));
+        assert_eq!(repeater(3 * 24 * 60).as_deref(), Some(" +3d"));
+        assert_eq!(repeater(120).as_deref(), Some(" +2h"));
+        assert_eq!(repeater(90), None);
+    }
+}
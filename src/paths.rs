@@ -0,0 +1,302 @@
+// Where files are kept: settings and credentials in the config folder, tasks
+// and their history in the data folder, and what can be fetched again in the
+// cache folder, in each platform's usual places. `--home` or TODO_HOME puts
+// all of them in one folder instead. A profile has folders of its own under
+// "profiles" in each.
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::i18n::t;
+
+// Folder name under the platform's folders
+const APP: &str = "todo_task";
+
+// Files that used to be kept in the current folder, by the folder they belong
+// in now; moved over on first run
+const CONFIG_FILES: [&str; 4] = ["config.json", "credentials.json", "token_store.json", "graph_token.json"];
+const DATA_FILES: [&str; 11] = [
+    "tasks.json",
+    "ops.jsonl",
+    "device.json",
+    "audit.jsonl",
+    "undo.json",
+    "context.json",
+    "sync_state.json",
+    "api_usage.json",
+    "reminders.json",
+    "serve.json",
+    // The Git sync's clone, a folder
+    "git_store",
+];
+const CACHE_FILES: [&str; 1] = ["feeds.json"];
+
+// Subfolder of each folder holding the profiles' folders
+const PROFILES: &str = "profiles";
+
+#[derive(Clone)]
+struct Folders {
+    config: PathBuf,
+    data: PathBuf,
+    cache: PathBuf,
+}
+
+impl Folders {
+    fn all(&self) -> [&PathBuf; 3] {
+        [&self.config, &self.data, &self.cache]
+    }
+
+    fn profile(&self, name: &str) -> Folders {
+        let [config, data, cache] = self.all().map(|dir| dir.join(PROFILES).join(name));
+        Folders { config, data, cache }
+    }
+}
+
+struct Dirs {
+    /// The folders of the profile in use, or the main ones
+    current: Folders,
+    main: Folders,
+    /// The folder given with `--home` or TODO_HOME, if any
+    home: Option<PathBuf>,
+    profile: Option<String>,
+}
+
+static DIRS: OnceLock<Dirs> = OnceLock::new();
+
+fn env_dir(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name).filter(|dir| !dir.is_empty()).map(PathBuf::from)
+}
+
+fn home_dir() -> PathBuf {
+    env_dir("HOME").or_else(|| env_dir("USERPROFILE")).unwrap_or_else(|| PathBuf::from("."))
+}
+
+// The platform's folders. The qualifier only shows on macOS, as in
+// "com.todo_task", the name launchd agents go by too.
+fn platform_dirs() -> Result<Folders, Box<dyn std::error::Error>> {
+    let dirs = directories::ProjectDirs::from("com", "", APP).ok_or("Can't find your home folder; set TODO_HOME to the folder to keep files in")?;
+    Ok(Folders { config: dirs.config_dir().to_path_buf(), data: dirs.data_dir().to_path_buf(), cache: dirs.cache_dir().to_path_buf() })
+}
+
+// Where versions before the `directories` crate kept files on macOS and
+// Windows, which differ from its folders; the same as its folders elsewhere
+fn old_platform_dirs() -> Option<Folders> {
+    if cfg!(windows) {
+        let roaming = env_dir("APPDATA").unwrap_or_else(|| home_dir().join("AppData").join("Roaming")).join(APP);
+        let local = env_dir("LOCALAPPDATA").unwrap_or_else(|| home_dir().join("AppData").join("Local")).join(APP);
+        Some(Folders { config: roaming.clone(), data: roaming, cache: local.join("cache") })
+    } else if cfg!(target_os = "macos") {
+        let support = home_dir().join("Library").join("Application Support").join(APP);
+        Some(Folders { config: support.clone(), data: support, cache: home_dir().join("Library").join("Caches").join(APP) })
+    } else {
+        None
+    }
+}
+
+// Letters, digits, '-' and '_', so a profile's name works in file and service names
+pub fn check_profile_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("'{}' can't be a profile name; use letters, digits, '-' and '_'", name));
+    }
+    Ok(())
+}
+
+// `name` made safe for file and keyring entry names, other characters than
+// letters, digits, '-' and '_' becoming '_'
+pub fn safe_name(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c.to_ascii_lowercase() } else { '_' }).collect()
+}
+
+// Decide on the folders, creating them, and move files left in the current
+// folder by earlier versions into them. Must run before any file is used.
+pub fn init(home: Option<PathBuf>, profile: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let home = home.or_else(|| env_dir("TODO_HOME")).map(std::path::absolute).transpose()?;
+    let main = match &home {
+        Some(home) => Folders { config: home.clone(), data: home.clone(), cache: home.clone() },
+        None => platform_dirs()?,
+    };
+    let profile = profile.or_else(|| std::env::var("TODO_PROFILE").ok()).filter(|name| !name.is_empty());
+    let current = match &profile {
+        Some(name) => {
+            check_profile_name(name)?;
+            let current = main.profile(name);
+            if !current.data.exists() {
+                return Err(format!("There is no profile named '{}'; create it with `todo_task profiles create {}`", name, name).into());
+            }
+            current
+        }
+        None => main.clone(),
+    };
+    for dir in current.all() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Can't create {}: {}", dir.display(), e))?;
+    }
+    let dirs = DIRS.get_or_init(|| Dirs { current, main, home, profile });
+    if dirs.home.is_none()
+        && let Some(old) = old_platform_dirs()
+    {
+        migrate_profiles(&old, &dirs.main)?;
+    }
+    if dirs.profile.is_some() {
+        return Ok(());
+    }
+    let current = std::env::current_dir()?;
+    migrate(&Folders { config: current.clone(), data: current.clone(), cache: current }, &dirs.current)
+}
+
+fn dirs() -> &'static Dirs {
+    DIRS.get().expect("paths::init runs first")
+}
+
+// Move the files of an old layout into the folders they belong in now: from
+// the current folder, or the platform folders of earlier versions. Files are
+// left where they are if `to` already has them, or it's the same folder.
+fn migrate(from: &Folders, to: &Folders) -> Result<(), Box<dyn std::error::Error>> {
+    let moves = CONFIG_FILES
+        .iter()
+        .map(|name| (name, &from.config, &to.config))
+        .chain(DATA_FILES.iter().map(|name| (name, &from.data, &to.data)))
+        .chain(CACHE_FILES.iter().map(|name| (name, &from.cache, &to.cache)));
+    for (name, from, to) in moves {
+        let (source, target) = (from.join(name), to.join(name));
+        if !source.exists() || target.exists() || same_dir(from, to) {
+            continue;
+        }
+        std::fs::create_dir_all(to)?;
+        std::fs::rename(&source, &target).map_err(|e| format!("Can't move {} to {}: {}; move it by hand", source.display(), target.display(), e))?;
+        eprintln!("{}", t!("paths-moved", source = source.display(), target = target.display()));
+    }
+    Ok(())
+}
+
+// Move the main folders' files and every profile's from `old` to `new`
+fn migrate_profiles(old: &Folders, new: &Folders) -> Result<(), Box<dyn std::error::Error>> {
+    if !old.data.exists() {
+        return Ok(());
+    }
+    migrate(old, new)?;
+    let Ok(entries) = std::fs::read_dir(old.data.join(PROFILES)) else { return Ok(()) };
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            migrate(&old.profile(&name), &new.profile(&name))?;
+        }
+    }
+    Ok(())
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn join(dir: &Path, name: &str) -> String {
+    dir.join(name).to_string_lossy().into_owned()
+}
+
+// A file in the config folder; absolute paths are kept as they are, so
+// settings can point anywhere
+pub fn config(name: &str) -> String {
+    join(&dirs().current.config, name)
+}
+
+// A file in the data folder
+pub fn data(name: &str) -> String {
+    join(&dirs().current.data, name)
+}
+
+// A file in the cache folder
+pub fn cache(name: &str) -> String {
+    join(&dirs().current.cache, name)
+}
+
+// A file in the main data folder, whichever profile is in use
+pub fn main_data(name: &str) -> String {
+    join(&dirs().main.data, name)
+}
+
+// The main config folder's file, when a profile is in use and builds on it
+pub fn main_config(name: &str) -> Option<String> {
+    dirs().profile.as_ref().map(|_| join(&dirs().main.config, name))
+}
+
+// The folder given with `--home` or TODO_HOME, for services to use the same
+pub fn home() -> Option<&'static Path> {
+    dirs().home.as_deref()
+}
+
+// The profile in use, if any
+pub fn profile() -> Option<&'static str> {
+    dirs().profile.as_deref()
+}
+
+// The folders in use, for `doctor`
+pub fn describe() -> [(&'static str, String); 3] {
+    let current = &dirs().current;
+    [
+        ("config", current.config.display().to_string()),
+        ("data", current.data.display().to_string()),
+        ("cache", current.cache.display().to_string()),
+    ]
+}
+
+// Names of the profiles there are, sorted
+pub fn profiles() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let dir = dirs().main.data.join(PROFILES);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(Box::new(e)),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+// Create a profile's folders, returning its data folder
+pub fn create_profile(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    check_profile_name(name)?;
+    let folders = dirs().main.profile(name);
+    if folders.data.exists() {
+        return Err(format!("There already is a profile named '{}'", name).into());
+    }
+    for dir in folders.all() {
+        std::fs::create_dir_all(dir)?;
+    }
+    Ok(folders.data)
+}
+
+// A file in a profile's config folder
+pub fn profile_config(name: &str, file: &str) -> String {
+    join(&dirs().main.profile(name).config, file)
+}
+
+// A file in a profile's data folder
+pub fn profile_data(name: &str, file: &str) -> String {
+    join(&dirs().main.profile(name).data, file)
+}
+
+// Remove a profile's folders and everything in them, returning the folders removed
+pub fn delete_profile(name: &str) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    check_profile_name(name)?;
+    let folders = dirs().main.profile(name);
+    if !folders.data.exists() {
+        return Err(format!("There is no profile named '{}'", name).into());
+    }
+    let mut removed = Vec::new();
+    for dir in folders.all() {
+        // The folders are the same one with `--home`
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)?;
+            removed.push(dir.clone());
+        }
+    }
+    Ok(removed)
+}
@@ -0,0 +1,39 @@
+// `pick`: one actionable task chosen at random, for when any small step beats
+// deciding which
+use chrono::{DateTime, Duration, Local, Utc};
+
+use crate::filter::Filter;
+use crate::{Task, TaskStatus};
+
+// Work a task is expected to take: its estimate, or for a dated task without
+// one, the time it's scheduled for
+pub fn length(task: &Task) -> Option<Duration> {
+    match (task.estimate_minutes, task.start_time, task.end_time) {
+        (Some(minutes), _, _) => Some(Duration::minutes(minutes)),
+        (None, Some(start), Some(end)) => Some(end - start),
+        _ => None,
+    }
+}
+
+// Tasks that can be done now and match `filter`: open, not waiting on someone
+// or an open dependency, undated or due by the end of today, and when
+// `max_length` is given, known to take no longer than it
+pub fn actionable<'a>(tasks: &'a [Task], filter: Option<&Filter>, max_length: Option<Duration>, now: DateTime<Utc>) -> Vec<&'a Task> {
+    let tomorrow = crate::local_midnight(now.with_timezone(&Local).date_naive() + Duration::days(1));
+    let open = |id: &u32| tasks.iter().any(|task| task.id == *id && task.status == TaskStatus::Todo);
+    tasks
+        .iter()
+        .filter(|task| task.status == TaskStatus::Todo && task.waiting.is_none() && !task.depends_on.iter().any(open))
+        .filter(|task| task.start_time.is_none_or(|start| start < tomorrow))
+        .filter(|task| max_length.is_none_or(|max| length(task).is_some_and(|length| length <= max)))
+        .filter(|task| filter.is_none_or(|filter| filter.matches(task)))
+        .collect()
+}
+
+// One of `tasks` at random
+pub fn choose<'a>(tasks: &[&'a Task]) -> Option<&'a Task> {
+    match tasks.len() {
+        0 => None,
+        count => Some(tasks[(uuid::Uuid::new_v4().as_u128() % count as u128) as usize]),
+    }
+}
@@ -0,0 +1,289 @@
+// Fuzzy finder for commands given no task ID: type some letters of a title,
+// in order but not necessarily next to each other, and pick from the best
+// matches with the arrow keys. Without a raw terminal (Windows), the search is
+// typed as a line and the task picked by number.
+use std::io::IsTerminal;
+
+use crate::Task;
+
+/// Matches shown at once
+const SHOWN: usize = 10;
+
+// How well `query` matches `text`, ignoring case, and the positions of the
+// matched characters in `text`; None unless every character of the query is
+// found in order. Matches right after each other or at the start of a word
+// count more, so "gro" ranks "Groceries" over "Go to the store".
+pub fn score(query: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut positions = Vec::new();
+    let mut score = 0;
+    let mut from = 0;
+    for wanted in query.chars().filter(|c| !c.is_whitespace()) {
+        let wanted = wanted.to_lowercase().collect::<String>();
+        let found = (from..chars.len()).find(|&i| chars[i].to_lowercase().collect::<String>() == wanted)?;
+        score += 16;
+        if found > 0 && positions.last() == Some(&(found - 1)) {
+            score += 8;
+        }
+        if found == 0 || !chars[found - 1].is_alphanumeric() {
+            score += 10;
+        }
+        // Characters skipped before the first match cost a little
+        if positions.is_empty() {
+            score -= found.min(10) as i64;
+        }
+        positions.push(found);
+        from = found + 1;
+    }
+    Some((score, positions))
+}
+
+// What a task is searched by
+fn search_text(task: &Task) -> String {
+    let mut text = format!("{} {}", task.id, task.title);
+    if let Some(project) = &task.project {
+        text.push_str(&format!(" {}", project));
+    }
+    for tag in &task.tags {
+        text.push_str(&format!(" {}", tag));
+    }
+    text
+}
+
+// The tasks matching `query`, best first, with the matched positions
+fn ranked<'a>(tasks: &'a [Task], query: &str) -> Vec<(&'a Task, String, Vec<usize>)> {
+    let mut found: Vec<(i64, &Task, String, Vec<usize>)> = tasks
+        .iter()
+        .filter_map(|task| {
+            let text = search_text(task);
+            let (score, positions) = score(query, &text)?;
+            Some((score, task, text, positions))
+        })
+        .collect();
+    // Stable, so equally good matches keep the order they were given in
+    found.sort_by_key(|(score, ..)| -score);
+    found.into_iter().map(|(_, task, text, positions)| (task, text, positions)).collect()
+}
+
+// Let the user pick one of `tasks`; None if they gave up. Fails without a
+// terminal to ask on.
+pub fn pick(tasks: &[Task]) -> Result<Option<u32>, String> {
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return Err("no task ID given, and there is no terminal to pick one on".to_string());
+    }
+    if tasks.is_empty() {
+        return Err("there are no tasks to pick from".to_string());
+    }
+    interactive(tasks)
+}
+
+#[cfg(unix)]
+fn interactive(tasks: &[Task]) -> Result<Option<u32>, String> {
+    raw::pick(tasks).map_err(|e| format!("can't read from the terminal: {}", e))
+}
+
+// Search by lines and pick by number
+#[cfg(not(unix))]
+fn interactive(tasks: &[Task]) -> Result<Option<u32>, String> {
+    use std::io::Write;
+
+    use crate::i18n::t;
+    let mut query = String::new();
+    loop {
+        let found = ranked(tasks, &query);
+        for (number, (_, text, _)) in found.iter().take(SHOWN).enumerate() {
+            eprintln!("{:>3}) {}", number + 1, text);
+        }
+        eprint!("{} ", t!("picker-prompt"));
+        let _ = std::io::stderr().flush();
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer).unwrap_or(0) == 0 || answer.trim().is_empty() {
+            return Ok(None);
+        }
+        match answer.trim().parse::<usize>() {
+            Ok(number) if (1..=found.len().min(SHOWN)).contains(&number) => return Ok(Some(found[number - 1].0.id)),
+            _ => query = answer.trim().to_string(),
+        }
+    }
+}
+
+#[cfg(unix)]
+mod raw {
+    use std::io::Write;
+
+    use super::{ranked, SHOWN};
+    use crate::Task;
+
+    // The terminal without line editing and echo while it lives, so keys are
+    // read as they're pressed; put back as it was when dropped
+    struct RawMode {
+        saved: libc::termios,
+    }
+
+    impl RawMode {
+        fn enable() -> std::io::Result<RawMode> {
+            // SAFETY: termios is plain data, filled in by tcgetattr before it's used
+            let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+            if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            let saved = termios;
+            // Ctrl-C arrives as a key too, so cancelling restores the terminal
+            termios.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+            termios.c_cc[libc::VMIN] = 1;
+            termios.c_cc[libc::VTIME] = 0;
+            if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios) } != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(RawMode { saved })
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.saved) };
+        }
+    }
+
+    fn width() -> usize {
+        // SAFETY: winsize is plain data the ioctl fills in
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        if unsafe { libc::ioctl(libc::STDERR_FILENO, libc::TIOCGWINSZ, &mut size) } == 0 && size.ws_col > 0 {
+            size.ws_col as usize
+        } else {
+            80
+        }
+    }
+
+    enum Key {
+        Text(String),
+        Backspace,
+        Clear,
+        Up,
+        Down,
+        Enter,
+        Cancel,
+    }
+
+    // The keys in what one read returned
+    fn keys(bytes: &[u8]) -> Vec<Key> {
+        let mut keys = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            let key = match bytes[i] {
+                b'\r' | b'\n' => Key::Enter,
+                // Ctrl-C, Ctrl-D, or Escape on its own
+                3 | 4 => Key::Cancel,
+                27 if bytes.get(i + 1) == Some(&b'[') || bytes.get(i + 1) == Some(&b'O') => {
+                    i += 2;
+                    match bytes.get(i) {
+                        Some(b'A') => Key::Up,
+                        Some(b'B') => Key::Down,
+                        _ => {
+                            i += 1;
+                            continue;
+                        }
+                    }
+                }
+                27 => Key::Cancel,
+                127 | 8 => Key::Backspace,
+                // Ctrl-U
+                21 => Key::Clear,
+                // Ctrl-P and Ctrl-K, Ctrl-N
+                16 | 11 => Key::Up,
+                14 => Key::Down,
+                byte if byte < 32 => {
+                    i += 1;
+                    continue;
+                }
+                _ => {
+                    let end = bytes[i..].iter().position(|&byte| byte < 32 || byte == 127).map_or(bytes.len(), |n| i + n);
+                    keys.push(Key::Text(String::from_utf8_lossy(&bytes[i..end]).into_owned()));
+                    i = end;
+                    continue;
+                }
+            };
+            keys.push(key);
+            i += 1;
+        }
+        keys
+    }
+
+    // One match, cut to the terminal's width, its matched characters in bold
+    fn line(text: &str, positions: &[usize], selected: bool, width: usize) -> String {
+        let mut line = String::from(if selected { "\x1b[7m> " } else { "  " });
+        for (i, c) in text.chars().take(width.saturating_sub(3)).enumerate() {
+            if positions.contains(&i) {
+                line.push_str(&format!("\x1b[1m{}\x1b[22m", c));
+            } else {
+                line.push(c);
+            }
+        }
+        line.push_str("\x1b[0m");
+        line
+    }
+
+    // Draw the prompt and the matches below it, leaving the cursor after the query
+    fn draw(out: &mut impl Write, query: &str, found: &[(&Task, String, Vec<usize>)], selected: usize, total: usize) -> std::io::Result<()> {
+        let width = width();
+        write!(out, "\r\x1b[J")?;
+        let shown = &found[..found.len().min(SHOWN)];
+        for (i, (_, text, positions)) in shown.iter().enumerate() {
+            write!(out, "\n\r{}", line(text, positions, i == selected, width))?;
+        }
+        write!(out, "\n\r  \x1b[2m{}/{}\x1b[0m", found.len(), total)?;
+        write!(out, "\x1b[{}A\r> {}", shown.len() + 1, query)?;
+        out.flush()
+    }
+
+    pub fn pick(tasks: &[Task]) -> std::io::Result<Option<u32>> {
+        let _raw = RawMode::enable()?;
+        let mut out = std::io::stderr();
+        let mut query = String::new();
+        let mut selected = 0;
+        let mut buffer = [0u8; 64];
+        let picked = loop {
+            let found = ranked(tasks, &query);
+            selected = selected.min(found.len().min(SHOWN).saturating_sub(1));
+            draw(&mut out, &query, &found, selected, tasks.len())?;
+            let read = unsafe { libc::read(libc::STDIN_FILENO, buffer.as_mut_ptr().cast(), buffer.len()) };
+            if read <= 0 {
+                break None;
+            }
+            let mut done = None;
+            for key in keys(&buffer[..read as usize]) {
+                match key {
+                    Key::Text(text) => {
+                        query.push_str(&text);
+                        selected = 0;
+                    }
+                    Key::Backspace => {
+                        query.pop();
+                        selected = 0;
+                    }
+                    Key::Clear => {
+                        query.clear();
+                        selected = 0;
+                    }
+                    Key::Up => selected = selected.saturating_sub(1),
+                    Key::Down => selected += 1,
+                    Key::Enter => {
+                        let found = ranked(tasks, &query);
+                        let last = found.len().min(SHOWN).saturating_sub(1);
+                        done = Some(found.get(selected.min(last)).map(|(task, ..)| task.id));
+                    }
+                    Key::Cancel => done = Some(None),
+                }
+                if done.is_some() {
+                    break;
+                }
+            }
+            if let Some(picked) = done {
+                break picked;
+            }
+        };
+        write!(out, "\r\x1b[J")?;
+        out.flush()?;
+        Ok(picked)
+    }
+}
@@ -0,0 +1,142 @@
+// Progress on stderr while something slow runs: a bar when the number of steps
+// is known, a spinner when it isn't, each with the item being worked on. Only
+// shown when stdout and stderr are terminals, so logs and pipes stay clean,
+// and not with `--quiet`. Drawn with indicatif; bars and spinners shown at the
+// same time, e.g. a push inside a sync, stack instead of drawing over each other.
+use std::io::IsTerminal;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+use crate::i18n::t;
+use crate::quiet;
+
+/// The spinner's frames, the last one shown once it's done
+const FRAMES: &str = "⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏ ";
+/// How often the spinner turns while nothing else changes
+const TICK: Duration = Duration::from_millis(100);
+
+/// Everything shown, one line each
+static SHOWN: LazyLock<MultiProgress> = LazyLock::new(|| MultiProgress::with_draw_target(ProgressDrawTarget::stderr()));
+
+pub struct Progress {
+    /// None when not shown
+    bar: Option<ProgressBar>,
+    label: String,
+    failed: AtomicUsize,
+}
+
+impl Progress {
+    // A bar for `total` steps, each counted with `step`
+    pub fn bar(label: &str, total: usize) -> Progress {
+        if total == 0 {
+            return Progress::hidden();
+        }
+        Progress::start(label, Some(total))
+    }
+
+    // A spinner for as long as the value lives
+    pub fn spinner(label: &str) -> Progress {
+        Progress::start(label, None)
+    }
+
+    // Progress that isn't shown, e.g. while a dry run prints every step
+    pub fn hidden() -> Progress {
+        Progress { bar: None, label: String::new(), failed: AtomicUsize::new(0) }
+    }
+
+    fn start(label: &str, total: Option<usize>) -> Progress {
+        if quiet::on() || !std::io::stdout().is_terminal() || !std::io::stderr().is_terminal() {
+            return Progress::hidden();
+        }
+        let progress = Progress::new(label, total);
+        if let Some(bar) = &progress.bar {
+            SHOWN.add(bar.clone());
+            bar.enable_steady_tick(TICK);
+        }
+        progress
+    }
+
+    // Progress drawn nowhere until it's added to `SHOWN`
+    fn new(label: &str, total: Option<usize>) -> Progress {
+        let (bar, template) = match total {
+            Some(total) => (ProgressBar::new(total as u64), "{spinner} {prefix} [{bar:24}] {pos}/{len} {wide_msg}"),
+            None => (ProgressBar::new_spinner(), "{spinner} {prefix} {wide_msg}"),
+        };
+        let style = ProgressStyle::with_template(template).expect("progress templates are valid").tick_chars(FRAMES).progress_chars("#-");
+        let bar = bar.with_style(style).with_prefix(label.to_string());
+        bar.set_draw_target(ProgressDrawTarget::hidden());
+        Progress { bar: Some(bar), label: label.to_string(), failed: AtomicUsize::new(0) }
+    }
+
+    // Show what is being worked on
+    pub fn item(&self, item: &str) {
+        if let Some(bar) = &self.bar {
+            bar.set_message(item.to_string());
+        }
+    }
+
+    // Count a step as done, or as failed
+    pub fn step(&self, ok: bool) {
+        if let Some(bar) = &self.bar {
+            bar.inc(1);
+            if !ok {
+                self.failed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    // Steps done and failed so far
+    fn counts(&self) -> (usize, usize) {
+        let done = self.bar.as_ref().map_or(0, |bar| bar.position() as usize);
+        (done, self.failed.load(Ordering::Relaxed))
+    }
+
+    // Take the progress off the screen, leaving a line saying how the steps
+    // went for a bar
+    pub fn finish(self) {
+        let Some(bar) = &self.bar else { return };
+        if let Some(total) = bar.length().filter(|&total| total > 0) {
+            let (done, failed) = self.counts();
+            let seconds = format!("{:.1}", bar.elapsed().as_secs_f64());
+            let line = match failed {
+                0 => t!("steps-done", label = self.label, done = done, total = total, seconds = seconds),
+                failed => t!("steps-done-failed", label = self.label, done = done - failed, total = total, failed = failed, seconds = seconds),
+            };
+            let _ = SHOWN.println(line);
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+            SHOWN.remove(bar);
+        }
+    }
+}
+
+// Wait for `work` with a spinner showing
+pub async fn spin<T>(label: &str, work: impl Future<Output = T>) -> T {
+    let _progress = Progress::spinner(label);
+    work.await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_past_the_total_are_counted() {
+        let progress = Progress::new("Pushing", Some(2));
+        for ok in [true, false, true] {
+            progress.item("task");
+            progress.step(ok);
+        }
+        assert_eq!(progress.counts(), (3, 1));
+        progress.finish();
+    }
+}
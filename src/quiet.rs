@@ -0,0 +1,25 @@
+// `--quiet`: messages saying what a command did, e.g. "Task successfully added
+// to the calendar.", are left out, so scripts and cron jobs only get results,
+// warnings and errors. "quiet": true in the config makes it the default.
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by `--quiet` or the config
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+// Print a message unless quiet
+macro_rules! say {
+    ($($message:tt)*) => {
+        if !$crate::quiet::on() {
+            println!($($message)*);
+        }
+    };
+}
+pub(crate) use say;
+
+pub fn set(quiet: bool) {
+    QUIET.store(quiet, Ordering::SeqCst);
+}
+
+pub fn on() -> bool {
+    QUIET.load(Ordering::SeqCst)
+}
@@ -0,0 +1,233 @@
+// Count calendar API requests per service and day, slow down near the daily
+// limit and retry requests the service rejected as rate limited
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::exit;
+use crate::http::StatusError;
+use crate::i18n::t;
+use crate::{atomic, paths};
+
+// File the request counts are kept in
+pub const USAGE_PATH: &str = "api_usage.json";
+
+// Days of counts kept in USAGE_PATH
+const KEEP_DAYS: i64 = 30;
+// Retries of a rate-limited request, waiting 1, 2, then 4 seconds
+const RETRIES: u32 = 3;
+// Share of the daily limit after which requests are spaced out
+const SLOW_DOWN_AT: f64 = 0.8;
+const SLOW_DOWN: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DayUsage {
+    pub requests: u64,
+    /// Requests delayed near the daily limit, refused at it, or rejected by the service as rate limited
+    pub throttled: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Usage {
+    /// Keyed by local date, then service name
+    pub days: BTreeMap<NaiveDate, BTreeMap<String, DayUsage>>,
+}
+
+impl Usage {
+    pub fn load(path: &str) -> Result<Usage, Box<dyn std::error::Error>> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Usage::default()),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        atomic::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+// Shared by every backend in the process, since each command builds its own
+static USAGE: Mutex<Option<Usage>> = Mutex::new(None);
+static DAILY_LIMIT: Mutex<Option<u64>> = Mutex::new(None);
+/// Failed requests since the process started, keyed by service name and what
+/// went wrong, for `serve`'s metrics
+static FAILURES: Mutex<BTreeMap<(String, String), u64>> = Mutex::new(BTreeMap::new());
+
+pub fn set_daily_limit(limit: Option<u64>) {
+    *DAILY_LIMIT.lock().unwrap() = limit;
+}
+
+// Apply `update` to today's counts for the service and save them
+fn record<R>(service: &str, update: impl FnOnce(&mut DayUsage) -> R) -> R {
+    let mut usage = USAGE.lock().unwrap();
+    let usage = usage.get_or_insert_with(|| {
+        Usage::load(&paths::data(USAGE_PATH)).unwrap_or_else(|e| {
+            eprintln!("{}", t!("error-reading", path = paths::data(USAGE_PATH), error = format!("{:?}", e)));
+            Usage::default()
+        })
+    });
+    let today = Local::now().date_naive();
+    let day = usage.days.entry(today).or_default().entry(service.to_string()).or_default();
+    let result = update(day);
+    usage.days.retain(|date, _| (today - *date).num_days() < KEEP_DAYS);
+    if let Err(e) = usage.save(&paths::data(USAGE_PATH)) {
+        eprintln!("{}", t!("error-saving", path = paths::data(USAGE_PATH), error = format!("{:?}", e)));
+    }
+    result
+}
+
+enum Admission {
+    Go,
+    SlowDown,
+    Refuse(u64),
+}
+
+// Services report rate limiting as HTTP 429 or, for Google, a 403 with one of these reasons
+fn is_rate_limited(error: &(dyn std::error::Error + 'static)) -> bool {
+    let message = format!("{:?}", error);
+    ["status: 429", "Too Many Requests", "rateLimitExceeded", "quotaExceeded"]
+        .iter()
+        .any(|marker| message.contains(marker))
+}
+
+// What a failed request is counted under: the HTTP status the service
+// answered with, else "network", "auth" or "other"
+fn failure_kind(error: &(dyn std::error::Error + 'static)) -> String {
+    let status = match error.downcast_ref::<google_calendar3::Error>() {
+        Some(google_calendar3::Error::Failure(response)) => Some(response.status().as_u16()),
+        Some(google_calendar3::Error::BadRequest(body)) => body["error"]["code"].as_u64().map(|status| status as u16),
+        _ => error.downcast_ref::<StatusError>().map(|error| error.status.as_u16()),
+    };
+    match (status, exit::code_of(error)) {
+        (Some(status), _) => status.to_string(),
+        (None, exit::NETWORK) => "network".to_string(),
+        (None, exit::AUTH) => "auth".to_string(),
+        _ => "other".to_string(),
+    }
+}
+
+fn record_failure(service: &str, error: &(dyn std::error::Error + 'static)) {
+    *FAILURES.lock().unwrap().entry((service.to_string(), failure_kind(error))).or_default() += 1;
+}
+
+// Failed requests by service and what went wrong, since the process started
+pub fn failures() -> BTreeMap<(String, String), u64> {
+    FAILURES.lock().unwrap().clone()
+}
+
+// Count `count` requests to the service, waiting or refusing as the daily limit nears
+async fn admit(service: &str, count: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let limit = *DAILY_LIMIT.lock().unwrap();
+    let admission = record(service, |day| {
+        let admission = match limit {
+            Some(limit) if day.requests >= limit => Admission::Refuse(limit),
+            Some(limit) if day.requests as f64 >= limit as f64 * SLOW_DOWN_AT => Admission::SlowDown,
+            _ => Admission::Go,
+        };
+        match admission {
+            Admission::Go => day.requests += count,
+            Admission::SlowDown => {
+                day.requests += count;
+                day.throttled += 1;
+            }
+            Admission::Refuse(_) => day.throttled += 1,
+        }
+        admission
+    });
+    match admission {
+        Admission::Go => Ok(()),
+        Admission::SlowDown => {
+            tokio::time::sleep(SLOW_DOWN).await;
+            Ok(())
+        }
+        Admission::Refuse(limit) => Err(format!("the daily limit of {} {} requests is used up; try again tomorrow", limit, service).into()),
+    }
+}
+
+// Make a request through `request`, retrying with exponential backoff while the
+// service says it is rate limited
+pub async fn call<T, F, Fut>(service: &str, request: F) -> Result<T, Box<dyn std::error::Error>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Box<dyn std::error::Error>>>,
+{
+    call_counted(service, 1, request).await
+}
+
+// Like `call`, for a batch the service counts as `count` requests
+pub async fn call_counted<T, F, Fut>(service: &str, count: u64, mut request: F) -> Result<T, Box<dyn std::error::Error>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Box<dyn std::error::Error>>>,
+{
+    let mut attempt = 0;
+    loop {
+        admit(service, count).await?;
+        let result = request().await;
+        if let Err(e) = &result {
+            record_failure(service, e.as_ref());
+        }
+        match result {
+            Err(e) if attempt < RETRIES && is_rate_limited(e.as_ref()) => {
+                record(service, |day| day.throttled += 1);
+                tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+// Print the request counts of the last week, newest first
+pub fn print_stats() {
+    let usage = match Usage::load(&paths::data(USAGE_PATH)) {
+        Ok(usage) => usage,
+        Err(e) => {
+            eprintln!("{}", t!("error-reading", path = paths::data(USAGE_PATH), error = format!("{:?}", e)));
+            return;
+        }
+    };
+    let today = Local::now().date_naive();
+    let recent: Vec<_> = usage.days.iter().rev().filter(|(date, _)| (today - **date).num_days() < 7).collect();
+    if recent.is_empty() {
+        println!("{}", t!("quota-empty"));
+    }
+    for (date, services) in recent {
+        for (service, day) in services {
+            println!("{}  {:<16} {:>6} requests, {} throttled", date, service, day.requests, day.throttled);
+        }
+    }
+    match *DAILY_LIMIT.lock().unwrap() {
+        Some(limit) => println!("{}", t!("quota-limit", limit = limit)),
+        None => println!("{}", t!("quota-no-limit")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_are_counted_by_status() {
+        let refused = google_calendar3::Error::BadRequest(serde_json::json!({"error": {"code": 403, "message": "Rate Limit Exceeded"}}));
+        assert_eq!(failure_kind(&refused), "403");
+        let missing = StatusError { status: hyper::StatusCode::NOT_FOUND, message: "Not Found".to_string() };
+        assert_eq!(failure_kind(&missing), "404");
+        assert_eq!(failure_kind(&std::io::Error::from(std::io::ErrorKind::ConnectionRefused)), "network");
+        assert_eq!(failure_kind(&*Box::<dyn std::error::Error>::from("no calendar")), "other");
+
+        record_failure("Test Calendar", &refused);
+        record_failure("Test Calendar", &refused);
+        record_failure("Test Calendar", &missing);
+        let failures = failures();
+        assert_eq!(failures[&("Test Calendar".to_string(), "403".to_string())], 2);
+        assert_eq!(failures[&("Test Calendar".to_string(), "404".to_string())], 1);
+    }
+}
@@ -0,0 +1,370 @@
+// Recurrence rules written as phrases ("weekday at 9am", "2 weeks on tuesday"):
+// parsing, description, occurrences in local time or the task's time zone and
+// translation to an RRULE
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::t;
+use crate::week;
+use crate::zone::Zone;
+
+// Days searched for the next occurrence before giving up
+const SEARCH_DAYS: i64 = 5 * 366;
+
+const WEEKDAY_NAMES: [(&str, Weekday); 7] = [
+    ("Monday", Weekday::Mon),
+    ("Tuesday", Weekday::Tue),
+    ("Wednesday", Weekday::Wed),
+    ("Thursday", Weekday::Thu),
+    ("Friday", Weekday::Fri),
+    ("Saturday", Weekday::Sat),
+    ("Sunday", Weekday::Sun),
+];
+const WORKDAYS: [Weekday; 5] = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri];
+const WEEKEND: [Weekday; 2] = [Weekday::Sat, Weekday::Sun];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    /// Repeat every `interval` units
+    pub interval: u32,
+    pub unit: Unit,
+    /// Days of weekly rules; the first occurrence's weekday when empty
+    #[serde(default)]
+    pub weekdays: Vec<Weekday>,
+    /// Time of day in local time or the task's zone; the first occurrence's when unset
+    #[serde(default)]
+    pub time: Option<NaiveTime>,
+}
+
+// "tuesday", "tuesdays", "tue" or "tues"
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    let word = word.trim_end_matches('s');
+    WEEKDAY_NAMES
+        .iter()
+        .find(|(name, _)| word.len() >= 3 && name.to_lowercase().starts_with(word))
+        .map(|(_, weekday)| *weekday)
+}
+
+fn weekday_name(weekday: Weekday) -> &'static str {
+    WEEKDAY_NAMES.iter().find(|(_, day)| *day == weekday).map_or("", |(name, _)| name)
+}
+
+// The weekday's name in the user's language, for descriptions
+fn weekday_shown(weekday: Weekday) -> String {
+    let names = t!("date-weekdays");
+    names.split(',').nth(weekday.num_days_from_monday() as usize).map_or_else(|| weekday_name(weekday).to_string(), |name| name.trim().to_string())
+}
+
+fn parse_unit(word: &str) -> Option<Unit> {
+    match word.trim_end_matches('s') {
+        "minute" | "min" => Some(Unit::Minute),
+        "hour" | "hourly" => Some(Unit::Hour),
+        "day" | "daily" => Some(Unit::Day),
+        "week" | "weekly" => Some(Unit::Week),
+        "month" | "monthly" => Some(Unit::Month),
+        _ => None,
+    }
+}
+
+// "9am", "9:30pm", "18:30", "noon", "midnight"
+pub fn parse_time(text: &str) -> Option<NaiveTime> {
+    match text {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return NaiveTime::from_hms_opt(0, 0, 0),
+        _ => {}
+    }
+    let (clock, offset) = match (text.strip_suffix("am"), text.strip_suffix("pm")) {
+        (Some(clock), _) => (clock, Some(0)),
+        (_, Some(clock)) => (clock, Some(12)),
+        _ => (text, None),
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) => (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?),
+        None => (clock.parse::<u32>().ok()?, 0),
+    };
+    let hour = match offset {
+        Some(offset) if (1..=12).contains(&hour) => hour % 12 + offset,
+        Some(_) => return None,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+// "every 90 minutes", "every 2 days", "every week" for a fixed interval
+pub fn describe_minutes(minutes: i64) -> String {
+    let (count, unit) = match minutes {
+        minutes if minutes > 0 && minutes % (7 * 24 * 60) == 0 => (minutes / (7 * 24 * 60), "week"),
+        minutes if minutes > 0 && minutes % (24 * 60) == 0 => (minutes / (24 * 60), "day"),
+        minutes if minutes > 0 && minutes % 60 == 0 => (minutes / 60, "hour"),
+        minutes => (minutes, "minute"),
+    };
+    t!("recur-every", unit = t!("recur-unit", unit = unit, count = count))
+}
+
+impl Rule {
+    // The rule for repeating every `minutes` when that's a whole number of
+    // days or weeks, which keeps the clock time across daylight saving changes
+    pub fn of_minutes(minutes: i64) -> Option<Rule> {
+        let (interval, unit) = match minutes {
+            minutes if minutes > 0 && minutes % (7 * 24 * 60) == 0 => (minutes / (7 * 24 * 60), Unit::Week),
+            minutes if minutes > 0 && minutes % (24 * 60) == 0 => (minutes / (24 * 60), Unit::Day),
+            _ => return None,
+        };
+        Some(Rule { interval: u32::try_from(interval).ok()?, unit, weekdays: Vec::new(), time: None })
+    }
+
+    // Parse a phrase such as "weekday at 9am", "2 weeks on tuesday",
+    // "monday and thursday at 18:30", "3 days" or "hour"; a leading "every" is optional
+    pub fn parse(phrase: &str) -> Result<Rule, String> {
+        let lower = phrase.to_lowercase().replace(',', " ");
+        let mut words: Vec<&str> = lower.split_whitespace().filter(|word| !["every", "and", "on"].contains(word)).collect();
+        let mut rule = Rule { interval: 1, unit: Unit::Week, weekdays: Vec::new(), time: None };
+
+        if let Some(position) = words.iter().position(|word| *word == "at") {
+            let time = words[position + 1..].join("");
+            rule.time = Some(parse_time(&time).ok_or_else(|| format!("'{}' is not a time of day", time))?);
+            words.truncate(position);
+        } else if let Some(time) = words.last().filter(|word| word.contains(':') || word.ends_with('m') || **word == "noon").and_then(|word| parse_time(word)) {
+            // A time of day at the end without "at", as `describe` writes it
+            rule.time = Some(time);
+            words.pop();
+        }
+        if let Some(count) = words.first().and_then(|word| word.parse::<u32>().ok()) {
+            if count == 0 {
+                return Err("the interval must be at least 1".to_string());
+            }
+            rule.interval = count;
+            words.remove(0);
+        }
+        let Some((first, rest)) = words.split_first() else {
+            return Err("say how often, e.g. \"day\", \"2 weeks\" or \"weekday at 9am\"".to_string());
+        };
+        match *first {
+            "weekday" | "weekdays" | "workday" | "workdays" if rest.is_empty() => rule.weekdays = WORKDAYS.to_vec(),
+            "weekend" | "weekends" if rest.is_empty() || rest == ["day"] || rest == ["days"] => rule.weekdays = WEEKEND.to_vec(),
+            word => {
+                // "2 weeks tuesday thursday", or just the days: "tuesday thursday"
+                let days = match parse_unit(word) {
+                    Some(unit) => {
+                        rule.unit = unit;
+                        rest
+                    }
+                    None => &words[..],
+                };
+                for word in days {
+                    rule.weekdays.push(parse_weekday(word).ok_or_else(|| format!("'{}' is not a unit or a day of the week", word))?);
+                }
+            }
+        }
+        if !rule.weekdays.is_empty() && rule.unit != Unit::Week {
+            return Err("days of the week only go with weeks, e.g. \"2 weeks on tuesday\"".to_string());
+        }
+        if rule.time.is_some() && matches!(rule.unit, Unit::Minute | Unit::Hour) {
+            return Err("a time of day doesn't go with minutes or hours".to_string());
+        }
+        rule.weekdays.sort_by_key(Weekday::num_days_from_monday);
+        rule.weekdays.dedup();
+        Ok(rule)
+    }
+
+    // "every weekday 09:00", "every 2 weeks on Tuesday", "every 3 days" in the
+    // user's language; `parse` reads the English back as the same rule
+    pub fn describe(&self) -> String {
+        let unit = match self.unit {
+            Unit::Minute => "minute",
+            Unit::Hour => "hour",
+            Unit::Day => "day",
+            Unit::Week => "week",
+            Unit::Month => "month",
+        };
+        let unit = t!("recur-unit", unit = unit, count = self.interval);
+        let days: Vec<String> = self.weekdays.iter().map(|day| weekday_shown(*day)).collect();
+        let text = if self.interval == 1 && self.weekdays == WORKDAYS {
+            t!("recur-every-weekday")
+        } else if self.interval == 1 && self.weekdays == WEEKEND {
+            t!("recur-every-weekend-day")
+        } else if self.interval == 1 && !days.is_empty() {
+            t!("recur-every", unit = days.join(", "))
+        } else if days.is_empty() {
+            t!("recur-every", unit = unit)
+        } else {
+            t!("recur-every-on", unit = unit, days = days.join(", "))
+        };
+        match self.time {
+            Some(time) => t!("recur-at", rule = text, time = time.format("%H:%M")),
+            None => text,
+        }
+    }
+
+    // Whether `date` is a day the series that started on `anchor` occurs on
+    fn day_matches(&self, anchor: NaiveDate, date: NaiveDate) -> bool {
+        let interval = self.interval as i64;
+        match self.unit {
+            Unit::Minute | Unit::Hour => true,
+            Unit::Day => (date - anchor).num_days() % interval == 0,
+            Unit::Week => {
+                // Weeks start on the configured day, which decides which days
+                // of a rule every few weeks fall in the same week
+                let on_day = if self.weekdays.is_empty() { date.weekday() == anchor.weekday() } else { self.weekdays.contains(&date.weekday()) };
+                on_day && ((week::first_day(date) - week::first_day(anchor)).num_days() / 7) % interval == 0
+            }
+            Unit::Month => {
+                let months = (date.year() - anchor.year()) as i64 * 12 + date.month() as i64 - anchor.month() as i64;
+                date.day() == anchor.day() && months % interval == 0
+            }
+        }
+    }
+
+    // First occurrence after `after` of the series first held at `anchor`
+    pub fn next_after(&self, anchor: DateTime<Utc>, after: DateTime<Utc>, zone: &Zone) -> Option<DateTime<Utc>> {
+        if after < anchor {
+            return Some(anchor);
+        }
+        let step = match self.unit {
+            Unit::Minute => Some(Duration::minutes(self.interval as i64)),
+            Unit::Hour => Some(Duration::hours(self.interval as i64)),
+            _ => None,
+        };
+        if let Some(step) = step {
+            let steps = (after - anchor).num_seconds() / step.num_seconds() + 1;
+            return Some(anchor + step * steps as i32);
+        }
+        let anchor_local = zone.naive(anchor);
+        let time = self.time.unwrap_or(anchor_local.time());
+        let first_day = zone.naive(after).date();
+        (0..SEARCH_DAYS)
+            .map(|offset| first_day + Duration::days(offset))
+            .filter(|date| self.day_matches(anchor_local.date(), *date))
+            // Times skipped by a daylight saving change don't occur
+            .filter_map(|date| zone.resolve(date.and_time(time)))
+            .find(|candidate| *candidate > after)
+    }
+
+    // The first occurrence after `now` of a new series, which needs a time of
+    // day unless it repeats by minutes or hours
+    pub fn first_after(&self, now: DateTime<Utc>, zone: &Zone) -> Option<DateTime<Utc>> {
+        match self.unit {
+            Unit::Minute | Unit::Hour => None,
+            _ => {
+                self.time?;
+                self.next_after(now, now, zone)
+            }
+        }
+    }
+
+    // The RRULE for the series, for an event whose days are `shift` days from
+    // the rule's, as for one kept in UTC
+    pub fn to_rrule(&self, shift: i64) -> Result<String, String> {
+        let freq = match self.unit {
+            Unit::Minute => "MINUTELY",
+            Unit::Hour => "HOURLY",
+            Unit::Day => "DAILY",
+            Unit::Week => "WEEKLY",
+            Unit::Month => "MONTHLY",
+        };
+        let mut rrule = format!("FREQ={};INTERVAL={}", freq, self.interval);
+        if !self.weekdays.is_empty() {
+            if shift != 0 && self.interval > 1 {
+                return Err("its weekdays fall in other weeks in UTC".to_string());
+            }
+            let days: Vec<String> = self
+                .weekdays
+                .iter()
+                .map(|day| {
+                    let day = if shift < 0 { day.pred() } else if shift > 0 { day.succ() } else { *day };
+                    weekday_name(day)[..2].to_uppercase()
+                })
+                .collect();
+            rrule.push_str(&format!(";BYDAY={}", days.join(",")));
+            if self.interval > 1 && week::start() != Weekday::Mon {
+                rrule.push_str(&format!(";WKST={}", weekday_name(week::start())[..2].to_uppercase()));
+            }
+        }
+        Ok(rrule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, minute: u32) -> Option<NaiveTime> {
+        NaiveTime::from_hms_opt(hour, minute, 0)
+    }
+
+    fn rule(interval: u32, unit: Unit, weekdays: &[Weekday], time: Option<NaiveTime>) -> Rule {
+        Rule { interval, unit, weekdays: weekdays.to_vec(), time }
+    }
+
+    #[test]
+    fn phrases() {
+        use Weekday::*;
+        let cases = [
+            ("weekday at 9am", rule(1, Unit::Week, &WORKDAYS, time(9, 0))),
+            ("every weekend at noon", rule(1, Unit::Week, &WEEKEND, time(12, 0))),
+            ("2 weeks on tuesday", rule(2, Unit::Week, &[Tue], None)),
+            ("monday and thursday at 18:30", rule(1, Unit::Week, &[Mon, Thu], time(18, 30))),
+            ("Thu, mon at 6:30 pm", rule(1, Unit::Week, &[Mon, Thu], time(18, 30))),
+            ("3 days", rule(3, Unit::Day, &[], None)),
+            ("daily at midnight", rule(1, Unit::Day, &[], time(0, 0))),
+            ("every 90 minutes", rule(90, Unit::Minute, &[], None)),
+            ("hour", rule(1, Unit::Hour, &[], None)),
+            ("month at 8am", rule(1, Unit::Month, &[], time(8, 0))),
+        ];
+        for (phrase, expected) in cases {
+            assert_eq!(Rule::parse(phrase), Ok(expected), "{}", phrase);
+        }
+    }
+
+    #[test]
+    fn descriptions_parse_back_to_the_rule() {
+        use Weekday::*;
+        let rules = [
+            (rule(1, Unit::Minute, &[], None), "every minute"),
+            (rule(90, Unit::Minute, &[], None), "every 90 minutes"),
+            (rule(2, Unit::Hour, &[], None), "every 2 hours"),
+            (rule(1, Unit::Day, &[], time(7, 15)), "every day 07:15"),
+            (rule(3, Unit::Day, &[], None), "every 3 days"),
+            (rule(1, Unit::Week, &[], None), "every week"),
+            (rule(1, Unit::Week, &WORKDAYS, time(9, 0)), "every weekday 09:00"),
+            (rule(1, Unit::Week, &WEEKEND, time(10, 0)), "every weekend day 10:00"),
+            (rule(1, Unit::Week, &[Mon, Thu], time(18, 30)), "every Monday, Thursday 18:30"),
+            (rule(2, Unit::Week, &[Tue], None), "every 2 weeks on Tuesday"),
+            (rule(2, Unit::Week, &[Tue, Fri], time(8, 0)), "every 2 weeks on Tuesday, Friday 08:00"),
+            (rule(1, Unit::Month, &[], time(12, 0)), "every month 12:00"),
+            (rule(6, Unit::Month, &[], None), "every 6 months"),
+        ];
+        for (rule, description) in rules {
+            assert_eq!(rule.describe(), description);
+            assert_eq!(Rule::parse(&rule.describe()), Ok(rule), "{}", description);
+        }
+    }
+
+    #[test]
+    fn rejected_phrases() {
+        let cases = [
+            ("0 days", "the interval must be at least 1"),
+            ("", "say how often, e.g. \"day\", \"2 weeks\" or \"weekday at 9am\""),
+            ("every 2", "say how often, e.g. \"day\", \"2 weeks\" or \"weekday at 9am\""),
+            ("fortnight", "'fortnight' is not a unit or a day of the week"),
+            ("week on blursday", "'blursday' is not a unit or a day of the week"),
+            ("month on friday", "days of the week only go with weeks, e.g. \"2 weeks on tuesday\""),
+            ("2 hours at 9am", "a time of day doesn't go with minutes or hours"),
+            ("day at 25:00", "'25:00' is not a time of day"),
+            ("day at 13pm", "'13pm' is not a time of day"),
+        ];
+        for (phrase, message) in cases {
+            assert_eq!(Rule::parse(phrase), Err(message.to_string()), "{}", phrase);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+// What `serve` keeps up to date while it runs: the config files are checked
+// every few seconds and read again when one changes, so edits made in an
+// editor or another terminal take effect without a restart, and the tasks are
+// read again whenever tasks.json has changed since they were last read, be it
+// by another command or a `sync --git` pulling the store.
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use tokio::sync::{watch, Mutex, Notify};
+
+use crate::config::{Config, CONFIG_PATH};
+use crate::i18n::t;
+use crate::paths;
+use crate::quiet::say;
+use crate::webhook;
+use crate::{AppState, Task};
+
+// How often the config files are checked for changes
+const POLL: std::time::Duration = std::time::Duration::from_secs(2);
+
+pub struct LiveConfig {
+    current: RwLock<Arc<Config>>,
+    /// Woken when the config is read again
+    reloaded: Notify,
+}
+
+impl LiveConfig {
+    pub fn new(config: Config) -> LiveConfig {
+        LiveConfig { current: RwLock::new(Arc::new(config)), reloaded: Notify::new() }
+    }
+
+    // Wait until the config is read again
+    pub async fn reloaded(&self) {
+        self.reloaded.notified().await;
+    }
+
+    // The config as last read
+    pub fn get(&self) -> Arc<Config> {
+        match self.current.read() {
+            Ok(current) => Arc::clone(&current),
+            Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+        }
+    }
+
+    fn set(&self, config: Config) {
+        match self.current.write() {
+            Ok(mut current) => *current = Arc::new(config),
+            Err(poisoned) => *poisoned.into_inner() = Arc::new(config),
+        }
+        self.reloaded.notify_waiters();
+    }
+}
+
+// Settings the server only reads when it starts that differ between `before`
+// and `after`
+fn needing_restart(before: &Config, after: &Config) -> Vec<&'static str> {
+    let mut settings = Vec::new();
+    if webhook::url(before) != webhook::url(after) {
+        settings.push("webhook_url");
+    }
+    settings
+}
+
+/// When tasks.json was last changed, and its size, as far as can be told
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &str) -> Stamp {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+// The tasks in the store, read again when the file has changed
+pub struct LiveTasks {
+    path: String,
+    read: Mutex<Option<(Stamp, Arc<Vec<Task>>)>>,
+}
+
+impl LiveTasks {
+    pub fn new(path: String) -> LiveTasks {
+        LiveTasks { path, read: Mutex::new(None) }
+    }
+
+    // The tasks by ID, as they are in the file now
+    pub async fn get(&self) -> Result<Arc<Vec<Task>>, Box<dyn std::error::Error>> {
+        let mut read = self.read.lock().await;
+        let latest = stamp(&self.path);
+        if let Some((seen, tasks)) = read.as_ref()
+            && *seen == latest
+        {
+            return Ok(Arc::clone(tasks));
+        }
+        let state = AppState::read(&self.path)?;
+        let mut tasks = state.list_tasks().await;
+        tasks.sort_by_key(|task| task.id);
+        let tasks = Arc::new(tasks);
+        *read = Some((latest, Arc::clone(&tasks)));
+        Ok(tasks)
+    }
+}
+
+// Modification times of the config files: the profile's and the main one it
+// builds on, or just the main one
+fn modified() -> Vec<Option<SystemTime>> {
+    let mut files = vec![paths::config(CONFIG_PATH)];
+    files.extend(paths::main_config(CONFIG_PATH));
+    files.iter().map(|file| std::fs::metadata(file).and_then(|metadata| metadata.modified()).ok()).collect()
+}
+
+// Read the config again whenever one of its files changes, until stopped. A
+// config that doesn't load is reported and the one before it kept.
+pub async fn watch_loop(live: &LiveConfig, mut stop: watch::Receiver<bool>) {
+    let started = live.get();
+    let mut seen = modified();
+    let mut interval = tokio::time::interval(POLL);
+    loop {
+        tokio::select! {
+            biased;
+            _ = stop.wait_for(|stop| *stop) => break,
+            _ = interval.tick() => {}
+        }
+        let latest = modified();
+        if latest == seen {
+            continue;
+        }
+        seen = latest;
+        match Config::load(&paths::config(CONFIG_PATH)) {
+            Ok(config) => {
+                match needing_restart(&started, &config).as_slice() {
+                    [] => say!("{}", t!("reload-done", path = paths::config(CONFIG_PATH))),
+                    settings => say!("{}", t!("reload-done-restart", path = paths::config(CONFIG_PATH), settings = settings.join(", "))),
+                }
+                live.set(config);
+            }
+            Err(e) => eprintln!("{}", t!("reload-failed", path = paths::config(CONFIG_PATH), error = e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BackendKind;
+
+    #[test]
+    fn webhook_url_needs_a_restart() {
+        let google = Config { backend: BackendKind::Google, ..Default::default() };
+        let pushed = Config { webhook_url: Some("https://example.com/push".to_string()), ..google.clone() };
+        assert_eq!(needing_restart(&google, &pushed), vec!["webhook_url"]);
+        assert!(needing_restart(&pushed, &pushed.clone()).is_empty());
+        // Only Google pushes changes, so the URL isn't used for other backends
+        let caldav = Config { backend: BackendKind::Caldav, ..Default::default() };
+        let other = Config { webhook_url: Some("https://example.com/push".to_string()), ..caldav.clone() };
+        assert!(needing_restart(&caldav, &other).is_empty());
+    }
+
+    #[tokio::test]
+    async fn tasks_are_read_again_when_the_file_changes() {
+        let path = std::env::temp_dir().join(format!("todo-task-live-{}.json", std::process::id())).to_string_lossy().into_owned();
+        let write = |titles: &[&str]| {
+            let tasks = titles.iter().zip(1..).map(|(title, id)| Task { id, title: title.to_string(), ..Default::default() }).collect();
+            let store = crate::StoreFile { version: crate::schema::VERSION, next_id: titles.len() as u32 + 1, tasks };
+            std::fs::write(&path, serde_json::to_string(&store).unwrap()).unwrap();
+        };
+        let live = LiveTasks::new(path.clone());
+        write(&["Write report"]);
+        let first = live.get().await.unwrap();
+        assert_eq!(first.len(), 1);
+        assert!(Arc::ptr_eq(&first, &live.get().await.unwrap()));
+        write(&["Write report", "Send invoice"]);
+        let titles: Vec<_> = live.get().await.unwrap().iter().map(|task| task.title.clone()).collect();
+        assert_eq!(titles, ["Write report", "Send invoice"]);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,81 @@
+// Versions of the task store's layout. tasks.json records the version it was
+// written in; an older file is upgraded one version at a time as plain JSON
+// before it's read into tasks, so a renamed or restructured field doesn't make
+// it unreadable. A change to `Task` that serde's defaults can't read from old
+// files raises VERSION and adds a migration below.
+use serde_json::Value;
+
+use crate::StoreFile;
+
+// Version this build writes
+pub const VERSION: u32 = 1;
+
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+// Migrations by the version they upgrade from: the first turns a version 0
+// file into version 1, and so on
+const MIGRATIONS: [Migration; VERSION as usize] = [unversioned];
+
+// Version 0 is every file from before versions were recorded; its tasks read
+// as they are
+fn unversioned(_store: &mut Value) -> Result<(), String> {
+    Ok(())
+}
+
+// The version a store was written in; 0 if it doesn't say
+pub fn version(store: &Value) -> u32 {
+    store.get("version").and_then(Value::as_u64).unwrap_or(0) as u32
+}
+
+// Bring a store up to VERSION, returning the version it had. Fails for a
+// store written by a newer version, which this one can't know how to read.
+pub fn migrate(store: &mut Value) -> Result<u32, String> {
+    let from = version(store);
+    if from > VERSION {
+        return Err(format!("it was written by a newer version of todo_task (store version {}, this one reads up to {}); upgrade todo_task", from, VERSION));
+    }
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(from as usize) {
+        migration(store).map_err(|e| format!("can't upgrade it from store version {}: {}", version, e))?;
+    }
+    if let Value::Object(fields) = store {
+        fields.insert("version".to_string(), Value::from(VERSION));
+    }
+    Ok(from)
+}
+
+// A store from the contents of tasks.json, upgraded if it's older, with the
+// version it had
+pub fn read(contents: &str) -> Result<(StoreFile, u32), Box<dyn std::error::Error>> {
+    let mut store: Value = serde_json::from_str(contents)?;
+    let from = migrate(&mut store)?;
+    Ok((serde_json::from_value(store)?, from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A store from before versions were recorded
+    fn unversioned() -> Value {
+        let task = crate::Task { id: 1, title: "Pack".to_string(), ..Default::default() };
+        serde_json::json!({"next_id": 2, "tasks": [task]})
+    }
+
+    #[test]
+    fn unversioned_stores_are_upgraded() {
+        let mut store = unversioned();
+        assert_eq!(migrate(&mut store), Ok(0));
+        assert_eq!(version(&store), VERSION);
+        let (store, from) = read(&unversioned().to_string()).unwrap();
+        assert_eq!(from, 0);
+        assert_eq!(store.version, VERSION);
+        assert_eq!(store.tasks[0].title, "Pack");
+    }
+
+    #[test]
+    fn newer_stores_are_refused() {
+        let mut store = serde_json::json!({"version": VERSION + 1, "next_id": 1, "tasks": []});
+        assert!(migrate(&mut store).is_err_and(|e| e.contains("newer version")));
+        assert_eq!(version(&store), VERSION + 1);
+    }
+}
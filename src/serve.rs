@@ -0,0 +1,484 @@
+// Long-running server: read-only iCalendar feed, Prometheus metrics,
+// reminders and periodic background sync
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+
+use crate::atomic;
+use crate::audit;
+use crate::config::Config;
+use crate::escalation;
+use crate::i18n::t;
+use crate::ics;
+use crate::metrics::Metrics;
+use crate::notify::Notifiers;
+use crate::paths;
+use crate::quiet::say;
+use crate::reload::{self, LiveConfig, LiveTasks};
+use crate::webhook::{self, Webhook};
+use crate::{service, shutdown};
+use crate::{AppState, Task, TaskStatus, STORE_PATH};
+
+// Written while the server runs so `doctor` can find it
+pub const SERVER_INFO_PATH: &str = "serve.json";
+
+// Days between follow-ups on a waiting task when the config doesn't say
+const DEFAULT_FOLLOW_UP_DAYS: i64 = 3;
+
+// How often the reminder loop looks for reminders that came due
+const REMINDER_TICK: std::time::Duration = std::time::Duration::from_secs(30);
+
+// How far reminders are checked up to, kept over restarts so the ones that
+// came due while the server was down still fire
+const REMINDER_STATE_PATH: &str = "reminders.json";
+
+// Reminders missed for longer than this are left out rather than all fired at once
+const MISSED_REMINDERS_DAYS: i64 = 1;
+
+// Wait after Google announces a change before syncing, for the rest of a
+// burst of changes to arrive
+const CHANGE_DELAY: std::time::Duration = std::time::Duration::from_secs(3);
+
+pub struct ServeOptions {
+    pub addr: SocketAddr,
+    /// Secret token of the ICS feed; the feed is disabled when None
+    pub ics_token: Option<String>,
+    pub metrics: bool,
+    /// Run a full sync this often when set
+    pub sync_minutes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerInfo {
+    pub pid: u32,
+    pub addr: SocketAddr,
+    pub started_at: DateTime<Utc>,
+    /// The background sync, when the server runs one
+    #[serde(default)]
+    pub sync: Option<BackgroundSync>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackgroundSync {
+    /// Minutes between syncs, when they run on a schedule
+    pub every_minutes: Option<u64>,
+    /// Most minutes each scheduled sync is put off at random
+    pub jitter_minutes: u64,
+    /// Changes pushed from Google Calendar start a sync too
+    pub on_push: bool,
+    pub last_run: Option<DateTime<Utc>>,
+    /// Services the last run failed for
+    pub last_failed: Vec<String>,
+    pub next_run: Option<DateTime<Utc>>,
+}
+
+impl ServerInfo {
+    // The running server's, if there is one
+    pub fn load() -> Option<ServerInfo> {
+        let contents = std::fs::read_to_string(paths::data(SERVER_INFO_PATH)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        atomic::write(&paths::data(SERVER_INFO_PATH), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReminderState {
+    checked_until: DateTime<Utc>,
+}
+
+impl ReminderState {
+    fn load(path: &str) -> Result<Option<ReminderState>, Box<dyn std::error::Error>> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        atomic::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+struct Routes {
+    feed_path: Option<String>,
+    metrics: Option<Arc<Metrics>>,
+    webhook: Option<Arc<Webhook>>,
+    tasks: Arc<LiveTasks>,
+}
+
+// Render the open, dated tasks currently in the store, leaving out private ones
+fn calendar(tasks: &[Task]) -> String {
+    let events: Vec<ics::VEvent> = tasks
+        .iter()
+        .filter(|task| task.status == TaskStatus::Todo && !task.local_only)
+        .filter_map(|task| ics::task_event(task, &task.uuid))
+        .collect();
+    ics::write_calendar(&events)
+}
+
+fn respond(status: StatusCode, content_type: &str, body: String) -> Response<String> {
+    let mut response = Response::new(body);
+    *response.status_mut() = status;
+    if let Ok(value) = content_type.parse() {
+        response.headers_mut().insert("Content-Type", value);
+    }
+    response
+}
+
+async fn handle(request: Request<Incoming>, routes: Arc<Routes>) -> Result<Response<String>, Infallible> {
+    let path = request.uri().path();
+    let is_get = request.method() == Method::GET;
+    if is_get && path == "/health" {
+        return Ok(respond(StatusCode::OK, "text/plain", "ok\n".to_string()));
+    }
+    // Google retries calls that fail, so even ones without the token are answered OK
+    if let Some(webhook) = routes.webhook.as_ref().filter(|_| request.method() == Method::POST && path == webhook::PATH) {
+        webhook.receive(request.headers());
+        return Ok(respond(StatusCode::OK, "text/plain", String::new()));
+    }
+    let feed = is_get && routes.feed_path.as_deref() == Some(path);
+    let metrics = routes.metrics.as_ref().filter(|_| is_get && path == "/metrics");
+    if !feed && metrics.is_none() {
+        return Ok(respond(StatusCode::NOT_FOUND, "text/plain", "Not found\n".to_string()));
+    }
+    let tasks = match routes.tasks.get().await {
+        Ok(tasks) => tasks,
+        Err(e) => {
+            eprintln!("{}", t!("error-reading", path = paths::data(STORE_PATH), error = format!("{:?}", e)));
+            return Ok(respond(StatusCode::INTERNAL_SERVER_ERROR, "text/plain", "Failed to read tasks\n".to_string()));
+        }
+    };
+    Ok(match metrics {
+        Some(metrics) => respond(StatusCode::OK, "text/plain; version=0.0.4", metrics.render(&tasks)),
+        None => respond(StatusCode::OK, "text/calendar; charset=utf-8", calendar(&tasks)),
+    })
+}
+
+// Announce a reminder: published over MQTT and sent to the notifiers
+async fn remind(config: &Config, notifiers: &Notifiers, metrics: &Metrics, task: &Task, before_start: Option<i64>, message: &str, line: String) {
+    crate::publish_event(config.mqtt.as_ref(), "reminder", task, Some(message)).await;
+    notifiers.send(task, before_start, message, &line).await;
+    metrics.reminder_fired();
+}
+
+// A reminder coming due
+pub struct Due {
+    pub time: DateTime<Utc>,
+    /// Minutes before the start, for the reminders counted from it
+    pub before_start: Option<i64>,
+    pub message: String,
+    pub line: String,
+}
+
+// The start and end reminders of `task`, the extra ones of escalation rules
+// and the follow-up if it's waiting on someone that come due in (since, until]
+pub fn due_reminders(config: &Config, notifiers: &Notifiers, task: &Task, since: DateTime<Utc>, until: DateTime<Utc>) -> Vec<Due> {
+    let mut due = Vec::new();
+    if task.status != TaskStatus::Todo {
+        return due;
+    }
+    let mut add = |time: DateTime<Utc>, before_start: Option<i64>, message: String, line: String| {
+        if time > since && time <= until {
+            due.push(Due { time, before_start, message, line });
+        }
+    };
+    // Occurrences from before the task was added, e.g. a past task logged
+    // with `add --force`, have no reminders
+    let added = task.created_at.unwrap_or(DateTime::<Utc>::MIN_UTC);
+    // Every occurrence of a recurring task, cron ones included, gets its reminders
+    // Look far enough ahead for the earliest reminder before a start
+    let offsets = notifiers.offsets(task);
+    let lookahead = offsets.first().copied().unwrap_or(5).max(5);
+    for (start_time, end_time) in task.occurrences(since, until + chrono::Duration::minutes(lookahead)) {
+        if start_time < added {
+            continue;
+        }
+        for &minutes in &offsets {
+            let message = crate::starts_in(minutes);
+            let line = t!("remind-message", title = task.title, message = message);
+            add(start_time - chrono::Duration::minutes(minutes), Some(minutes), message, line);
+        }
+        let message = t!("remind-ends-in", length = crate::describe_length(chrono::Duration::minutes(2)));
+        let line = t!("remind-message", title = task.title, message = message);
+        add(end_time - chrono::Duration::minutes(2), None, message, line);
+    }
+    for (time, message) in escalation::reminders(&config.escalation, task, since.max(added), until) {
+        let line = t!("remind-message", title = task.title, message = message);
+        add(time, None, message, line);
+    }
+    if let Some(waiting) = &task.waiting {
+        let interval = chrono::Duration::days(config.follow_up_days.unwrap_or(DEFAULT_FOLLOW_UP_DAYS).max(1));
+        // The latest follow-up due by `until`, if it isn't older than `since`
+        let time = waiting.since + interval * ((until - waiting.since).num_seconds() / interval.num_seconds()) as i32;
+        if time > waiting.since {
+            let message = t!("remind-follow-up", name = waiting.on);
+            let line = t!("remind-message", title = task.title, message = message);
+            add(time, None, message, line);
+        }
+    }
+    due
+}
+
+// Fire the reminders that came due in (since, until]
+async fn fire_reminders(config: &Config, notifiers: &Notifiers, metrics: &Metrics, tasks: &LiveTasks, since: DateTime<Utc>, until: DateTime<Utc>) {
+    let tasks = match tasks.get().await {
+        Ok(tasks) => tasks,
+        Err(e) => {
+            eprintln!("{}", t!("error-reading", path = paths::data(STORE_PATH), error = format!("{:?}", e)));
+            return;
+        }
+    };
+    for task in tasks.iter() {
+        for due in due_reminders(config, notifiers, task, since, until) {
+            remind(config, notifiers, metrics, task, due.before_start, &due.message, due.line).await;
+        }
+    }
+}
+
+// Wait for the next tick, or return false once the server is stopping
+async fn tick(interval: &mut tokio::time::Interval, stop: &mut watch::Receiver<bool>) -> bool {
+    tokio::select! {
+        biased;
+        _ = stop.wait_for(|stop| *stop) => false,
+        _ = interval.tick() => true,
+    }
+}
+
+// Fire reminders every tick, starting with those missed since the server last
+// ran, until stopped; a round that has started is finished first
+async fn reminder_loop(live: &LiveConfig, mut notifiers: Notifiers, metrics: Arc<Metrics>, tasks: Arc<LiveTasks>, mut stop: watch::Receiver<bool>) {
+    let mut config = live.get();
+    let now = Utc::now();
+    let mut since = match ReminderState::load(&paths::data(REMINDER_STATE_PATH)) {
+        Ok(Some(state)) if state.checked_until < now && state.checked_until > now - chrono::Duration::days(MISSED_REMINDERS_DAYS) => {
+            state.checked_until
+        }
+        Ok(_) => now,
+        Err(e) => {
+            eprintln!("{}", t!("error-reading", path = paths::data(REMINDER_STATE_PATH), error = format!("{:?}", e)));
+            now
+        }
+    };
+    let mut interval = tokio::time::interval(REMINDER_TICK);
+    while tick(&mut interval, &mut stop).await {
+        // Notifiers follow the config when it's reloaded
+        let latest = live.get();
+        if !Arc::ptr_eq(&latest, &config) {
+            match Notifiers::new(&latest) {
+                Ok(reloaded) => notifiers = reloaded,
+                Err(e) => eprintln!("{}", t!("serve-notifier-invalid", path = paths::config(crate::config::CONFIG_PATH), error = e)),
+            }
+            config = latest;
+        }
+        let now = Utc::now();
+        fire_reminders(&config, &notifiers, &metrics, &tasks, since, now).await;
+        since = now;
+    }
+    if let Err(e) = (ReminderState { checked_until: since }).save(&paths::data(REMINDER_STATE_PATH)) {
+        eprintln!("{}", t!("error-saving", path = paths::data(REMINDER_STATE_PATH), error = format!("{:?}", e)));
+    }
+}
+
+/// What the sync loop woke up for
+enum Wake {
+    Sync,
+    Reloaded,
+    Stop,
+}
+
+// Wait until `next`, the next scheduled sync, for a change announced by
+// Google, which is given a moment for the rest of a burst of changes to
+// arrive, or for the config to be reloaded
+async fn next_sync(next: Option<DateTime<Utc>>, webhook: Option<&Webhook>, live: &LiveConfig, stop: &mut watch::Receiver<bool>) -> Wake {
+    let scheduled = async {
+        match next {
+            Some(next) => tokio::time::sleep((next - Utc::now()).to_std().unwrap_or_default()).await,
+            None => std::future::pending().await,
+        }
+    };
+    let changed = async {
+        match webhook {
+            Some(webhook) => {
+                webhook.changed.notified().await;
+                tokio::time::sleep(CHANGE_DELAY).await;
+            }
+            None => std::future::pending().await,
+        }
+    };
+    tokio::select! {
+        biased;
+        _ = stop.wait_for(|stop| *stop) => Wake::Stop,
+        _ = live.reloaded() => Wake::Reloaded,
+        _ = scheduled => Wake::Sync,
+        _ = changed => Wake::Sync,
+    }
+}
+
+// Minutes between scheduled syncs, `fixed` by `--sync-minutes` or else from
+// the config, and the most minutes each is put off at random
+fn sync_schedule(config: &Config, fixed: Option<u64>) -> (Option<u64>, u64) {
+    let every_minutes = fixed.or(config.sync.every_minutes).filter(|minutes| *minutes > 0);
+    (every_minutes, config.sync.jitter_minutes.unwrap_or(every_minutes.unwrap_or(0) / 10))
+}
+
+// Up to `minutes` at random
+fn jitter(minutes: u64) -> chrono::Duration {
+    let seconds = uuid::Uuid::new_v4().as_u128() % (u128::from(minutes) * 60 + 1);
+    chrono::Duration::seconds(seconds as i64)
+}
+
+// Sync right away, then every `every_minutes` and a random part of
+// `jitter_minutes` after the last sync, and on changes announced by Google,
+// until stopped; a sync that has started is finished and saved first. The
+// schedule follows the config when it's reloaded, unless `fixed` by
+// `--sync-minutes`. How it went is kept in the server info for `sync status`.
+async fn sync_loop(live: &LiveConfig, fixed: Option<u64>, metrics: Arc<Metrics>, mut info: ServerInfo, webhook: Option<Arc<Webhook>>, mut stop: watch::Receiver<bool>) {
+    let mut background = info.sync.take().unwrap_or_else(|| background_sync(&live.get(), fixed, webhook.is_some()));
+    loop {
+        match next_sync(background.next_run, webhook.as_deref(), live, &mut stop).await {
+            Wake::Stop => break,
+            Wake::Sync => {}
+            Wake::Reloaded => {
+                let (every_minutes, jitter_minutes) = sync_schedule(&live.get(), fixed);
+                if (every_minutes, jitter_minutes) != (background.every_minutes, background.jitter_minutes) {
+                    background.every_minutes = every_minutes;
+                    background.jitter_minutes = jitter_minutes;
+                    background.next_run = every_minutes.map(|minutes| match background.last_run {
+                        Some(last_run) => last_run + chrono::Duration::minutes(minutes as i64) + jitter(jitter_minutes),
+                        None => Utc::now(),
+                    });
+                    save_sync(&mut info, &background);
+                }
+                continue;
+            }
+        }
+        let state = match AppState::load(&paths::data(STORE_PATH)) {
+            Ok(state) => state,
+            Err(e) => {
+                eprintln!("{}", t!("error-reading", path = paths::data(STORE_PATH), error = format!("{:?}", e)));
+                continue;
+            }
+        };
+        let config = live.get();
+        // Each sync is one step for undo
+        audit::begin();
+        let started = Instant::now();
+        background.last_run = Some(Utc::now());
+        crate::refresh_feeds(&config).await;
+        let failed = crate::sync_all(&config, &state, false, false, false).await;
+        metrics.sync_finished(started.elapsed(), &failed);
+        crate::save_state(&state).await;
+        background.last_failed = failed.iter().map(|name| name.to_string()).collect();
+        (background.every_minutes, background.jitter_minutes) = sync_schedule(&live.get(), fixed);
+        background.next_run = background
+            .every_minutes
+            .map(|minutes| Utc::now() + chrono::Duration::minutes(minutes as i64) + jitter(background.jitter_minutes));
+        save_sync(&mut info, &background);
+    }
+}
+
+// Background syncing before the first run, starting right away when it's
+// on a schedule
+fn background_sync(config: &Config, fixed: Option<u64>, on_push: bool) -> BackgroundSync {
+    let (every_minutes, jitter_minutes) = sync_schedule(config, fixed);
+    BackgroundSync { every_minutes, jitter_minutes, on_push, last_run: None, last_failed: Vec::new(), next_run: every_minutes.map(|_| Utc::now()) }
+}
+
+// Keep how background syncing is going in the server info, none being shown
+// when nothing starts a sync
+fn save_sync(info: &mut ServerInfo, background: &BackgroundSync) {
+    info.sync = (background.every_minutes.is_some() || background.on_push).then(|| background.clone());
+    if let Err(e) = info.save() {
+        eprintln!("{}", t!("error-saving", path = paths::data(SERVER_INFO_PATH), error = format!("{:?}", e)));
+    }
+}
+
+// Serve until stopped by SIGINT or SIGTERM, then let the background loops
+// finish what they're doing and remove the server info file
+pub async fn run(config: Config, options: ServeOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let notifiers = Notifiers::new(&config).map_err(|e| format!("invalid notifier in {}: {}", paths::config(crate::config::CONFIG_PATH), e))?;
+    let listener = TcpListener::bind(options.addr).await?;
+    let live = LiveConfig::new(config);
+    let config = live.get();
+    let metrics = Arc::new(Metrics::default());
+    let push = webhook::url(&config).map(|_| Arc::new(Webhook::new()));
+    let tasks = Arc::new(LiveTasks::new(paths::data(STORE_PATH)));
+    let routes = Arc::new(Routes {
+        feed_path: options.ics_token.map(|token| format!("/{}/tasks.ics", token)),
+        metrics: options.metrics.then(|| Arc::clone(&metrics)),
+        webhook: push.clone(),
+        tasks: Arc::clone(&tasks),
+    });
+    let address = listener.local_addr()?;
+    let background = background_sync(&config, options.sync_minutes, push.is_some());
+    let sync = (background.every_minutes.is_some() || background.on_push).then_some(background);
+    let info = ServerInfo { pid: std::process::id(), addr: address, started_at: Utc::now(), sync };
+    info.save()?;
+    if let Some(feed_path) = &routes.feed_path {
+        say!("{}", t!("serve-feed", address = address, feed_path = feed_path));
+    }
+    if routes.metrics.is_some() {
+        say!("{}", t!("serve-metrics", address = address));
+    }
+    service::notify("READY=1");
+
+    let (stop, stopping) = watch::channel(false);
+    let serve = async {
+        let result = tokio::select! {
+            result = accept_loop(listener, routes) => result,
+            signal = shutdown::signal() => {
+                say!("{}", t!("serve-stopped", signal = signal));
+                Ok(())
+            }
+        };
+        service::notify("STOPPING=1");
+        let _ = stop.send(true);
+        result
+    };
+    let sync = sync_loop(&live, options.sync_minutes, Arc::clone(&metrics), info, push.clone(), stopping.clone());
+    let channel = async {
+        if let (Some(push), Some(url)) = (&push, webhook::url(&config)) {
+            webhook::channel_loop(&config, push, url, stopping.clone()).await;
+        }
+    };
+    // The background loops share this task since sync errors aren't Send
+    let (result, (), (), (), ()) = tokio::join!(
+        serve,
+        reminder_loop(&live, notifiers, Arc::clone(&metrics), tasks, stopping.clone()),
+        sync,
+        channel,
+        reload::watch_loop(&live, stopping.clone())
+    );
+    let _ = std::fs::remove_file(paths::data(SERVER_INFO_PATH));
+    result
+}
+
+async fn accept_loop(listener: TcpListener, routes: Arc<Routes>) -> Result<(), Box<dyn std::error::Error>> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let routes = Arc::clone(&routes);
+        tokio::spawn(async move {
+            let service = service_fn(move |request| handle(request, Arc::clone(&routes)));
+            if let Err(e) = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await {
+                eprintln!("{}", t!("serve-connection-failed", error = format!("{:?}", e)));
+            }
+        });
+    }
+}
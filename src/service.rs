@@ -0,0 +1,390 @@
+// Running `serve` as a background service started at login, with `sync` run
+// periodically if wanted: user-level systemd units on Linux, Task Scheduler
+// tasks on Windows and launchd agents on macOS
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::i18n::t;
+use crate::paths;
+
+// Program name the services are named after
+const APP: &str = "todo_task";
+
+// Names of the installed units; see `named` for a profile's
+const SERVICE_UNIT: &str = "todo_task.service";
+const SYNC_SERVICE_UNIT: &str = "todo_task-sync.service";
+const SYNC_TIMER_UNIT: &str = "todo_task-sync.timer";
+
+// Names of the scheduled tasks on Windows
+const WINDOWS_TASK: &str = "todo_task";
+const WINDOWS_SYNC_TASK: &str = "todo_task sync";
+
+// Where the service runs from: this program, in the current folder, using the
+// same folder as this run if one was given with `--home` or TODO_HOME
+struct Paths {
+    exe: PathBuf,
+    dir: PathBuf,
+    home: Option<PathBuf>,
+    profile: Option<&'static str>,
+}
+
+impl Paths {
+    fn current() -> Result<Paths, Box<dyn std::error::Error>> {
+        Ok(Paths {
+            exe: std::env::current_exe()?,
+            dir: std::env::current_dir()?,
+            home: paths::home().map(Path::to_path_buf),
+            profile: paths::profile(),
+        })
+    }
+
+    // The arguments to run a command with
+    fn args(&self, args: &[&str]) -> Vec<String> {
+        let mut all = Vec::new();
+        if let Some(home) = &self.home {
+            all.extend(["--home".to_string(), home.display().to_string()]);
+        }
+        if let Some(profile) = self.profile {
+            all.extend(["--profile".to_string(), profile.to_string()]);
+        }
+        all.extend(args.iter().map(|arg| arg.to_string()));
+        all
+    }
+}
+
+// A unit, task or agent name with the profile in use in it, e.g.
+// "todo_task-work.service", so each profile can have its own server
+pub fn named(name: &str) -> String {
+    match paths::profile() {
+        Some(profile) => name.replacen(APP, &format!("{}-{}", APP, profile), 1),
+        None => name.to_string(),
+    }
+}
+
+// Where systemd looks for the user's own units
+fn systemd_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let config = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME").ok_or("HOME is not set")?).join(".config"),
+    };
+    Ok(config.join("systemd").join("user"))
+}
+
+// A command line for ExecStart=, quoting every part in case it has spaces
+fn exec(paths: &Paths, args: &[&str]) -> String {
+    let quote = |arg: &str| format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut line = quote(&paths.exe.display().to_string());
+    for arg in paths.args(args) {
+        line.push(' ');
+        line.push_str(&quote(&arg));
+    }
+    line
+}
+
+fn service_unit(paths: &Paths, addr: SocketAddr) -> String {
+    format!(
+        "[Unit]\n\
+         Description=todo_task reminders\n\
+         After=network-online.target\n\
+         Wants=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=notify\n\
+         WorkingDirectory={}\n\
+         ExecStart={}\n\
+         Restart=on-failure\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        paths.dir.display(),
+        exec(paths, &["serve", "--metrics", "--addr", &addr.to_string()])
+    )
+}
+
+fn sync_service_unit(paths: &Paths) -> String {
+    format!(
+        "[Unit]\n\
+         Description=todo_task sync\n\
+         After=network-online.target\n\
+         Wants=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=oneshot\n\
+         WorkingDirectory={}\n\
+         ExecStart={}\n",
+        paths.dir.display(),
+        exec(paths, &["sync"])
+    )
+}
+
+fn sync_timer_unit(minutes: u64) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Run todo_task sync every {} minutes\n\
+         \n\
+         [Timer]\n\
+         OnStartupSec=2min\n\
+         OnUnitActiveSec={}min\n\
+         \n\
+         [Install]\n\
+         WantedBy=timers.target\n",
+        minutes, minutes
+    )
+}
+
+fn systemctl(args: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+    let output = Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Can't run systemctl ({}); is this a systemd system?", e))?;
+    if !output.status.success() {
+        return Err(format!("systemctl --user {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(())
+}
+
+// Write the units and start them, returning the files written
+pub fn install_systemd(addr: SocketAddr, sync_minutes: Option<u64>) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let paths = Paths::current()?;
+    let dir = systemd_dir()?;
+    std::fs::create_dir_all(&dir)?;
+    let mut units = vec![(named(SERVICE_UNIT), service_unit(&paths, addr))];
+    if let Some(minutes) = sync_minutes {
+        units.push((named(SYNC_SERVICE_UNIT), sync_service_unit(&paths)));
+        units.push((named(SYNC_TIMER_UNIT), sync_timer_unit(minutes.max(1))));
+    }
+    let mut written = Vec::new();
+    for (name, contents) in &units {
+        let path = dir.join(name);
+        std::fs::write(&path, contents)?;
+        written.push(path);
+    }
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["enable", "--now", &named(SERVICE_UNIT)])?;
+    if sync_minutes.is_some() {
+        systemctl(&["enable", "--now", &named(SYNC_TIMER_UNIT)])?;
+    }
+    Ok(written)
+}
+
+// Stop and remove whichever units are installed, returning the files removed
+pub fn uninstall_systemd() -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let dir = systemd_dir()?;
+    let mut removed = Vec::new();
+    for name in [SYNC_TIMER_UNIT, SYNC_SERVICE_UNIT, SERVICE_UNIT] {
+        let path = dir.join(named(name));
+        if !path.exists() {
+            continue;
+        }
+        if name != SYNC_SERVICE_UNIT {
+            systemctl(&["disable", "--now", &named(name)])?;
+        }
+        std::fs::remove_file(&path)?;
+        removed.push(path);
+    }
+    if !removed.is_empty() {
+        systemctl(&["daemon-reload"])?;
+    }
+    Ok(removed)
+}
+
+// Exit code of the scripts below for a task that isn't there
+const MISSING: i32 = 3;
+
+// Run a PowerShell script, handing it values through TODO_TASK_* variables so
+// they need no quoting. False when it exits with MISSING.
+fn powershell(script: &str, vars: &[(&str, String)]) -> Result<bool, Box<dyn std::error::Error>> {
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .envs(vars.iter().map(|(name, value)| (format!("TODO_TASK_{}", name), value)))
+        .output()
+        .map_err(|e| format!("Can't run powershell ({})", e))?;
+    match output.status.code() {
+        Some(0) => Ok(true),
+        Some(MISSING) => Ok(false),
+        _ => Err(format!("powershell failed: {}", String::from_utf8_lossy(&output.stderr).trim()).into()),
+    }
+}
+
+// Register the server as a task started at logon, and the sync as one
+// repeating every `sync_minutes`, returning the names of the tasks. A Windows
+// service would run outside the user's session, where reminders can't show
+// toasts, so Task Scheduler is used instead.
+pub fn install_windows(addr: SocketAddr, sync_minutes: Option<u64>) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    if !cfg!(windows) {
+        return Err("Task Scheduler is only on Windows".into());
+    }
+    let paths = Paths::current()?;
+    // One string for the task's arguments, quoting the ones with spaces
+    let vars = |name: &str, args: &[&str]| {
+        let args: Vec<String> = paths.args(args).into_iter().map(|arg| if arg.contains(' ') { format!("\"{}\"", arg) } else { arg }).collect();
+        vec![
+            ("NAME", name.to_string()),
+            ("EXE", paths.exe.display().to_string()),
+            ("DIR", paths.dir.display().to_string()),
+            ("ARGS", args.join(" ")),
+        ]
+    };
+    // No time limit, and restarted if it stops, like Restart=on-failure
+    let settings = "$settings = New-ScheduledTaskSettingsSet -AllowStartIfOnBatteries -DontStopIfGoingOnBatteries \
+                    -ExecutionTimeLimit ([TimeSpan]::Zero) -RestartCount 999 -RestartInterval (New-TimeSpan -Minutes 1); \
+                    $action = New-ScheduledTaskAction -Execute $env:TODO_TASK_EXE -Argument $env:TODO_TASK_ARGS -WorkingDirectory $env:TODO_TASK_DIR; ";
+    let register = "Register-ScheduledTask -TaskName $env:TODO_TASK_NAME -Action $action -Trigger $trigger -Settings $settings -Force | Out-Null";
+    let script = format!(
+        "{}$trigger = New-ScheduledTaskTrigger -AtLogOn -User $env:USERNAME; {}; Start-ScheduledTask -TaskName $env:TODO_TASK_NAME",
+        settings, register
+    );
+    powershell(&script, &vars(&named(WINDOWS_TASK), &["serve", "--metrics", "--addr", &addr.to_string()]))?;
+    let mut installed = vec![named(WINDOWS_TASK)];
+    if let Some(minutes) = sync_minutes {
+        let script = format!(
+            "{}$trigger = New-ScheduledTaskTrigger -Once -At (Get-Date) -RepetitionInterval (New-TimeSpan -Minutes $env:TODO_TASK_MINUTES); {}",
+            settings, register
+        );
+        let mut vars = vars(&named(WINDOWS_SYNC_TASK), &["sync"]);
+        vars.push(("MINUTES", minutes.max(1).to_string()));
+        powershell(&script, &vars)?;
+        installed.push(named(WINDOWS_SYNC_TASK));
+    }
+    Ok(installed)
+}
+
+// Stop and remove whichever scheduled tasks are there, returning their names
+pub fn uninstall_windows() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let script = format!(
+        "if (-not (Get-ScheduledTask -TaskName $env:TODO_TASK_NAME -ErrorAction SilentlyContinue)) {{ exit {} }}; \
+         Stop-ScheduledTask -TaskName $env:TODO_TASK_NAME; \
+         Unregister-ScheduledTask -TaskName $env:TODO_TASK_NAME -Confirm:$false",
+        MISSING
+    );
+    let mut removed = Vec::new();
+    for name in [WINDOWS_SYNC_TASK, WINDOWS_TASK].map(named) {
+        if powershell(&script, &[("NAME", name.clone())])? {
+            removed.push(name);
+        }
+    }
+    Ok(removed)
+}
+
+// Labels of the launchd agents on macOS, also their file names
+const LAUNCHD_AGENT: &str = "com.todo_task.serve";
+const LAUNCHD_SYNC_AGENT: &str = "com.todo_task.sync";
+
+// Where launchd looks for the user's own agents
+fn launch_agents_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(PathBuf::from(std::env::var_os("HOME").ok_or("HOME is not set")?).join("Library").join("LaunchAgents"))
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// A property list for an agent running this program with `args` from the
+// current folder, logging to a file in the data folder, and kept running or
+// started every `interval` seconds
+fn launchd_plist(label: &str, paths: &Paths, args: &[&str], interval: Option<u64>) -> String {
+    let mut arguments = format!("        <string>{}</string>\n", xml_escape(&paths.exe.display().to_string()));
+    for arg in paths.args(args) {
+        arguments.push_str(&format!("        <string>{}</string>\n", xml_escape(&arg)));
+    }
+    let schedule = match interval {
+        Some(seconds) => format!("    <key>StartInterval</key>\n    <integer>{}</integer>\n", seconds),
+        None => "    <key>KeepAlive</key>\n    <true/>\n".to_string(),
+    };
+    let dir = xml_escape(&paths.dir.display().to_string());
+    let log = xml_escape(&paths::data(&format!("{}.log", label)));
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n\
+         <dict>\n    \
+             <key>Label</key>\n    <string>{label}</string>\n    \
+             <key>ProgramArguments</key>\n    <array>\n{arguments}    </array>\n    \
+             <key>WorkingDirectory</key>\n    <string>{dir}</string>\n    \
+             <key>RunAtLoad</key>\n    <true/>\n\
+         {schedule}    \
+             <key>StandardOutPath</key>\n    <string>{log}</string>\n    \
+             <key>StandardErrorPath</key>\n    <string>{log}</string>\n\
+         </dict>\n\
+         </plist>\n"
+    )
+}
+
+fn launchctl(args: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+    let output = Command::new("launchctl").args(args).output().map_err(|e| format!("Can't run launchctl ({})", e))?;
+    if !output.status.success() {
+        return Err(format!("launchctl {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(())
+}
+
+// Write the agents and load them, returning the files written
+pub fn install_launchd(addr: SocketAddr, sync_minutes: Option<u64>) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    if !cfg!(target_os = "macos") {
+        return Err("launchd is only on macOS".into());
+    }
+    let paths = Paths::current()?;
+    let dir = launch_agents_dir()?;
+    std::fs::create_dir_all(&dir)?;
+    let addr = addr.to_string();
+    let serve = ["serve", "--metrics", "--addr", &addr];
+    let label = named(LAUNCHD_AGENT);
+    let mut agents = vec![(launchd_plist(&label, &paths, &serve, None), label)];
+    if let Some(minutes) = sync_minutes {
+        let (label, interval) = (named(LAUNCHD_SYNC_AGENT), Some(minutes.max(1) * 60));
+        agents.push((launchd_plist(&label, &paths, &["sync"], interval), label));
+    }
+    let mut written = Vec::new();
+    for (contents, label) in agents {
+        let path = dir.join(format!("{}.plist", label));
+        // Loaded agents keep their old settings until unloaded
+        if path.exists() {
+            let _ = launchctl(&["unload", &path.display().to_string()]);
+        }
+        std::fs::write(&path, contents)?;
+        launchctl(&["load", "-w", &path.display().to_string()])?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+// Unload and remove whichever agents are installed, returning the files removed
+pub fn uninstall_launchd() -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let dir = launch_agents_dir()?;
+    let mut removed = Vec::new();
+    for label in [LAUNCHD_SYNC_AGENT, LAUNCHD_AGENT].map(named) {
+        let path = dir.join(format!("{}.plist", label));
+        if !path.exists() {
+            continue;
+        }
+        launchctl(&["unload", "-w", &path.display().to_string()])?;
+        std::fs::remove_file(&path)?;
+        removed.push(path);
+    }
+    Ok(removed)
+}
+
+// Tell systemd how a Type=notify service is doing, e.g. "READY=1"; nothing
+// happens when not started by systemd
+#[cfg(target_os = "linux")]
+pub fn notify(state: &str) {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET") else { return };
+    let socket = socket.to_string_lossy();
+    // A leading '@' stands for an abstract socket
+    let address = match socket.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name.as_bytes()),
+        None => SocketAddr::from_pathname(Path::new(&*socket)),
+    };
+    let sent = address.and_then(|address| UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &address));
+    if let Err(e) = sent {
+        eprintln!("{}", t!("service-notify-failed", socket = socket, error = e));
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn notify(_state: &str) {}
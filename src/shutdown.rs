@@ -0,0 +1,25 @@
+// Stopping cleanly on Ctrl-C (SIGINT) or SIGTERM, as sent by `kill` and
+// service managers
+
+// Wait for a request to stop, returning the signal's name
+#[cfg(unix)]
+pub async fn signal() -> &'static str {
+    use tokio::signal::unix::{SignalKind, signal};
+    let (mut interrupt, mut terminate) = match (signal(SignalKind::interrupt()), signal(SignalKind::terminate())) {
+        (Ok(interrupt), Ok(terminate)) => (interrupt, terminate),
+        // Without handlers the process just dies on the signal, as before
+        _ => return std::future::pending().await,
+    };
+    tokio::select! {
+        _ = interrupt.recv() => "SIGINT",
+        _ = terminate.recv() => "SIGTERM",
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn signal() -> &'static str {
+    match tokio::signal::ctrl_c().await {
+        Ok(()) => "Ctrl-C",
+        Err(_) => std::future::pending().await,
+    }
+}
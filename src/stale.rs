@@ -0,0 +1,115 @@
+// `stale`: open tasks nobody has touched in a while, and deciding what to do with them
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+
+use crate::config::Config;
+use crate::conflicts::{prompt, read_answer};
+use crate::i18n::t;
+use crate::{format_time, when, AppState, Task, TaskStatus};
+
+// Snoozes since the last change after which a task is stale however recent that was
+const SNOOZE_LIMIT: u32 = 3;
+
+// Open tasks unchanged for `days` or snoozed again and again, with the reason,
+// least recently touched first; tasks waiting on someone get follow-ups instead
+pub fn find(tasks: &[Task], days: i64, now: DateTime<Utc>) -> Vec<(Task, String)> {
+    let mut stale: Vec<(Task, String)> = tasks
+        .iter()
+        .filter(|task| task.status == TaskStatus::Todo && task.waiting.is_none())
+        .filter_map(|task| {
+            let reason = match task.touched_at.or(task.created_at) {
+                _ if task.snoozes >= SNOOZE_LIMIT => t!("stale-snoozed", count = task.snoozes),
+                None => t!("stale-never-changed"),
+                Some(last) if now - last > Duration::days(days) => t!("stale-untouched", days = (now - last).num_days()),
+                Some(_) => return None,
+            };
+            Some((task.clone(), reason))
+        })
+        .collect();
+    stale.sort_by_key(|(task, _)| task.touched_at.or(task.created_at));
+    stale
+}
+
+// "+3" for three days from today at the task's time of day, or a time as `add` takes it
+fn parse_start(answer: &str, task: &Task) -> Option<DateTime<Utc>> {
+    let zone = task.zone();
+    let Some(days) = answer.strip_prefix('+') else {
+        return when::parse_in(answer, &zone).ok();
+    };
+    let time = task.start_time.map_or(NaiveTime::from_hms_opt(9, 0, 0)?, |start| zone.naive(start).time());
+    let date = zone.naive(Utc::now()).date() + Duration::days(days.parse().ok()?);
+    zone.resolve(date.and_time(time))
+}
+
+async fn reschedule(config: &Config, state: &AppState, task: &Task) {
+    let Some(answer) = read_answer("  New start (+N for N days from today, or e.g. \"tomorrow 9am\" or 2024-12-31T15:00:00Z):") else { return };
+    let Some(start_time) = parse_start(&answer, task) else {
+        println!("  {}", t!("stale-invalid-time", answer = answer));
+        return;
+    };
+    let length = task.schedule().map_or(Duration::minutes(30), |(start, end)| end - start);
+    let Some(updated) = state.reschedule_task(task.id, start_time, start_time + length).await else { return };
+    crate::save_state(state).await;
+    println!("  {}", t!("stale-moved", start = format_time(updated.start_time)));
+    let result = if updated.event_id.is_some() {
+        crate::update_on_calendar(config, &updated).await
+    } else {
+        crate::push_to_calendar(config, state, updated.id).await
+    };
+    if let Err(e) = result {
+        eprintln!("  {}", t!("stale-update-failed", error = format!("{:?}", e)));
+    }
+}
+
+// Ask what to do with each stale task
+pub async fn review(config: &Config, state: &AppState, stale: &[(Task, String)]) {
+    for (task, reason) in stale {
+        println!();
+        println!("{}", t!("stale-task", id = task.id, title = task.title, reason = reason, start = task.format_time(task.start_time)));
+        let answer = loop {
+            let answer = prompt("[r]eschedule, [d]elegate, drop with [x], [s]kip or [q]uit?");
+            if ["r", "d", "x", "s", "q"].contains(&answer.as_str()) {
+                break answer;
+            }
+        };
+        match answer.as_str() {
+            "r" => reschedule(config, state, task).await,
+            "d" => {
+                let Some(delegate) = read_answer("  Delegate to:").filter(|delegate| !delegate.is_empty()) else { continue };
+                if state.set_waiting(task.id, Some(delegate.clone())).await.is_some() {
+                    crate::save_state(state).await;
+                    println!("  {}", t!("stale-delegated", delegate = delegate));
+                }
+            }
+            "x" => {
+                let dropped = crate::remove_task(config, state, task.id).await;
+                if dropped.is_some() {
+                    println!("  {}", t!("stale-dropped"));
+                }
+            }
+            "q" => break,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn untouched_and_snoozed_tasks_are_stale() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 30, 12, 0, 0).unwrap();
+        let touched = |id: u32, days: i64| Task { id, touched_at: Some(now - Duration::days(days)), ..Default::default() };
+        let tasks = vec![
+            touched(1, 20),
+            touched(2, 3),
+            Task { snoozes: SNOOZE_LIMIT, ..touched(3, 1) },
+            Task { status: TaskStatus::Done, ..touched(4, 40) },
+            Task { id: 5, ..Default::default() },
+        ];
+        let ids: Vec<_> = find(&tasks, 14, now).into_iter().map(|(task, _)| task.id).collect();
+        // Never changed first, then by when they were last touched
+        assert_eq!(ids, [5, 1, 3]);
+    }
+}
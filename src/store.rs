@@ -0,0 +1,366 @@
+// The tasks in memory, with indexes by start time, status and tag so queries
+// only look at the tasks that can match instead of scanning every one
+use std::collections::{BTreeSet, HashMap};
+use std::ops::{Bound, Deref, DerefMut};
+
+use chrono::{DateTime, Utc};
+
+use crate::{Task, TaskStatus};
+
+// What a filter can be answered from the indexes with; every task it matches
+// is among the ones found for each of these
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lookup {
+    Status(TaskStatus),
+    /// Compared case-insensitively, without a leading '#'
+    Tag(String),
+    /// Dated tasks starting in the range
+    Start(Bound<DateTime<Utc>>, Bound<DateTime<Utc>>),
+    Undated,
+}
+
+// How a tag is indexed and compared: case-insensitively, without a leading '#'
+pub fn tag_key(tag: &str) -> String {
+    tag.trim_start_matches('#').to_lowercase()
+}
+
+// The indexed values of a task, kept to take it out of the indexes again
+struct Keys {
+    start: Option<DateTime<Utc>>,
+    status: TaskStatus,
+    tags: Vec<String>,
+}
+
+impl Keys {
+    fn of(task: &Task) -> Keys {
+        Keys { start: task.start_time, status: task.status, tags: task.tags.iter().map(|tag| tag_key(tag)).collect() }
+    }
+}
+
+// Reads go straight to the map; changes go through the methods below, which
+// keep the indexes up to date
+#[derive(Default)]
+pub(crate) struct TaskStore {
+    tasks: HashMap<u32, Task>,
+    /// ID the next new task gets
+    next_id: u32,
+    keys: HashMap<u32, Keys>,
+    /// Undated tasks first
+    by_start: BTreeSet<(Option<DateTime<Utc>>, u32)>,
+    by_status: HashMap<TaskStatus, BTreeSet<u32>>,
+    by_tag: HashMap<String, BTreeSet<u32>>,
+    /// Set when tasks were handed out through `values_mut`, so the indexes are
+    /// rebuilt before they're used next
+    stale: bool,
+    /// Whether tasks were added, changed or removed since `mark_saved`
+    changed: bool,
+    /// The tasks as last read from or written to disk, by UUID, to tell the
+    /// changes made here from the ones another process saved meanwhile
+    saved: HashMap<String, Task>,
+    /// New IDs of tasks added here whose IDs another process gave out first
+    moved: HashMap<u32, u32>,
+}
+
+impl Deref for TaskStore {
+    type Target = HashMap<u32, Task>;
+
+    fn deref(&self) -> &HashMap<u32, Task> {
+        &self.tasks
+    }
+}
+
+
+// A task borrowed for changing; it's indexed again once the borrow ends
+pub(crate) struct TaskMut<'a> {
+    store: &'a mut TaskStore,
+    id: u32,
+}
+
+impl Deref for TaskMut<'_> {
+    type Target = Task;
+
+    fn deref(&self) -> &Task {
+        &self.store.tasks[&self.id]
+    }
+}
+
+impl DerefMut for TaskMut<'_> {
+    fn deref_mut(&mut self) -> &mut Task {
+        self.store.tasks.get_mut(&self.id).expect("borrowed task is in the store")
+    }
+}
+
+impl Drop for TaskMut<'_> {
+    fn drop(&mut self) {
+        self.store.reindex(self.id);
+        self.store.changed = true;
+    }
+}
+
+impl TaskStore {
+    // A store holding `tasks`, giving new ones IDs from `next_id` on, or past
+    // the highest ID there if that's higher
+    pub fn new(next_id: u32, tasks: Vec<Task>) -> TaskStore {
+        let mut store = TaskStore { next_id, ..Default::default() };
+        for task in tasks {
+            store.next_id = store.next_id.max(task.id + 1);
+            store.insert(task.id, task);
+        }
+        store.changed = false;
+        store.saved = store.tasks.values().map(|task| (task.uuid.clone(), task.clone())).collect();
+        store
+    }
+
+    // Swap in other tasks, e.g. an earlier version, keeping the IDs handed out
+    pub fn replace(&mut self, tasks: Vec<Task>) {
+        let saved = std::mem::take(&mut self.saved);
+        let moved = std::mem::take(&mut self.moved);
+        *self = TaskStore { saved, moved, ..TaskStore::new(self.next_id, tasks) };
+        self.changed = true;
+    }
+
+    pub fn next_id(&self) -> u32 {
+        self.next_id
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.changed = false;
+        self.saved = self.tasks.values().map(|task| (task.uuid.clone(), task.clone())).collect();
+    }
+
+    // The ID a task added here has now
+    pub fn moved_id(&self, id: u32) -> u32 {
+        self.moved.get(&id).copied().unwrap_or(id)
+    }
+
+    // Take in what another process saved since the tasks were read or last
+    // saved, `disk` being the store file as it is now. Tasks changed or
+    // removed here keep that; the rest become what's on disk. Tasks added on
+    // both sides are all kept, the ones added here moving to a free ID when
+    // theirs was given out there too.
+    pub fn rebase(&mut self, disk: Vec<Task>, next_id: u32) {
+        let unchanged = |task: &Task| {
+            self.saved.get(&task.uuid).is_some_and(|saved| serde_json::to_value(saved).ok() == serde_json::to_value(task).ok())
+        };
+        let mut ours: HashMap<&str, &Task> = self.tasks.values().map(|task| (task.uuid.as_str(), task)).collect();
+        let mut theirs = Vec::new();
+        for task in disk {
+            match ours.get(task.uuid.as_str()) {
+                Some(own) if !unchanged(own) => continue,
+                Some(_) => {
+                    ours.remove(task.uuid.as_str());
+                    theirs.push(task);
+                }
+                // Removed here
+                None if self.saved.contains_key(&task.uuid) => {}
+                None => theirs.push(task),
+            }
+        }
+        // Left are the tasks added or changed here, and the ones removed there
+        let mut ours: Vec<Task> = ours.into_values().filter(|task| !unchanged(task)).cloned().collect();
+        ours.sort_by_key(|task| task.id);
+
+        let taken: BTreeSet<u32> = theirs.iter().map(|task| task.id).collect();
+        let mut next_id = self.next_id.max(next_id).max(taken.last().map_or(0, |id| id + 1));
+        let mut moved = HashMap::new();
+        for task in &ours {
+            if taken.contains(&task.id) {
+                moved.insert(task.id, next_id);
+                next_id += 1;
+            }
+        }
+        for task in &mut ours {
+            let id = |id: u32| moved.get(&id).copied().unwrap_or(id);
+            task.id = id(task.id);
+            task.depends_on = task.depends_on.iter().map(|depends_on| id(*depends_on)).collect();
+            task.parent = task.parent.map(id);
+        }
+        for id in self.moved.values_mut() {
+            *id = moved.get(id).copied().unwrap_or(*id);
+        }
+        self.moved.extend(moved);
+
+        let saved = std::mem::take(&mut self.saved);
+        let moved = std::mem::take(&mut self.moved);
+        theirs.extend(ours);
+        *self = TaskStore { saved, moved, ..TaskStore::new(next_id, theirs) };
+        self.changed = true;
+    }
+
+    // Hand out the ID for a new task
+    pub fn take_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    fn unindex(&mut self, id: u32) {
+        let Some(keys) = self.keys.remove(&id) else { return };
+        self.by_start.remove(&(keys.start, id));
+        if let Some(ids) = self.by_status.get_mut(&keys.status) {
+            ids.remove(&id);
+        }
+        for tag in keys.tags {
+            if let Some(ids) = self.by_tag.get_mut(&tag) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.by_tag.remove(&tag);
+                }
+            }
+        }
+    }
+
+    fn reindex(&mut self, id: u32) {
+        self.unindex(id);
+        let Some(task) = self.tasks.get(&id) else { return };
+        let keys = Keys::of(task);
+        self.by_start.insert((keys.start, id));
+        self.by_status.entry(keys.status).or_default().insert(id);
+        for tag in &keys.tags {
+            self.by_tag.entry(tag.clone()).or_default().insert(id);
+        }
+        self.keys.insert(id, keys);
+    }
+
+    // Bring the indexes up to date; needed before `candidates` after `values_mut`
+    pub fn refresh(&mut self) {
+        if std::mem::take(&mut self.stale) {
+            let ids: Vec<u32> = self.tasks.keys().copied().collect();
+            for id in ids {
+                self.reindex(id);
+            }
+        }
+    }
+
+    pub fn insert(&mut self, id: u32, task: Task) -> Option<Task> {
+        let replaced = self.tasks.insert(id, task);
+        self.reindex(id);
+        self.changed = true;
+        replaced
+    }
+
+    pub fn remove(&mut self, id: &u32) -> Option<Task> {
+        let removed = self.tasks.remove(id)?;
+        self.unindex(*id);
+        self.changed = true;
+        Some(removed)
+    }
+
+    pub fn get_mut(&mut self, id: &u32) -> Option<TaskMut<'_>> {
+        self.tasks.contains_key(id).then_some(TaskMut { store: self, id: *id })
+    }
+
+    // Every task, to change several at once; the indexes are rebuilt afterwards
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Task> {
+        self.stale = true;
+        self.changed = true;
+        self.tasks.values_mut()
+    }
+
+    // IDs of the tasks found by one lookup
+    fn ids(&self, lookup: &Lookup) -> BTreeSet<u32> {
+        match lookup {
+            Lookup::Status(status) => self.by_status.get(status).cloned().unwrap_or_default(),
+            Lookup::Tag(tag) => self.by_tag.get(&tag_key(tag)).cloned().unwrap_or_default(),
+            Lookup::Start(from, to) => {
+                let from = match from {
+                    Bound::Included(time) => Bound::Included((Some(*time), 0)),
+                    Bound::Excluded(time) => Bound::Excluded((Some(*time), u32::MAX)),
+                    // Past the undated tasks
+                    Bound::Unbounded => Bound::Excluded((None, u32::MAX)),
+                };
+                let to = match to {
+                    Bound::Included(time) => Bound::Included((Some(*time), u32::MAX)),
+                    Bound::Excluded(time) => Bound::Excluded((Some(*time), 0)),
+                    Bound::Unbounded => Bound::Unbounded,
+                };
+                self.by_start.range((from, to)).map(|(_, id)| *id).collect()
+            }
+            Lookup::Undated => self.by_start.range((None, 0)..=(None, u32::MAX)).map(|(_, id)| *id).collect(),
+        }
+    }
+
+    // The tasks that can match, narrowed down by every lookup; all of them
+    // without any. The caller still has to test each one.
+    pub fn candidates(&self, lookups: &[Lookup]) -> Vec<&Task> {
+        let mut found: Option<BTreeSet<u32>> = None;
+        for lookup in lookups {
+            let ids = self.ids(lookup);
+            found = Some(match found {
+                Some(found) => found.intersection(&ids).copied().collect(),
+                None => ids,
+            });
+        }
+        match found {
+            Some(ids) => ids.iter().filter_map(|id| self.tasks.get(id)).collect(),
+            None => self.tasks.values().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: u32, uuid: &str, title: &str) -> Task {
+        Task { id, uuid: uuid.to_string(), title: title.to_string(), ..Default::default() }
+    }
+
+    // The store's tasks by ID, as (ID, title)
+    fn titles(store: &TaskStore) -> Vec<(u32, String)> {
+        let mut titles: Vec<_> = store.values().map(|task| (task.id, task.title.clone())).collect();
+        titles.sort();
+        titles
+    }
+
+    #[test]
+    fn tasks_added_on_both_sides_are_kept() {
+        let base = || TaskStore::new(2, vec![task(1, "report", "Write report")]);
+        let (mut here, mut there) = (base(), base());
+        let id = here.take_id();
+        here.insert(id, task(id, "invoice", "Send invoice"));
+        let id = there.take_id();
+        there.insert(id, task(id, "call", "Call Sam"));
+
+        here.rebase(there.values().cloned().collect(), there.next_id());
+        assert_eq!(titles(&here), [(1, "Write report".to_string()), (2, "Call Sam".to_string()), (3, "Send invoice".to_string())]);
+        assert_eq!(here.moved_id(2), 3);
+        assert_eq!(here.next_id(), 4);
+        assert!(here.is_changed());
+    }
+
+    #[test]
+    fn changes_and_removals_made_here_are_kept() {
+        let base = || TaskStore::new(3, vec![task(1, "report", "Write report"), task(2, "invoice", "Send invoice")]);
+        let (mut here, mut there) = (base(), base());
+        here.get_mut(&1).unwrap().title = "Write the report".to_string();
+        here.remove(&2);
+        there.remove(&1);
+        there.get_mut(&2).unwrap().title = "Send the invoice".to_string();
+
+        here.rebase(there.values().cloned().collect(), there.next_id());
+        assert_eq!(titles(&here), [(1, "Write the report".to_string())]);
+    }
+
+    #[test]
+    fn moved_ids_follow_every_rebase() {
+        let mut here = TaskStore::new(2, vec![task(1, "report", "Write report")]);
+        let id = here.take_id();
+        here.insert(id, task(id, "invoice", "Send invoice"));
+        let id = here.take_id();
+        here.insert(id, Task { parent: Some(2), depends_on: vec![2], ..task(id, "stamp", "Buy stamps") });
+
+        // Another process gave out 2, then 4
+        here.rebase(vec![task(1, "report", "Write report"), task(2, "call", "Call Sam")], 3);
+        assert_eq!((here.moved_id(2), here.moved_id(3)), (4, 3));
+        let stamps = &here[&3];
+        assert_eq!((stamps.parent, stamps.depends_on.clone()), (Some(4), vec![4]));
+        here.rebase(vec![task(1, "report", "Write report"), task(2, "call", "Call Sam"), task(4, "plan", "Plan trip")], 5);
+        assert_eq!((here.moved_id(2), here.moved_id(3)), (5, 3));
+        assert_eq!(here[&3].parent, Some(5));
+        assert_eq!(here.moved_id(1), 1);
+    }
+}
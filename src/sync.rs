@@ -0,0 +1,1210 @@
+// Backend-independent two-way sync between the task store and a calendar
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use futures::stream::{self, StreamExt};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::atomic;
+use crate::caldav::CalDavBackend;
+use crate::config::{BackendKind, CalendarConfig, CompletedEvents, Config, MappingRule, SyncRules};
+use crate::confirm;
+use crate::conflicts::Resolution;
+use crate::google::{self, GoogleBackend};
+use crate::google_tasks::GoogleTasksBackend;
+use crate::graph::GraphBackend;
+use crate::i18n::t;
+use crate::ics;
+use crate::progress::Progress;
+use crate::quiet::say;
+use crate::quota;
+use crate::{AppState, Occurrence, Task, TaskStatus};
+
+// File the per-service sync bookkeeping is kept in
+pub const SYNC_STATE_PATH: &str = "sync_state.json";
+
+// A calendar event as seen by the sync logic, independent of the service
+#[derive(Debug, Clone, Default)]
+pub struct RemoteEvent {
+    pub id: String,
+    pub title: String,
+    pub details: String,
+    /// Unset for undated items such as Google Tasks
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub frequency_minutes: Option<i64>,
+    /// The user turned the invitation down
+    pub declined: bool,
+    /// The user is invited and hasn't replied yet
+    pub awaiting_reply: bool,
+    /// App or account that created the event, where the service reports one
+    pub creator: Option<String>,
+    /// Name of the calendar the event was read from
+    pub calendar: Option<String>,
+    /// Change markers: any edit to the event changes its ETag and last-modified time
+    pub etag: Option<String>,
+    pub updated: Option<DateTime<Utc>>,
+    /// For a modified or cancelled occurrence of a recurring event: the series'
+    /// event ID and the start the occurrence originally had
+    pub series_id: Option<String>,
+    pub original_start: Option<DateTime<Utc>>,
+    pub cancelled: bool,
+    /// Custom attributes kept on the event by this tool
+    pub meta: BTreeMap<String, String>,
+    /// Web page of the event on its service, where it has one
+    pub link: Option<String>,
+}
+
+// Prefix of the private event properties that carry a task's own fields.
+// Custom attribute keys can't contain a dot, so they never clash.
+const PROPERTY_PREFIX: &str = "todo_task.";
+
+// Private properties for the task's event: its custom attributes, and its
+// UUID, tags, priority and status, so the event can be told apart as one of
+// this tool's and pulled back as the same task
+pub fn task_properties(task: &Task) -> BTreeMap<String, String> {
+    let mut properties = task.meta.clone();
+    let mut own = |key: &str, value: String| properties.insert(format!("{}{}", PROPERTY_PREFIX, key), value);
+    own("uuid", task.uuid.clone());
+    if !task.tags.is_empty() {
+        own("tags", task.tags.join(","));
+    }
+    if let Ok(serde_json::Value::String(priority)) = serde_json::to_value(task.priority) {
+        own("priority", priority);
+    }
+    own("status", if task.status == TaskStatus::Done { "done" } else { "todo" }.to_string());
+    properties
+}
+
+impl RemoteEvent {
+    // UUID of the task the event was made for, when this tool made it
+    pub fn task_uuid(&self) -> Option<&str> {
+        self.meta.get(&format!("{}uuid", PROPERTY_PREFIX)).map(String::as_str)
+    }
+
+    pub fn into_task(self) -> Task {
+        let mut meta = self.meta;
+        let mut own = |key: &str| meta.remove(&format!("{}{}", PROPERTY_PREFIX, key));
+        let uuid = own("uuid").unwrap_or_default();
+        let tags = own("tags").map(|tags| tags.split(',').filter(|tag| !tag.is_empty()).map(str::to_string).collect()).unwrap_or_default();
+        let priority = own("priority").and_then(|priority| serde_json::from_value(serde_json::Value::String(priority)).ok()).unwrap_or_default();
+        let status = if own("status").as_deref() == Some("done") { TaskStatus::Done } else { TaskStatus::Todo };
+        Task {
+            uuid,
+            tags,
+            priority,
+            id: 0,
+            title: self.title,
+            details: self.details,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            is_recurring: self.frequency_minutes.is_some(),
+            frequency_minutes: self.frequency_minutes,
+            status,
+            event_id: Some(self.id),
+            event_etag: self.etag,
+            event_updated: self.updated,
+            event_link: self.link,
+            meta,
+            ..Default::default()
+        }
+    }
+
+    // Whether the event is the version the task last synced with. Uses the ETag
+    // where the service has one, else the last-modified time.
+    fn unchanged_since(&self, task: &Task) -> bool {
+        match (&self.etag, self.updated) {
+            (Some(etag), _) => task.event_etag.as_ref() == Some(etag),
+            (None, Some(updated)) => task.event_updated == Some(updated),
+            (None, None) => false,
+        }
+    }
+}
+
+// A reply to a calendar invitation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Rsvp {
+    Accept,
+    Decline,
+    Tentative,
+}
+
+// The synced fields of an event or task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub title: String,
+    pub details: String,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub frequency_minutes: Option<i64>,
+}
+
+impl Snapshot {
+    pub fn of_task(task: &Task) -> Snapshot {
+        Snapshot {
+            title: task.title.clone(),
+            details: task.details.clone(),
+            start_time: task.start_time,
+            end_time: task.end_time,
+            // A task with a cron expression or rule looks the way its event comes back from the calendar
+            frequency_minutes: if task.has_rule() {
+                task.rrule().as_deref().and_then(ics::rrule_to_minutes)
+            } else {
+                task.frequency_minutes.filter(|_| task.is_recurring)
+            },
+        }
+    }
+
+    // A cron expression or rule is kept unless the recurrence itself changed
+    pub fn apply_to(&self, task: &mut Task) {
+        let recurrence_changed = Snapshot::of_task(task).frequency_minutes != self.frequency_minutes;
+        task.title = self.title.clone();
+        task.details = self.details.clone();
+        task.start_time = self.start_time;
+        task.end_time = self.end_time;
+        if !task.has_rule() || recurrence_changed {
+            task.cron = None;
+            task.recurrence = None;
+            task.is_recurring = self.frequency_minutes.is_some();
+            task.frequency_minutes = self.frequency_minutes;
+        }
+    }
+
+    pub fn of_event(event: &RemoteEvent) -> Snapshot {
+        Snapshot {
+            title: event.title.clone(),
+            details: event.details.clone(),
+            start_time: event.start_time,
+            end_time: event.end_time,
+            frequency_minutes: event.frequency_minutes,
+        }
+    }
+}
+
+// A task and its event both changed since the last sync, in different ways
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conflict {
+    pub task_id: u32,
+    pub event_id: String,
+    pub local: Snapshot,
+    pub remote: Snapshot,
+    pub detected_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServiceState {
+    pub last_attempt: Option<DateTime<Utc>>,
+    pub last_success: Option<DateTime<Utc>>,
+    /// Error of the last attempt, if it failed
+    pub last_error: Option<String>,
+    /// Incremental sync token, for services that issue one
+    pub sync_token: Option<String>,
+    /// Each linked event as of the last sync, keyed by event ID. Comparing the
+    /// task and the event against it tells which side changed.
+    pub base: BTreeMap<String, Snapshot>,
+    pub conflicts: Vec<Conflict>,
+    /// Conflicts settled with `sync --interactive`
+    pub resolutions: Vec<Resolution>,
+    /// Dated events as of the last pull, so the agenda works offline
+    pub events: Vec<CachedEvent>,
+    pub events_fetched_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedEvent {
+    pub id: String,
+    pub title: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    /// Repeats this often from the start time, so the agenda can list later occurrences
+    #[serde(default)]
+    pub frequency_minutes: Option<i64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SyncState {
+    /// Keyed by service name
+    pub services: BTreeMap<String, ServiceState>,
+}
+
+impl SyncState {
+    pub fn load(path: &str) -> Result<SyncState, Box<dyn std::error::Error>> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SyncState::default()),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        atomic::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn service(&mut self, name: &str) -> &mut ServiceState {
+        self.services.entry(name.to_string()).or_default()
+    }
+
+    // Drop an event that was deleted from its service outside of sync
+    pub fn forget_event(&mut self, event_id: &str) {
+        for service in self.services.values_mut() {
+            service.base.remove(event_id);
+            service.conflicts.retain(|conflict| conflict.event_id != event_id);
+        }
+    }
+}
+
+// The config's sync rules, with patterns compiled
+pub struct SyncFilter {
+    push_tags: Vec<String>,
+    push_projects: Vec<String>,
+    pull_titles: Vec<Regex>,
+    ignore_titles: Vec<String>,
+    ignore_title_patterns: Vec<Regex>,
+    ignore_declined: bool,
+    ignore_creators: Vec<Regex>,
+    rules: Vec<(MappingRule, Option<Regex>)>,
+    /// Completed tasks' events were marked or deleted, so they no longer sync
+    freeze_completed: bool,
+}
+
+fn compile(patterns: &[String], setting: &str) -> Result<Vec<Regex>, Box<dyn std::error::Error>> {
+    Ok(patterns
+        .iter()
+        .map(|pattern| Regex::new(pattern).map_err(|e| t!("sync-invalid-pattern", setting = setting, pattern = pattern, error = e)))
+        .collect::<Result<_, _>>()?)
+}
+
+impl SyncFilter {
+    pub fn new(rules: &SyncRules) -> Result<SyncFilter, Box<dyn std::error::Error>> {
+        Ok(SyncFilter {
+            push_tags: rules.push_tags.iter().map(|tag| tag.trim_start_matches('#').to_string()).collect(),
+            push_projects: rules.push_projects.clone(),
+            pull_titles: compile(&rules.pull_titles, "pull_titles")?,
+            ignore_titles: rules.ignore.titles.iter().map(|title| title.to_lowercase()).collect(),
+            ignore_title_patterns: compile(&rules.ignore.title_patterns, "ignore.title_patterns")?,
+            ignore_declined: rules.ignore.declined,
+            ignore_creators: compile(&rules.ignore.creators, "ignore.creators")?,
+            rules: rules
+                .rules
+                .iter()
+                .map(|rule| {
+                    let title = compile(rule.title.as_slice(), "rules.title")?.pop();
+                    Ok((rule.clone(), title))
+                })
+                .collect::<Result<_, Box<dyn std::error::Error>>>()?,
+            freeze_completed: rules.completed != CompletedEvents::Keep,
+        })
+    }
+
+    pub fn pushes(&self, task: &Task) -> bool {
+        if task.local_only || task.inbox {
+            return false;
+        }
+        if self.push_tags.is_empty() && self.push_projects.is_empty() {
+            return true;
+        }
+        task.tags.iter().any(|tag| self.push_tags.contains(tag))
+            || task.project.as_ref().is_some_and(|project| self.push_projects.contains(project))
+    }
+
+    // Whether a new event should become a task
+    pub fn pulls(&self, event: &RemoteEvent) -> bool {
+        let wanted = self.pull_titles.is_empty() || self.pull_titles.iter().any(|pattern| pattern.is_match(&event.title));
+        wanted && !self.ignores(event)
+    }
+
+    // Give a task created from the event the project and tags of every matching rule
+    pub fn classify(&self, event: &RemoteEvent, task: &mut Task) {
+        for (rule, title) in &self.rules {
+            let calendar_matches = rule.calendar.as_ref().is_none_or(|calendar| {
+                event.calendar.as_ref().is_some_and(|name| name.eq_ignore_ascii_case(calendar))
+            });
+            let title_matches = title.as_ref().is_none_or(|title| title.is_match(&event.title));
+            if !calendar_matches || !title_matches {
+                continue;
+            }
+            if rule.project.is_some() {
+                task.project = rule.project.clone();
+            }
+            for tag in &rule.tags {
+                let tag = tag.trim_start_matches('#').to_string();
+                if !task.tags.contains(&tag) {
+                    task.tags.push(tag);
+                }
+            }
+        }
+    }
+
+    fn ignores(&self, event: &RemoteEvent) -> bool {
+        (self.ignore_declined && event.declined)
+            || self.ignore_titles.contains(&event.title.to_lowercase())
+            || self.ignore_title_patterns.iter().any(|pattern| pattern.is_match(&event.title))
+            || event
+                .creator
+                .as_ref()
+                .is_some_and(|creator| self.ignore_creators.iter().any(|pattern| pattern.is_match(creator)))
+    }
+}
+
+// Name of the calendar service a backend kind syncs with
+pub fn calendar_name(kind: BackendKind) -> &'static str {
+    match kind {
+        BackendKind::Google => "Google Calendar",
+        BackendKind::Caldav => "CalDAV",
+        BackendKind::Outlook => "Outlook",
+    }
+}
+
+pub trait CalendarBackend {
+    fn name(&self) -> &'static str;
+    // Whether the task belongs on this backend; calendars only take dated tasks
+    fn accepts(&self, task: &Task) -> bool {
+        task.start_time.is_some()
+    }
+    // Create the task's event on the calendar, returning the remote event ID
+    async fn push(&self, task: &Task) -> Result<String, Box<dyn std::error::Error>>;
+    // Create events for several tasks, returning each one's event ID or error in order
+    async fn push_all(&self, tasks: &[Task], progress: &Progress) -> Vec<Result<String, Box<dyn std::error::Error>>> {
+        push_each(self, tasks, progress).await
+    }
+    // Replace the linked event's fields with the task's current ones
+    async fn update(&self, event_id: &str, task: &Task) -> Result<(), Box<dyn std::error::Error>>;
+    async fn delete(&self, event_id: &str) -> Result<(), Box<dyn std::error::Error>>;
+    // Give the event a color; None picks the service's color for completed tasks
+    async fn set_color(&self, _event_id: &str, _task: &Task, _color: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        Err(t!("sync-no-color", service = self.name()).into())
+    }
+    // Fetch the calendar's upcoming events
+    async fn pull(&self) -> Result<Vec<RemoteEvent>, Box<dyn std::error::Error>>;
+    // Set the user's reply to an invitation and let the organizer know
+    async fn respond(&self, _event_id: &str, _reply: Rsvp) -> Result<(), Box<dyn std::error::Error>> {
+        Err(t!("sync-no-replies", service = self.name()).into())
+    }
+}
+
+/// Pushes in flight at once when the config doesn't say
+pub const DEFAULT_CONCURRENCY: usize = 5;
+static CONCURRENCY: AtomicUsize = AtomicUsize::new(DEFAULT_CONCURRENCY);
+
+pub fn set_concurrency(concurrency: Option<usize>) {
+    CONCURRENCY.store(concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1), Ordering::SeqCst);
+}
+
+fn concurrency() -> usize {
+    CONCURRENCY.load(Ordering::SeqCst)
+}
+
+// Push tasks a request each, several at once; the results are in the tasks' order
+async fn push_each<B: CalendarBackend + ?Sized>(backend: &B, tasks: &[Task], progress: &Progress) -> Vec<Result<String, Box<dyn std::error::Error>>> {
+    stream::iter(tasks)
+        .map(|task| async move {
+            progress.item(&task.title);
+            let result = backend.push(task).await;
+            progress.step(result.is_ok());
+            result
+        })
+        .buffered(concurrency())
+        .collect()
+        .await
+}
+
+// The backend selected in the config
+pub enum Backend {
+    Google(GoogleBackend),
+    CalDav(CalDavBackend),
+    Graph(GraphBackend),
+    GoogleTasks(GoogleTasksBackend),
+}
+
+impl Backend {
+    pub async fn from_config(config: &Config) -> Result<Backend, Box<dyn std::error::Error>> {
+        match config.backend {
+            BackendKind::Google => Ok(Backend::Google(GoogleBackend::new(config).await?)),
+            BackendKind::Caldav => {
+                let caldav = config
+                    .caldav
+                    .as_ref()
+                    .ok_or_else(|| t!("sync-section-missing", section = "caldav"))?;
+                Ok(Backend::CalDav(CalDavBackend::new(caldav)?))
+            }
+            BackendKind::Outlook => {
+                let graph = config
+                    .outlook
+                    .as_ref()
+                    .ok_or_else(|| t!("sync-section-missing", section = "outlook"))?;
+                Ok(Backend::Graph(GraphBackend::new(graph, config.token_store).await?))
+            }
+        }
+    }
+
+    // A calendar from "calendars", whose events are only read
+    pub async fn for_calendar(config: &Config, calendar: &CalendarConfig) -> Result<Backend, Box<dyn std::error::Error>> {
+        match calendar.kind {
+            BackendKind::Google => Ok(Backend::Google(GoogleBackend::for_calendar(config, calendar).await?)),
+            BackendKind::Caldav => {
+                let caldav = calendar
+                    .caldav
+                    .as_ref()
+                    .ok_or_else(|| t!("sync-calendar-section-missing", name = calendar.name, section = "caldav"))?;
+                Ok(Backend::CalDav(CalDavBackend::new(caldav)?))
+            }
+            BackendKind::Outlook => {
+                let graph = calendar
+                    .outlook
+                    .as_ref()
+                    .ok_or_else(|| t!("sync-calendar-section-missing", name = calendar.name, section = "outlook"))?;
+                Ok(Backend::Graph(GraphBackend::for_calendar(graph, config.token_store, &calendar.name).await?))
+            }
+        }
+    }
+
+    // The backend a task's event lives on: the calendar for dated tasks,
+    // Google Tasks (if enabled) for undated ones
+    pub async fn for_task(config: &Config, task: &Task) -> Result<Option<Backend>, Box<dyn std::error::Error>> {
+        if task.start_time.is_some() {
+            return Ok(Some(Backend::from_config(config).await?));
+        }
+        match &config.google_tasks {
+            Some(google_tasks) => Ok(Some(Backend::GoogleTasks(GoogleTasksBackend::new(google_tasks, config.token_store, &google::credentials_path(config)).await?))),
+            None => Ok(None),
+        }
+    }
+}
+
+// Every request goes through `quota` to be counted and rate limited
+impl CalendarBackend for Backend {
+    fn name(&self) -> &'static str {
+        match self {
+            Backend::Google(backend) => backend.name(),
+            Backend::CalDav(backend) => backend.name(),
+            Backend::Graph(backend) => backend.name(),
+            Backend::GoogleTasks(backend) => backend.name(),
+        }
+    }
+
+    fn accepts(&self, task: &Task) -> bool {
+        match self {
+            Backend::Google(backend) => backend.accepts(task),
+            Backend::CalDav(backend) => backend.accepts(task),
+            Backend::Graph(backend) => backend.accepts(task),
+            Backend::GoogleTasks(backend) => backend.accepts(task),
+        }
+    }
+
+    async fn push(&self, task: &Task) -> Result<String, Box<dyn std::error::Error>> {
+        quota::call(self.name(), || async move {
+            match self {
+                Backend::Google(backend) => backend.push(task).await,
+                Backend::CalDav(backend) => backend.push(task).await,
+                Backend::Graph(backend) => backend.push(task).await,
+                Backend::GoogleTasks(backend) => backend.push(task).await,
+            }
+        })
+        .await
+    }
+
+    // Google takes batches of inserts; other services get one request per task
+    async fn push_all(&self, tasks: &[Task], progress: &Progress) -> Vec<Result<String, Box<dyn std::error::Error>>> {
+        let Backend::Google(backend) = self else {
+            return push_each(self, tasks, progress).await;
+        };
+        // Batches go out several at once too
+        let batches: Vec<Vec<_>> = stream::iter(tasks.chunks(google::BATCH_SIZE))
+            .map(|batch| async move {
+                progress.item(&t!("sync-batch", count = batch.len(), title = batch[0].title));
+                // Google counts every request in a batch against the quota
+                let results: Vec<Result<String, Box<dyn std::error::Error>>> =
+                    match quota::call_counted(self.name(), batch.len() as u64, || backend.push_batch(batch)).await {
+                        Ok(results) => results,
+                        Err(e) => {
+                            let message = e.to_string();
+                            batch.iter().map(|_| Err(message.clone().into())).collect()
+                        }
+                    };
+                for result in &results {
+                    progress.step(result.is_ok());
+                }
+                results
+            })
+            .buffered(concurrency())
+            .collect()
+            .await;
+        batches.into_iter().flatten().collect()
+    }
+
+    async fn update(&self, event_id: &str, task: &Task) -> Result<(), Box<dyn std::error::Error>> {
+        quota::call(self.name(), || async move {
+            match self {
+                Backend::Google(backend) => backend.update(event_id, task).await,
+                Backend::CalDav(backend) => backend.update(event_id, task).await,
+                Backend::Graph(backend) => backend.update(event_id, task).await,
+                Backend::GoogleTasks(backend) => backend.update(event_id, task).await,
+            }
+        })
+        .await
+    }
+
+    async fn delete(&self, event_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        quota::call(self.name(), || async move {
+            match self {
+                Backend::Google(backend) => backend.delete(event_id).await,
+                Backend::CalDav(backend) => backend.delete(event_id).await,
+                Backend::Graph(backend) => backend.delete(event_id).await,
+                Backend::GoogleTasks(backend) => backend.delete(event_id).await,
+            }
+        })
+        .await
+    }
+
+    async fn set_color(&self, event_id: &str, task: &Task, color: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        quota::call(self.name(), || async move {
+            match self {
+                Backend::Google(backend) => backend.set_color(event_id, task, color).await,
+                Backend::CalDav(backend) => backend.set_color(event_id, task, color).await,
+                Backend::Graph(backend) => backend.set_color(event_id, task, color).await,
+                Backend::GoogleTasks(backend) => backend.set_color(event_id, task, color).await,
+            }
+        })
+        .await
+    }
+
+    async fn pull(&self) -> Result<Vec<RemoteEvent>, Box<dyn std::error::Error>> {
+        quota::call(self.name(), || async move {
+            match self {
+                Backend::Google(backend) => backend.pull().await,
+                Backend::CalDav(backend) => backend.pull().await,
+                Backend::Graph(backend) => backend.pull().await,
+                Backend::GoogleTasks(backend) => backend.pull().await,
+            }
+        })
+        .await
+    }
+
+    async fn respond(&self, event_id: &str, reply: Rsvp) -> Result<(), Box<dyn std::error::Error>> {
+        quota::call(self.name(), || async move {
+            match self {
+                Backend::Google(backend) => backend.respond(event_id, reply).await,
+                Backend::CalDav(backend) => backend.respond(event_id, reply).await,
+                Backend::Graph(backend) => backend.respond(event_id, reply).await,
+                Backend::GoogleTasks(backend) => backend.respond(event_id, reply).await,
+            }
+        })
+        .await
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SyncSummary {
+    pub pushed: usize,
+    pub added: usize,
+    pub updated: usize,
+    /// Local edits sent to the service
+    pub sent: usize,
+    /// Events of locally removed tasks deleted from the service
+    pub deleted: usize,
+    pub conflicts: usize,
+    /// New events left out by the sync rules
+    pub skipped: usize,
+    /// (task ID, title) of invitations the user hasn't replied to
+    pub invitations: Vec<(u32, String)>,
+    /// Modified or cancelled occurrences recorded on recurring tasks
+    pub exceptions: usize,
+    /// Events made by this tool earlier linked to tasks again
+    pub adopted: usize,
+}
+
+// Keep the dated events just pulled for the agenda
+fn cache_events(service: &mut ServiceState, events: &[RemoteEvent]) {
+    service.events = events
+        .iter()
+        .filter_map(|event| {
+            Some(CachedEvent {
+                id: event.id.clone(),
+                title: event.title.clone(),
+                start_time: event.start_time?,
+                end_time: event.end_time?,
+                frequency_minutes: event.frequency_minutes,
+            })
+        })
+        .collect();
+    service.events_fetched_at = Some(Utc::now());
+}
+
+// Fetch the events of a calendar that's only read, from "calendars", into
+// the agenda; returns how many there are
+pub async fn fetch<B: CalendarBackend>(backend: &B, service: &mut ServiceState) -> Result<usize, Box<dyn std::error::Error>> {
+    let pulled = backend.pull().await?;
+    let events: Vec<RemoteEvent> = pulled.into_iter().filter(|event| event.series_id.is_none() && !event.declined).collect();
+    cache_events(service, &events);
+    Ok(service.events.len())
+}
+
+// Push tasks not yet on the calendar, then pull remote events into the store.
+// Linked events are merged against `service.base`: whichever side changed wins,
+// and a change on both sides is recorded as a conflict and left alone.
+// With `dry_run` nothing is changed, neither the service, the tasks nor
+// `service`, and every step is printed instead.
+pub async fn sync<B: CalendarBackend>(
+    backend: &B,
+    state: &AppState,
+    service: &mut ServiceState,
+    filter: &SyncFilter,
+    dry_run: bool,
+    confirm: bool,
+) -> Result<SyncSummary, Box<dyn std::error::Error>> {
+    let mut summary = SyncSummary::default();
+    // Events are fetched first so ones this tool made earlier are known
+    // before anything is pushed again. Occurrences that differ from their
+    // series are applied to the series' task below.
+    let pulled = {
+        let _progress = if dry_run { Progress::hidden() } else { Progress::spinner(&t!("sync-fetching", service = backend.name())) };
+        backend.pull().await?
+    };
+    let (exceptions, events): (Vec<RemoteEvent>, Vec<RemoteEvent>) = pulled.into_iter().partition(|event| event.series_id.is_some());
+    if !dry_run {
+        cache_events(service, &events);
+    }
+    let held = adopt_orphans(state, service, &events, &mut summary, dry_run, confirm).await;
+
+    let unpushed: Vec<Task> = state
+        .list_tasks()
+        .await
+        .into_iter()
+        .filter(|task| task.event_id.is_none() && task.status != TaskStatus::Done && backend.accepts(task) && filter.pushes(task))
+        .filter(|task| !held.iter().any(|event| event.task_uuid() == Some(task.uuid.as_str())))
+        .collect();
+    summary.pushed = unpushed.len();
+    if dry_run {
+        for task in &unpushed {
+            println!("  {}", t!("sync-would-push", id = task.id, title = task.title));
+        }
+    } else if !unpushed.is_empty() {
+        let mut first_error = None;
+        let progress = Progress::bar(&t!("sync-pushing", service = backend.name()), unpushed.len());
+        let results = backend.push_all(&unpushed, &progress).await;
+        progress.finish();
+        for (task, result) in unpushed.iter().zip(results) {
+            match result {
+                Ok(event_id) => {
+                    state.link_event(task.id, event_id.clone()).await;
+                    service.base.insert(event_id, Snapshot::of_task(task));
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        // Tasks pushed before the failure stay linked
+        if let Some(e) = first_error {
+            return Err(e);
+        }
+    }
+
+    let tasks = state.list_tasks().await;
+    let events: Vec<RemoteEvent> = events.into_iter().filter(|event| !held.iter().any(|orphan| orphan.id == event.id)).collect();
+    // Merging may update or delete events, one request each
+    let progress = if dry_run { Progress::hidden() } else { Progress::bar(&t!("sync-merging", service = backend.name()), events.len()) };
+    for event in events {
+        progress.item(&event.title);
+        progress.step(true);
+        let remote = Snapshot::of_event(&event);
+        let Some(task) = tasks.iter().find(|task| task.event_id.as_deref() == Some(event.id.as_str())) else {
+            let known = if dry_run { service.base.contains_key(&event.id) } else { service.base.remove(&event.id).is_some() };
+            if known {
+                // Known event whose task was removed here
+                if dry_run {
+                    println!("  {}", t!("sync-would-delete", title = event.title));
+                } else {
+                    backend.delete(&event.id).await?;
+                }
+                summary.deleted += 1;
+            } else if !filter.pulls(&event) {
+                if dry_run {
+                    println!("  {}", t!("sync-would-skip", title = event.title));
+                }
+                summary.skipped += 1;
+            } else {
+                let mut task = event.clone().into_task();
+                filter.classify(&event, &mut task);
+                if dry_run {
+                    println!(
+                        "  {}",
+                        t!(
+                            "sync-would-add",
+                            title = task.title,
+                            project = task.project.as_deref().unwrap_or("-"),
+                            tags = if task.tags.is_empty() { "-".to_string() } else { task.tags.join(", ") }
+                        )
+                    );
+                } else {
+                    service.base.insert(event.id.clone(), remote);
+                    let title = task.title.clone();
+                    let task_id = state.add_task(task).await;
+                    if event.awaiting_reply {
+                        summary.invitations.push((task_id, title));
+                    }
+                }
+                summary.added += 1;
+            }
+            continue;
+        };
+        if task.status == TaskStatus::Done && filter.freeze_completed {
+            continue;
+        }
+        if event.awaiting_reply && task.status == TaskStatus::Todo {
+            summary.invitations.push((task.id, task.title.clone()));
+        }
+        let local = Snapshot::of_task(task);
+        // An unmodified event needn't be compared field by field
+        let remote = match service.base.get(&event.id) {
+            Some(base) if event.unchanged_since(task) => base.clone(),
+            _ => remote,
+        };
+        if !dry_run {
+            service.conflicts.retain(|conflict| conflict.event_id != event.id);
+        }
+        let mut version = (event.etag.clone(), event.updated);
+        let agreed = match service.base.get(&event.id) {
+            _ if local == remote => remote,
+            Some(base) if *base == remote => {
+                if dry_run {
+                    println!("  {}", t!("sync-would-send", id = task.id, title = task.title));
+                } else {
+                    backend.update(&event.id, task).await?;
+                }
+                // The update gave the event a new version, which the next sync picks up
+                version = (None, None);
+                summary.sent += 1;
+                local
+            }
+            Some(base) if *base != local => {
+                if dry_run {
+                    println!("  {}", t!("sync-would-conflict", id = task.id, title = task.title));
+                } else {
+                    service.conflicts.push(Conflict {
+                        task_id: task.id,
+                        event_id: event.id.clone(),
+                        local,
+                        remote,
+                        detected_at: Utc::now(),
+                    });
+                }
+                summary.conflicts += 1;
+                continue;
+            }
+            // Only the event changed, or there is no base yet
+            _ => {
+                if dry_run {
+                    println!("  {}", t!("sync-would-update", id = task.id, title = task.title));
+                } else {
+                    state.update_from_event(&event).await;
+                }
+                summary.updated += 1;
+                remote
+            }
+        };
+        if !dry_run {
+            state.set_event_version(task.id, version.0, version.1, event.link.clone()).await;
+            service.base.insert(event.id, agreed);
+        }
+    }
+
+    drop(progress);
+
+    let tasks = state.list_tasks().await;
+    for exception in exceptions {
+        let (Some(series_id), Some(original_start)) = (&exception.series_id, exception.original_start) else { continue };
+        let Some(task) = tasks.iter().find(|task| task.event_id.as_ref() == Some(series_id)) else { continue };
+        let occurrence = if exception.cancelled {
+            Occurrence { cancelled: true, ..Default::default() }
+        } else {
+            Occurrence {
+                cancelled: false,
+                title: Some(exception.title).filter(|title| *title != task.title),
+                start_time: exception.start_time,
+                end_time: exception.end_time,
+            }
+        };
+        if task.overrides.get(&original_start) == Some(&occurrence) {
+            continue;
+        }
+        if dry_run {
+            println!(
+                "  {}",
+                t!("sync-would-record", cancelled = occurrence.cancelled, id = task.id, title = task.title, time = crate::format_time(Some(original_start)))
+            );
+        } else {
+            state.set_override(task.id, original_start, occurrence).await;
+        }
+        summary.exceptions += 1;
+    }
+    Ok(summary)
+}
+
+// Take back events this tool made whose tasks aren't linked to them, as after
+// the task store was lost or restored from an older copy, instead of pushing
+// the tasks or pulling the events a second time. A task with the event's UUID
+// is linked to it again; otherwise the event comes back as a new task, sync
+// rules or not. Asked first when `confirm` is set. Events not adopted, when
+// declined or on a dry run, are returned, for the rest of the sync to leave
+// alone along with their tasks.
+async fn adopt_orphans(
+    state: &AppState,
+    service: &mut ServiceState,
+    events: &[RemoteEvent],
+    summary: &mut SyncSummary,
+    dry_run: bool,
+    confirm: bool,
+) -> Vec<RemoteEvent> {
+    let tasks = state.list_tasks().await;
+    let linked = |event: &RemoteEvent| tasks.iter().any(|task| task.event_id.as_deref() == Some(event.id.as_str()));
+    // A task linked to another event already has one; this event is a copy
+    let copy = |uuid: &str| tasks.iter().any(|task| task.uuid == uuid && task.event_id.is_some());
+    let orphans: Vec<&RemoteEvent> = events
+        .iter()
+        .filter(|event| event.task_uuid().is_some_and(|uuid| !copy(uuid)) && !linked(event) && !service.base.contains_key(&event.id))
+        .collect();
+    if orphans.is_empty() {
+        return Vec::new();
+    }
+    if dry_run {
+        for event in &orphans {
+            println!("  {}", t!("sync-would-adopt", title = event.title));
+        }
+        return orphans.into_iter().cloned().collect();
+    }
+    let question = t!("sync-confirm-adopt", count = orphans.len());
+    if confirm && !confirm::ask(&question) {
+        say!("{}", t!("sync-adopt-declined", count = orphans.len()));
+        return orphans.into_iter().cloned().collect();
+    }
+    for event in orphans {
+        let task = tasks.iter().find(|task| Some(task.uuid.as_str()) == event.task_uuid());
+        match task {
+            Some(task) => state.link_event(task.id, event.id.clone()).await,
+            None => {
+                state.add_task(event.clone().into_task()).await;
+            }
+        }
+        // Whatever differs is sent from the task at the merge below
+        service.base.insert(event.id.clone(), Snapshot::of_event(event));
+        summary.adopted += 1;
+    }
+    Vec::new()
+}
+
+// Work `sync` would do for a service without contacting it
+#[derive(Debug, Default)]
+pub struct Pending {
+    /// Linked tasks edited since the last sync
+    pub changed: usize,
+    /// Events whose task was removed here
+    pub deleted: usize,
+}
+
+pub fn pending(service: &ServiceState, tasks: &[Task]) -> Pending {
+    let mut pending = Pending::default();
+    for (event_id, base) in &service.base {
+        match tasks.iter().find(|task| task.event_id.as_deref() == Some(event_id.as_str())) {
+            Some(task) if Snapshot::of_task(task) != *base => pending.changed += 1,
+            Some(_) => {}
+            None => pending.deleted += 1,
+        }
+    }
+    pending
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::TaskStore;
+    use chrono::TimeZone;
+    use std::sync::Mutex;
+
+    // A calendar kept in memory that records the changes asked of it
+    #[derive(Default)]
+    struct FakeCalendar {
+        events: Vec<RemoteEvent>,
+        pushed: Mutex<Vec<String>>,
+        updated: Mutex<Vec<String>>,
+        deleted: Mutex<Vec<String>>,
+    }
+
+    impl CalendarBackend for FakeCalendar {
+        fn name(&self) -> &'static str {
+            "Fake"
+        }
+
+        async fn push(&self, task: &Task) -> Result<String, Box<dyn std::error::Error>> {
+            let mut pushed = self.pushed.lock().unwrap();
+            pushed.push(task.title.clone());
+            Ok(format!("pushed-{}", pushed.len()))
+        }
+
+        async fn update(&self, event_id: &str, _task: &Task) -> Result<(), Box<dyn std::error::Error>> {
+            self.updated.lock().unwrap().push(event_id.to_string());
+            Ok(())
+        }
+
+        async fn delete(&self, event_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+            self.deleted.lock().unwrap().push(event_id.to_string());
+            Ok(())
+        }
+
+        async fn pull(&self) -> Result<Vec<RemoteEvent>, Box<dyn std::error::Error>> {
+            Ok(self.events.clone())
+        }
+    }
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, hour, 0, 0).unwrap()
+    }
+
+    fn event(id: &str, title: &str, hour: u32) -> RemoteEvent {
+        RemoteEvent {
+            id: id.to_string(),
+            title: title.to_string(),
+            start_time: Some(at(hour)),
+            end_time: Some(at(hour + 1)),
+            etag: Some(format!("{}-{}-{}", id, title, hour)),
+            ..Default::default()
+        }
+    }
+
+    // A task linked to `event`, as the last sync left it
+    fn linked(id: u32, event: &RemoteEvent) -> Task {
+        Task {
+            id,
+            uuid: format!("task-{}", id),
+            event_id: Some(event.id.clone()),
+            event_etag: event.etag.clone(),
+            ..event.clone().into_task()
+        }
+    }
+
+    fn state(tasks: Vec<Task>) -> AppState {
+        AppState { tasks: tokio::sync::Mutex::new(TaskStore::new(1, tasks)) }
+    }
+
+    fn filter() -> SyncFilter {
+        SyncFilter::new(&SyncRules::default()).unwrap()
+    }
+
+    async fn titles(state: &AppState) -> Vec<(u32, String)> {
+        let mut titles: Vec<_> = state.list_tasks().await.into_iter().map(|task| (task.id, task.title)).collect();
+        titles.sort();
+        titles
+    }
+
+    #[tokio::test]
+    async fn dry_run_changes_nothing() {
+        let standup = event("standup", "Standup", 9);
+        let mut edited = linked(1, &standup);
+        edited.title = "Daily standup".to_string();
+        let state = state(vec![edited, Task { id: 2, uuid: "unpushed".to_string(), title: "Review".to_string(), start_time: Some(at(14)), ..Default::default() }]);
+        let mut service = ServiceState::default();
+        service.base.insert(standup.id.clone(), Snapshot::of_event(&standup));
+        let moved = RemoteEvent { series_id: Some("standup".to_string()), original_start: Some(at(9)), ..event("standup-moved", "Standup", 10) };
+        let calendar = FakeCalendar { events: vec![standup.clone(), event("lunch", "Lunch", 12), moved], ..Default::default() };
+
+        let summary = sync(&calendar, &state, &mut service, &filter(), true, false).await.unwrap();
+        assert_eq!((summary.pushed, summary.added, summary.sent, summary.exceptions), (1, 1, 1, 1));
+        assert_eq!(titles(&state).await, [(1, "Daily standup".to_string()), (2, "Review".to_string())]);
+        assert!(!state.has_unsaved_changes().await);
+        assert!(state.get_task(1).await.unwrap().overrides.is_empty());
+        assert_eq!(service.base.keys().collect::<Vec<_>>(), ["standup"]);
+        assert!(service.events.is_empty());
+        assert!(calendar.pushed.lock().unwrap().is_empty() && calendar.updated.lock().unwrap().is_empty());
+    }
+
+    // Sync `calendar` once into a store holding `tasks`, with `base` as the
+    // last sync left the events
+    async fn merge(calendar: &FakeCalendar, tasks: Vec<Task>, base: &[&RemoteEvent]) -> (AppState, ServiceState, SyncSummary) {
+        let state = state(tasks);
+        let mut service = ServiceState::default();
+        for event in base {
+            service.base.insert(event.id.clone(), Snapshot::of_event(event));
+        }
+        let summary = sync(calendar, &state, &mut service, &filter(), false, false).await.unwrap();
+        (state, service, summary)
+    }
+
+    #[tokio::test]
+    async fn local_edits_are_sent() {
+        let standup = event("standup", "Standup", 9);
+        let mut task = linked(1, &standup);
+        task.title = "Daily standup".to_string();
+        let calendar = FakeCalendar { events: vec![standup.clone()], ..Default::default() };
+        let (state, service, summary) = merge(&calendar, vec![task], &[&standup]).await;
+        assert_eq!((summary.sent, summary.updated, summary.conflicts), (1, 0, 0));
+        assert_eq!(*calendar.updated.lock().unwrap(), ["standup"]);
+        assert_eq!(service.base["standup"].title, "Daily standup");
+        assert_eq!(state.get_task(1).await.unwrap().title, "Daily standup");
+    }
+
+    #[tokio::test]
+    async fn remote_edits_update_the_task() {
+        let standup = event("standup", "Standup", 9);
+        let task = linked(1, &standup);
+        let moved = event("standup", "Standup", 10);
+        let calendar = FakeCalendar { events: vec![moved.clone()], ..Default::default() };
+        let (state, service, summary) = merge(&calendar, vec![task], &[&standup]).await;
+        assert_eq!((summary.sent, summary.updated, summary.conflicts), (0, 1, 0));
+        assert!(calendar.updated.lock().unwrap().is_empty());
+        let task = state.get_task(1).await.unwrap();
+        assert_eq!((task.start_time, task.event_etag), (Some(at(10)), moved.etag.clone()));
+        assert_eq!(service.base["standup"], Snapshot::of_event(&moved));
+    }
+
+    #[tokio::test]
+    async fn edits_on_both_sides_are_a_conflict() {
+        let standup = event("standup", "Standup", 9);
+        let mut task = linked(1, &standup);
+        task.title = "Daily standup".to_string();
+        let renamed = event("standup", "Team standup", 9);
+        let calendar = FakeCalendar { events: vec![renamed.clone()], ..Default::default() };
+        let (state, service, summary) = merge(&calendar, vec![task], &[&standup]).await;
+        assert_eq!((summary.sent, summary.updated, summary.conflicts), (0, 0, 1));
+        assert!(calendar.updated.lock().unwrap().is_empty());
+        assert_eq!(state.get_task(1).await.unwrap().title, "Daily standup");
+        let conflict = &service.conflicts[0];
+        assert_eq!((conflict.task_id, conflict.event_id.as_str()), (1, "standup"));
+        assert_eq!((conflict.local.title.as_str(), conflict.remote.title.as_str()), ("Daily standup", "Team standup"));
+        // The base stays as it was, so the next sync still sees both edits
+        assert_eq!(service.base["standup"], Snapshot::of_event(&standup));
+    }
+
+    #[tokio::test]
+    async fn without_a_base_the_event_wins() {
+        let standup = event("standup", "Standup", 9);
+        let mut task = linked(1, &standup);
+        task.title = "Daily standup".to_string();
+        let calendar = FakeCalendar { events: vec![standup.clone()], ..Default::default() };
+        let (state, service, summary) = merge(&calendar, vec![task], &[]).await;
+        assert_eq!((summary.sent, summary.updated, summary.conflicts), (0, 1, 0));
+        assert_eq!(state.get_task(1).await.unwrap().title, "Standup");
+        assert_eq!(service.base["standup"], Snapshot::of_event(&standup));
+    }
+
+    #[tokio::test]
+    async fn conflicts_last_until_resolved() {
+        let standup = event("standup", "Standup", 9);
+        let mut task = linked(1, &standup);
+        task.title = "Daily standup".to_string();
+        let renamed = event("standup", "Team standup", 9);
+        let calendar = FakeCalendar { events: vec![renamed.clone()], ..Default::default() };
+        let (state, mut service, _) = merge(&calendar, vec![task], &[&standup]).await;
+
+        let summary = sync(&calendar, &state, &mut service, &filter(), false, false).await.unwrap();
+        assert_eq!(summary.conflicts, 1);
+        assert_eq!(service.conflicts.len(), 1);
+        assert_eq!(state.get_task(1).await.unwrap().title, "Daily standup");
+
+        let conflict = service.conflicts.pop().unwrap();
+        let local = conflict.local.clone();
+        assert!(crate::conflicts::settle(&calendar, &state, &mut service, conflict, local, "local".to_string()).await);
+        assert_eq!(*calendar.updated.lock().unwrap(), ["standup"]);
+        assert_eq!(service.base["standup"].title, "Daily standup");
+        assert_eq!(service.resolutions[0].choice, "local");
+
+        // The calendar now has the local title under a new version
+        let calendar = FakeCalendar { events: vec![event("standup", "Daily standup", 9)], ..Default::default() };
+        let summary = sync(&calendar, &state, &mut service, &filter(), false, false).await.unwrap();
+        assert_eq!((summary.conflicts, summary.sent, summary.updated), (0, 0, 0));
+        assert!(service.conflicts.is_empty());
+    }
+
+    // `changed` as an occurrence of `series` first held at `original_start`
+    fn occurrence(series: &RemoteEvent, original_start: DateTime<Utc>, changed: RemoteEvent) -> RemoteEvent {
+        RemoteEvent { series_id: Some(series.id.clone()), original_start: Some(original_start), ..changed }
+    }
+
+    #[tokio::test]
+    async fn changed_occurrences_are_recorded_once() {
+        let standup = RemoteEvent { frequency_minutes: Some(24 * 60), ..event("standup", "Standup", 9) };
+        let tomorrow = at(9) + chrono::Duration::days(1);
+        let later = |hours| tomorrow + chrono::Duration::hours(hours);
+        let cancelled = occurrence(&standup, at(9), RemoteEvent { cancelled: true, ..event("standup-1", "Standup", 9) });
+        let moved = occurrence(&standup, tomorrow, RemoteEvent { start_time: Some(later(2)), end_time: Some(later(3)), ..event("standup-2", "Standup", 11) });
+        let calendar = FakeCalendar { events: vec![standup.clone(), cancelled, moved], ..Default::default() };
+        let (state, mut service, summary) = merge(&calendar, vec![linked(1, &standup)], &[&standup]).await;
+        assert_eq!(summary.exceptions, 2);
+        let overrides = state.get_task(1).await.unwrap().overrides;
+        assert_eq!(overrides[&at(9)], Occurrence { cancelled: true, ..Default::default() });
+        assert_eq!(overrides[&tomorrow], Occurrence { cancelled: false, title: None, start_time: Some(later(2)), end_time: Some(later(3)) });
+
+        // Both are already recorded the next time
+        let summary = sync(&calendar, &state, &mut service, &filter(), false, false).await.unwrap();
+        assert_eq!(summary.exceptions, 0);
+        assert_eq!(state.get_task(1).await.unwrap().overrides.len(), 2);
+    }
+
+    // An event this tool made for the task with `uuid`
+    fn made_for(uuid: &str, event: RemoteEvent) -> RemoteEvent {
+        let mut event = event;
+        event.meta.insert(format!("{}uuid", PROPERTY_PREFIX), uuid.to_string());
+        event
+    }
+
+    // A dated task not linked to any event
+    fn unlinked(id: u32, uuid: &str, title: &str) -> Task {
+        Task { id, uuid: uuid.to_string(), title: title.to_string(), start_time: Some(at(9)), end_time: Some(at(10)), ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn orphans_are_linked_to_the_task_with_their_uuid() {
+        let orphan = made_for("report", event("report-event", "Write report", 9));
+        let calendar = FakeCalendar { events: vec![orphan.clone()], ..Default::default() };
+        let tasks = vec![unlinked(1, "report", "Write report"), unlinked(2, "invoice", "Send invoice")];
+        let (state, service, summary) = merge(&calendar, tasks, &[]).await;
+        assert_eq!((summary.adopted, summary.pushed, summary.added), (1, 1, 0));
+        assert_eq!(*calendar.pushed.lock().unwrap(), ["Send invoice"]);
+        assert_eq!(state.get_task(1).await.unwrap().event_id.as_deref(), Some("report-event"));
+        assert!(service.base.contains_key("report-event"));
+    }
+
+    #[tokio::test]
+    async fn orphans_without_a_task_come_back_as_tasks() {
+        let orphan = made_for("lost", event("lost-event", "Call Sam", 9));
+        let calendar = FakeCalendar { events: vec![orphan], ..Default::default() };
+        let (state, _, summary) = merge(&calendar, vec![unlinked(1, "report", "Write report")], &[]).await;
+        assert_eq!((summary.adopted, summary.added), (1, 0));
+        let lost = state.list_tasks().await.into_iter().find(|task| task.uuid == "lost").unwrap();
+        assert_eq!((lost.title.as_str(), lost.event_id.as_deref()), ("Call Sam", Some("lost-event")));
+        assert_eq!(state.get_task(1).await.unwrap().event_id.as_deref(), Some("pushed-1"));
+    }
+
+    #[tokio::test]
+    async fn orphans_are_held_on_a_dry_run_and_adopted_once_confirmed() {
+        let orphan = made_for("report", event("report-event", "Write report", 9));
+        let calendar = FakeCalendar { events: vec![orphan], ..Default::default() };
+        let state = state(vec![unlinked(1, "report", "Write report")]);
+        let mut service = ServiceState::default();
+
+        // Neither pushed again nor linked while held
+        let summary = sync(&calendar, &state, &mut service, &filter(), true, false).await.unwrap();
+        assert_eq!((summary.adopted, summary.pushed), (0, 0));
+        assert_eq!(state.get_task(1).await.unwrap().event_id, None);
+
+        // Confirmation goes ahead without a terminal to ask on
+        let summary = sync(&calendar, &state, &mut service, &filter(), false, true).await.unwrap();
+        assert_eq!((summary.adopted, summary.pushed), (1, 0));
+        assert_eq!(state.get_task(1).await.unwrap().event_id.as_deref(), Some("report-event"));
+        assert!(calendar.pushed.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn copies_of_a_linked_tasks_event_are_left_alone() {
+        let report = event("report-event", "Write report", 9);
+        let copy = made_for("task-1", event("report-copy", "Write report", 9));
+        let calendar = FakeCalendar { events: vec![report.clone(), copy], ..Default::default() };
+        let (state, _, summary) = merge(&calendar, vec![linked(1, &report)], &[&report]).await;
+        assert_eq!(summary.adopted, 0);
+        assert_eq!(state.get_task(1).await.unwrap().event_id.as_deref(), Some("report-event"));
+    }
+}
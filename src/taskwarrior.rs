@@ -0,0 +1,183 @@
+// Taskwarrior `task export` / `task import` JSON
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde_json::{json, Map, Value};
+
+use crate::{AppState, Priority, Task, TaskStatus};
+
+// Attributes Taskwarrior defines itself; anything else is a UDA
+const KNOWN_FIELDS: &[&str] = &[
+    "id", "uuid", "description", "entry", "modified", "end", "start", "status", "project",
+    "tags", "priority", "due", "scheduled", "wait", "until", "annotations", "recur", "mask",
+    "imask", "parent", "depends", "urgency",
+];
+
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub updated: usize,
+    /// Deleted tasks and recurrence templates
+    pub skipped: usize,
+}
+
+fn parse_date(value: &Value) -> Option<DateTime<Utc>> {
+    let naive = NaiveDateTime::parse_from_str(value.as_str()?, "%Y%m%dT%H%M%SZ").ok()?;
+    Some(naive.and_utc())
+}
+
+fn format_date(time: &DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn strings(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| item.as_str().map(str::to_string))
+        .collect()
+}
+
+// Map one exported task; `scheduled` (or else `due`) becomes the start time
+fn from_taskwarrior(item: &Map<String, Value>) -> Option<Task> {
+    let status = match item.get("status")?.as_str()? {
+        "completed" => TaskStatus::Done,
+        "pending" | "waiting" => TaskStatus::Todo,
+        _ => return None,
+    };
+    let start_time = item
+        .get("scheduled")
+        .or_else(|| item.get("due"))
+        .and_then(parse_date);
+    let details = item
+        .get("annotations")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|annotation| annotation["description"].as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let meta = item
+        .iter()
+        .filter(|(key, _)| !KNOWN_FIELDS.contains(&key.as_str()))
+        .map(|(key, value)| {
+            let value = value.as_str().map_or_else(|| value.to_string(), str::to_string);
+            (key.clone(), value)
+        })
+        .collect();
+    Some(Task {
+        id: 0,
+        uuid: item.get("uuid")?.as_str()?.to_string(),
+        title: item.get("description")?.as_str()?.to_string(),
+        details,
+        start_time,
+        end_time: start_time.map(|start| start + Duration::minutes(30)),
+        status,
+        project: item.get("project").and_then(Value::as_str).map(str::to_string),
+        tags: item.get("tags").map(strings).unwrap_or_default(),
+        priority: match item.get("priority").and_then(Value::as_str) {
+            Some("H") => Priority::High,
+            Some("L") => Priority::Low,
+            _ => Priority::Normal,
+        },
+        created_at: item.get("entry").and_then(parse_date),
+        meta,
+        ..Default::default()
+    })
+}
+
+pub async fn import(json: &str, state: &AppState) -> Result<ImportSummary, Box<dyn std::error::Error>> {
+    let items: Vec<Map<String, Value>> = serde_json::from_str(json)?;
+    let mut summary = ImportSummary::default();
+    for item in &items {
+        match from_taskwarrior(item) {
+            Some(task) if state.upsert_uuid(task.clone()).await => summary.updated += 1,
+            Some(_) => summary.added += 1,
+            None => summary.skipped += 1,
+        }
+    }
+    Ok(summary)
+}
+
+fn to_taskwarrior(task: &Task) -> Value {
+    let entry = task.created_at.unwrap_or_else(Utc::now);
+    let mut item = json!({
+        "uuid": task.uuid,
+        "description": task.title,
+        "entry": format_date(&entry),
+        "status": match task.status {
+            TaskStatus::Todo => "pending",
+            TaskStatus::Done => "completed",
+        },
+    });
+    if task.status == TaskStatus::Done {
+        item["end"] = json!(format_date(&Utc::now()));
+    }
+    if let Some(start_time) = &task.start_time {
+        item["scheduled"] = json!(format_date(start_time));
+    }
+    if let Some(project) = &task.project {
+        item["project"] = json!(project);
+    }
+    if !task.tags.is_empty() {
+        item["tags"] = json!(task.tags);
+    }
+    match task.priority {
+        Priority::High | Priority::Urgent => item["priority"] = json!("H"),
+        Priority::Low => item["priority"] = json!("L"),
+        Priority::Normal => {}
+    }
+    if !task.details.is_empty() {
+        item["annotations"] = json!([{ "entry": format_date(&entry), "description": task.details }]);
+    }
+    // Attributes named like a Taskwarrior field would overwrite it
+    for (key, value) in task.meta.iter().filter(|(key, _)| !KNOWN_FIELDS.contains(&key.as_str())) {
+        item[key] = json!(value);
+    }
+    item
+}
+
+pub fn export(tasks: &[Task]) -> Result<String, serde_json::Error> {
+    let items: Vec<Value> = tasks.iter().map(to_taskwarrior).collect();
+    serde_json::to_string_pretty(&items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn exported_tasks_read_back() {
+        let start = Utc.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap();
+        let task = Task {
+            uuid: "5f2c".to_string(),
+            title: "Renew passport".to_string(),
+            details: "Photos first".to_string(),
+            start_time: Some(start),
+            project: Some("Travel".to_string()),
+            tags: vec!["errand".to_string()],
+            priority: Priority::Urgent,
+            created_at: Some(start),
+            meta: [("ticket".to_string(), "T-1".to_string()), ("due".to_string(), "ignored".to_string())].into(),
+            ..Default::default()
+        };
+        let exported = to_taskwarrior(&task);
+        assert_eq!(exported["priority"], "H");
+        assert!(exported["due"].is_null());
+        let read = from_taskwarrior(exported.as_object().unwrap()).unwrap();
+        assert_eq!((&read.uuid, &read.title, &read.details, &read.project, &read.tags), (&task.uuid, &task.title, &task.details, &task.project, &task.tags));
+        assert_eq!((read.start_time, read.created_at, read.priority), (Some(start), Some(start), Priority::High));
+        assert_eq!(read.meta.get("ticket").map(String::as_str), Some("T-1"));
+    }
+
+    #[test]
+    fn due_dates_stand_in_for_scheduled_ones() {
+        let item = json!({"uuid": "a", "description": "Pay rent", "status": "waiting", "due": "20260301T090000Z", "estimate": 2});
+        let task = from_taskwarrior(item.as_object().unwrap()).unwrap();
+        assert_eq!(task.status, TaskStatus::Todo);
+        assert_eq!(task.start_time.map(|time| format_date(&time)).as_deref(), Some("20260301T090000Z"));
+        assert_eq!(task.meta.get("estimate").map(String::as_str), Some("2"));
+        let deleted = json!({"uuid": "b", "description": "Old", "status": "deleted"});
+        assert!(from_taskwarrior(deleted.as_object().unwrap()).is_none());
+    }
+}
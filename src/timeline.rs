@@ -0,0 +1,69 @@
+// Timeline: task occurrences as bars across each day, one row per occurrence,
+// so overlapping and long-running tasks stand out
+use chrono::{Duration, Local, NaiveDate};
+
+use crate::agenda::AgendaItem;
+
+// Columns per day, half an hour each
+const COLUMNS: i64 = 48;
+
+// The hour scale over the bars, labelled every three hours
+fn scale() -> String {
+    (0..24).step_by(3).map(|hour| format!("{:<6}", format!("{:02}", hour))).collect()
+}
+
+// Bar of the part of `item` falling in the day from `midnight`
+fn bar(midnight: chrono::DateTime<chrono::Utc>, item: &AgendaItem) -> String {
+    let slot = Duration::minutes(24 * 60 / COLUMNS);
+    let mut cells: String = (0..COLUMNS)
+        .map(|column| {
+            let from = midnight + slot * column as i32;
+            if item.start_time < from + slot && item.end_time > from { '#' } else { '.' }
+        })
+        .collect();
+    // Short tasks still get a cell
+    if !cells.contains('#') {
+        let column = ((item.start_time - midnight).num_minutes() / slot.num_minutes()).clamp(0, COLUMNS - 1) as usize;
+        cells.replace_range(column..column + 1, "#");
+    }
+    cells
+}
+
+// Rows for the `days` days from `first`, of the items overlapping each day
+pub fn render(items: &[AgendaItem], first: NaiveDate, days: i64) -> String {
+    let mut out = format!("{:<11}{}\n", "", scale().trim_end());
+    for date in first.iter_days().take(days.max(0) as usize) {
+        let midnight = crate::local_midnight(date);
+        let next = crate::local_midnight(date + Duration::days(1));
+        let label = date.format("%a %m-%d").to_string();
+        let today: Vec<&AgendaItem> = items.iter().filter(|item| item.start_time < next && item.end_time > midnight).collect();
+        if today.is_empty() {
+            out.push_str(&format!("{:<11}{}\n", label, ".".repeat(COLUMNS as usize)));
+            continue;
+        }
+        for (index, item) in today.iter().enumerate() {
+            let mut overlaps: Vec<String> = today
+                .iter()
+                .filter(|other| other.source != item.source && other.start_time < item.end_time && other.end_time > item.start_time)
+                .map(|other| other.source.clone())
+                .collect();
+            overlaps.sort();
+            overlaps.dedup();
+            let continued = if item.start_time < midnight {
+                format!(" (since {})", item.start_time.with_timezone(&Local).format("%a %H:%M"))
+            } else {
+                String::new()
+            };
+            out.push_str(&format!(
+                "{:<11}{} {} {}{}{}\n",
+                if index == 0 { label.as_str() } else { "" },
+                bar(midnight, item),
+                item.source,
+                item.title,
+                continued,
+                if overlaps.is_empty() { String::new() } else { format!(" (overlaps {})", overlaps.join(", ")) }
+            ));
+        }
+    }
+    out
+}
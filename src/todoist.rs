@@ -0,0 +1,176 @@
+// Todoist import and sync through the Todoist API
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use hyper::{Method, Request};
+use serde_json::Value;
+
+use crate::config::TodoistConfig;
+use crate::http::{self, HttpClient};
+use crate::{date_only_start, AppState, Priority, Task, TaskStatus};
+
+const TODOIST_URL: &str = "https://api.todoist.com/api/v1";
+
+#[derive(Debug, Default)]
+pub struct TodoistSummary {
+    pub added: usize,
+    pub updated: usize,
+    /// Local tasks marked done because they're no longer active in Todoist
+    pub completed: usize,
+    /// Todoist tasks closed because they were completed here
+    pub closed: usize,
+}
+
+struct TodoistClient {
+    client: HttpClient,
+    token: String,
+}
+
+impl TodoistClient {
+    fn new(config: &TodoistConfig) -> Result<TodoistClient, Box<dyn std::error::Error>> {
+        Ok(TodoistClient { client: http::client()?, token: config.token.clone() })
+    }
+
+    async fn call(&self, method: Method, path: &str) -> Result<Value, Box<dyn std::error::Error>> {
+        let request = Request::builder()
+            .method(method.clone())
+            .uri(format!("{}{}", TODOIST_URL, path))
+            .header("Authorization", format!("Bearer {}", self.token))
+            .body(String::new())?;
+        let (status, body) = http::send(&self.client, request).await?;
+        http::check(status, &body, &format!("Todoist {} {}", method, path))?;
+        if body.trim().is_empty() {
+            return Ok(Value::Null);
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    // Follow the cursor through every page of a list endpoint
+    async fn list(&self, path: &str) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let page_path = match &cursor {
+                Some(cursor) => format!("{}?limit=200&cursor={}", path, cursor),
+                None => format!("{}?limit=200", path),
+            };
+            let page = self.call(Method::GET, &page_path).await?;
+            items.extend(page["results"].as_array().cloned().unwrap_or_default());
+            match page["next_cursor"].as_str() {
+                Some(next) => cursor = Some(next.to_string()),
+                None => return Ok(items),
+            }
+        }
+    }
+}
+
+// Todoist priorities run from 1 (none) to 4 (urgent)
+fn priority_from_todoist(priority: i64) -> Priority {
+    match priority {
+        4 => Priority::Urgent,
+        3 => Priority::High,
+        _ => Priority::Normal,
+    }
+}
+
+// A Todoist due date becomes the task's start time; without a duration the
+// task lasts 30 minutes.
+fn due_times(item: &Value) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+    let due = &item["due"];
+    let start = if let Some(datetime) = due["datetime"].as_str() {
+        datetime.parse::<DateTime<Utc>>().ok().or_else(|| {
+            // Floating times have no offset and follow the user's local clock
+            NaiveDateTime::parse_from_str(datetime, "%Y-%m-%dT%H:%M:%S")
+                .ok()
+                .and_then(|naive| chrono::Local.from_local_datetime(&naive).earliest())
+                .map(|time| time.with_timezone(&Utc))
+        })
+    } else {
+        due["date"]
+            .as_str()
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .and_then(date_only_start)
+    };
+    let Some(start) = start else { return (None, None) };
+    let amount = item["duration"]["amount"].as_i64();
+    let length = match item["duration"]["unit"].as_str() {
+        Some("day") => Duration::days(amount.unwrap_or(1)),
+        _ => Duration::minutes(amount.unwrap_or(30)),
+    };
+    (Some(start), Some(start + length))
+}
+
+fn todoist_task(item: &Value, projects: &HashMap<String, String>) -> Option<Task> {
+    let (start_time, end_time) = due_times(item);
+    Some(Task {
+        id: 0,
+        title: item["content"].as_str()?.to_string(),
+        details: item["description"].as_str().unwrap_or_default().to_string(),
+        start_time,
+        end_time,
+        status: TaskStatus::Todo,
+        project: item["project_id"].as_str().and_then(|id| projects.get(id)).cloned(),
+        tags: item["labels"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|label| label.as_str().map(str::to_string))
+            .collect(),
+        priority: priority_from_todoist(item["priority"].as_i64().unwrap_or(1)),
+        todoist_id: Some(item["id"].as_str()?.to_string()),
+        ..Default::default()
+    })
+}
+
+// Pull active Todoist tasks, returning the IDs that were seen
+async fn pull(client: &TodoistClient, state: &AppState, summary: &mut TodoistSummary) -> Result<HashSet<String>, Box<dyn std::error::Error>> {
+    let projects: HashMap<String, String> = client
+        .list("/projects")
+        .await?
+        .iter()
+        .filter_map(|project| Some((project["id"].as_str()?.to_string(), project["name"].as_str()?.to_string())))
+        .collect();
+
+    let mut seen = HashSet::new();
+    for item in client.list("/tasks").await? {
+        let Some(task) = todoist_task(&item, &projects) else { continue };
+        seen.extend(task.todoist_id.clone());
+        if state.upsert_imported(task, |task| task.todoist_id.clone()).await {
+            summary.updated += 1;
+        } else {
+            summary.added += 1;
+        }
+    }
+    Ok(seen)
+}
+
+pub async fn import(config: &TodoistConfig, state: &AppState) -> Result<TodoistSummary, Box<dyn std::error::Error>> {
+    let client = TodoistClient::new(config)?;
+    let mut summary = TodoistSummary::default();
+    pull(&client, state, &mut summary).await?;
+    Ok(summary)
+}
+
+// Import, then reconcile completions in both directions
+pub async fn sync(config: &TodoistConfig, state: &AppState) -> Result<TodoistSummary, Box<dyn std::error::Error>> {
+    let client = TodoistClient::new(config)?;
+    let mut summary = TodoistSummary::default();
+    let active = pull(&client, state, &mut summary).await?;
+
+    for task in state.list_tasks().await {
+        let Some(todoist_id) = &task.todoist_id else { continue };
+        match (task.status, active.contains(todoist_id)) {
+            (TaskStatus::Done, true) => {
+                client.call(Method::POST, &format!("/tasks/{}/close", todoist_id)).await?;
+                summary.closed += 1;
+            }
+            // Completed or deleted in Todoist
+            (TaskStatus::Todo, false) => {
+                state.complete_task(task.id).await;
+                summary.completed += 1;
+            }
+            _ => {}
+        }
+    }
+    Ok(summary)
+}
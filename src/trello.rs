@@ -0,0 +1,119 @@
+// Trello board import, from the API or a board's JSON export
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use hyper::{Method, Request};
+use serde_json::{json, Value};
+
+use crate::config::TrelloConfig;
+use crate::http::{self, HttpClient};
+use crate::{AppState, Task, TaskStatus};
+
+const TRELLO_URL: &str = "https://api.trello.com/1";
+
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub updated: usize,
+    /// Archived cards
+    pub skipped: usize,
+}
+
+// Lists with these names hold finished cards
+fn is_done_list(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ["done", "complete", "completed", "finished"].iter().any(|done| name.contains(done))
+}
+
+// Accept a board ID, short link, or board URL
+fn board_id(board: &str) -> &str {
+    match board.split_once("/b/") {
+        Some((_, rest)) => rest.split('/').next().unwrap_or(rest),
+        None => board,
+    }
+}
+
+async fn fetch(client: &HttpClient, config: &TrelloConfig, path: &str) -> Result<Value, Box<dyn std::error::Error>> {
+    let separator = if path.contains('?') { '&' } else { '?' };
+    let url = format!("{}{}{}key={}&token={}", TRELLO_URL, path, separator, config.key, config.token);
+    let request = Request::builder().method(Method::GET).uri(url).body(String::new())?;
+    let (status, body) = http::send(client, request).await?;
+    http::check(status, &body, "Trello request")?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+async fn fetch_board(config: &TrelloConfig, board: &str) -> Result<Value, Box<dyn std::error::Error>> {
+    let client = http::client()?;
+    let id = board_id(board);
+    let board = fetch(&client, config, &format!("/boards/{}?fields=name", id)).await?;
+    let lists = fetch(&client, config, &format!("/boards/{}/lists?fields=name&filter=all", id)).await?;
+    let cards_path = format!("/boards/{}/cards?fields=name,desc,due,dueComplete,idList,labels,closed&filter=all", id);
+    let cards = fetch(&client, config, &cards_path).await?;
+    // Same shape as the board's JSON export
+    Ok(json!({ "name": board["name"], "lists": lists, "cards": cards }))
+}
+
+fn card_task(card: &Value, board_name: &str, lists: &HashMap<&str, &str>) -> Option<Task> {
+    let list = card["idList"].as_str().and_then(|id| lists.get(id)).copied().unwrap_or("");
+    let due: Option<DateTime<Utc>> = card["due"].as_str().and_then(|due| due.parse().ok());
+    let done = is_done_list(list) || card["dueComplete"].as_bool() == Some(true);
+    let mut tags: Vec<String> = card["labels"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|label| {
+            // Unnamed labels are identified by their color
+            let name = label["name"].as_str().filter(|name| !name.is_empty());
+            name.or(label["color"].as_str()).map(str::to_string)
+        })
+        .collect();
+    if !list.is_empty() && !is_done_list(list) {
+        tags.push(list.to_lowercase().replace(' ', "-"));
+    }
+    let mut task = Task {
+        id: 0,
+        title: card["name"].as_str()?.to_string(),
+        details: card["desc"].as_str().unwrap_or_default().to_string(),
+        start_time: due,
+        end_time: due.map(|due| due + Duration::minutes(30)),
+        status: if done { TaskStatus::Done } else { TaskStatus::Todo },
+        project: Some(board_name.to_string()),
+        tags,
+        ..Default::default()
+    };
+    task.meta.insert("trello_card".to_string(), card["id"].as_str()?.to_string());
+    Some(task)
+}
+
+// `board` is either the path of a JSON export or a board ID/URL fetched with the API
+pub async fn import(config: Option<&TrelloConfig>, board: &str, state: &AppState) -> Result<ImportSummary, Box<dyn std::error::Error>> {
+    let data: Value = if std::path::Path::new(board).is_file() {
+        serde_json::from_str(&std::fs::read_to_string(board)?)?
+    } else {
+        let config = config.ok_or("Add a \"trello\" section with your API key and token to config.json, or pass a board's JSON export")?;
+        fetch_board(config, board).await?
+    };
+
+    let board_name = data["name"].as_str().unwrap_or("Trello").to_string();
+    let lists: HashMap<&str, &str> = data["lists"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|list| Some((list["id"].as_str()?, list["name"].as_str()?)))
+        .collect();
+
+    let mut summary = ImportSummary::default();
+    for card in data["cards"].as_array().into_iter().flatten() {
+        if card["closed"].as_bool() == Some(true) {
+            summary.skipped += 1;
+            continue;
+        }
+        let Some(task) = card_task(card, &board_name, &lists) else { continue };
+        if state.upsert_imported(task, |task| task.meta.get("trello_card").cloned()).await {
+            summary.updated += 1;
+        } else {
+            summary.added += 1;
+        }
+    }
+    Ok(summary)
+}
@@ -0,0 +1,293 @@
+// Undo and redo through the change log: each command that changed tasks is one
+// step, reverted by putting back the values it replaced
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::Task;
+use crate::atomic;
+use crate::audit::Entry;
+
+// How many steps are undone, so redo knows where to pick up
+pub const UNDO_PATH: &str = "undo.json";
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct UndoState {
+    /// Steps undone, counted back from the latest
+    pub undone: usize,
+    /// Steps in the log when that was counted
+    pub steps: usize,
+    /// Steps that were undone when a new change was made, which can't be redone
+    /// and are already reverted
+    #[serde(default)]
+    pub dropped: Vec<usize>,
+}
+
+impl UndoState {
+    pub fn load(path: &str) -> Result<UndoState, Box<dyn std::error::Error>> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(UndoState::default()),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        atomic::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // Index of the step among `steps` (as `steps` returns them) to undo next,
+    // or to redo next
+    pub fn next(&self, steps: usize, redo: bool) -> Option<usize> {
+        match redo {
+            false if self.undone < steps => Some(steps - 1 - self.undone),
+            true if self.undone > 0 => Some(steps - self.undone),
+            _ => None,
+        }
+    }
+
+    // Count the step `next` gave as undone, or redone
+    pub fn step(&mut self, redo: bool) {
+        match redo {
+            false => self.undone += 1,
+            true => self.undone -= 1,
+        }
+    }
+}
+
+// Whether an entry was made by undo or redo themselves
+fn is_undo(entry: &Entry) -> bool {
+    matches!(entry.command.split_whitespace().next(), Some("undo" | "redo"))
+}
+
+// The steps that can be undone or redone, oldest first: the log split into
+// commands, leaving out undo and redo themselves and the steps dropped. A change
+// made since the last undo drops the steps that were undone.
+pub fn steps<'a>(entries: &'a [Entry], position: &mut UndoState) -> Vec<Vec<&'a Entry>> {
+    let mut steps: Vec<Vec<&Entry>> = Vec::new();
+    for entry in entries.iter().filter(|entry| !is_undo(entry)) {
+        match steps.last_mut() {
+            Some(step) if step[0].same_run(entry) => step.push(entry),
+            _ => steps.push(vec![entry]),
+        }
+    }
+    if position.steps != steps.len() {
+        let undone = position.steps.saturating_sub(position.undone)..position.steps;
+        position.dropped.extend(undone.filter(|index| *index < steps.len()));
+        position.undone = 0;
+        position.steps = steps.len();
+    }
+    steps.into_iter().enumerate().filter(|(index, _)| !position.dropped.contains(index)).map(|(_, step)| step).collect()
+}
+
+// Put `value` in the field of a task in JSON form; tags, dependencies and
+// attributes are set one element at a time
+fn set(task: &mut Map<String, Value>, field: &str, value: &Value, ids: &HashMap<String, u32>) {
+    let present = !value.is_null();
+    match field.split_once('/') {
+        Some(("tags", tag)) => {
+            let tags = task.entry("tags").or_insert_with(|| Value::Array(Vec::new()));
+            if let Value::Array(tags) = tags {
+                tags.retain(|own| own.as_str() != Some(tag));
+                if present {
+                    tags.push(Value::from(tag));
+                }
+            }
+        }
+        Some(("meta", key)) => {
+            if let Value::Object(meta) = task.entry("meta").or_insert_with(|| Value::Object(Map::new())) {
+                match present {
+                    true => meta.insert(key.to_string(), value.clone()),
+                    false => meta.remove(key),
+                };
+            }
+        }
+        Some(("depends_on", uuid)) => {
+            let Some(id) = ids.get(uuid) else { return };
+            if let Value::Array(depends_on) = task.entry("depends_on").or_insert_with(|| Value::Array(Vec::new())) {
+                depends_on.retain(|own| own.as_u64() != Some(*id as u64));
+                if present {
+                    depends_on.push(Value::from(*id));
+                }
+            }
+        }
+        _ if field == "parent" => {
+            let parent = value.as_str().and_then(|uuid| ids.get(uuid)).map_or(Value::Null, |id| Value::from(*id));
+            task.insert(field.to_string(), parent);
+        }
+        _ => {
+            task.insert(field.to_string(), value.clone());
+        }
+    }
+}
+
+// Apply a step to `tasks`, backwards to undo it or forwards to redo it. Entries
+// are applied newest first when undoing.
+pub fn apply(tasks: &mut Vec<Task>, step: &[&Entry], backwards: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let ordered: Vec<&&Entry> = if backwards { step.iter().rev().collect() } else { step.iter().collect() };
+    for entry in ordered {
+        let value = if backwards { &entry.before } else { &entry.after };
+        let ids: HashMap<String, u32> = tasks.iter().map(|task| (task.uuid.clone(), task.id)).collect();
+        let adds = (entry.field == "created" && !backwards) || (entry.field == "deleted" && backwards);
+        let removes = (entry.field == "created" && backwards) || (entry.field == "deleted" && !backwards);
+        if adds {
+            if ids.contains_key(&entry.task) || !value.is_object() {
+                continue;
+            }
+            let mut task: Task = serde_json::from_value(value.clone())?;
+            // Its ID may have been given to another task since
+            if tasks.iter().any(|other| other.id == task.id) {
+                task.id = tasks.iter().map(|other| other.id + 1).max().unwrap_or(0);
+            }
+            tasks.push(task);
+        } else if removes {
+            let Some(&id) = ids.get(&entry.task) else { continue };
+            tasks.retain(|task| task.id != id);
+            for task in tasks.iter_mut() {
+                task.depends_on.retain(|own| *own != id);
+                if task.parent == Some(id) {
+                    task.parent = None;
+                }
+            }
+        } else if let Some(task) = tasks.iter_mut().find(|task| task.uuid == entry.task) {
+            let Value::Object(mut fields) = serde_json::to_value(&*task)? else { continue };
+            set(&mut fields, &entry.field, value, &ids);
+            *task = serde_json::from_value(Value::Object(fields))?;
+            task.touch();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn entry(run: Option<&str>, second: u32, command: &str, task: &str, field: &str, before: Value, after: Value) -> Entry {
+        Entry {
+            time: Utc.with_ymd_and_hms(2030, 1, 1, 9, 0, second).unwrap(),
+            who: "sam".to_string(),
+            command: command.to_string(),
+            run: run.map(str::to_string),
+            task: task.to_string(),
+            id: 1,
+            title: String::new(),
+            field: field.to_string(),
+            before,
+            after,
+        }
+    }
+
+    fn task(id: u32, uuid: &str, title: &str) -> Task {
+        Task { id, uuid: uuid.to_string(), title: title.to_string(), ..Default::default() }
+    }
+
+    fn created(run: &str, task: &Task) -> Entry {
+        entry(Some(run), 0, "add", &task.uuid, "created", Value::Null, serde_json::to_value(task).unwrap())
+    }
+
+    fn retitled(run: &str, second: u32, uuid: &str, before: &str, after: &str) -> Entry {
+        entry(Some(run), second, "edit", uuid, "title", Value::from(before), Value::from(after))
+    }
+
+    fn sizes(steps: &[Vec<&Entry>]) -> Vec<usize> {
+        steps.iter().map(Vec::len).collect()
+    }
+
+    #[test]
+    fn a_run_is_one_step_across_seconds() {
+        // `add` saving the task, then again once it's pushed a second later
+        let entries = [created("one", &task(1, "a", "A")), entry(Some("one"), 1, "add", "a", "event_id", Value::Null, Value::from("e1"))];
+        assert_eq!(sizes(&steps(&entries, &mut UndoState::default())), [2]);
+    }
+
+    #[test]
+    fn identical_commands_in_the_same_second_are_separate_steps() {
+        let entries = [created("one", &task(1, "a", "X")), created("two", &task(2, "b", "X"))];
+        assert_eq!(sizes(&steps(&entries, &mut UndoState::default())), [1, 1]);
+    }
+
+    #[test]
+    fn entries_without_a_run_go_by_time_and_command() {
+        let entries = [
+            entry(None, 0, "edit 1", "a", "title", Value::from("A"), Value::from("B")),
+            entry(None, 0, "edit 1", "a", "details", Value::from(""), Value::from("d")),
+            entry(None, 1, "edit 1", "a", "title", Value::from("B"), Value::from("C")),
+            retitled("one", 1, "a", "C", "D"),
+        ];
+        assert_eq!(sizes(&steps(&entries, &mut UndoState::default())), [2, 1, 1]);
+    }
+
+    #[test]
+    fn undo_and_redo_are_not_steps() {
+        let entries = [retitled("one", 0, "a", "A", "B"), entry(Some("two"), 1, "undo", "a", "title", Value::from("B"), Value::from("A"))];
+        assert_eq!(sizes(&steps(&entries, &mut UndoState::default())), [1]);
+    }
+
+    #[test]
+    fn apply_edits_backwards_and_forwards() {
+        let mut tasks = vec![Task { tags: vec!["home".to_string()], ..task(1, "a", "B") }];
+        let entries = [retitled("one", 0, "a", "A", "B"), entry(Some("one"), 0, "edit", "a", "tags/home", Value::Null, Value::Bool(true))];
+        let step: Vec<&Entry> = entries.iter().collect();
+        apply(&mut tasks, &step, true).unwrap();
+        assert_eq!((tasks[0].title.as_str(), tasks[0].tags.len()), ("A", 0));
+        apply(&mut tasks, &step, false).unwrap();
+        assert_eq!((tasks[0].title.as_str(), tasks[0].tags.as_slice()), ("B", ["home".to_string()].as_slice()));
+    }
+
+    #[test]
+    fn apply_removes_and_brings_back_tasks() {
+        let added = task(2, "b", "Added");
+        let mut tasks = vec![Task { depends_on: vec![2], ..task(1, "a", "A") }, added.clone()];
+        let entries = [created("one", &added)];
+        let step: Vec<&Entry> = entries.iter().collect();
+        apply(&mut tasks, &step, true).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert!(tasks[0].depends_on.is_empty());
+        // Its ID was given out meanwhile
+        tasks.push(task(2, "c", "Other"));
+        apply(&mut tasks, &step, false).unwrap();
+        let back = tasks.iter().find(|task| task.uuid == "b").unwrap();
+        assert_eq!((back.id, back.title.as_str()), (3, "Added"));
+    }
+
+    #[test]
+    fn position_moves_through_the_steps() {
+        let entries = [retitled("one", 0, "a", "A", "B"), retitled("two", 1, "a", "B", "C"), retitled("three", 2, "a", "C", "D")];
+        let mut position = UndoState::default();
+        let all = steps(&entries, &mut position);
+        assert_eq!(position.steps, 3);
+        assert_eq!(position.next(all.len(), true), None);
+        assert_eq!(position.next(all.len(), false), Some(2));
+        position.step(false);
+        assert_eq!(position.next(all.len(), false), Some(1));
+        position.step(false);
+        assert_eq!(position.next(all.len(), true), Some(1));
+        position.step(true);
+        assert_eq!(position.next(all.len(), true), Some(2));
+        position.step(false);
+        position.step(false);
+        position.step(false);
+        assert_eq!(position.next(all.len(), false), None);
+    }
+
+    #[test]
+    fn a_new_change_drops_the_steps_undone() {
+        let mut entries = vec![retitled("one", 0, "a", "A", "B"), retitled("two", 1, "a", "B", "C"), retitled("three", 2, "a", "C", "D")];
+        let mut position = UndoState::default();
+        steps(&entries, &mut position);
+        position.step(false);
+        // The undo's own changes, then a new edit
+        entries.push(entry(Some("four"), 3, "undo", "a", "title", Value::from("D"), Value::from("C")));
+        entries.push(retitled("five", 4, "a", "C", "E"));
+        let after = steps(&entries, &mut position);
+        assert_eq!(position.dropped, [2]);
+        let runs: Vec<&str> = after.iter().map(|step| step[0].run.as_deref().unwrap()).collect();
+        assert_eq!(runs, ["one", "two", "five"]);
+        assert_eq!(position.next(after.len(), true), None);
+        assert_eq!(position.next(after.len(), false), Some(2));
+    }
+}
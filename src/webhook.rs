@@ -0,0 +1,94 @@
+// Push notifications from Google Calendar for `serve`. With "webhook_url" in
+// the config the server opens a push channel, Google calls it whenever an
+// event changes, and the change is synced within seconds instead of at the
+// next periodic sync. Google only calls public HTTPS addresses, so the URL is
+// usually a reverse proxy or tunnel in front of the server's PATH.
+use chrono::Utc;
+use hyper::HeaderMap;
+use tokio::sync::{watch, Notify};
+
+use crate::config::{BackendKind, Config};
+use crate::google::{GoogleBackend, WatchChannel};
+use crate::i18n::t;
+use crate::quiet::say;
+use crate::quota;
+
+// Path on the server the notifications are received at
+pub const PATH: &str = "/google/notify";
+
+// Channels are replaced this long before Google lets them expire
+const RENEW_BEFORE: chrono::Duration = chrono::Duration::hours(1);
+
+// Wait after a channel couldn't be opened before trying again
+const RETRY: std::time::Duration = std::time::Duration::from_secs(300);
+
+pub struct Webhook {
+    /// Sent back with every notification, so calls from others are ignored
+    token: String,
+    /// Woken by each change
+    pub changed: Notify,
+}
+
+impl Webhook {
+    pub fn new() -> Webhook {
+        Webhook { token: crate::new_uuid(), changed: Notify::new() }
+    }
+
+    // Take a notification. A change wakes the sync; the "sync" message Google
+    // sends when a channel opens, and calls without the token, don't.
+    pub fn receive(&self, headers: &HeaderMap) {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        if header("X-Goog-Channel-Token") == Some(self.token.as_str()) && header("X-Goog-Resource-State") != Some("sync") {
+            self.changed.notify_one();
+        }
+    }
+}
+
+// The URL Google should call, when the config sets one and syncs with Google
+pub fn url(config: &Config) -> Option<&str> {
+    config.webhook_url.as_deref().filter(|_| config.backend == BackendKind::Google)
+}
+
+async fn open(config: &Config, url: &str, token: &str) -> Result<(GoogleBackend, WatchChannel), Box<dyn std::error::Error>> {
+    let backend = GoogleBackend::new(config).await?;
+    let channel = quota::call("Google Calendar", || backend.watch(url, token)).await?;
+    Ok((backend, channel))
+}
+
+async fn close(backend: &GoogleBackend, channel: &WatchChannel) {
+    if let Err(e) = quota::call("Google Calendar", || backend.stop_watch(channel)).await {
+        eprintln!("{}", t!("webhook-close-failed", id = channel.id, error = e));
+    }
+}
+
+// Keep a channel open until stopped, replacing it before it expires, and
+// close it on the way out
+pub async fn channel_loop(config: &Config, webhook: &Webhook, url: &str, mut stop: watch::Receiver<bool>) {
+    let mut current: Option<(GoogleBackend, WatchChannel)> = None;
+    loop {
+        let wait = match open(config, url, &webhook.token).await {
+            Ok((backend, channel)) => {
+                if current.is_none() {
+                    say!("{}", t!("webhook-receiving", url = url));
+                }
+                let wait = (channel.expires - RENEW_BEFORE - Utc::now()).to_std().unwrap_or(RETRY);
+                if let Some((backend, channel)) = current.replace((backend, channel)) {
+                    close(&backend, &channel).await;
+                }
+                wait
+            }
+            Err(e) => {
+                eprintln!("{}", t!("webhook-open-failed", url = url, error = e));
+                RETRY
+            }
+        };
+        tokio::select! {
+            biased;
+            _ = stop.wait_for(|stop| *stop) => break,
+            _ = tokio::time::sleep(wait) => {}
+        }
+    }
+    if let Some((backend, channel)) = current {
+        close(&backend, &channel).await;
+    }
+}
@@ -0,0 +1,60 @@
+// The week as the config sets it up: the day it starts on, for week views and
+// repeats every few weeks, and the numbers week views give it
+use std::sync::OnceLock;
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+use crate::config::WeekNumbers;
+
+static START: OnceLock<Weekday> = OnceLock::new();
+static NUMBERS: OnceLock<WeekNumbers> = OnceLock::new();
+
+pub fn set(start: Option<Weekday>, numbers: Option<WeekNumbers>) {
+    if let Some(start) = start {
+        let _ = START.set(start);
+    }
+    if let Some(numbers) = numbers {
+        let _ = NUMBERS.set(numbers);
+    }
+}
+
+// Day weeks start on: Monday unless the config says otherwise
+pub fn start() -> Weekday {
+    START.get().copied().unwrap_or(Weekday::Mon)
+}
+
+// First day of the week `date` is in
+pub fn first_day(date: NaiveDate) -> NaiveDate {
+    date.week(start()).first_day()
+}
+
+// Short names of the days of the week, from its first day
+pub fn day_names() -> Vec<String> {
+    let mut day = start();
+    (0..7)
+        .map(|_| {
+            let name = day.to_string();
+            day = day.succ();
+            name
+        })
+        .collect()
+}
+
+// Number of the week starting on `first`, when the config turns week numbers
+// on: the ISO 8601 week most of its days are in, or for US numbering, counted
+// in weeks from Sunday with week 1 the one holding 1 January
+pub fn number(first: NaiveDate) -> Option<u32> {
+    match NUMBERS.get()? {
+        WeekNumbers::Iso => Some((first + Duration::days(3)).iso_week().week()),
+        WeekNumbers::Us => {
+            let last = first + Duration::days(6);
+            let new_year = NaiveDate::from_ymd_opt(last.year(), 1, 1)?;
+            Some((last.ordinal0() + new_year.weekday().num_days_from_sunday()) / 7 + 1)
+        }
+    }
+}
+
+// "Week 42" for the week starting on `first`, when week numbers are on
+pub fn label(first: NaiveDate) -> Option<String> {
+    number(first).map(|number| format!("Week {}", number))
+}
@@ -0,0 +1,160 @@
+// Dates and times as people type them: RFC 3339, "2024-12-31 15:00", "15:00"
+// or "3pm" for today, "tomorrow 9am", "friday 14:30", "12/31 3pm" and 12-hour
+// times such as "3:30 PM". Anything without an offset is local time, or the
+// task's time zone's.
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Utc};
+
+use crate::i18n::t;
+use crate::zone::Zone;
+use crate::{filter, recurrence};
+
+// The formats accepted, for error messages
+const FORMATS: &str = "2024-12-31T15:00:00Z, 2024-12-31 15:00, 15:00 or 3pm (today), tomorrow 9am, friday 14:30, 12/31 3pm or 12/31/2024 3:30 PM";
+
+// Lowercase, with "a.m."/"p.m." as "am"/"pm" next to the time and commas, as
+// in "12/31/2024, 3:00 PM", left out
+fn normalize(text: &str) -> String {
+    let text = text.trim().to_lowercase().replace("a.m.", "am").replace("p.m.", "pm").replace(',', " ");
+    let mut words: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match words.last_mut() {
+            Some(last) if word == "am" || word == "pm" => last.push_str(word),
+            _ => words.push(word.to_string()),
+        }
+    }
+    words.join(" ")
+}
+
+// "9am", "18:30", "noon", or with seconds, "15:00:06" and "3:00:06pm"
+fn parse_time(text: &str) -> Option<NaiveTime> {
+    recurrence::parse_time(text)
+        .or_else(|| NaiveTime::parse_from_str(text, "%H:%M:%S").ok())
+        .or_else(|| NaiveTime::parse_from_str(text, "%I:%M:%S%p").ok())
+}
+
+// A day as `filter` reads it (today, tomorrow, a weekday, YYYY-MM-DD), or
+// month/day with an optional year; day/month when that's the only reading
+fn parse_date(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    if let Some(date) = filter::parse_day(text, today) {
+        return Some(date);
+    }
+    let parts: Vec<&str> = text.split('/').collect();
+    let (first, second, year) = match parts[..] {
+        [first, second] => (first.parse().ok()?, second.parse().ok()?, today.year()),
+        [first, second, year] => {
+            let year: i32 = year.parse().ok()?;
+            (first.parse().ok()?, second.parse().ok()?, if year < 100 { 2000 + year } else { year })
+        }
+        _ => return None,
+    };
+    NaiveDate::from_ymd_opt(year, first, second).or_else(|| NaiveDate::from_ymd_opt(year, second, first))
+}
+
+// The date and time of day in `text`, read with `today` as the current day
+fn parse_local(text: &str, today: NaiveDate) -> Option<(NaiveDate, NaiveTime)> {
+    // "2024-12-31t15:00", as RFC 3339 but without an offset
+    if let Some((date, time)) = text.split_once('t')
+        && let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+    {
+        return Some((date, parse_time(time)?));
+    }
+    match text.rsplit_once(' ') {
+        Some((date, time)) => Some((parse_date(date, today)?, parse_time(time)?)),
+        None => match parse_time(text) {
+            Some(time) => Some((today, time)),
+            None => Some((parse_date(text, today)?, NaiveTime::MIN)),
+        },
+    }
+}
+
+// A point in time from what was typed; Err lists the formats accepted
+pub fn parse(text: &str) -> Result<DateTime<Utc>, String> {
+    parse_in(text, &Zone::Local)
+}
+
+// A point in time from what was typed, with days and times without an offset
+// in `zone`
+pub fn parse_in(text: &str, zone: &Zone) -> Result<DateTime<Utc>, String> {
+    parse_on(text, zone, zone.naive(Utc::now()).date())
+}
+
+// `parse_in` with `today` as the current day in `zone`
+fn parse_on(text: &str, zone: &Zone, today: NaiveDate) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = text.trim().to_uppercase().parse::<DateTime<Utc>>() {
+        return Ok(time);
+    }
+    let invalid = || t!("time-invalid", text = text.trim(), formats = FORMATS);
+    let (date, time) = parse_local(&normalize(text), today).ok_or_else(invalid)?;
+    zone.resolve(date.and_time(time)).ok_or_else(|| t!("time-skipped", text = text.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Wednesday, with New York on daylight saving time
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 7, 10).unwrap()
+    }
+
+    fn parse(text: &str) -> Result<String, String> {
+        let zone = crate::zone::parse("America/New_York").unwrap();
+        parse_on(text, &zone, today()).map(|time| time.to_rfc3339())
+    }
+
+    #[test]
+    fn formats() {
+        // Each of FORMATS, then variants; New York is 4 hours behind UTC in
+        // summer and 5 in winter
+        let cases = [
+            ("2024-12-31T15:00:00Z", "2024-12-31T15:00:00+00:00"),
+            ("2024-12-31t15:00:00+02:00", "2024-12-31T13:00:00+00:00"),
+            ("2024-12-31 15:00", "2024-12-31T20:00:00+00:00"),
+            ("2024-12-31T15:00", "2024-12-31T20:00:00+00:00"),
+            ("2024-12-31", "2024-12-31T05:00:00+00:00"),
+            ("15:00", "2024-07-10T19:00:00+00:00"),
+            ("15:00:06", "2024-07-10T19:00:06+00:00"),
+            ("3pm", "2024-07-10T19:00:00+00:00"),
+            ("3 PM", "2024-07-10T19:00:00+00:00"),
+            ("3:00:06pm", "2024-07-10T19:00:06+00:00"),
+            ("noon", "2024-07-10T16:00:00+00:00"),
+            ("today", "2024-07-10T04:00:00+00:00"),
+            ("tomorrow 9am", "2024-07-11T13:00:00+00:00"),
+            ("Tomorrow 9 a.m.", "2024-07-11T13:00:00+00:00"),
+            ("friday 14:30", "2024-07-12T18:30:00+00:00"),
+            ("wednesday 14:30", "2024-07-17T18:30:00+00:00"),
+            ("12/31 3pm", "2024-12-31T20:00:00+00:00"),
+            ("12/31/2024 3:30 PM", "2024-12-31T20:30:00+00:00"),
+            ("12/31/2024, 3:30 P.M.", "2024-12-31T20:30:00+00:00"),
+            ("12/31/25 3:30pm", "2025-12-31T20:30:00+00:00"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse(text).as_deref(), Ok(expected), "{}", text);
+        }
+    }
+
+    #[test]
+    fn month_comes_before_day_unless_it_cant() {
+        assert_eq!(parse("05/06 9am").as_deref(), Ok("2024-05-06T13:00:00+00:00"));
+        assert_eq!(parse("13/06 9am").as_deref(), Ok("2024-06-13T13:00:00+00:00"));
+        assert_eq!(parse("31/12/2024 9am").as_deref(), Ok("2024-12-31T14:00:00+00:00"));
+        assert!(parse("13/13 9am").is_err());
+    }
+
+    #[test]
+    fn times_clocks_skip_or_repeat() {
+        // Clocks went from 2:00 to 3:00 on 10 March 2024 and back from 2:00
+        // to 1:00 on 3 November; a repeated time is the earlier one
+        assert!(parse("2024-03-10 2:30").unwrap_err().contains("2024-03-10 2:30"));
+        assert_eq!(parse("2024-03-10 3:30").as_deref(), Ok("2024-03-10T07:30:00+00:00"));
+        assert_eq!(parse("2024-11-03 1:30").as_deref(), Ok("2024-11-03T05:30:00+00:00"));
+    }
+
+    #[test]
+    fn rejected() {
+        for text in ["", "someday", "25:00", "tomorrow 25:00", "2024-13-01 9am", "12/31/2024/1 3pm"] {
+            let error = parse(text).unwrap_err();
+            assert!(error.contains(FORMATS), "{}: {}", text, error);
+        }
+    }
+}
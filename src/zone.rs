@@ -0,0 +1,126 @@
+// Time zones a task can be pinned to with `--tz`, so a task set for 9am in
+// Tokyo stays at 9am Tokyo time wherever the computer is. Times typed for the
+// task, its recurrence and how its times are shown follow the zone; tasks
+// without one use the computer's local time. Names are IANA ones such as
+// "Asia/Tokyo", looked up in the system's time zone database.
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDateTime, Offset, TimeZone, Utc};
+
+use crate::i18n::t;
+
+#[derive(Clone)]
+pub enum Zone {
+    Local,
+    Named(jiff::tz::TimeZone),
+}
+
+// The zone named `name`, with the error saying what's wrong with it
+pub fn parse(name: &str) -> Result<Zone, String> {
+    jiff::tz::TimeZone::get(name.trim())
+        .map(Zone::Named)
+        .map_err(|_| t!("time-zone-invalid", name = name.trim()))
+}
+
+fn timestamp(time: DateTime<Utc>) -> jiff::Timestamp {
+    jiff::Timestamp::from_second(time.timestamp()).unwrap_or(jiff::Timestamp::UNIX_EPOCH)
+}
+
+impl Zone {
+    // A task's zone: the one it names, or local time. A name the system's
+    // database doesn't have falls back to local time too.
+    pub fn of(name: Option<&str>) -> Zone {
+        name.and_then(|name| parse(name).ok()).unwrap_or(Zone::Local)
+    }
+
+    // Date and time of day `time` has in the zone
+    pub fn naive(&self, time: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Zone::Local => time.with_timezone(&Local).naive_local(),
+            Zone::Named(zone) => time.naive_utc() + Duration::seconds(zone.to_offset(timestamp(time)).seconds() as i64),
+        }
+    }
+
+    // The point in time a date and time of day in the zone is: the earlier one
+    // when clocks go back, none for a time skipped when they go forward
+    pub fn resolve(&self, naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+        let Zone::Named(zone) = self else {
+            return Local.from_local_datetime(&naive).earliest().map(|time| time.with_timezone(&Utc));
+        };
+        let civil = naive.to_string().parse::<jiff::civil::DateTime>().ok()?;
+        let offset = match zone.to_ambiguous_timestamp(civil).offset() {
+            jiff::tz::AmbiguousOffset::Unambiguous { offset } => offset,
+            jiff::tz::AmbiguousOffset::Fold { before, .. } => before,
+            jiff::tz::AmbiguousOffset::Gap { .. } => return None,
+        };
+        Some(Utc.from_utc_datetime(&(naive - Duration::seconds(offset.seconds() as i64))))
+    }
+
+    // The zone's offset from UTC at `time`
+    pub fn offset(&self, time: DateTime<Utc>) -> FixedOffset {
+        let seconds = (self.naive(time) - time.naive_utc()).num_seconds() as i32;
+        FixedOffset::east_opt(seconds).unwrap_or(Utc.fix())
+    }
+
+    // IANA name of the zone, e.g. "Asia/Tokyo"; for local time the system's,
+    // when it's known
+    pub fn name(&self) -> Option<String> {
+        match self {
+            Zone::Local => jiff::tz::TimeZone::system().iana_name().map(str::to_string),
+            Zone::Named(zone) => zone.iana_name().map(str::to_string),
+        }
+    }
+
+    // Short name of the zone at `time`, e.g. "JST" or "CEST"; the offset for
+    // zones without one
+    pub fn abbreviation(&self, time: DateTime<Utc>) -> String {
+        match self {
+            Zone::Local => time.with_timezone(&Local).format("%Z").to_string(),
+            Zone::Named(zone) => zone.to_offset_info(timestamp(time)).abbreviation().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    // Berlin's clocks go from 2:00 to 3:00 on 29 March 2026 and back from
+    // 3:00 to 2:00 on 25 October
+    fn berlin() -> Zone {
+        parse("Europe/Berlin").unwrap()
+    }
+
+    fn naive(month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn utc(month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.from_utc_datetime(&naive(month, day, hour, minute))
+    }
+
+    #[test]
+    fn skipped_times_have_no_instant() {
+        assert_eq!(berlin().resolve(naive(3, 29, 2, 30)), None);
+        assert_eq!(berlin().resolve(naive(3, 29, 3, 0)), Some(utc(3, 29, 1, 0)));
+    }
+
+    #[test]
+    fn repeated_times_are_the_earlier_instant() {
+        assert_eq!(berlin().resolve(naive(10, 25, 2, 30)), Some(utc(10, 25, 0, 30)));
+        assert_eq!(berlin().naive(utc(10, 25, 1, 30)), naive(10, 25, 2, 30));
+    }
+
+    #[test]
+    fn offsets_follow_the_clock_changes() {
+        let hours = |time| berlin().offset(time).local_minus_utc() / 3600;
+        assert_eq!((hours(utc(3, 29, 0, 59)), hours(utc(3, 29, 1, 0))), (1, 2));
+        assert_eq!((hours(utc(10, 25, 0, 59)), hours(utc(10, 25, 1, 0))), (2, 1));
+        assert_eq!(berlin().abbreviation(utc(7, 1, 12, 0)), "CEST");
+    }
+
+    #[test]
+    fn unknown_zones_are_rejected() {
+        assert!(parse("Europe/Atlantis").err().is_some_and(|error| error.contains("Europe/Atlantis")));
+        assert!(matches!(Zone::of(Some("Europe/Atlantis")), Zone::Local));
+    }
+}